@@ -0,0 +1,243 @@
+use ark_crypto_primitives::{merkle_tree::Config as MTConfig, Path};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_sponge::Absorb;
+use ark_std::{boxed::Box, collections::BTreeMap, mem::take, vec::Vec};
+
+use crate::{bcs::prover::BCSProof, BCSError, Error};
+
+/// Merkle tree data of a prover round where all aggregated proofs have the
+/// same merkle root. Authentication path nodes queried by different proofs
+/// are stored only once.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig"))]
+pub struct SharedRound<MT: MTConfig> {
+    /// Index of the prover round in each proof.
+    pub round: usize,
+    /// Merkle root shared by all proofs.
+    pub root: MT::InnerDigest,
+    /// Length of each authentication path of this round.
+    pub auth_path_length: usize,
+    /// Queried leaf indices, with axes `[proof index, query order]`.
+    pub leaf_indices: Vec<Vec<usize>>,
+    /// Leaf sibling hashes, keyed by the leaf index of the sibling.
+    pub leaf_sibling_hashes: BTreeMap<usize, MT::LeafDigest>,
+    /// Authentication path nodes, keyed by `(depth, index at that depth)`,
+    /// where the root has depth 0.
+    pub inner_nodes: BTreeMap<(usize, usize), MT::InnerDigest>,
+}
+
+/// Several BCS proofs, where merkle roots and authentication paths of rounds
+/// shared by all proofs are deduplicated. Created by
+/// `BCSProof::merge_proofs`, and verified by `BCSVerifier::verify_aggregated`.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig, F: PrimeField"))]
+pub struct AggregatedBCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// The aggregated proofs. Shared rounds have no merkle root and no paths
+    /// here; they are stored in `shared_rounds` instead.
+    pub proofs: Vec<BCSProof<MT, F>>,
+    /// Rounds where all proofs have the same merkle root.
+    pub shared_rounds: Vec<SharedRound<MT>>,
+}
+
+impl<MT, F> BCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Aggregate `proofs`: for each prover round where all proofs have the
+    /// same merkle root (e.g. proofs opening the same committed oracle), the
+    /// root is stored once, and authentication path nodes common to several
+    /// queries are stored once.
+    ///
+    /// Returns `BCSError::MalformedProof` if two paths of a shared round
+    /// disagree on a node, which cannot happen for honestly generated proofs.
+    pub fn merge_proofs(proofs: &[Self]) -> Result<AggregatedBCSProof<MT, F>, Error> {
+        let mut proofs = proofs.to_vec();
+        let num_rounds = proofs
+            .iter()
+            .map(|proof| proof.prover_messages_mt_root.len())
+            .min()
+            .unwrap_or(0);
+        let mut shared_rounds = Vec::new();
+        for round in 0..num_rounds {
+            let root = match &proofs[0].prover_messages_mt_root[round] {
+                Some(root) => root.clone(),
+                None => continue,
+            };
+            if proofs
+                .iter()
+                .any(|proof| proof.prover_messages_mt_root[round].as_ref() != Some(&root))
+            {
+                continue;
+            }
+            let mut shared_round = SharedRound {
+                round,
+                root,
+                auth_path_length: 0,
+                leaf_indices: Vec::with_capacity(proofs.len()),
+                leaf_sibling_hashes: BTreeMap::new(),
+                inner_nodes: BTreeMap::new(),
+            };
+            let mut paths_of_round = Vec::new();
+            for proof in proofs.iter_mut() {
+                proof.prover_messages_mt_root[round] = None;
+                paths_of_round.push(take(&mut proof.prover_oracles_mt_path[round]));
+            }
+            if let Some(path) = paths_of_round.iter().flatten().next() {
+                shared_round.auth_path_length = path.auth_path.len();
+            }
+            for paths in paths_of_round {
+                let mut leaf_indices = Vec::with_capacity(paths.len());
+                for path in paths {
+                    if path.auth_path.len() != shared_round.auth_path_length
+                        || !insert_consistent(
+                            &mut shared_round.leaf_sibling_hashes,
+                            path.leaf_index ^ 1,
+                            path.leaf_sibling_hash,
+                        )
+                    {
+                        return Err(conflicting_paths(round));
+                    }
+                    for (node_key, node) in
+                        auth_path_node_keys(path.leaf_index, shared_round.auth_path_length)
+                            .zip(path.auth_path)
+                    {
+                        if !insert_consistent(&mut shared_round.inner_nodes, node_key, node) {
+                            return Err(conflicting_paths(round));
+                        }
+                    }
+                    leaf_indices.push(path.leaf_index);
+                }
+                shared_round.leaf_indices.push(leaf_indices);
+            }
+            shared_rounds.push(shared_round);
+        }
+        Ok(AggregatedBCSProof {
+            proofs,
+            shared_rounds,
+        })
+    }
+
+    /// Number of merkle roots, leaf sibling hashes and authentication path
+    /// nodes stored in this proof.
+    pub fn num_merkle_digests(&self) -> usize {
+        self.prover_messages_mt_root.iter().flatten().count()
+            + self
+                .prover_oracles_mt_path
+                .iter()
+                .flatten()
+                .map(|path| path.auth_path.len() + 1)
+                .sum::<usize>()
+    }
+}
+
+impl<MT, F> AggregatedBCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Recover the proofs given to `BCSProof::merge_proofs`. Returns
+    /// `BCSError::MalformedProof` if a shared round refers to a missing round
+    /// or merkle tree node.
+    pub fn to_proofs(&self) -> Result<Vec<BCSProof<MT, F>>, Error> {
+        let mut proofs = self.proofs.clone();
+        for shared_round in &self.shared_rounds {
+            let round = shared_round.round;
+            let malformed =
+                |reason| -> Error { Box::new(BCSError::MalformedProof { round, reason }) };
+            if shared_round.leaf_indices.len() != proofs.len() {
+                return Err(malformed(
+                    "number of leaf index lists is not number of proofs",
+                ));
+            }
+            for (proof, leaf_indices) in proofs.iter_mut().zip(&shared_round.leaf_indices) {
+                if round >= proof.prover_messages_mt_root.len()
+                    || round >= proof.prover_oracles_mt_path.len()
+                {
+                    return Err(malformed("shared round does not exist"));
+                }
+                let paths = leaf_indices
+                    .iter()
+                    .map(|&leaf_index| {
+                        let leaf_sibling_hash = shared_round
+                            .leaf_sibling_hashes
+                            .get(&(leaf_index ^ 1))
+                            .ok_or_else(|| malformed("missing leaf sibling hash"))?
+                            .clone();
+                        let auth_path =
+                            auth_path_node_keys(leaf_index, shared_round.auth_path_length)
+                                .map(|node_key| {
+                                    shared_round
+                                        .inner_nodes
+                                        .get(&node_key)
+                                        .cloned()
+                                        .ok_or_else(|| malformed("missing merkle tree node"))
+                                })
+                                .collect::<Result<Vec<_>, _>>()?;
+                        Ok(Path {
+                            leaf_sibling_hash,
+                            auth_path,
+                            leaf_index,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                proof.prover_messages_mt_root[round] = Some(shared_round.root.clone());
+                proof.prover_oracles_mt_path[round] = paths;
+            }
+        }
+        Ok(proofs)
+    }
+
+    /// Number of merkle roots, leaf sibling hashes and authentication path
+    /// nodes stored in this aggregated proof.
+    pub fn num_merkle_digests(&self) -> usize {
+        self.proofs
+            .iter()
+            .map(|proof| proof.num_merkle_digests())
+            .sum::<usize>()
+            + self
+                .shared_rounds
+                .iter()
+                .map(|round| 1 + round.leaf_sibling_hashes.len() + round.inner_nodes.len())
+                .sum::<usize>()
+    }
+}
+
+/// Keys `(depth, index at that depth)` of the authentication path nodes of
+/// leaf `leaf_index`, in the order of `Path::auth_path` (from the root to the
+/// leaves).
+fn auth_path_node_keys(
+    leaf_index: usize,
+    auth_path_length: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    // leaves have depth `auth_path_length + 1`
+    (1..=auth_path_length)
+        .map(move |depth| (depth, (leaf_index >> (auth_path_length + 1 - depth)) ^ 1))
+}
+
+/// Insert `value` at `key`, and returns false if `key` already has a
+/// different value.
+fn insert_consistent<K: Ord, V: PartialEq>(map: &mut BTreeMap<K, V>, key: K, value: V) -> bool {
+    match map.get(&key) {
+        Some(existing) => existing == &value,
+        None => {
+            map.insert(key, value);
+            true
+        },
+    }
+}
+
+fn conflicting_paths(round: usize) -> Error {
+    Box::new(BCSError::MalformedProof {
+        round,
+        reason: "authentication paths disagree on a merkle tree node",
+    })
+}
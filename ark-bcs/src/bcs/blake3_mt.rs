@@ -0,0 +1,111 @@
+use ark_crypto_primitives::{
+    crh::{CRHScheme, TwoToOneCRHScheme},
+    merkle_tree::{Config as MTConfig, IdentityDigestConverter},
+    Error,
+};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_sponge::Absorb;
+use ark_std::{borrow::Borrow, marker::PhantomData, rand::Rng, vec::Vec};
+
+/// Prefix of the hashed bytes of a leaf, so that a leaf digest is never the
+/// digest of two inner nodes.
+const LEAF_PREFIX: u8 = 0;
+/// Prefix of the hashed bytes of an inner node.
+const INNER_PREFIX: u8 = 1;
+
+/// A 32-byte BLAKE3 digest. It is absorbed by the sponge as bytes.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Default, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct Blake3Digest(pub [u8; 32]);
+
+impl Absorb for Blake3Digest {
+    fn to_sponge_bytes(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(&self.0)
+    }
+
+    fn to_sponge_field_elements<F: PrimeField>(&self, dest: &mut Vec<F>) {
+        self.0.to_vec().to_sponge_field_elements(dest)
+    }
+}
+
+/// BLAKE3 hash of a leaf of field elements, each serialized using
+/// `Absorb::to_sponge_bytes`.
+pub struct Blake3LeafHash<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> CRHScheme for Blake3LeafHash<F> {
+    type Input = [F];
+    type Output = Blake3Digest;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_rng: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(
+        _parameters: &Self::Parameters,
+        input: T,
+    ) -> Result<Self::Output, Error> {
+        let mut bytes = Vec::new();
+        input
+            .borrow()
+            .iter()
+            .for_each(|x| x.to_sponge_bytes(&mut bytes));
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&[LEAF_PREFIX]);
+        hasher.update(&bytes);
+        Ok(Blake3Digest(*hasher.finalize().as_bytes()))
+    }
+}
+
+/// BLAKE3 hash of two merkle tree nodes.
+pub struct Blake3TwoToOneHash;
+
+impl TwoToOneCRHScheme for Blake3TwoToOneHash {
+    type Input = Blake3Digest;
+    type Output = Blake3Digest;
+    type Parameters = ();
+
+    fn setup<R: Rng>(_rng: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<T: Borrow<Self::Input>>(
+        parameters: &Self::Parameters,
+        left_input: T,
+        right_input: T,
+    ) -> Result<Self::Output, Error> {
+        Self::compress(parameters, left_input, right_input)
+    }
+
+    fn compress<T: Borrow<Self::Output>>(
+        _parameters: &Self::Parameters,
+        left_input: T,
+        right_input: T,
+    ) -> Result<Self::Output, Error> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&[INNER_PREFIX]);
+        hasher.update(&left_input.borrow().0);
+        hasher.update(&right_input.borrow().0);
+        Ok(Blake3Digest(*hasher.finalize().as_bytes()))
+    }
+}
+
+/// Merkle tree config using BLAKE3 for leaf and inner hashes, which is much
+/// faster than an algebraic hash natively, but is expensive to verify in
+/// constraints. Both hash parameters are `()`.
+pub struct Blake3MTConfig<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MTConfig for Blake3MTConfig<F> {
+    type Leaf = [F];
+    type LeafDigest = Blake3Digest;
+    type LeafInnerDigestConverter = IdentityDigestConverter<Blake3Digest>;
+    type InnerDigest = Blake3Digest;
+    type LeafHash = Blake3LeafHash<F>;
+    type TwoToOneHash = Blake3TwoToOneHash;
+}
@@ -0,0 +1,180 @@
+use ark_crypto_primitives::{
+    crh::{CRHScheme, TwoToOneCRHScheme},
+    merkle_tree::{Config as MTConfig, DigestConverter},
+    MerkleTree, Path,
+};
+use ark_ff::PrimeField;
+use ark_std::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+
+use crate::{bcs::MTHashParameters, BCSError, Error};
+
+/// Merkle tree commitment to an oracle, where each leaf is one element of the
+/// oracle. Unlike oracles sent in a transcript, the commitment is not
+/// absorbed by any sponge, and can be opened at any time using `open_oracle`.
+pub struct OracleCommitment<MT: MTConfig<Leaf = [F]>, F: PrimeField> {
+    tree: MerkleTree<MT>,
+    oracle: Vec<F>,
+}
+
+impl<MT: MTConfig<Leaf = [F]>, F: PrimeField> OracleCommitment<MT, F> {
+    /// Merkle root of the committed oracle.
+    pub fn root(&self) -> MT::InnerDigest {
+        self.tree.root()
+    }
+
+    /// The committed oracle.
+    pub fn oracle(&self) -> &[F] {
+        &self.oracle
+    }
+}
+
+/// Proof that a committed oracle evaluates to some answers at some
+/// positions, checked by `verify_opening`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig"))]
+pub struct OpeningProof<MT: MTConfig> {
+    /// Authentication path of each opened position, in order.
+    pub paths: Vec<Path<MT>>,
+}
+
+/// Commit to `oracle` using a merkle tree whose leaves are elements of
+/// `oracle`. The length of `oracle` should be a power of two.
+pub fn commit_oracle<MT, F>(
+    hash_params: &MTHashParameters<MT>,
+    oracle: Vec<F>,
+) -> Result<OracleCommitment<MT, F>, Error>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField,
+{
+    if !oracle.len().is_power_of_two() {
+        return Err(Box::new(BCSError::InvalidOracleLength {
+            oracle_length: oracle.len(),
+        }));
+    }
+    let tree = MerkleTree::new(
+        &hash_params.leaf_hash_param,
+        &hash_params.inner_hash_param,
+        oracle.iter().map(|x| vec![*x]),
+    )?;
+    Ok(OracleCommitment { tree, oracle })
+}
+
+/// Open `commitment` at `positions`. The answers are
+/// `commitment.oracle()[position]` for each position.
+pub fn open_oracle<MT, F>(
+    commitment: &OracleCommitment<MT, F>,
+    positions: &[usize],
+) -> Result<OpeningProof<MT>, Error>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField,
+{
+    let paths = positions
+        .iter()
+        .map(|&position| commitment.tree.generate_proof(position))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(OpeningProof { paths })
+}
+
+/// Check that the oracle committed with merkle root `root` evaluates to
+/// `answers` at `positions`.
+pub fn verify_opening<MT, F>(
+    hash_params: &MTHashParameters<MT>,
+    root: &MT::InnerDigest,
+    positions: &[usize],
+    answers: &[F],
+    proof: &OpeningProof<MT>,
+) -> Result<bool, Error>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField,
+{
+    if positions.len() != answers.len() || positions.len() != proof.paths.len() {
+        return Ok(false);
+    }
+    for ((&position, answer), path) in positions.iter().zip(answers).zip(&proof.paths) {
+        if path.leaf_index != position
+            || !path.verify(
+                &hash_params.leaf_hash_param,
+                &hash_params.inner_hash_param,
+                root,
+                [*answer].as_slice(),
+            )?
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Inner nodes of one merkle tree on authentication paths verified so far,
+/// keyed by `(depth, index)` of the node, where the root has depth 0. A path
+/// that reaches a cached node is checked against it instead of hashing up to
+/// the root, so paths sharing upper nodes hash them once.
+pub(crate) struct PathCache<MT: MTConfig> {
+    nodes: BTreeMap<(usize, usize), MT::InnerDigest>,
+    /// Number of leaf hash and two-to-one hash invocations so far.
+    pub(crate) num_hash_invocations: usize,
+}
+
+impl<MT: MTConfig> PathCache<MT> {
+    /// Create an empty cache for the tree with merkle root `root`.
+    pub(crate) fn new(root: &MT::InnerDigest) -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert((0, 0), root.clone());
+        Self {
+            nodes,
+            num_hash_invocations: 0,
+        }
+    }
+
+    /// Check that `path` authenticates `leaf`, like `Path::verify` against
+    /// the root of the cache. Nodes of a valid path are added to the cache.
+    pub(crate) fn verify(
+        &mut self,
+        hash_params: &MTHashParameters<MT>,
+        path: &Path<MT>,
+        leaf: &MT::Leaf,
+    ) -> Result<bool, Error> {
+        let leaf_digest = MT::LeafHash::evaluate(&hash_params.leaf_hash_param, leaf)?;
+        let (left, right) = if path.leaf_index & 1 == 0 {
+            (leaf_digest, path.leaf_sibling_hash.clone())
+        } else {
+            (path.leaf_sibling_hash.clone(), leaf_digest)
+        };
+        let mut node = MT::TwoToOneHash::evaluate(
+            &hash_params.inner_hash_param,
+            MT::LeafInnerDigestConverter::convert(left)?,
+            MT::LeafInnerDigestConverter::convert(right)?,
+        )?;
+        self.num_hash_invocations += 2;
+
+        let mut depth = path.auth_path.len();
+        let mut index = path.leaf_index >> 1;
+        let mut new_nodes = Vec::new();
+        let valid = loop {
+            if let Some(cached) = self.nodes.get(&(depth, index)) {
+                break *cached == node;
+            }
+            if depth == 0 {
+                break false;
+            }
+            depth -= 1;
+            let sibling = &path.auth_path[depth];
+            let parent = if index & 1 == 0 {
+                MT::TwoToOneHash::compress(&hash_params.inner_hash_param, &node, sibling)?
+            } else {
+                MT::TwoToOneHash::compress(&hash_params.inner_hash_param, sibling, &node)?
+            };
+            self.num_hash_invocations += 1;
+            new_nodes.push(((depth + 1, index), node));
+            node = parent;
+            index >>= 1;
+        };
+        if valid {
+            self.nodes.extend(new_nodes);
+        }
+        Ok(valid)
+    }
+}
@@ -0,0 +1,171 @@
+use ark_crypto_primitives::merkle_tree::Config as MTConfig;
+use ark_ff::PrimeField;
+use ark_sponge::{
+    poseidon::{PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge,
+};
+use ark_std::{boxed::Box, marker::PhantomData};
+
+use crate::{
+    bcs::{prover::BCSProof, verifier::BCSVerifier, MTHashParameters},
+    iop::{
+        prover::IOPProver,
+        verifier::{IOPVerifier, IOPVerifierForProver},
+    },
+    ldt::LDT,
+    BCSError, Error,
+};
+
+/// Everything BCS needs to generate or verify a proof, besides the IOP
+/// parameters and inputs.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig, S: CryptographicSponge"))]
+pub struct BCSConfig<MT, S, F, L>
+where
+    MT: MTConfig<Leaf = [F]>,
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+    L: LDT<F>,
+{
+    /// Initial state of the sponge. Each proof generation and verification
+    /// starts with a clone of this sponge.
+    pub sponge: S,
+    /// Merkle tree hash parameters.
+    pub hash_params: MTHashParameters<MT>,
+    /// LDT parameters.
+    pub ldt_params: L::LDTParameters,
+}
+
+impl<MT, S, F, L> BCSConfig<MT, S, F, L>
+where
+    MT: MTConfig<Leaf = [F]>,
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+    L: LDT<F>,
+{
+    /// Bundle the sponge, merkle tree hash parameters and LDT parameters.
+    /// The sponge is not checked; use `new_with_poseidon` to check the
+    /// parameters of a Poseidon sponge.
+    pub fn new(sponge: S, hash_params: MTHashParameters<MT>, ldt_params: L::LDTParameters) -> Self {
+        Self {
+            sponge,
+            hash_params,
+            ldt_params,
+        }
+    }
+}
+
+impl<MT, F, L> BCSConfig<MT, PoseidonSponge<F>, F, L>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    L: LDT<F>,
+{
+    /// Bundle a Poseidon sponge using `sponge_params` with merkle tree hash
+    /// parameters and LDT parameters, after checking the sponge parameters
+    /// with `validate_poseidon_parameters`.
+    pub fn new_with_poseidon(
+        sponge_params: &PoseidonConfig<F>,
+        security_bits: usize,
+        hash_params: MTHashParameters<MT>,
+        ldt_params: L::LDTParameters,
+    ) -> Result<Self, Error> {
+        validate_poseidon_parameters(sponge_params, security_bits)?;
+        Ok(Self::new(
+            PoseidonSponge::new(sponge_params),
+            hash_params,
+            ldt_params,
+        ))
+    }
+}
+
+/// Check that a transcript using a Poseidon sponge with `params` can absorb
+/// and squeeze field elements, i.e. rate is at least 1, and that the capacity
+/// holds at least `2 * security_bits` bits, as a sponge with `c` bits of
+/// capacity gives at most `c / 2` bits of security. Returns
+/// `BCSError::InvalidSpongeParameters` otherwise.
+///
+/// `BCSConfig::new_with_poseidon` is the only entry point that runs this
+/// check. A sponge given to `BCSConfig::new`, `BCSProof::generate` or
+/// `BCSVerifier::verify` is used as is, so a caller constructing its own
+/// Poseidon sponge should call this on its parameters first.
+pub fn validate_poseidon_parameters<F: PrimeField>(
+    params: &PoseidonConfig<F>,
+    security_bits: usize,
+) -> Result<(), Error> {
+    let field_bits = F::MODULUS_BIT_SIZE as usize;
+    if params.rate == 0 || params.capacity * field_bits < 2 * security_bits {
+        return Err(Box::new(BCSError::InvalidSpongeParameters {
+            rate: params.rate,
+            capacity: params.capacity,
+            field_bits,
+            security_bits,
+        }));
+    }
+    Ok(())
+}
+
+/// High-level API to generate and verify BCS proofs using a `BCSConfig`,
+/// without constructing transcripts manually.
+pub struct BCS<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    _merkle_tree_config: PhantomData<MT>,
+    _field: PhantomData<F>,
+}
+
+impl<MT, F> BCS<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    /// Generate a BCS proof of IOP prover `P`, whose verifier is `V`.
+    pub fn prove<V, P, L, S>(
+        prover_parameter: &P::ProverParameter,
+        public_input: &P::PublicInput,
+        private_input: &P::PrivateInput,
+        config: &BCSConfig<MT, S, F, L>,
+    ) -> Result<BCSProof<MT, F>, Error>
+    where
+        V: IOPVerifierForProver<S, F, P>,
+        P: IOPProver<F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        BCSProof::generate::<V, P, L, S>(
+            config.sponge.clone(),
+            public_input,
+            private_input,
+            prover_parameter,
+            &config.ldt_params,
+            config.hash_params.clone(),
+        )
+    }
+
+    /// Verify a BCS proof generated by `prove` using the same `config`.
+    /// Returns the output of IOP verifier `V`.
+    pub fn verify<V, L, S>(
+        proof: &BCSProof<MT, F>,
+        verifier_parameter: &V::VerifierParameter,
+        public_input: &V::PublicInput,
+        config: &BCSConfig<MT, S, F, L>,
+    ) -> Result<bool, Error>
+    where
+        V: IOPVerifier<S, F, VerifierOutput = bool>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        BCSVerifier::verify::<V, L, S>(
+            config.sponge.clone(),
+            proof,
+            public_input,
+            verifier_parameter,
+            &config.ldt_params,
+            config.hash_params.clone(),
+        )
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    bcs::{constraints::proof::BCSProofVar, transcript::LDTInfo},
+    bcs::{
+        constraints::proof::BCSProofVar,
+        transcript::{round_structure, LDTInfo, TranscriptVersion},
+    },
     iop::{
         bookkeeper::{MessageBookkeeper, NameSpace},
         constraints::{
@@ -13,7 +16,7 @@ use crate::{
 use ark_crypto_primitives::merkle_tree::{constraints::ConfigGadget, Config};
 use ark_ff::PrimeField;
 use ark_ldt::domain::Radix2CosetDomain;
-use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::{fields::fp::FpVar, uint8::UInt8};
 use ark_relations::r1cs::SynthesisError;
 use ark_sponge::{
     constraints::{AbsorbGadget, CryptographicSpongeVar, SpongeWithGadget},
@@ -62,12 +65,18 @@ where
 {
     pub(crate) fn new_transcript(
         bcs_proof: &'a BCSProofVar<MT, MTG, F>,
-        sponge: S::Var,
+        mut sponge: S::Var,
         ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
         ldt_localization_parameter: Option<usize>,
         trace: TraceInfo,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, SynthesisError> {
+        let bookkeeper = MessageBookkeeper::new(trace);
+        sponge.absorb(&UInt8::constant_vec(&bookkeeper.domain_separator(0)))?;
+        // only the default version is supported in constraints
+        if let Some(separator) = TranscriptVersion::default().domain_separator() {
+            sponge.absorb(&UInt8::constant_vec(separator))?;
+        }
+        Ok(Self {
             proof: bcs_proof,
             expected_prover_messages_info: Vec::new(),
             ldt_codeword_domain,
@@ -76,14 +85,24 @@ where
             current_prover_round: 0,
             reconstructed_verifier_messages: Vec::new(),
             pending_verifier_messages: Vec::new(),
-            bookkeeper: MessageBookkeeper::new(trace),
+            bookkeeper,
             registered_virtual_oracles: Vec::new(),
-        }
+        })
     }
 
-    /// Create a new namespace in this transcript
-    pub fn new_namespace(&mut self, current_namespace: NameSpace, trace: TraceInfo) -> NameSpace {
-        self.bookkeeper.new_namespace(trace, current_namespace.id)
+    /// Create a new namespace in this transcript. The sponge absorbs the
+    /// domain separator of the new namespace, in the same way as
+    /// `SimulationTranscript::new_namespace`.
+    pub fn new_namespace(
+        &mut self,
+        current_namespace: NameSpace,
+        trace: TraceInfo,
+    ) -> Result<NameSpace, SynthesisError> {
+        let namespace = self.bookkeeper.new_namespace(trace, current_namespace.id);
+        self.sponge.absorb(&UInt8::constant_vec(
+            &self.bookkeeper.domain_separator(namespace.id),
+        ))?;
+        Ok(namespace)
     }
 
     /// Number of submitted rounds in the transcript
@@ -91,6 +110,27 @@ where
         self.current_prover_round
     }
 
+    /// Receive prover's current round messages if `is_present` is true, and
+    /// return `None` otherwise. `is_present` should be derived from verifier
+    /// parameter, and must agree with the condition used by prover.
+    pub fn receive_optional_prover_current_round(
+        &mut self,
+        ns: NameSpace,
+        expected_message_info: ProverRoundMessageInfo,
+        is_present: bool,
+        trace: TraceInfo,
+    ) -> Result<Option<MsgRoundRef>, SynthesisError> {
+        if is_present {
+            Ok(Some(self.receive_prover_current_round(
+                ns,
+                expected_message_info,
+                trace,
+            )?))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Receive prover's current round messages, which can possibly contain
     /// multiple oracles with same size. This function will absorb the
     /// merkle tree root and short messages (if any).
@@ -220,7 +260,15 @@ where
             );
         }
 
-        // absorb merkle tree root, if any
+        // absorb round structure and merkle tree root, if any
+        if TranscriptVersion::default().absorbs_round_structure() && num_oracles_expected > 0 {
+            self.sponge.absorb(
+                &round_structure::<F>(&expected_message_info)
+                    .into_iter()
+                    .map(FpVar::Constant)
+                    .collect::<Vec<_>>(),
+            )?;
+        }
         self.sponge
             .absorb(&self.proof.prover_messages_mt_root[index])?;
         // absorb short messages for this round, if any
@@ -323,6 +371,30 @@ where
         Ok(())
     }
 
+    /// Squeeze and discard `num_bytes` bytes, in the same way as
+    /// `Transcript::advance_sponge`. The step is attached to pending messages,
+    /// and need to be submitted through `submit_verifier_current_round`.
+    pub fn advance_sponge(&mut self, num_bytes: usize) -> Result<(), SynthesisError> {
+        self.sponge.squeeze_bytes(num_bytes)?;
+        self.pending_verifier_messages
+            .push(VerifierMessageVar::Advance(num_bytes));
+        Ok(())
+    }
+
+    /// Absorb public field elements prefixed by their number, in the same
+    /// way as `Transcript::absorb_public_field_elements`.
+    pub fn absorb_public_field_elements(
+        &mut self,
+        elements: &[FpVar<F>],
+    ) -> Result<(), SynthesisError> {
+        let length = FpVar::Constant(F::from(elements.len() as u64));
+        self.sponge.absorb(
+            &ark_std::iter::once(length)
+                .chain(elements.iter().cloned())
+                .collect::<Vec<_>>(),
+        )
+    }
+
     /// Returns if there is a verifier message for the transcript.
     pub fn is_pending_message_available(&self) -> bool {
         !self.pending_verifier_messages.is_empty()
@@ -62,7 +62,7 @@ where
             L::codeword_domain(ldt_params),
             L::localization_param(ldt_params),
             iop_trace!("BCS root"),
-        );
+        )?;
         let root_namespace = NameSpace::root(iop_trace!("IOP Root: BCS Proof Verify"));
 
         V::register_iop_structure_var(
@@ -77,7 +77,7 @@ where
 
         let codewords = transcript.bookkeeper.dump_all_prover_messages_in_order();
 
-        let ldt_namespace = transcript.new_namespace(root_namespace, iop_trace!("LDT"));
+        let ldt_namespace = transcript.new_namespace(root_namespace, iop_trace!("LDT"))?;
 
         let num_rs_oracles = codewords
             .clone()
@@ -179,12 +179,23 @@ where
                 } else {
                     return Ok(()); // no queries this round: no need to verify
                 };
+                let salts = &round_oracle.oracle.queried_salts;
+                assert_eq!(
+                    salts.len(),
+                    if round_oracle.info.hiding {
+                        round_oracle.oracle.queried_cosets.len()
+                    } else {
+                        0
+                    },
+                    "hiding round should have one salt per queried coset"
+                );
                 round_oracle
                     .coset_queries
                     .iter()
                     .zip(round_oracle.oracle.queried_cosets.iter())
                     .zip(paths.iter())
-                    .try_for_each(|((index, coset), path)| {
+                    .enumerate()
+                    .try_for_each(|(query, ((index, coset), path))| {
                         let mut path = path.clone();
                         let old_path = path.get_leaf_position().value().unwrap_or_default();
                         path.set_leaf_position(index.clone());
@@ -194,10 +205,11 @@ where
                             &hash_params.leaf_params,
                             &hash_params.inner_params,
                             mt_root,
-                            // flatten by concatenating cosets of all queries
-                            coset
-                                .iter()
-                                .flatten()
+                            // flatten by concatenating salt and cosets of all queries
+                            salts
+                                .get(query)
+                                .into_iter()
+                                .chain(coset.iter().flatten())
                                 .map(|x| x.clone())
                                 .collect::<Vec<_>>()
                                 .as_slice(),
@@ -0,0 +1,107 @@
+use ark_crypto_primitives::merkle_tree::Config as MTConfig;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_sponge::Absorb;
+use ark_std::{boxed::Box, mem::take, vec::Vec};
+
+use crate::{bcs::prover::BCSProof, BCSError, Error};
+
+/// A BCS proof where identical short messages are stored once. Created by
+/// `BCSProof::intern_short_messages`, and verified by
+/// `BCSVerifier::verify_interned`.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig, F: PrimeField"))]
+pub struct InternedBCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// The proof, where all prover rounds have no short messages.
+    pub proof: BCSProof<MT, F>,
+    /// Distinct short messages of the proof, in order of first appearance.
+    pub short_messages: Vec<Vec<F>>,
+    /// Indices into `short_messages`, with axes `[prover round, short
+    /// message index]`.
+    pub short_message_indices: Vec<Vec<u32>>,
+}
+
+impl<MT, F> BCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Store each distinct short message of the proof once, and refer to it
+    /// by index from every round sending it. This only changes the encoding:
+    /// `InternedBCSProof::to_proof` recovers `self`, so the sponge absorbs
+    /// the same messages when verifying.
+    pub fn intern_short_messages(&self) -> InternedBCSProof<MT, F> {
+        let mut proof = self.clone();
+        let mut short_messages: Vec<Vec<F>> = Vec::new();
+        let mut short_message_indices =
+            Vec::with_capacity(proof.prover_iop_messages_by_round.len());
+        for round in proof.prover_iop_messages_by_round.iter_mut() {
+            let mut indices = Vec::with_capacity(round.short_messages.len());
+            for message in take(&mut round.short_messages) {
+                let index = match short_messages.iter().position(|m| *m == message) {
+                    Some(index) => index,
+                    None => {
+                        short_messages.push(message);
+                        short_messages.len() - 1
+                    },
+                };
+                indices.push(index as u32);
+            }
+            short_message_indices.push(indices);
+        }
+        InternedBCSProof {
+            proof,
+            short_messages,
+            short_message_indices,
+        }
+    }
+}
+
+impl<MT, F> InternedBCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Recover the proof given to `BCSProof::intern_short_messages`. Returns
+    /// `BCSError::MalformedProof` if a round has short messages of its own,
+    /// or refers to a missing short message.
+    pub fn to_proof(&self) -> Result<BCSProof<MT, F>, Error> {
+        let mut proof = self.proof.clone();
+        let num_rounds = proof.prover_iop_messages_by_round.len();
+        if self.short_message_indices.len() != num_rounds {
+            return Err(Box::new(BCSError::MalformedProof {
+                round: num_rounds.min(self.short_message_indices.len()),
+                reason: "number of short message index lists is not number of rounds",
+            }));
+        }
+        for (round, (message, indices)) in proof
+            .prover_iop_messages_by_round
+            .iter_mut()
+            .zip(&self.short_message_indices)
+            .enumerate()
+        {
+            let malformed =
+                |reason| -> Error { Box::new(BCSError::MalformedProof { round, reason }) };
+            if !message.short_messages.is_empty() {
+                return Err(malformed("interned round has short messages"));
+            }
+            message.short_messages = indices
+                .iter()
+                .map(|&index| {
+                    self.short_messages
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or_else(|| malformed("missing short message"))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+        }
+        Ok(proof)
+    }
+}
@@ -0,0 +1,172 @@
+use ark_crypto_primitives::{merkle_tree::Config as MTConfig, CRHScheme, Path};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_sponge::Absorb;
+use ark_std::{
+    boxed::Box,
+    io::{Read, Write},
+    vec::Vec,
+};
+
+use crate::{
+    bcs::{prover::BCSProof, MTHashParameters},
+    iop::oracles::SuccinctRoundMessage,
+    BCSError, Error,
+};
+
+/// An authentication path, where the leaf sibling hash is omitted if it is
+/// the hash of another leaf queried in the same round.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct MinimalPath<MT: MTConfig> {
+    leaf_index: usize,
+    leaf_sibling_hash: Option<MT::LeafDigest>,
+    auth_path: Vec<MT::InnerDigest>,
+}
+
+/// Encoding used by `BCSProof::serialize_minimal`.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct MinimalBCSProof<MT: MTConfig, F: PrimeField> {
+    prover_iop_messages_by_round: Vec<SuccinctRoundMessage<F>>,
+    prover_messages_mt_root: Vec<Option<MT::InnerDigest>>,
+    prover_oracles_mt_path: Vec<Vec<MinimalPath<MT>>>,
+}
+
+/// Hash of the leaf `leaf_index ^ 1` if it is in `leaf_indices`, where
+/// `cosets[i]` is the leaf at `leaf_indices[i]`, prefixed with `salts[i]` if
+/// the round is hiding.
+fn sibling_leaf_hash<MT, F>(
+    hash_params: &MTHashParameters<MT>,
+    leaf_indices: &[usize],
+    cosets: &[Vec<Vec<F>>],
+    salts: &[F],
+    leaf_index: usize,
+) -> Result<Option<MT::LeafDigest>, Error>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField,
+{
+    let position = match leaf_indices.iter().position(|&i| i == leaf_index ^ 1) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    let leaf = match cosets.get(position) {
+        Some(coset) => salts
+            .get(position)
+            .into_iter()
+            .chain(coset.iter().flatten())
+            .copied()
+            .collect::<Vec<_>>(),
+        None => return Ok(None),
+    };
+    Ok(Some(MT::LeafHash::evaluate(
+        &hash_params.leaf_hash_param,
+        leaf.as_slice(),
+    )?))
+}
+
+impl<MT, F> BCSProof<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Serialize `self` like `serialize_compressed`, but omit the data that
+    /// verifier can recompute: the leaf sibling hash of each authentication
+    /// path whose sibling leaf is also queried in the same round. Merkle
+    /// roots, including the ones of FRI layers, cannot be recomputed, as
+    /// verifier only sees the queried cosets. Leaves of hiding rounds are
+    /// prefixed with the queried salts in the proof. Parse the result using
+    /// `deserialize_minimal` with the same `hash_params`.
+    pub fn serialize_minimal<W: Write>(
+        &self,
+        hash_params: &MTHashParameters<MT>,
+        writer: W,
+    ) -> Result<(), Error> {
+        let prover_oracles_mt_path = self
+            .prover_oracles_mt_path
+            .iter()
+            .zip(self.prover_iop_messages_by_round.iter())
+            .map(|(paths, message)| {
+                let leaf_indices = paths.iter().map(|p| p.leaf_index).collect::<Vec<_>>();
+                paths
+                    .iter()
+                    .map(|path| {
+                        let recomputed = sibling_leaf_hash(
+                            hash_params,
+                            &leaf_indices,
+                            &message.queried_cosets,
+                            &message.queried_salts,
+                            path.leaf_index,
+                        )?;
+                        let leaf_sibling_hash = match recomputed {
+                            Some(hash) if hash == path.leaf_sibling_hash => None,
+                            _ => Some(path.leaf_sibling_hash.clone()),
+                        };
+                        Ok(MinimalPath {
+                            leaf_index: path.leaf_index,
+                            leaf_sibling_hash,
+                            auth_path: path.auth_path.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        MinimalBCSProof::<MT, F> {
+            prover_iop_messages_by_round: self.prover_iop_messages_by_round.clone(),
+            prover_messages_mt_root: self.prover_messages_mt_root.clone(),
+            prover_oracles_mt_path,
+        }
+        .serialize_compressed(writer)?;
+        Ok(())
+    }
+
+    /// Parse a proof serialized using `serialize_minimal`, and recompute the
+    /// omitted leaf sibling hashes using `hash_params`. Returns
+    /// `BCSError::MalformedProof` if an omitted hash has no sibling leaf.
+    pub fn deserialize_minimal<R: Read>(
+        reader: R,
+        hash_params: &MTHashParameters<MT>,
+    ) -> Result<Self, Error> {
+        let minimal = MinimalBCSProof::<MT, F>::deserialize_compressed(reader)?;
+        let prover_oracles_mt_path = minimal
+            .prover_oracles_mt_path
+            .into_iter()
+            .zip(minimal.prover_iop_messages_by_round.iter())
+            .enumerate()
+            .map(|(round, (paths, message))| {
+                let leaf_indices = paths.iter().map(|p| p.leaf_index).collect::<Vec<_>>();
+                paths
+                    .into_iter()
+                    .map(|path| {
+                        let leaf_sibling_hash = match path.leaf_sibling_hash {
+                            Some(hash) => hash,
+                            None => sibling_leaf_hash(
+                                hash_params,
+                                &leaf_indices,
+                                &message.queried_cosets,
+                                &message.queried_salts,
+                                path.leaf_index,
+                            )?
+                            .ok_or_else(|| -> Error {
+                                Box::new(BCSError::MalformedProof {
+                                    round,
+                                    reason: "omitted leaf sibling hash has no queried sibling",
+                                })
+                            })?,
+                        };
+                        Ok(Path {
+                            leaf_sibling_hash,
+                            auth_path: path.auth_path,
+                            leaf_index: path.leaf_index,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(BCSProof {
+            prover_iop_messages_by_round: minimal.prover_iop_messages_by_round,
+            prover_messages_mt_root: minimal.prover_messages_mt_root,
+            prover_oracles_mt_path,
+        })
+    }
+}
@@ -1,9 +1,24 @@
 use ark_crypto_primitives::merkle_tree::{Config as MTConfig, LeafParam, TwoToOneParam};
 
+/// Aggregation of BCS proofs sharing merkle roots.
+pub mod aggregation;
+#[cfg(feature = "blake3")]
+/// Merkle tree config using BLAKE3, for native-only verification.
+pub mod blake3_mt;
+/// Merkle commitments to oracles, independent of the transcript.
+pub mod commitment;
+/// High-level API bundling all BCS parameters.
+pub mod config;
+/// Proof encoding storing identical short messages once.
+pub mod interning;
+/// Proof encoding omitting data that verifier can recompute.
+mod minimal;
 /// BCS prover.
 pub mod prover;
 /// BCS simulation transcript used by IOP Verifier.
 pub mod simulation_transcript;
+/// Merkle trees of oracles whose elements are mostly a default value.
+pub mod sparse_mt;
 /// BCS transcript used by IOP Prover.
 pub mod transcript;
 /// BCS verifier.
@@ -16,6 +31,23 @@ pub mod constraints;
 pub(crate) mod tests;
 
 /// Specify the merkle tree hash parameters used for this protocol.
+///
+/// Each merkle tree leaf is hashed with one call to the leaf hash. To hash
+/// more elements per call (e.g. to fill the rate of a Poseidon sponge when
+/// oracles are short), increase the localization parameter of the round:
+/// a leaf then contains a coset of `2 ^ localization_parameter` elements of
+/// every oracle in the round, and the tree has fewer leaves. Larger leaves
+/// make leaf hashing cheaper per element, but each query reveals the whole
+/// coset, so proofs get larger. There is deliberately no separate leaf batch
+/// size: hashing `2 ^ k` cosets in one leaf gives the same leaves as
+/// increasing the localization parameter by `k`, so it would only be a second
+/// encoding of the same tree.
+///
+/// The merkle tree hash is independent of the Fiat-Shamir sponge: the merkle
+/// tree config `MT` of `BCSProof` and `BCSVerifier` only requires
+/// `MT::InnerDigest: Absorb`, so that the sponge can absorb merkle roots. For
+/// example, `blake3_mt::Blake3MTConfig` hashes with BLAKE3 and its digest is
+/// absorbed by the sponge as bytes.
 #[derive(Derivative)]
 #[derivative(Clone(bound = "P: MTConfig"))]
 pub struct MTHashParameters<P: MTConfig> {
@@ -1,18 +1,22 @@
 use crate::{
-    bcs::{transcript::Transcript, MTHashParameters},
+    bcs::{
+        transcript::{Transcript, TranscriptVersion},
+        verifier::ProofLimits,
+        MTHashParameters,
+    },
     iop::{
         bookkeeper::NameSpace, message::MessagesCollection, oracles::SuccinctRoundMessage,
         prover::IOPProver, verifier::IOPVerifierForProver, ProverParam,
     },
-    ldt::{NoLDT, LDT},
-    Error,
+    ldt::{ldt_params_with_num_queries, NoLDT, LDT},
+    BCSError, Error,
 };
 use ark_crypto_primitives::{merkle_tree::Config as MTConfig, Path};
 use ark_ff::PrimeField;
 use ark_ldt::domain::Radix2CosetDomain;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_sponge::{Absorb, CryptographicSponge};
-use ark_std::vec::Vec;
+use ark_std::{boxed::Box, convert::TryFrom, fmt, vec, vec::Vec};
 
 /// BCSProof contains all prover messages that use succinct oracle, and thus is
 /// itself succinct.
@@ -41,12 +45,357 @@ where
     pub prover_oracles_mt_path: Vec<Vec<Path<MT>>>,
 }
 
+impl<MT, F> PartialEq for BCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    fn eq(&self, other: &Self) -> bool {
+        // `Path` does not implement `PartialEq`, so compare its fields
+        let path_eq = |a: &Path<MT>, b: &Path<MT>| {
+            a.leaf_index == b.leaf_index
+                && a.leaf_sibling_hash == b.leaf_sibling_hash
+                && a.auth_path == b.auth_path
+        };
+        self.prover_iop_messages_by_round == other.prover_iop_messages_by_round
+            && self.prover_messages_mt_root == other.prover_messages_mt_root
+            && self.prover_oracles_mt_path.len() == other.prover_oracles_mt_path.len()
+            && self
+                .prover_oracles_mt_path
+                .iter()
+                .zip(other.prover_oracles_mt_path.iter())
+                .all(|(a, b)| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| path_eq(a, b)))
+    }
+}
+
+impl<MT, F> Eq for BCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+}
+
+/// `BCSError::MalformedProof` for prover round `round`.
+fn malformed(round: usize, reason: &'static str) -> Result<(), Error> {
+    Err(Box::new(BCSError::MalformedProof { round, reason }))
+}
+
+impl<MT, F> TryFrom<&[u8]> for BCSProof<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    type Error = Error;
+
+    /// Same as `BCSProof::try_from_bytes`.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::try_from_bytes(bytes)
+    }
+}
+
 impl<MT, F> BCSProof<MT, F>
 where
     MT: MTConfig<Leaf = [F]>,
     F: PrimeField + Absorb,
     MT::InnerDigest: Absorb,
 {
+    /// Cheaply check that the proof is structurally possible, so that junk
+    /// proofs are rejected before running the verifier. Returns
+    /// `BCSError::MalformedProof` if the proof has a different number of
+    /// rounds, roots and paths, an all-zero (default) merkle root, queried
+    /// cosets without a merkle root, a different number of paths and queried
+    /// cosets, salts that are not one per queried coset, or queried cosets
+    /// that are empty or of different shapes.
+    /// Rounds without merkle root and paths are taken as committed inline, so
+    /// their cosets are checked when verifying.
+    pub fn validate_structure(&self) -> Result<(), Error> {
+        let num_rounds = self.prover_iop_messages_by_round.len();
+        if self.prover_messages_mt_root.len() != num_rounds {
+            return malformed(num_rounds, "number of merkle roots is not number of rounds");
+        }
+        if self.prover_oracles_mt_path.len() != num_rounds {
+            return malformed(num_rounds, "number of path lists is not number of rounds");
+        }
+        for (round, paths) in self.prover_oracles_mt_path.iter().enumerate() {
+            self.validate_round(round, Some(paths.len()))?;
+        }
+        Ok(())
+    }
+
+    /// Check the structure of the proof like `validate_structure`, except
+    /// for the paths, which have not been read yet when verifying a stream.
+    /// `validate_round` should be called with the number of paths of each
+    /// round once it is known.
+    pub(crate) fn validate_structure_without_paths(&self) -> Result<(), Error> {
+        let num_rounds = self.prover_iop_messages_by_round.len();
+        if self.prover_messages_mt_root.len() != num_rounds {
+            return malformed(num_rounds, "number of merkle roots is not number of rounds");
+        }
+        (0..num_rounds).try_for_each(|round| self.validate_round(round, None))
+    }
+
+    /// Check the structure of prover round `round`, which has `num_paths`
+    /// paths. Checks that depend on the paths are skipped if `num_paths` is
+    /// `None`.
+    pub(crate) fn validate_round(
+        &self,
+        round: usize,
+        num_paths: Option<usize>,
+    ) -> Result<(), Error> {
+        let message = &self.prover_iop_messages_by_round[round];
+        let root = &self.prover_messages_mt_root[round];
+        if root.as_ref() == Some(&MT::InnerDigest::default()) {
+            return malformed(round, "merkle root is zero");
+        }
+        // a round with oracles committed inline has all cosets, but no
+        // merkle root and no paths
+        let inline = root.is_none() && num_paths == Some(0);
+        if let Some(num_paths) = num_paths {
+            if !inline && num_paths != message.queried_cosets.len() {
+                return malformed(round, "number of paths is not number of queried cosets");
+            }
+        }
+        if !message.queried_salts.is_empty()
+            && (inline || message.queried_salts.len() != message.queried_cosets.len())
+        {
+            return malformed(round, "number of salts is not number of queried cosets");
+        }
+        if let Some(first_coset) = message.queried_cosets.first() {
+            if root.is_none() && num_paths.map_or(false, |num_paths| num_paths > 0) {
+                return malformed(round, "queried cosets have no merkle root");
+            }
+            if first_coset.is_empty() || first_coset[0].is_empty() {
+                return malformed(round, "queried coset is empty");
+            }
+            let coset_length = first_coset[0].len();
+            if !message.queried_cosets.iter().all(|coset| {
+                coset.len() == first_coset.len()
+                    && coset.iter().all(|oracle| oracle.len() == coset_length)
+            }) {
+                return malformed(round, "queried cosets have different shapes");
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterate over the answers of all queries in the proof, in the order
+    /// they were made. Each item is `(namespace, round, position, answer)`,
+    /// where `round` is the index of the prover round in `namespace`, as used
+    /// by `tamper_answer` and `MessagesCollection::prover_round`, and
+    /// `answer[j]` is the element at `position` of oracle `j` in the round.
+    /// Every element of a queried coset is one answer, and all cosets of a
+    /// round committed inline are answers.
+    ///
+    /// Namespaces and oracle lengths are not part of the proof, so
+    /// `transcript` should be a transcript the prover of the proof has run its
+    /// commit phase on. Rounds of the proof that are not in `transcript`, e.g.
+    /// LDT rounds when only the commit phase of the protocol is run, are
+    /// skipped.
+    ///
+    /// # Panics
+    /// Panics if a round with a merkle tree has fewer paths than queried
+    /// cosets, which `validate_structure` rejects.
+    pub fn iter_query_answers<'a, S: CryptographicSponge>(
+        &'a self,
+        transcript: &'a Transcript<MT, S, F>,
+    ) -> impl Iterator<Item = (NameSpace, usize, usize, Vec<F>)> + 'a {
+        let bookkeeper = &transcript.bookkeeper;
+        let mut round_in_namespace = vec![None; transcript.prover_message_oracles.len()];
+        for (id, indices) in &bookkeeper.messages_store {
+            for (round, round_ref) in indices.prover_rounds.iter().enumerate() {
+                if !round_ref.is_virtual {
+                    round_in_namespace[round_ref.index] = Some((bookkeeper.ns_details[id], round));
+                }
+            }
+        }
+        self.prover_iop_messages_by_round
+            .iter()
+            .zip(self.prover_oracles_mt_path.iter())
+            .zip(transcript.prover_message_oracles.iter())
+            .zip(round_in_namespace)
+            .filter_map(|(((message, paths), oracle), location)| {
+                location.map(|(namespace, round)| (message, paths, oracle, namespace, round))
+            })
+            .flat_map(|(message, paths, oracle, namespace, round)| {
+                let num_cosets = oracle.info.length >> oracle.info.localization_parameter;
+                message
+                    .queried_cosets
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(query, coset)| {
+                        // cosets of a round committed inline are sent in order
+                        let coset_index = if oracle.inline {
+                            query
+                        } else {
+                            paths[query].leaf_index
+                        };
+                        let coset_length = coset.first().map_or(0, Vec::len);
+                        (0..coset_length).map(move |element_index| {
+                            (
+                                namespace,
+                                round,
+                                coset_index + element_index * num_cosets,
+                                coset
+                                    .iter()
+                                    .map(|elements| elements[element_index])
+                                    .collect(),
+                            )
+                        })
+                    })
+            })
+    }
+
+    /// Check that the proof is within `limits`. Returns
+    /// `BCSError::ResourceLimitExceeded` for the first exceeded limit.
+    pub fn check_limits(&self, limits: &ProofLimits) -> Result<(), Error> {
+        let num_queries = self
+            .prover_iop_messages_by_round
+            .iter()
+            .map(|message| message.queried_cosets.len())
+            .sum::<usize>();
+        let num_elements = self
+            .prover_iop_messages_by_round
+            .iter()
+            .map(|message| {
+                message
+                    .queried_cosets
+                    .iter()
+                    .flatten()
+                    .chain(message.short_messages.iter())
+                    .map(|elements| elements.len())
+                    .sum::<usize>()
+                    + message.queried_salts.len()
+            })
+            .sum::<usize>();
+        limits.check_rounds(self.prover_iop_messages_by_round.len())?;
+        limits.check_queries(num_queries)?;
+        limits.check_elements(num_elements)
+    }
+
+    /// Strictly parse a proof serialized using `serialize_compressed`. Unlike
+    /// `CanonicalDeserialize`, this checks that the numbers of roots and
+    /// paths agree with the number of rounds and queried cosets before
+    /// reading them, that the proof passes `validate_structure`, and that
+    /// `bytes` has no trailing bytes. Returns `BCSError::ProofParseError`
+    /// with the offset of the item that cannot be parsed.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::try_from_bytes_with_limits(bytes, &ProofLimits::unlimited())
+    }
+
+    /// Strictly parse a proof like `try_from_bytes`, and return
+    /// `BCSError::ResourceLimitExceeded` as soon as a length read from
+    /// `bytes` exceeds `limits`, before allocating for it.
+    pub fn try_from_bytes_with_limits(bytes: &[u8], limits: &ProofLimits) -> Result<Self, Error> {
+        let mut reader = bytes;
+        let parse_error = |reader: &&[u8], reason| -> Error {
+            Box::new(BCSError::ProofParseError {
+                offset: bytes.len() - reader.len(),
+                reason,
+            })
+        };
+        fn read<T: CanonicalDeserialize>(reader: &mut &[u8]) -> Result<T, &'static str> {
+            T::deserialize_compressed(reader).map_err(|e| match e {
+                SerializationError::IoError(_) => "unexpected end of input",
+                _ => "invalid encoding",
+            })
+        }
+        let read_length = |reader: &mut &[u8], expected: Option<usize>| -> Result<usize, Error> {
+            let start = *reader;
+            let length = read::<u64>(reader).map_err(|reason| parse_error(&start, reason))?;
+            match expected {
+                // every item takes at least one byte
+                None if length > reader.len() as u64 => {
+                    Err(parse_error(&start, "length exceeds remaining input"))
+                },
+                Some(expected) if length != expected as u64 => Err(parse_error(
+                    &start,
+                    "length is inconsistent with previous items",
+                )),
+                _ => Ok(length as usize),
+            }
+        };
+        // `num_elements` counts field elements read so far
+        let read_elements =
+            |reader: &mut &[u8], num_elements: &mut usize| -> Result<Vec<F>, Error> {
+                let length = read_length(reader, None)?;
+                *num_elements += length;
+                limits.check_elements(*num_elements)?;
+                (0..length)
+                    .map(|_| {
+                        let start = *reader;
+                        read::<F>(reader).map_err(|reason| parse_error(&start, reason))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            };
+
+        let num_rounds = read_length(&mut reader, None)?;
+        limits.check_rounds(num_rounds)?;
+        let mut num_queries = 0;
+        let mut num_elements = 0;
+        let mut prover_iop_messages_by_round = Vec::with_capacity(num_rounds);
+        for _ in 0..num_rounds {
+            let num_cosets = read_length(&mut reader, None)?;
+            num_queries += num_cosets;
+            limits.check_queries(num_queries)?;
+            let mut queried_cosets = Vec::with_capacity(num_cosets);
+            for _ in 0..num_cosets {
+                let num_oracles = read_length(&mut reader, None)?;
+                let mut coset = Vec::with_capacity(num_oracles);
+                for _ in 0..num_oracles {
+                    coset.push(read_elements(&mut reader, &mut num_elements)?);
+                }
+                queried_cosets.push(coset);
+            }
+            let num_short_messages = read_length(&mut reader, None)?;
+            let mut short_messages = Vec::with_capacity(num_short_messages);
+            for _ in 0..num_short_messages {
+                short_messages.push(read_elements(&mut reader, &mut num_elements)?);
+            }
+            let queried_salts = read_elements(&mut reader, &mut num_elements)?;
+            prover_iop_messages_by_round.push(SuccinctRoundMessage {
+                queried_cosets,
+                short_messages,
+                queried_salts,
+            });
+        }
+        read_length(&mut reader, Some(num_rounds))?;
+        let mut prover_messages_mt_root = Vec::with_capacity(num_rounds);
+        for _ in 0..num_rounds {
+            let start = reader;
+            prover_messages_mt_root.push(
+                read::<Option<MT::InnerDigest>>(&mut reader)
+                    .map_err(|reason| parse_error(&start, reason))?,
+            );
+        }
+        read_length(&mut reader, Some(num_rounds))?;
+        let mut prover_oracles_mt_path = Vec::with_capacity(num_rounds);
+        for message in prover_iop_messages_by_round.iter() {
+            let num_paths = read_length(&mut reader, Some(message.queried_cosets.len()))?;
+            let mut paths = Vec::with_capacity(num_paths);
+            for _ in 0..num_paths {
+                let start = reader;
+                paths.push(
+                    read::<Path<MT>>(&mut reader).map_err(|reason| parse_error(&start, reason))?,
+                );
+            }
+            prover_oracles_mt_path.push(paths);
+        }
+        if !reader.is_empty() {
+            return Err(parse_error(&reader, "trailing bytes after proof"));
+        }
+
+        let proof = BCSProof {
+            prover_iop_messages_by_round,
+            prover_messages_mt_root,
+            prover_oracles_mt_path,
+        };
+        proof.validate_structure()?;
+        Ok(proof)
+    }
+
     /// Generate proof from any IOPProver and IOPVerifier with consistent
     /// parameter and public input.
     pub fn generate<V, P, L, S>(
@@ -57,6 +406,99 @@ where
         ldt_params: &L::LDTParameters,
         hash_params: MTHashParameters<MT>,
     ) -> Result<Self, Error>
+    where
+        L: LDT<F>,
+        P: IOPProver<F>,
+        V: IOPVerifierForProver<S, F, P>,
+        S: CryptographicSponge,
+    {
+        Self::generate_with_optional_fixed_queries::<V, P, L, S>(
+            sponge,
+            public_input,
+            private_input,
+            prover_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+        )
+    }
+
+    /// Generate proof like `generate`, but use transcript of `version`. The
+    /// proof can only be verified by `BCSVerifier::verify_with_version`
+    /// using the same version.
+    pub fn generate_with_version<V, P, L, S>(
+        sponge: S,
+        public_input: &P::PublicInput,
+        private_input: &P::PrivateInput,
+        prover_parameter: &P::ProverParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+    ) -> Result<Self, Error>
+    where
+        L: LDT<F>,
+        P: IOPProver<F>,
+        V: IOPVerifierForProver<S, F, P>,
+        S: CryptographicSponge,
+    {
+        Self::generate_with_optional_fixed_queries::<V, P, L, S>(
+            sponge,
+            public_input,
+            private_input,
+            prover_parameter,
+            ldt_params,
+            hash_params,
+            version,
+            None,
+        )
+    }
+
+    /// Generate proof like `generate`, but query every prover round at
+    /// `positions` instead of the positions derived from the sponge; see
+    /// `Transcript::with_fixed_queries`. The proof can only be verified by
+    /// `BCSVerifier::verify_with_fixed_queries` using the same `positions`.
+    ///
+    /// This is **unsound** and only meant for benchmarks.
+    #[cfg(feature = "bench")]
+    pub fn generate_with_fixed_queries<V, P, L, S>(
+        sponge: S,
+        public_input: &P::PublicInput,
+        private_input: &P::PrivateInput,
+        prover_parameter: &P::ProverParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        positions: &[usize],
+    ) -> Result<Self, Error>
+    where
+        L: LDT<F>,
+        P: IOPProver<F>,
+        V: IOPVerifierForProver<S, F, P>,
+        S: CryptographicSponge,
+    {
+        Self::generate_with_optional_fixed_queries::<V, P, L, S>(
+            sponge,
+            public_input,
+            private_input,
+            prover_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            Some(positions),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_with_optional_fixed_queries<V, P, L, S>(
+        sponge: S,
+        public_input: &P::PublicInput,
+        private_input: &P::PrivateInput,
+        prover_parameter: &P::ProverParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+        fixed_queries: Option<&[usize]>,
+    ) -> Result<Self, Error>
     where
         L: LDT<F>,
         P: IOPProver<F>,
@@ -64,17 +506,29 @@ where
         S: CryptographicSponge,
     {
         let verifier_parameter = prover_parameter.to_verifier_param();
+        let ldt_params = &ldt_params_with_num_queries::<F, L>(
+            ldt_params,
+            V::ldt_num_queries(&verifier_parameter),
+        );
+        L::validate_parameters(ldt_params)?;
 
         // create a BCS transcript
         let mut transcript = {
-            Transcript::new(
+            Transcript::new_with_version(
                 sponge,
                 hash_params,
                 L::codeword_domain(ldt_params),
                 L::localization_param(ldt_params),
+                version,
                 iop_trace!("BCS Proof Generation"),
             )
         };
+        #[cfg(feature = "bench")]
+        if let Some(positions) = fixed_queries {
+            transcript = transcript.with_fixed_queries(positions);
+        }
+        #[cfg(not(feature = "bench"))]
+        debug_assert!(fixed_queries.is_none());
 
         let root_namespace = NameSpace::root(iop_trace!("BCS Proof Generation: Commit Phase"));
 
@@ -119,6 +573,11 @@ where
             transcript.verifier_messages,
             transcript.bookkeeper,
         );
+        transcript_messages.version = transcript.version;
+        #[cfg(feature = "bench")]
+        {
+            transcript_messages.fixed_queries = transcript.fixed_queries;
+        }
 
         // run LDT verifier code to obtain all queries. We will use this query to
         // generate succinct oracles from message recording oracle.
@@ -161,7 +620,12 @@ where
         let all_mt_paths = all_queries
             .iter()
             .zip(merkle_trees.iter())
-            .map(|(queries, mt)| {
+            .zip(transcript_messages.real_oracles.iter())
+            .map(|((queries, mt), oracle)| {
+                // oracles committed inline are sent in entirety without paths
+                if oracle.inline {
+                    return Vec::new();
+                }
                 queries
                     .iter()
                     .map(|query| {
@@ -236,3 +700,85 @@ where
         )
     }
 }
+
+/// A wrapper of `BCSProof` whose `Debug` output renders the structure of the
+/// proof round by round: the merkle root, the number and shape of queried
+/// cosets and authentication paths, and a preview of each short message.
+///
+/// This is only for debugging. Namespaces and `ProverRoundMessageInfo` are
+/// not part of the proof; they are declared by the verifier in
+/// `register_iop_structure`.
+pub struct DebugProof<'a, MT, F>(pub &'a BCSProof<MT, F>)
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb;
+
+impl<'a, MT, F> DebugProof<'a, MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    /// Number of leading elements of a short message shown in the preview.
+    pub const SHORT_MESSAGE_PREVIEW_LENGTH: usize = 3;
+}
+
+impl<'a, MT, F> fmt::Debug for DebugProof<'a, MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let proof = self.0;
+        writeln!(
+            f,
+            "BCSProof: {} prover rounds",
+            proof.prover_iop_messages_by_round.len()
+        )?;
+        for (index, round) in proof.prover_iop_messages_by_round.iter().enumerate() {
+            writeln!(f, "  round {}:", index)?;
+            match proof.prover_messages_mt_root.get(index) {
+                Some(Some(root)) => writeln!(f, "    merkle root: {:?}", root)?,
+                Some(None) => writeln!(f, "    merkle root: none")?,
+                None => writeln!(f, "    merkle root: missing")?,
+            }
+            let num_paths = proof
+                .prover_oracles_mt_path
+                .get(index)
+                .map_or(0, |paths| paths.len());
+            match round.queried_cosets.first() {
+                Some(coset) => writeln!(
+                    f,
+                    "    queried cosets: {} ({} oracles x {} elements), {} authentication paths",
+                    round.queried_cosets.len(),
+                    coset.len(),
+                    coset.first().map_or(0, |oracle| oracle.len()),
+                    num_paths
+                )?,
+                None => writeln!(
+                    f,
+                    "    queried cosets: 0, {} authentication paths",
+                    num_paths
+                )?,
+            }
+            writeln!(f, "    short messages: {}", round.short_messages.len())?;
+            for (msg_index, msg) in round.short_messages.iter().enumerate() {
+                let preview = &msg[..msg.len().min(Self::SHORT_MESSAGE_PREVIEW_LENGTH)];
+                write!(
+                    f,
+                    "      [{}] {} elements: {:?}",
+                    msg_index,
+                    msg.len(),
+                    preview
+                )?;
+                if msg.len() > preview.len() {
+                    write!(f, " ..")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
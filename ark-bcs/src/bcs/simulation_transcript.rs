@@ -1,20 +1,79 @@
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
 use ark_ff::PrimeField;
 use ark_sponge::{Absorb, CryptographicSponge, FieldElementSize};
-use ark_std::vec::Vec;
+use ark_std::{vec, vec::Vec};
 use tracing::info;
 
 use crate::{
-    bcs::{prover::BCSProof, transcript::LDTInfo},
+    bcs::{
+        prover::BCSProof,
+        transcript::{
+            field_element_from_bits, fork_separator, inline_elements, length_prefixed,
+            round_structure, sample_permutation, sample_uniform_field_element, sponge_snapshot,
+            AbsorbOp, AbsorbedValue, HashToFieldConfig, LDTInfo, SpongeCheckpoints,
+            TranscriptVersion,
+        },
+    },
     iop::{
         bookkeeper::{MessageBookkeeper, NameSpace},
-        message::{LeavesType, MsgRoundRef, ProverRoundMessageInfo, VerifierMessage},
+        message::{LeavesType, Localization, MsgRoundRef, ProverRoundMessageInfo, VerifierMessage},
         oracles::{VirtualOracle, VirtualOracleWithInfo},
     },
     tracer::TraceInfo,
 };
 use ark_ldt::domain::Radix2CosetDomain;
-use ark_std::{boxed::Box, mem::take};
+use ark_std::{boxed::Box, mem::take, rand::RngCore};
+
+/// A source of verifier challenges that replaces the sponge in commit phase.
+/// This is for testing only: a protocol is sound only if verifier messages are
+/// squeezed from the sponge, so **never** use a challenge source in
+/// production.
+///
+/// A challenge source can be used by `SimulationTranscript` to check the
+/// behavior of a verifier under fixed or adversarial challenges, and by
+/// `Transcript` to run a prover interactively with truly random challenges.
+/// Only verifier messages are taken from the challenge source. Prover
+/// messages are still absorbed by the sponge, and the query phase still uses
+/// the sponge.
+pub trait ChallengeSource<F: PrimeField> {
+    /// Returns the next `num_elements` field elements.
+    fn next_field_elements(&mut self, num_elements: usize) -> Vec<F>;
+    /// Returns the next `num_bytes` bytes.
+    fn next_bytes(&mut self, num_bytes: usize) -> Vec<u8>;
+    /// Returns the next `num_bits` bits.
+    fn next_bits(&mut self, num_bits: usize) -> Vec<bool>;
+}
+
+/// A challenge source that samples verifier messages uniformly using `rng`,
+/// which plays the role of a live verifier in an interactive run.
+pub struct RngChallengeSource<R: RngCore> {
+    rng: R,
+}
+
+impl<R: RngCore> RngChallengeSource<R> {
+    /// Returns a challenge source using `rng`.
+    pub fn new(rng: R) -> Self {
+        Self { rng }
+    }
+}
+
+impl<F: PrimeField, R: RngCore> ChallengeSource<F> for RngChallengeSource<R> {
+    fn next_field_elements(&mut self, num_elements: usize) -> Vec<F> {
+        (0..num_elements).map(|_| F::rand(&mut self.rng)).collect()
+    }
+
+    fn next_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; num_bytes];
+        self.rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn next_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        (0..num_bits)
+            .map(|_| self.rng.next_u32() & 1 == 1)
+            .collect()
+    }
+}
 
 /// A wrapper for BCS proof, so that verifier can reconstruct verifier messages
 /// by simulating commit phase easily.
@@ -35,12 +94,25 @@ pub struct SimulationTranscript<
     // /// After absorb merkle tree root for this round, absorb the short messages
     // /// in entirety
     // prover_short_messages: Vec<&'a Vec<Vec<F>>>,
-    pub(crate) proof: &'a BCSProof<P, F>,
+    /// `None` if commit phase is simulated without a proof; see
+    /// `new_transcript_without_proof`.
+    pub(crate) proof: Option<&'a BCSProof<P, F>>,
 
     /// sponge is used to sample verifier message
     pub(crate) sponge: S,
     /// the next prover round message to absorb
     pub(crate) current_prover_round: usize,
+    /// index of the first prover round of this transcript in the proof. This
+    /// is zero unless this transcript is a fork.
+    pub(crate) prover_round_offset: usize,
+    /// Number of prover rounds declared by a fork in `fork`, or `None` if
+    /// this transcript is not a fork.
+    num_declared_prover_rounds: Option<usize>,
+    /// Number of prover rounds reserved in the proof by forks that are not
+    /// joined yet. They come right after the rounds of this transcript.
+    num_reserved_prover_rounds: usize,
+    /// Number of forks created by `fork`.
+    num_forks: u64,
 
     /// Those reconstructed messages will be used in query and decision phase
     pub(crate) reconstructed_verifier_messages: Vec<Vec<VerifierMessage<F>>>,
@@ -53,6 +125,25 @@ pub struct SimulationTranscript<
 
     /// Virtual oracle registered during commit phase simulation.
     pub(crate) registered_virtual_oracles: Vec<VirtualOracleWithInfo<F>>,
+    /// Proof indices of rounds registered by `expect_known_root` whose
+    /// committed root differs from the registered one.
+    pub(crate) unexpected_roots: Vec<usize>,
+
+    /// If set, verifier messages are taken from this source instead of the
+    /// sponge.
+    challenge_source: Option<Box<dyn ChallengeSource<F> + Send + 'a>>,
+    /// Mapping used by `squeeze_verifier_bytes_as_field`.
+    hash_to_field: HashToFieldConfig<F>,
+    /// Operations on `sponge`, in order.
+    absorption_script: Vec<AbsorbOp>,
+    /// Version of the absorption order.
+    pub(crate) version: TranscriptVersion,
+    /// If set, query phase uses these positions instead of the ones derived
+    /// from the sponge.
+    #[cfg(feature = "bench")]
+    pub(crate) fixed_queries: Option<Vec<usize>>,
+    /// Sponge states recorded by prover, checked by `assert_sponge_matches`.
+    sponge_checkpoints: SpongeCheckpoints,
 }
 
 impl<'a, P: MTConfig<Leaf = [F]>, S: CryptographicSponge, F: PrimeField + Absorb>
@@ -61,7 +152,10 @@ where
     P::InnerDigest: Absorb,
 {
     /// Returns a wrapper for BCS proof so that verifier can reconstruct
-    /// verifier messages by simulating commit phase easily.
+    /// verifier messages by simulating commit phase easily. As in
+    /// `Transcript::new`, `sponge` absorbs the domain separators of root
+    /// namespace and of the version first, and the default
+    /// `TranscriptVersion` is used.
     pub(crate) fn new_transcript(
         bcs_proof: &'a BCSProof<P, F>,
         sponge: S,
@@ -69,6 +163,66 @@ where
         ldt_localization_parameter: Option<usize>,
         trace: TraceInfo,
     ) -> Self {
+        Self::new_transcript_with_version(
+            bcs_proof,
+            sponge,
+            ldt_codeword_domain,
+            ldt_localization_parameter,
+            TranscriptVersion::default(),
+            trace,
+        )
+    }
+
+    /// Returns a wrapper for BCS proof like `new_transcript`, but using
+    /// `version`, which should be the version of the prover transcript.
+    pub(crate) fn new_transcript_with_version(
+        bcs_proof: &'a BCSProof<P, F>,
+        sponge: S,
+        ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
+        ldt_localization_parameter: Option<usize>,
+        version: TranscriptVersion,
+        trace: TraceInfo,
+    ) -> Self {
+        Self::new_transcript_with_optional_proof(
+            Some(bcs_proof),
+            sponge,
+            ldt_codeword_domain,
+            ldt_localization_parameter,
+            version,
+            trace,
+        )
+    }
+
+    /// Returns a transcript like `new_transcript`, but simulating commit
+    /// phase without a proof, e.g. to count the queries of a verifier. No
+    /// prover message is absorbed, and the structure of received rounds is
+    /// not checked against any proof.
+    pub(crate) fn new_transcript_without_proof(
+        sponge: S,
+        ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
+        ldt_localization_parameter: Option<usize>,
+        trace: TraceInfo,
+    ) -> Self {
+        Self::new_transcript_with_optional_proof(
+            None,
+            sponge,
+            ldt_codeword_domain,
+            ldt_localization_parameter,
+            TranscriptVersion::default(),
+            trace,
+        )
+    }
+
+    fn new_transcript_with_optional_proof(
+        bcs_proof: Option<&'a BCSProof<P, F>>,
+        mut sponge: S,
+        ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
+        ldt_localization_parameter: Option<usize>,
+        version: TranscriptVersion,
+        trace: TraceInfo,
+    ) -> Self {
+        let bookkeeper = MessageBookkeeper::new(trace);
+        let absorption_script = version.absorb_into(&mut sponge, &bookkeeper);
         Self {
             proof: bcs_proof,
             expected_prover_messages_info: Vec::new(),
@@ -76,16 +230,180 @@ where
             ldt_localization_parameter,
             sponge,
             current_prover_round: 0,
+            prover_round_offset: 0,
+            num_declared_prover_rounds: None,
+            num_reserved_prover_rounds: 0,
+            num_forks: 0,
             reconstructed_verifier_messages: Vec::new(),
             pending_verifier_messages: Vec::new(),
-            bookkeeper: MessageBookkeeper::new(trace),
+            bookkeeper,
             registered_virtual_oracles: Vec::new(),
+            unexpected_roots: Vec::new(),
+            challenge_source: None,
+            hash_to_field: HashToFieldConfig::default(),
+            absorption_script,
+            version,
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
+            sponge_checkpoints: SpongeCheckpoints::default(),
         }
     }
 
-    /// Create a new namespace in bookkeeper.
+    /// Take verifier messages from `source` instead of the sponge. This is
+    /// **unsafe for production** and only meant for testing; see
+    /// `ChallengeSource`.
+    pub fn with_challenge_source(
+        mut self,
+        source: Box<dyn ChallengeSource<F> + Send + 'a>,
+    ) -> Self {
+        self.challenge_source = Some(source);
+        self
+    }
+
+    /// Check the sponge against `checkpoints`, recorded by prover using
+    /// `Transcript::checkpoint_sponge`, in `assert_sponge_matches`.
+    pub fn with_sponge_checkpoints(mut self, checkpoints: SpongeCheckpoints) -> Self {
+        self.sponge_checkpoints = checkpoints;
+        self
+    }
+
+    /// In query phase, answer every query to a prover round at `positions`
+    /// instead of the positions derived from the sponge, so that benchmarks
+    /// measure the query phase at a controlled position set. The proof should
+    /// be generated with the same `positions` using
+    /// `Transcript::with_fixed_queries`.
+    ///
+    /// This is **unsound** and only meant for benchmarks: prover knows all
+    /// query positions before committing, so **never** use it in production.
+    #[cfg(feature = "bench")]
+    pub fn with_fixed_queries(mut self, positions: &[usize]) -> Self {
+        self.fixed_queries = Some(positions.to_vec());
+        self
+    }
+
+    /// Use `config` to map squeezed bytes to field elements in
+    /// `squeeze_verifier_bytes_as_field`. It should be the same config as the
+    /// one used by prover transcript.
+    pub fn with_hash_to_field_config(mut self, config: HashToFieldConfig<F>) -> Self {
+        self.hash_to_field = config;
+        self
+    }
+
+    /// Create a new namespace in bookkeeper. Unless the transcript uses
+    /// `TranscriptVersion::V1`, the sponge absorbs the domain separator of the
+    /// new namespace, which encodes its path from root namespace.
     pub fn new_namespace(&mut self, current_namespace: NameSpace, trace: TraceInfo) -> NameSpace {
-        self.bookkeeper.new_namespace(trace, current_namespace.id)
+        let namespace = self.bookkeeper.new_namespace(trace, current_namespace.id);
+        if self.version.separates_namespaces() {
+            let domain_separator = self.bookkeeper.domain_separator(namespace.id);
+            self.absorb(AbsorbedValue::DomainSeparator, &domain_separator);
+        }
+        namespace
+    }
+
+    /// Fork this transcript to simulate an independent subprotocol, which
+    /// receives `num_prover_rounds` prover rounds. The fork starts with a
+    /// clone of the current sponge and absorbs the same separator as
+    /// `Transcript::fork`, of which it is the verifier counterpart.
+    ///
+    /// The rounds of the fork are reserved in the proof right after the ones
+    /// reserved by earlier sibling forks, as prover joins them in the order
+    /// they are created. A fork does not share any state with its parent or
+    /// siblings, so sibling forks can be simulated in any order, or on
+    /// separate threads, before they are joined using `join` in the order
+    /// they are created. See `Transcript::fork` for the full contract.
+    ///
+    /// # Panics
+    /// Panics if there is a pending verifier message, or if the transcript
+    /// uses a challenge source.
+    pub fn fork(&mut self, num_prover_rounds: usize) -> Self {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot fork a transcript with pending message"
+        );
+        assert!(
+            self.challenge_source.is_none(),
+            "cannot fork a transcript with challenge source"
+        );
+        let fork_index = self.num_forks;
+        self.num_forks += 1;
+        let fork_offset =
+            self.prover_round_offset + self.current_prover_round + self.num_reserved_prover_rounds;
+        self.num_reserved_prover_rounds += num_prover_rounds;
+        let mut fork = Self {
+            proof: self.proof,
+            expected_prover_messages_info: Vec::new(),
+            ldt_codeword_domain: self.ldt_codeword_domain,
+            ldt_localization_parameter: self.ldt_localization_parameter,
+            sponge: self.sponge.clone(),
+            current_prover_round: 0,
+            prover_round_offset: fork_offset,
+            num_declared_prover_rounds: Some(num_prover_rounds),
+            num_reserved_prover_rounds: 0,
+            num_forks: 0,
+            reconstructed_verifier_messages: Vec::new(),
+            pending_verifier_messages: Vec::new(),
+            bookkeeper: self.bookkeeper.fork(),
+            registered_virtual_oracles: Vec::new(),
+            unexpected_roots: Vec::new(),
+            challenge_source: None,
+            hash_to_field: self.hash_to_field,
+            absorption_script: vec![AbsorbOp::Fork {
+                parent_position: self.absorption_script.len(),
+            }],
+            version: self.version,
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
+            sponge_checkpoints: self.sponge_checkpoints.clone(),
+        };
+        fork.absorb(AbsorbedValue::ForkSeparator, &fork_separator(fork_index));
+        fork
+    }
+
+    /// Merge a fork created by `fork` back into this transcript. After
+    /// merging, the sponge of `self` absorbs 32 bytes squeezed from the
+    /// sponge of `fork`, just like `Transcript::join`.
+    ///
+    /// # Panics
+    /// Panics if forks are not joined in the order they are created, if the
+    /// fork receives fewer prover rounds than declared in `fork`, or if the
+    /// fork violates the contract described in `Transcript::fork`.
+    pub fn join(&mut self, mut fork: Self) {
+        assert!(
+            !fork.is_pending_message_available(),
+            "cannot join a transcript with pending message"
+        );
+        assert_eq!(
+            fork.prover_round_offset,
+            self.prover_round_offset + self.current_prover_round,
+            "forked transcripts must be joined in the order they are created"
+        );
+        assert_eq!(
+            Some(fork.current_prover_round),
+            fork.num_declared_prover_rounds,
+            "forked transcript receives fewer prover rounds than declared"
+        );
+        self.num_reserved_prover_rounds -= fork.current_prover_round;
+        assert!(
+            fork.registered_virtual_oracles.is_empty(),
+            "forked transcript cannot register virtual rounds"
+        );
+        self.bookkeeper.join(
+            fork.bookkeeper,
+            self.current_prover_round,
+            self.reconstructed_verifier_messages.len(),
+        );
+        self.current_prover_round += fork.current_prover_round;
+        self.expected_prover_messages_info
+            .extend(fork.expected_prover_messages_info);
+        self.unexpected_roots.extend(fork.unexpected_roots);
+        self.reconstructed_verifier_messages
+            .extend(fork.reconstructed_verifier_messages);
+        let fork_digest = fork.sponge.squeeze_bytes(32);
+        fork.absorption_script.push(AbsorbOp::SqueezeBytes(32));
+        self.absorption_script
+            .push(AbsorbOp::Join(fork.absorption_script));
+        self.absorb(AbsorbedValue::ForkDigest, &fork_digest);
     }
 
     /// Returns the number of prover rounds that prover have submitted.
@@ -93,6 +411,72 @@ where
         self.current_prover_round
     }
 
+    /// Returns the index in `namespace` that the next prover round received
+    /// or virtual round registered in `namespace` will get. This is the
+    /// index returned by `Transcript::current_round_index` at the same point
+    /// of the protocol.
+    pub fn current_round_index(&self, namespace: NameSpace) -> usize {
+        self.bookkeeper
+            .get_message_indices(namespace)
+            .prover_rounds
+            .len()
+    }
+
+    /// Receive the segments of a message oracle of `total_length` elements
+    /// sent by `Transcript::send_split_oracle` with the same `segment_length`
+    /// and `localization`. Returns the rounds of the segments in order.
+    ///
+    /// # Panics
+    /// Panics if `total_length` is not a multiple of `segment_length`.
+    pub fn receive_split_oracle(
+        &mut self,
+        ns: NameSpace,
+        total_length: usize,
+        segment_length: usize,
+        localization: Localization,
+        trace: TraceInfo,
+    ) -> Vec<MsgRoundRef> {
+        assert!(
+            segment_length > 0 && total_length % segment_length == 0,
+            "oracle length should be a multiple of segment length"
+        );
+        (0..total_length / segment_length)
+            .map(|_| {
+                let expected_info =
+                    ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                        segment_length,
+                        localization,
+                    )
+                    .with_num_message_oracles(1)
+                    .build();
+                self.receive_prover_current_round(ns, expected_info, trace)
+            })
+            .collect()
+    }
+
+    /// Receive prover's current round messages if `is_present` is true, and
+    /// return `None` otherwise. Use this for rounds that prover only sends
+    /// under some condition. `is_present` should be derived from verifier
+    /// parameter, and must agree with the condition used by prover.
+    ///
+    /// # Panic
+    /// This function will panic is prover message structure contained in proof
+    /// is not consistent with `expected_message_structure`.
+    pub fn receive_optional_prover_current_round(
+        &mut self,
+        ns: NameSpace,
+        expected_message_info: ProverRoundMessageInfo,
+        is_present: bool,
+        trace: TraceInfo,
+    ) -> Option<MsgRoundRef> {
+        if is_present {
+            Some(self.receive_prover_current_round(ns, expected_message_info, trace))
+        } else {
+            info!("optional prover round absent: {}", trace);
+            None
+        }
+    }
+
     /// Receive prover's current round messages, which can possibly contain
     /// multiple oracles with same size. This function will absorb the
     /// merkle tree root and short messages (if any).
@@ -118,8 +502,25 @@ where
                        "This round contains low-degree oracle, but custom length and localization parameter is used. ");
         }
 
-        let index = self.current_prover_round;
+        assert_eq!(
+            self.num_reserved_prover_rounds, 0,
+            "cannot receive prover round before joining all forks"
+        );
+        if let Some(num_declared_prover_rounds) = self.num_declared_prover_rounds {
+            assert!(
+                self.current_prover_round < num_declared_prover_rounds,
+                "forked transcript receives more prover rounds than declared"
+            );
+        }
+        let proof_index = self.prover_round_offset + self.current_prover_round;
         self.current_prover_round += 1;
+        #[cfg(feature = "logging")]
+        log::debug!(
+            "prover round received: namespace {}, round {}, oracle length {}",
+            ns.id,
+            proof_index,
+            expected_message_info.length
+        );
 
         let trace_info = {
             ark_std::format!(
@@ -129,42 +530,44 @@ where
             )
         };
 
-        if index >= self.proof.prover_iop_messages_by_round.len() {
-            panic!(
-                "Verifier tried to receive extra prove round message. {}",
-                trace_info
-            );
-        }
-
-        // check basic consistency with message received
-        let current_round = &self.proof.prover_iop_messages_by_round[index];
-        let num_short_message_expected = expected_message_info.num_short_messages;
-        let num_short_message_received = current_round.short_messages.len();
         let num_oracles_expected = expected_message_info.num_oracles();
-        let num_oracles_received = current_round.queried_cosets.get(0).map_or(0, |c| c.len());
+        if let Some(proof) = self.proof {
+            if proof_index >= proof.prover_iop_messages_by_round.len() {
+                panic!(
+                    "Verifier tried to receive extra prove round message. {}",
+                    trace_info
+                );
+            }
 
-        // here are some sanity check to make sure user is not doing wrong thing
-        // check 1: `num_short_messages` and `num_oracles` should be consistent with
-        // expected
-        assert_eq!(
-            num_short_message_expected, num_short_message_received,
-            "Number of short messages received is not equal to expected. {}",
-            trace_info
-        );
-        assert_eq!(
-            num_oracles_expected, num_oracles_received,
-            "Number of oracles received is not equal to expected. {}",
-            trace_info
-        );
-        // check 2: number of oracles in each query result should be the same
-        current_round.queried_cosets.iter().for_each(|c| {
+            // check basic consistency with message received
+            let current_round = &proof.prover_iop_messages_by_round[proof_index];
+            let num_short_message_expected = expected_message_info.num_short_messages;
+            let num_short_message_received = current_round.short_messages.len();
+            let num_oracles_received = current_round.queried_cosets.get(0).map_or(0, |c| c.len());
+
+            // here are some sanity check to make sure user is not doing wrong thing
+            // check 1: `num_short_messages` and `num_oracles` should be consistent with
+            // expected
+            assert_eq!(
+                num_short_message_expected, num_short_message_received,
+                "Number of short messages received is not equal to expected. {}",
+                trace_info
+            );
             assert_eq!(
-                c.len(),
-                num_oracles_expected,
-                "Number of oracles in each query result is not equal to expected. {}",
+                num_oracles_expected, num_oracles_received,
+                "Number of oracles received is not equal to expected. {}",
                 trace_info
             );
-        });
+            // check 2: number of oracles in each query result should be the same
+            current_round.queried_cosets.iter().for_each(|c| {
+                assert_eq!(
+                    c.len(),
+                    num_oracles_expected,
+                    "Number of oracles in each query result is not equal to expected. {}",
+                    trace_info
+                );
+            });
+        }
         // check 3: number of rs-codes should not exceed number of oracles
         assert!(
             expected_message_info.reed_solomon_code_degree_bound.len() <= num_oracles_expected,
@@ -229,20 +632,66 @@ where
             );
         }
 
-        // absorb merkle tree root, if any
-        self.sponge
-            .absorb(&self.proof.prover_messages_mt_root[index]);
-        // absorb short messages for this round, if any
-        self.proof.prover_iop_messages_by_round[index]
-            .short_messages
-            .iter()
-            .for_each(|msg| self.sponge.absorb(msg));
+        let inline = self.version.inlines_oracles(&expected_message_info);
+        assert!(
+            !inline || !expected_message_info.hiding,
+            "oracles committed inline cannot be hiding. {}",
+            trace_info
+        );
+        // absorb round structure and merkle tree root, if any
+        if self.version.absorbs_round_structure() && num_oracles_expected > 0 {
+            self.absorb(
+                AbsorbedValue::RoundStructure,
+                &round_structure::<F>(&expected_message_info),
+            );
+        }
+        if let Some(proof) = self.proof {
+            self.absorb(
+                AbsorbedValue::MerkleRoot,
+                &proof.prover_messages_mt_root[proof_index],
+            );
+            // absorb oracles committed inline in entirety
+            if inline {
+                self.absorb(
+                    AbsorbedValue::InlineOracles,
+                    &inline_elements(
+                        &proof.prover_iop_messages_by_round[proof_index].queried_cosets,
+                    ),
+                );
+            }
+            // absorb short messages for this round, if any
+            proof.prover_iop_messages_by_round[proof_index]
+                .short_messages
+                .iter()
+                .for_each(|msg| self.absorb(AbsorbedValue::ShortMessage, msg));
+        }
         // attach prover info to transcript
         self.expected_prover_messages_info
             .push(expected_message_info);
         self.attach_latest_prover_round_to_namespace(ns, false, trace)
     }
 
+    /// Require the merkle root committed by prover for `round` to be
+    /// `known_root`, e.g. the root of a fixed table that verifier knows in
+    /// advance. `BCSVerifier` rejects the proof with
+    /// `BCSError::UnexpectedRoot` after commit phase if the roots differ.
+    ///
+    /// # Panics
+    /// Panics if `round` is virtual, or has not been received.
+    pub fn expect_known_root(&mut self, round: MsgRoundRef, known_root: P::InnerDigest) {
+        assert!(!round.is_virtual, "virtual round does not have merkle root");
+        assert!(
+            round.index < self.current_prover_round,
+            "round has not been received"
+        );
+        let proof_index = self.prover_round_offset + round.index;
+        if let Some(proof) = self.proof {
+            if proof.prover_messages_mt_root[proof_index] != Some(known_root) {
+                self.unexpected_roots.push(proof_index);
+            }
+        }
+    }
+
     /// Register a virtual oracle specified by coset evaluator.
     pub fn register_prover_virtual_round<VO: VirtualOracle<F>>(
         &mut self,
@@ -292,10 +741,51 @@ where
     ///
     /// **Note**: In original IOP paper, verifier do not use sampled element in
     /// commit phase. So in this implementation, this function returns nothing.
+    ///
+    /// If a challenge source is set, `field_size.len()` elements are taken
+    /// from it, regardless of `field_size`.
     pub fn squeeze_verifier_field_elements(&mut self, field_size: &[FieldElementSize]) {
-        let msg = self.sponge.squeeze_field_elements_with_sizes(field_size);
+        let msg = self.squeeze_field_elements(field_size);
+        self.pending_verifier_messages
+            .push(VerifierMessage::FieldElements(msg));
+    }
+
+    /// Squeeze `K` full size verifier field elements as an array, in the same
+    /// way as `Transcript::squeeze_verifier_field_elements_array`. The
+    /// elements are attached to pending messages, and need to be submitted
+    /// through `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_verifier_field_elements_array<const K: usize>(&mut self) -> [F; K] {
+        let msg = self.squeeze_field_elements(&[FieldElementSize::Full; K]);
+        let mut result = [F::zero(); K];
+        result.copy_from_slice(&msg);
         self.pending_verifier_messages
             .push(VerifierMessage::FieldElements(msg));
+        result
+    }
+
+    /// Squeeze `num_claims` full size verifier field elements, in the same way
+    /// as `Transcript::squeeze_folding_coefficients`. The elements are
+    /// attached to pending messages, and need to be submitted through
+    /// `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_folding_coefficients(&mut self, num_claims: usize) -> Vec<F> {
+        let msg = self.squeeze_field_elements(&vec![FieldElementSize::Full; num_claims]);
+        self.pending_verifier_messages
+            .push(VerifierMessage::FieldElements(msg.clone()));
+        msg
     }
 
     /// Squeeze sampled verifier message as bytes. The squeezed bytes is
@@ -311,12 +801,31 @@ where
     /// User may need to check if this flexibility will affect soundness
     /// analysis in a case-to-case basis.
     pub fn squeeze_verifier_field_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
-        let msg = self.sponge.squeeze_bytes(num_bytes);
+        let msg = self.squeeze_bytes(num_bytes);
         self.pending_verifier_messages
             .push(VerifierMessage::Bytes(msg.clone()));
         msg
     }
 
+    /// Squeeze `num_elements` verifier field elements derived from squeezed
+    /// bytes, in the same way as `Transcript::squeeze_verifier_bytes_as_field`.
+    /// The squeezed elements are attached to pending messages, and need to be
+    /// submitted through `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_verifier_bytes_as_field(&mut self, num_elements: usize) -> Vec<F> {
+        let bytes = self.squeeze_bytes(num_elements * self.hash_to_field.bytes_per_element);
+        let msg = self.hash_to_field.map_bytes(&bytes);
+        self.pending_verifier_messages
+            .push(VerifierMessage::FieldElements(msg.clone()));
+        msg
+    }
+
     /// Squeeze sampled verifier message as bytes. The squeezed bytes is
     /// attached to pending messages, and need to be submitted through
     /// `submit_verifier_current_round`. Submitted messages will be stored
@@ -330,17 +839,177 @@ where
     /// User may need to check if this flexibility will affect soundness
     /// analysis in a case-to-case basis.
     pub fn squeeze_verifier_field_bits(&mut self, num_bits: usize) -> Vec<bool> {
-        let msg = self.sponge.squeeze_bits(num_bits);
+        let msg = self.squeeze_bits(num_bits);
         self.pending_verifier_messages
             .push(VerifierMessage::Bits(msg.clone()));
         msg
     }
 
+    /// Squeeze a verifier field element in `[0, 2^num_bits)`, in the same way
+    /// as `Transcript::squeeze_verifier_field_element_from_bits`. The element
+    /// is attached to pending messages, and need to be submitted through
+    /// `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_verifier_field_element_from_bits(&mut self, num_bits: usize) -> F {
+        let msg = field_element_from_bits(&self.squeeze_bits(num_bits));
+        self.pending_verifier_messages
+            .push(VerifierMessage::FieldElements(vec![msg]));
+        msg
+    }
+
+    /// Squeeze an unbiased full size verifier field element, in the same way
+    /// as `Transcript::squeeze_verifier_uniform_element`. The element is
+    /// attached to pending messages, and need to be submitted through
+    /// `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_verifier_uniform_element(&mut self) -> F {
+        let msg = sample_uniform_field_element(|num_bits| self.squeeze_bits(num_bits));
+        self.pending_verifier_messages
+            .push(VerifierMessage::FieldElements(vec![msg]));
+        msg
+    }
+
+    /// Squeeze a permutation of `0..n` as verifier message, in the same way as
+    /// `Transcript::squeeze_verifier_permutation`. The permutation is attached
+    /// to pending messages, and need to be submitted through
+    /// `submit_verifier_current_round`.
+    ///
+    /// **Note**: In original IOP paper, verifier do not use sampled element in
+    /// commit phase. However, this implementation allows verifier to have
+    /// access to sampled elements in `register_iop_structure` to
+    /// add flexibility.
+    /// User may need to check if this flexibility will affect soundness
+    /// analysis in a case-to-case basis.
+    pub fn squeeze_verifier_permutation(&mut self, n: usize) -> Vec<usize> {
+        let msg = sample_permutation(n, |num_bytes| self.squeeze_bytes(num_bytes));
+        self.pending_verifier_messages
+            .push(VerifierMessage::Permutation(msg.clone()));
+        msg
+    }
+
+    /// Squeeze and discard `num_bytes` bytes, in the same way as
+    /// `Transcript::advance_sponge`. The step is attached to pending messages,
+    /// and need to be submitted through `submit_verifier_current_round`.
+    pub fn advance_sponge(&mut self, num_bytes: usize) {
+        self.squeeze_bytes(num_bytes);
+        self.pending_verifier_messages
+            .push(VerifierMessage::Advance(num_bytes));
+    }
+
+    /// Absorb public field elements prefixed by their number, in the same
+    /// way as `Transcript::absorb_public_field_elements`.
+    pub fn absorb_public_field_elements(&mut self, elements: &[F]) {
+        self.absorb(AbsorbedValue::PublicInput, &length_prefixed(elements));
+    }
+
+    /// Absorb the commit phase digest of another proof, in the same way as
+    /// `Transcript::bind_sibling`. Verification fails if the prover bound a
+    /// different digest.
+    pub fn bind_sibling(&mut self, other_digest: F) {
+        self.absorb(AbsorbedValue::SiblingDigest, &other_digest);
+    }
+
+    /// Every operation on the sponge so far, in order, so that an external
+    /// implementation can reproduce the evolution of the sponge. Squeezes
+    /// answered by a challenge source are not recorded.
+    pub fn absorption_script(&self) -> &[AbsorbOp] {
+        &self.absorption_script
+    }
+
+    /// Assert that the sponge is in the state recorded by prover at `label`,
+    /// which should be placed at the same point of the protocol as this call.
+    /// Placing checkpoints around a suspicious step pinpoints the first step
+    /// where verifier diverges from prover.
+    ///
+    /// # Panics
+    /// Panics if the states differ, or if no state is recorded at `label` in
+    /// the checkpoints given to `with_sponge_checkpoints`.
+    pub fn assert_sponge_matches(&self, label: &str) {
+        let expected = self
+            .sponge_checkpoints
+            .get(label)
+            .unwrap_or_else(|| panic!("no sponge checkpoint `{}` is recorded by prover", label));
+        assert!(
+            expected == sponge_snapshot(&self.sponge).as_slice(),
+            "verifier sponge diverges from prover at checkpoint `{}`",
+            label
+        );
+    }
+
+    /// Squeeze the digest of all messages received so far, in the same way as
+    /// `Transcript::commit_phase_digest`.
+    ///
+    /// # Panics
+    /// Panics if there is a pending verifier message.
+    pub fn commit_phase_digest(&self) -> F {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot compute commit phase digest with pending message"
+        );
+        self.sponge.clone().squeeze_field_elements::<F>(1)[0]
+    }
+
     /// Returns if there is a verifier message for the transcript.
     pub fn is_pending_message_available(&self) -> bool {
         !self.pending_verifier_messages.is_empty()
     }
 
+    /// Squeeze field elements from the challenge source if it is set, or the
+    /// sponge otherwise.
+    fn squeeze_field_elements(&mut self, field_size: &[FieldElementSize]) -> Vec<F> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_field_elements(field_size.len()),
+            None => {
+                self.absorption_script
+                    .push(AbsorbOp::SqueezeFieldElements(field_size.to_vec()));
+                self.sponge.squeeze_field_elements_with_sizes(field_size)
+            },
+        }
+    }
+
+    /// Squeeze bytes from the challenge source if it is set, or the sponge
+    /// otherwise.
+    fn squeeze_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_bytes(num_bytes),
+            None => {
+                self.absorption_script
+                    .push(AbsorbOp::SqueezeBytes(num_bytes));
+                self.sponge.squeeze_bytes(num_bytes)
+            },
+        }
+    }
+
+    /// Squeeze bits from the challenge source if it is set, or the sponge
+    /// otherwise.
+    fn squeeze_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_bits(num_bits),
+            None => {
+                self.absorption_script.push(AbsorbOp::SqueezeBits(num_bits));
+                self.sponge.squeeze_bits(num_bits)
+            },
+        }
+    }
+
+    /// Absorb `item` into the sponge, and record it in the absorption script.
+    fn absorb(&mut self, value: AbsorbedValue, item: &impl Absorb) {
+        self.sponge.absorb(item);
+        self.absorption_script.push(AbsorbOp::absorb(value, item));
+    }
+
     fn attach_latest_prover_round_to_namespace(
         &mut self,
         namespace: NameSpace,
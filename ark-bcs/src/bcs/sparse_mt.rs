@@ -0,0 +1,149 @@
+use ark_crypto_primitives::{
+    crh::TwoToOneCRHScheme,
+    merkle_tree::{Config as MTConfig, DigestConverter},
+    Path,
+};
+use ark_std::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use crate::{bcs::MTHashParameters, Error};
+
+/// Merkle tree whose leaves all have the same digest, except for a few. An
+/// inner node whose leaves all have the default digest is hashed once per
+/// level and shared, and only nodes above a non-default leaf are hashed and
+/// stored, so building the tree takes `O(height * num_non_default_leaves)`
+/// hashes instead of `O(num_leaves)`.
+///
+/// The root and authentication paths are the same as those of `MerkleTree`
+/// built from the same leaves.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig"))]
+pub struct SparseMerkleTree<MT: MTConfig> {
+    num_leaves: usize,
+    default_leaf_digest: MT::LeafDigest,
+    leaf_digests: BTreeMap<usize, MT::LeafDigest>,
+    /// `default_inner_digests[depth]` is the digest of an inner node of
+    /// `depth` whose leaves are all default, where the root has depth 0.
+    default_inner_digests: Vec<MT::InnerDigest>,
+    /// Inner nodes above a non-default leaf, keyed by `(depth, index)`.
+    inner_digests: BTreeMap<(usize, usize), MT::InnerDigest>,
+    /// Number of two-to-one hash invocations used to build this tree.
+    pub(crate) num_hash_invocations: usize,
+}
+
+impl<MT: MTConfig> SparseMerkleTree<MT> {
+    /// Build a tree with `num_leaves` leaves, where the digest of leaf `i` is
+    /// `leaf_digests[i]` if present and `default_leaf_digest` otherwise.
+    /// # Panics
+    /// Panics if `num_leaves` is not a power of two greater than one, or if
+    /// an index of `leaf_digests` is out of range.
+    pub fn new(
+        hash_params: &MTHashParameters<MT>,
+        num_leaves: usize,
+        default_leaf_digest: MT::LeafDigest,
+        leaf_digests: BTreeMap<usize, MT::LeafDigest>,
+    ) -> Result<Self, Error> {
+        assert!(
+            num_leaves > 1 && num_leaves.is_power_of_two(),
+            "number of leaves should be a power of two greater than one"
+        );
+        assert!(
+            leaf_digests
+                .keys()
+                .next_back()
+                .map_or(true, |&index| index < num_leaves),
+            "leaf index out of range"
+        );
+        let inner_hash_param = &hash_params.inner_hash_param;
+        let height = num_leaves.trailing_zeros() as usize;
+
+        // digests of all-default nodes, from the lowest inner level to the root
+        let mut default_inner_digests = Vec::with_capacity(height);
+        default_inner_digests.push(MT::TwoToOneHash::evaluate(
+            inner_hash_param,
+            MT::LeafInnerDigestConverter::convert(default_leaf_digest.clone())?,
+            MT::LeafInnerDigestConverter::convert(default_leaf_digest.clone())?,
+        )?);
+        for _ in 1..height {
+            let child = default_inner_digests.last().unwrap();
+            let node = MT::TwoToOneHash::compress(inner_hash_param, child, child)?;
+            default_inner_digests.push(node);
+        }
+        default_inner_digests.reverse();
+
+        let mut dirty_nodes = leaf_digests
+            .keys()
+            .map(|index| index >> 1)
+            .collect::<BTreeSet<_>>();
+        let mut tree = Self {
+            num_leaves,
+            default_leaf_digest,
+            leaf_digests,
+            default_inner_digests,
+            inner_digests: BTreeMap::new(),
+            num_hash_invocations: height,
+        };
+        for &index in &dirty_nodes {
+            let node = MT::TwoToOneHash::evaluate(
+                inner_hash_param,
+                MT::LeafInnerDigestConverter::convert(tree.leaf_digest(index << 1))?,
+                MT::LeafInnerDigestConverter::convert(tree.leaf_digest((index << 1) | 1))?,
+            )?;
+            tree.inner_digests.insert((height - 1, index), node);
+        }
+        tree.num_hash_invocations += dirty_nodes.len();
+        for depth in (0..height - 1).rev() {
+            dirty_nodes = dirty_nodes.iter().map(|index| index >> 1).collect();
+            for &index in &dirty_nodes {
+                let node = MT::TwoToOneHash::compress(
+                    inner_hash_param,
+                    tree.inner_digest(depth + 1, index << 1),
+                    tree.inner_digest(depth + 1, (index << 1) | 1),
+                )?;
+                tree.inner_digests.insert((depth, index), node);
+            }
+            tree.num_hash_invocations += dirty_nodes.len();
+        }
+        Ok(tree)
+    }
+
+    /// Merkle root of the tree.
+    pub fn root(&self) -> MT::InnerDigest {
+        self.inner_digest(0, 0).clone()
+    }
+
+    /// Authentication path of leaf `index`, in the format of
+    /// `MerkleTree::generate_proof`.
+    /// # Panics
+    /// Panics if `index` is out of range.
+    pub fn generate_proof(&self, index: usize) -> Path<MT> {
+        assert!(index < self.num_leaves, "leaf index out of range");
+        let height = self.default_inner_digests.len();
+        let auth_path = (1..height)
+            .map(|depth| {
+                self.inner_digest(depth, (index >> (height - depth)) ^ 1)
+                    .clone()
+            })
+            .collect();
+        Path {
+            leaf_sibling_hash: self.leaf_digest(index ^ 1),
+            auth_path,
+            leaf_index: index,
+        }
+    }
+
+    fn leaf_digest(&self, index: usize) -> MT::LeafDigest {
+        self.leaf_digests
+            .get(&index)
+            .unwrap_or(&self.default_leaf_digest)
+            .clone()
+    }
+
+    fn inner_digest(&self, depth: usize, index: usize) -> &MT::InnerDigest {
+        self.inner_digests
+            .get(&(depth, index))
+            .unwrap_or(&self.default_inner_digests[depth])
+    }
+}
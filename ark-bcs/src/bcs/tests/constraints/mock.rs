@@ -9,7 +9,7 @@ use crate::{
             message::MessagesCollectionVar, oracles::VirtualOracleVar, IOPVerifierWithGadget,
             Nothing,
         },
-        message::{OracleIndex, ProverRoundMessageInfo},
+        message::{Localization, OracleIndex, ProverRoundMessageInfo},
     },
     prelude::MsgRoundRef,
 };
@@ -75,11 +75,13 @@ impl<S: SpongeWithGadget<CF>, CF: PrimeField + Absorb> IOPVerifierWithGadget<S,
         MTG::InnerDigest: AbsorbGadget<CF>,
     {
         // prover send
-        let expected_info =
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(256, 2)
-                .with_num_message_oracles(2)
-                .with_num_short_messages(1)
-                .build();
+        let expected_info = ProverRoundMessageInfo::new_using_custom_length_and_localization(
+            256,
+            Localization::new(2, 256).unwrap(),
+        )
+        .with_num_message_oracles(2)
+        .with_num_short_messages(1)
+        .build();
         transcript.receive_prover_current_round(namespace, expected_info, iop_trace!())?;
 
         // verifier send
@@ -92,11 +94,13 @@ impl<S: SpongeWithGadget<CF>, CF: PrimeField + Absorb> IOPVerifierWithGadget<S,
         transcript.submit_verifier_current_round(namespace, iop_trace!());
 
         // prover send
-        let expected_info =
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(256, 0)
-                .with_num_message_oracles(1)
-                .with_num_short_messages(1)
-                .build();
+        let expected_info = ProverRoundMessageInfo::new_using_custom_length_and_localization(
+            256,
+            Localization::default(),
+        )
+        .with_num_message_oracles(1)
+        .with_num_short_messages(1)
+        .build();
         transcript.receive_prover_current_round(namespace, expected_info, iop_trace!())?;
 
         // prover send2
@@ -15,17 +15,19 @@ use crate::{
             mock::{MockTest1Verifier, MockTestProver},
             FieldMTConfig, Fr,
         },
-        MTHashParameters,
     },
     iop::{
         bookkeeper::NameSpace,
         constraints::{IOPVerifierWithGadget, Nothing},
     },
     ldt::{
-        rl_ldt::{LinearCombinationLDT, LinearCombinationLDTParameters},
+        rl_ldt::{
+            CoefficientStrategy, LinearCombinationLDT, LinearCombinationLDTParameters,
+            QueryStrategy,
+        },
         LDT,
     },
-    test_utils::poseidon_parameters,
+    test_utils::{mt_hash_parameters, poseidon_parameters},
 };
 use ark_crypto_primitives::crh::poseidon::constraints::CRHParametersVar;
 use ark_ldt::{domain::Radix2CosetDomain, fri::FRIParameters};
@@ -62,12 +64,11 @@ fn test_bcs() {
     let ldt_parameters = LinearCombinationLDTParameters {
         fri_parameters,
         num_queries: 1,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
     };
     let sponge = PoseidonSponge::new(&poseidon_parameters());
-    let mt_hash_param = MTHashParameters::<FieldMTConfig> {
-        leaf_hash_param: poseidon_parameters(),
-        inner_hash_param: poseidon_parameters(),
-    };
+    let mt_hash_param = mt_hash_parameters();
     let bcs_proof = BCSProof::generate::<
         MockTest1Verifier<Fr>,
         MockTestProver<Fr>,
@@ -100,7 +101,8 @@ fn test_bcs() {
             LinearCombinationLDT::codeword_domain(&ldt_parameters),
             LinearCombinationLDT::localization_param(&ldt_parameters),
             iop_trace!("test bcs"),
-        );
+        )
+        .unwrap();
 
     MockTest1Verifier::register_iop_structure_var(
         NameSpace::root(iop_trace!("BCS test")),
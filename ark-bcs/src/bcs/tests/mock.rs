@@ -1,21 +1,31 @@
 use crate::{
     bcs::{simulation_transcript::SimulationTranscript, transcript::Transcript},
     iop::{
-        bookkeeper::NameSpace,
-        message::{MessagesCollection, OracleIndex, ProverRoundMessageInfo, VerifierMessage},
+        bookkeeper::{BookkeeperContainer, NameSpace, ToMsgRoundRef},
+        message::{Localization, MessagesCollection, OracleIndex, ProverRoundMessageInfo},
         oracles::{RoundOracle, VirtualOracle},
         prover::IOPProver,
-        verifier::IOPVerifier,
+        verifier::{
+            num_queries_from_challenge, squeeze_query_phase_challenge, IOPVerifier, QuerySampler,
+        },
+        ProverParam,
     },
     prelude::MsgRoundRef,
+    util::le_bits_to_usize,
     Error,
 };
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
-use ark_ff::{PrimeField, ToConstraintField};
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
 use ark_ldt::domain::Radix2CosetDomain;
 use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use ark_serialize::CanonicalDeserialize;
 use ark_sponge::{Absorb, CryptographicSponge, FieldElementSize};
-use ark_std::{marker::PhantomData, test_rng, vec, vec::Vec};
+use ark_std::{
+    marker::PhantomData,
+    rand::{rngs::StdRng, SeedableRng},
+    test_rng, vec,
+    vec::Vec,
+};
 use tracing::Level;
 
 pub(crate) struct MockTestProver<F: PrimeField + Absorb> {
@@ -86,7 +96,7 @@ impl<F: PrimeField + Absorb> IOPProver<F> for MockTestProver<F> {
         //     .unwrap();
         let msg3 = (0..256).map(|_| F::rand(&mut rng)).collect::<Vec<_>>();
         transcript
-            .add_prover_round_with_custom_length_and_localization(256, 2)
+            .add_prover_round_with_custom_length_and_localization(256, Localization::new(2, 256)?)
             .send_short_message(msg1)
             .send_oracle_message_without_degree_bound(msg2)
             .send_oracle_message_without_degree_bound(msg3)
@@ -111,7 +121,7 @@ impl<F: PrimeField + Absorb> IOPProver<F> for MockTestProver<F> {
             F::from(x) + rhs
         });
         transcript
-            .add_prover_round_with_custom_length_and_localization(256, 0)
+            .add_prover_round_with_custom_length_and_localization(256, Localization::default())
             .send_short_message(msg1)
             .send_oracle_message_without_degree_bound(msg2)
             .submit(namespace, iop_trace!("mock send2"))?;
@@ -172,11 +182,13 @@ impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockT
         let span = tracing::span!(Level::INFO, "main register");
         let _enter = span.enter();
 
-        let expected_info =
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(256, 2)
-                .with_num_message_oracles(2)
-                .with_num_short_messages(1)
-                .build();
+        let expected_info = ProverRoundMessageInfo::new_using_custom_length_and_localization(
+            256,
+            Localization::new(2, 256).unwrap(),
+        )
+        .with_num_message_oracles(2)
+        .with_num_short_messages(1)
+        .build();
         transcript.receive_prover_current_round(namespace, expected_info, iop_trace!());
 
         // verifier send
@@ -193,11 +205,13 @@ impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockT
         transcript.submit_verifier_current_round(namespace, iop_trace!());
 
         // prover send
-        let expected_info =
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(256, 0)
-                .with_num_message_oracles(1)
-                .with_num_short_messages(1)
-                .build();
+        let expected_info = ProverRoundMessageInfo::new_using_custom_length_and_localization(
+            256,
+            Localization::default(),
+        )
+        .with_num_message_oracles(1)
+        .with_num_short_messages(1)
+        .build();
 
         transcript.receive_prover_current_round(namespace, expected_info, iop_trace!());
 
@@ -241,51 +255,39 @@ impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockT
         let pm1_2: Vec<_> = (0..256).map(|_| F::rand(&mut rng)).collect();
         let pm1_3: Vec<_> = (0..256).map(|_| F::rand(&mut rng)).collect();
 
-        assert_eq!(
-            transcript_messages
-                .prover_round((namespace, 0))
-                .short_message(0, iop_trace!()),
-            &pm1_1
-        );
-        assert_eq!(
-            transcript_messages
-                .prover_round((namespace, 0))
-                .query_point(&[123, 223], iop_trace!("mock query 0")),
-            vec![vec![pm1_2[123], pm1_3[123]], vec![pm1_2[223], pm1_3[223]]]
-        );
+        let ok = transcript_messages
+            .prover_round((namespace, 0))
+            .short_message(0, iop_trace!())
+            == &pm1_1;
+        transcript_messages.check(ok, "short message of round 0");
+        let ok = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&[123, 223], iop_trace!("mock query 0"))
+            == vec![vec![pm1_2[123], pm1_3[123]], vec![pm1_2[223], pm1_3[223]]];
+        transcript_messages.check(ok, "oracles of round 0");
 
-        let vm1_1 = if let VerifierMessage::FieldElements(fe) =
-            transcript_messages.verifier_round((namespace, 0))[0].clone()
-        {
-            assert_eq!(fe.len(), 3);
-            fe
-        } else {
-            panic!("invalid vm message type")
-        };
-        let vm1_2 = if let VerifierMessage::Bytes(bytes) =
-            transcript_messages.verifier_round((namespace, 0))[1].clone()
-        {
-            assert_eq!(bytes.len(), 16);
-            bytes
-        } else {
-            panic!("invalid vm message type");
-        };
+        let vm1_1 = transcript_messages.verifier_round((namespace, 0))[0]
+            .as_field_elements()?
+            .to_vec();
+        transcript_messages.check(vm1_1.len() == 3, "length of verifier round 0 message 0");
+        let vm1_2 = transcript_messages.verifier_round((namespace, 0))[1]
+            .as_bytes()?
+            .to_vec();
+        transcript_messages.check(vm1_2.len() == 16, "length of verifier round 0 message 1");
 
-        if let VerifierMessage::Bits(bits) = &transcript_messages.verifier_round((namespace, 1))[0]
-        {
-            assert_eq!(bits.len(), 19);
-        } else {
-            panic!("invalid vm message type");
-        }
+        let ok = transcript_messages.verifier_round((namespace, 1))[0]
+            .as_bits()?
+            .len()
+            == 19;
+        transcript_messages.check(ok, "length of verifier round 1 message 0");
 
         let pm2_1: Vec<_> = vm1_1.into_iter().map(|x| x.square()).collect();
 
-        assert_eq!(
-            transcript_messages
-                .prover_round((namespace, 1))
-                .short_message(0, iop_trace!()),
-            &pm2_1
-        );
+        let ok = transcript_messages
+            .prover_round((namespace, 1))
+            .short_message(0, iop_trace!())
+            == &pm2_1;
+        transcript_messages.check(ok, "short message of round 1");
 
         let pm2_2: Vec<_> = (0..256u128)
             .map(|x| {
@@ -294,25 +296,1950 @@ impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockT
             })
             .collect();
 
-        assert_eq!(
-            transcript_messages
-                .prover_round((namespace, 1))
-                .query_point(&[19, 29, 39], iop_trace!()),
-            vec![vec![pm2_2[19]], vec![pm2_2[29]], vec![pm2_2[39]]]
-        );
+        let ok = transcript_messages
+            .prover_round((namespace, 1))
+            .query_point(&[19, 29, 39], iop_trace!())
+            == vec![vec![pm2_2[19]], vec![pm2_2[29]], vec![pm2_2[39]]];
+        transcript_messages.check(ok, "oracle of round 1");
 
         let pm3_1: Vec<_> = (0..6).map(|_| F::rand(&mut rng)).collect();
-        assert_eq!(
-            transcript_messages
-                .prover_round((namespace, 2))
-                .short_message(0, iop_trace!()),
-            &pm3_1
-        );
+        let ok = transcript_messages
+            .prover_round((namespace, 2))
+            .short_message(0, iop_trace!())
+            == &pm3_1;
+        transcript_messages.check(ok, "short message of round 2");
         // just query some points
         transcript_messages
             .prover_round((namespace, 2))
             .query_point(&vec![1, 2], iop_trace!());
 
+        transcript_messages.finalize_checks()?;
+        Ok(true)
+    }
+}
+
+/// A small subprotocol that uses only custom length oracles, so that it can
+/// run in a forked transcript.
+pub(crate) struct MockSubprotocol;
+
+impl MockSubprotocol {
+    /// Number of prover rounds sent by this subprotocol.
+    pub(crate) const NUM_PROVER_ROUNDS: usize = 2;
+
+    fn oracle<F: PrimeField>(seed: u64) -> Vec<F> {
+        (0..64u64).map(|i| F::from(seed * 1000 + i)).collect()
+    }
+
+    pub(crate) fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge, F: PrimeField + Absorb>(
+        namespace: NameSpace,
+        transcript: &mut Transcript<MT, S, F>,
+        seed: u64,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::new(1, 64)?)
+            .send_oracle_message_without_degree_bound(Self::oracle::<F>(seed))
+            .submit(namespace, iop_trace!("mock subprotocol oracle"))?;
+        let challenge = transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full])[0];
+        transcript
+            .submit_verifier_current_round(namespace, iop_trace!("mock subprotocol challenge"));
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![challenge * F::from(seed)])
+            .submit(namespace, iop_trace!("mock subprotocol response"))?;
+        Ok(())
+    }
+
+    pub(crate) fn register<
+        MT: MTConfig<Leaf = [F]>,
+        S: CryptographicSponge,
+        F: PrimeField + Absorb,
+    >(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::new(1, 64).unwrap(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock subprotocol oracle"),
+        );
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript
+            .submit_verifier_current_round(namespace, iop_trace!("mock subprotocol challenge"));
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
+            iop_trace!("mock subprotocol response"),
+        );
+    }
+
+    pub(crate) fn query_and_decide<F: PrimeField, O: RoundOracle<F>>(
+        namespace: NameSpace,
+        transcript_messages: &mut MessagesCollection<F, O>,
+        seed: u64,
+    ) -> bool {
+        let oracle = Self::oracle::<F>(seed);
+        let queried = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&[3, 40], iop_trace!("mock subprotocol query"));
+        let challenge = transcript_messages.verifier_round((namespace, 0))[0]
+            .as_field_elements()
+            .unwrap()[0];
+        let response = transcript_messages
+            .prover_round((namespace, 1))
+            .short_message(0, iop_trace!("mock subprotocol response"))
+            .to_vec();
+        queried == vec![vec![oracle[3]], vec![oracle[40]]]
+            && response == vec![challenge * F::from(seed)]
+    }
+}
+
+/// Runs two instances of `MockSubprotocol` in forked transcripts.
+pub(crate) struct MockForkProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockForkProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let ns_a = transcript.new_namespace(namespace, iop_trace!("fork a"));
+        let ns_b = transcript.new_namespace(namespace, iop_trace!("fork b"));
+        let mut fork_a = transcript.fork();
+        let mut fork_b = transcript.fork();
+        MockSubprotocol::prove(ns_a, &mut fork_a, 1)?;
+        MockSubprotocol::prove(ns_b, &mut fork_b, 2)?;
+        transcript.join(fork_a);
+        transcript.join(fork_b);
+
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("after join"));
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockForkProtocol<F> {
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let ns_a = transcript.new_namespace(namespace, iop_trace!("fork a"));
+        let ns_b = transcript.new_namespace(namespace, iop_trace!("fork b"));
+        let mut fork_a = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+        let mut fork_b = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+        MockSubprotocol::register(ns_a, &mut fork_a);
+        MockSubprotocol::register(ns_b, &mut fork_b);
+        transcript.join(fork_a);
+        transcript.join(fork_b);
+
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("after join"));
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let ns_a = transcript_messages.get_subprotocol_namespace(namespace, 0);
+        let ns_b = transcript_messages.get_subprotocol_namespace(namespace, 1);
+        let a = transcript_messages.decide_namespace(ns_a, |messages| {
+            Ok(MockSubprotocol::query_and_decide(ns_a, messages, 1))
+        });
+        let b = transcript_messages.decide_namespace(ns_b, |messages| {
+            Ok(MockSubprotocol::query_and_decide(ns_b, messages, 2))
+        });
+        Ok(a && b)
+    }
+}
+
+/// Prover of `MockForkProtocol` that runs the second subprotocol with seed
+/// `private_input` instead of 2. Its merkle trees are built honestly, so only
+/// the decision of the second subprotocol fails for another seed.
+pub(crate) struct MockDishonestForkProver<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockDishonestForkProver<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let ns_a = transcript.new_namespace(namespace, iop_trace!("fork a"));
+        let ns_b = transcript.new_namespace(namespace, iop_trace!("fork b"));
+        let mut fork_a = transcript.fork();
+        let mut fork_b = transcript.fork();
+        MockSubprotocol::prove(ns_a, &mut fork_a, 1)?;
+        MockSubprotocol::prove(ns_b, &mut fork_b, *private_input)?;
+        transcript.join(fork_a);
+        transcript.join(fork_b);
+
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("after join"));
+        Ok(())
+    }
+}
+
+/// Parameter of `MockOptionalRoundProtocol`.
+#[derive(Clone, Debug)]
+pub(crate) struct MockOptionalRoundParameter {
+    pub(crate) with_optional_round: bool,
+}
+
+impl ProverParam for MockOptionalRoundParameter {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// Sends an oracle round only if `with_optional_round` is set.
+pub(crate) struct MockOptionalRoundProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockOptionalRoundProtocol<F> {
+    type ProverParameter = MockOptionalRoundParameter;
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![F::from(1u64)])
+            .submit(namespace, iop_trace!("mock required round"))?;
+        if prover_parameter.with_optional_round {
+            transcript
+                .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+                .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(3))
+                .submit(namespace, iop_trace!("mock optional round"))?;
+        }
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock challenge"));
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockOptionalRoundProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = MockOptionalRoundParameter;
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
+            iop_trace!("mock required round"),
+        );
+        transcript.receive_optional_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            verifier_parameter.with_optional_round,
+            iop_trace!("mock optional round"),
+        );
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock challenge"));
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let expected_num_rounds = 1 + verifier_parameter.with_optional_round as usize;
+        if transcript_messages.num_prover_rounds_in_namespace(namespace) != expected_num_rounds {
+            return Ok(false);
+        }
+        if verifier_parameter.with_optional_round {
+            let queried = transcript_messages
+                .prover_round((namespace, 1))
+                .query_point(&[5], iop_trace!("mock optional query"));
+            return Ok(queried == vec![vec![MockSubprotocol::oracle::<F>(3)[5]]]);
+        }
         Ok(true)
     }
 }
+
+/// Sends one oracle, and queries it at a position that depends on the answer
+/// of a previous query.
+pub(crate) struct MockAdaptiveQueryProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockAdaptiveQueryProtocol<F> {
+    const SEED: u64 = 7;
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockAdaptiveQueryProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(Self::SEED))
+            .submit(namespace, iop_trace!("mock adaptive oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockAdaptiveQueryProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock adaptive oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let oracle = MockSubprotocol::oracle::<F>(Self::SEED);
+        let first_position = le_bits_to_usize(&sponge.squeeze_bits(6));
+        let first_answer = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&[first_position], iop_trace!("mock first query"));
+
+        let challenge =
+            squeeze_query_phase_challenge(sponge, &first_answer, &[FieldElementSize::Full])[0];
+        let second_position = le_bits_to_usize(&challenge.into_bigint().to_bits_le()[..6]);
+        let second_answer = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&[second_position], iop_trace!("mock adaptive query"));
+
+        Ok(first_answer == vec![vec![oracle[first_position]]]
+            && second_answer == vec![vec![oracle[second_position]]])
+    }
+}
+
+/// Sends two oracles in one namespace, and queries the second oracle from a
+/// sibling namespace using `MessagesCollection::referenced_oracle`.
+pub(crate) struct MockOracleReferenceProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockOracleReferenceProtocol<F> {
+    pub(crate) const QUERY_POSITIONS: [usize; 2] = [3, 17];
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockOracleReferenceProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let owner_ns = transcript.new_namespace(namespace, iop_trace!("oracle owner"));
+        let user_ns = transcript.new_namespace(namespace, iop_trace!("oracle user"));
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(4))
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(5))
+            .submit(owner_ns, iop_trace!("mock owned oracles"))?;
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![F::one()])
+            .submit(user_ns, iop_trace!("mock user message"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockOracleReferenceProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let owner_ns = transcript.new_namespace(namespace, iop_trace!("oracle owner"));
+        let user_ns = transcript.new_namespace(namespace, iop_trace!("oracle user"));
+        transcript.receive_prover_current_round(
+            owner_ns,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(2)
+            .build(),
+            iop_trace!("mock owned oracles"),
+        );
+        transcript.receive_prover_current_round(
+            user_ns,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
+            iop_trace!("mock user message"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let owner_ns = transcript_messages.get_subprotocol_namespace(namespace, 0);
+        let queried = transcript_messages
+            .referenced_oracle((owner_ns, 0), OracleIndex::new(1, false))
+            .query(
+                &Self::QUERY_POSITIONS,
+                iop_trace!("mock query referenced oracle"),
+            );
+        let oracle = MockSubprotocol::oracle::<F>(5);
+        Ok(queried
+            == Self::QUERY_POSITIONS
+                .iter()
+                .map(|&position| oracle[position])
+                .collect::<Vec<_>>())
+    }
+}
+
+/// Sends the same oracle in two rounds whose merkle trees are salted using an
+/// rng seeded with the private input, and queries both oracles.
+pub(crate) struct MockHidingProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockHidingProtocol<F> {
+    pub(crate) const QUERY_POSITIONS: [usize; 2] = [5, 42];
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockHidingProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let mut rng = StdRng::seed_from_u64(*private_input);
+        for _ in 0..2 {
+            transcript
+                .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+                .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(6))
+                .with_hiding(&mut rng)
+                .submit(namespace, iop_trace!("mock hiding oracle"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for MockHidingProtocol<F> {
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        for _ in 0..2 {
+            transcript.receive_prover_current_round(
+                namespace,
+                ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                    64,
+                    Localization::default(),
+                )
+                .with_num_message_oracles(1)
+                .with_hiding(true)
+                .build(),
+                iop_trace!("mock hiding oracle"),
+            );
+        }
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let oracle = MockSubprotocol::oracle::<F>(6);
+        let expected = Self::QUERY_POSITIONS
+            .iter()
+            .map(|&position| vec![oracle[position]])
+            .collect::<Vec<_>>();
+        Ok((0..2).all(|round| {
+            transcript_messages
+                .prover_round((namespace, round))
+                .query_point(
+                    &Self::QUERY_POSITIONS,
+                    iop_trace!("mock query hiding oracle"),
+                )
+                == expected
+        }))
+    }
+}
+
+/// Sends two rounds with a fixed number of short messages, where the second
+/// round sends fewer messages and is padded.
+pub(crate) struct MockFixedShortMessagesProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockFixedShortMessagesProtocol<F> {
+    pub(crate) const NUM_SHORT_MESSAGES: usize = 2;
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockFixedShortMessagesProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![F::one()])
+            .send_short_message(vec![F::one(), F::one()])
+            .with_fixed_short_messages(Self::NUM_SHORT_MESSAGES)
+            .submit(namespace, iop_trace!("mock full round"))?;
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .with_fixed_short_messages(Self::NUM_SHORT_MESSAGES)
+            .send_short_message(vec![F::from(3u64)])
+            .submit(namespace, iop_trace!("mock padded round"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockFixedShortMessagesProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        for _ in 0..2 {
+            transcript.receive_prover_current_round(
+                namespace,
+                ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                    0,
+                    Localization::default(),
+                )
+                .with_num_short_messages(Self::NUM_SHORT_MESSAGES)
+                .build(),
+                iop_trace!("mock fixed short messages"),
+            );
+        }
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let padded_round = transcript_messages.prover_round((namespace, 1));
+        Ok(
+            padded_round.short_message(0, iop_trace!("mock padded round message"))
+                == [F::from(3u64)]
+                && padded_round
+                    .short_message(1, iop_trace!("mock padding"))
+                    .is_empty(),
+        )
+    }
+}
+
+/// Sends `MockSubprotocol::oracle(4)` and the oracle generated from private
+/// input in two rounds with different localization parameters, and checks
+/// they are equal using `MessagesCollection::verify_oracle_equality`.
+pub(crate) struct MockOracleEqualityProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockOracleEqualityProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::new(1, 64)?)
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(4))
+            .submit(namespace, iop_trace!("mock first oracle"))?;
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::new(2, 64)?)
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(*private_input))
+            .submit(namespace, iop_trace!("mock second oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockOracleEqualityProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        for (localization, trace) in [
+            (1, iop_trace!("mock first oracle")),
+            (2, iop_trace!("mock second oracle")),
+        ] {
+            transcript.receive_prover_current_round(
+                namespace,
+                ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                    64,
+                    Localization::new(localization, 64).unwrap(),
+                )
+                .with_num_message_oracles(1)
+                .build(),
+                trace,
+            );
+        }
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        Ok(transcript_messages.verify_oracle_equality(
+            sponge,
+            ((namespace, 0), OracleIndex::new(0, false)),
+            ((namespace, 1), OracleIndex::new(0, false)),
+            3,
+            iop_trace!("mock oracle equality"),
+        ))
+    }
+}
+
+/// Sends one oracle, then queries it at a number of positions chosen by a
+/// verifier challenge using `num_queries_from_challenge`.
+pub(crate) struct MockDynamicQueryCountProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockDynamicQueryCountProtocol<F> {
+    const SEED: u64 = 8;
+    pub(crate) const MIN_QUERIES: usize = 1;
+    pub(crate) const MAX_QUERIES: usize = 6;
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockDynamicQueryCountProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(Self::SEED))
+            .submit(namespace, iop_trace!("mock dynamic query oracle"))?;
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock query count"));
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockDynamicQueryCountProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock dynamic query oracle"),
+        );
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock query count"));
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let challenge =
+            transcript_messages.verifier_round((namespace, 0))[0].as_field_elements()?[0];
+        let num_queries =
+            num_queries_from_challenge(challenge, Self::MIN_QUERIES, Self::MAX_QUERIES);
+        let positions = (0..num_queries)
+            .map(|_| le_bits_to_usize(&sponge.squeeze_bits(6)))
+            .collect::<Vec<_>>();
+        let answers = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&positions, iop_trace!("mock dynamic queries"));
+        let oracle = MockSubprotocol::oracle::<F>(Self::SEED);
+        Ok(answers
+            == positions
+                .iter()
+                .map(|&position| vec![oracle[position]])
+                .collect::<Vec<_>>())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MockKnownRootParameter {
+    /// Compressed serialization of the root verifier expects.
+    pub(crate) known_root: Vec<u8>,
+}
+
+impl ProverParam for MockKnownRootParameter {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// Sends `MockSubprotocol::oracle(seed)`, where `seed` is the private input.
+/// Verifier requires the root of this round to be `known_root`.
+pub(crate) struct MockKnownRootProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockKnownRootProtocol<F> {
+    type ProverParameter = MockKnownRootParameter;
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(*private_input))
+            .submit(namespace, iop_trace!("mock known root oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockKnownRootProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = MockKnownRootParameter;
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let round = transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock known root oracle"),
+        );
+        let known_root =
+            MT::InnerDigest::deserialize_compressed(&verifier_parameter.known_root[..]).unwrap();
+        transcript.expect_known_root(round, known_root);
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        _namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        _transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        Ok(true)
+    }
+}
+
+/// Sends the private input as an oracle over the codeword domain, in the same
+/// round layout as `Transcript::send_precommitted_oracle`, followed by the
+/// public input as a short message. Verifier requires the root of the oracle
+/// to be `known_root`.
+pub(crate) struct MockPrecommittedOracleProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockPrecommittedOracleProtocol<F> {
+    type ProverParameter = MockKnownRootParameter;
+    type PublicInput = u64;
+    type PrivateInput = Vec<F>;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_oracle_message_without_degree_bound(private_input.clone())
+            .submit(namespace, iop_trace!("mock precommitted oracle"))?;
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![F::from(*public_input)])
+            .submit(namespace, iop_trace!("mock statement"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockPrecommittedOracleProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = MockKnownRootParameter;
+    type PublicInput = u64;
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let known_root =
+            MT::InnerDigest::deserialize_compressed(&verifier_parameter.known_root[..]).unwrap();
+        transcript.receive_precommitted_oracle(
+            namespace,
+            known_root,
+            iop_trace!("mock precommitted oracle"),
+        );
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
+            iop_trace!("mock statement"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let statement = transcript_messages
+            .prover_round((namespace, 1))
+            .short_message(0, iop_trace!("mock statement"))
+            .to_vec();
+        Ok(statement == vec![F::from(*public_input)])
+    }
+}
+
+/// Sends `MockSubprotocol::oracle(seed)`, where `seed` is the private input,
+/// and outputs the oracle values at positions 3 and 40 instead of a decision.
+pub(crate) struct MockEvaluationOutputProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockEvaluationOutputProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(*private_input))
+            .submit(namespace, iop_trace!("mock evaluated oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockEvaluationOutputProtocol<F>
+{
+    type VerifierOutput = Vec<F>;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock evaluated oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        Ok(transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&[3, 40], iop_trace!("mock evaluations"))
+            .into_iter()
+            .map(|answer| answer[0])
+            .collect())
+    }
+}
+
+/// Sends an oracle of `MockSplitOracleProtocol::LENGTH` elements split into
+/// two segments, and checks its values at positions around the boundary of
+/// the segments.
+pub(crate) struct MockSplitOracleProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockSplitOracleProtocol<F> {
+    /// Logical length of the split oracle.
+    pub(crate) const LENGTH: usize = 512;
+    /// Length of each segment.
+    pub(crate) const SEGMENT_LENGTH: usize = 256;
+    /// Logical positions queried by verifier.
+    pub(crate) const POSITIONS: [usize; 5] = [7, 255, 256, 300, 511];
+
+    fn oracle() -> Vec<F> {
+        (0..Self::LENGTH as u64)
+            .map(|i| F::from(3 * i + 1))
+            .collect()
+    }
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockSplitOracleProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.send_split_oracle(
+            namespace,
+            Self::oracle(),
+            Self::SEGMENT_LENGTH,
+            Localization::default(),
+            iop_trace!("mock split oracle"),
+        )?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockSplitOracleProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_split_oracle(
+            namespace,
+            Self::LENGTH,
+            Self::SEGMENT_LENGTH,
+            Localization::default(),
+            iop_trace!("mock split oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let segments = transcript_messages
+            .prover_round_refs_in_namespace(namespace)
+            .clone();
+        let answers = transcript_messages.query_split_oracle(
+            &segments,
+            &Self::POSITIONS,
+            iop_trace!("mock split oracle query"),
+        );
+        let oracle = Self::oracle();
+        Ok(Self::POSITIONS
+            .iter()
+            .zip(answers)
+            .all(|(&position, answer)| answer == oracle[position]))
+    }
+}
+
+/// Sends `NUM_ROUNDS` rounds in one namespace, where round `i` has short
+/// message `[i]`. Prover and verifier check `current_round_index` before each
+/// round.
+pub(crate) struct MockRoundIndexProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockRoundIndexProtocol<F> {
+    pub(crate) const NUM_ROUNDS: usize = 3;
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockRoundIndexProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        for i in 0..Self::NUM_ROUNDS {
+            let index = transcript.current_round_index(namespace);
+            assert_eq!(index, i);
+            let round = transcript
+                .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+                .send_short_message(vec![F::from(index as u64)])
+                .submit(namespace, iop_trace!("mock indexed round"))?;
+            let reference = (namespace, index).to_prover_msg_round_ref(&transcript.bookkeeper);
+            assert_eq!(reference.index, round.index);
+        }
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockRoundIndexProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        for i in 0..Self::NUM_ROUNDS {
+            assert_eq!(transcript.current_round_index(namespace), i);
+            transcript.receive_prover_current_round(
+                namespace,
+                ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                    0,
+                    Localization::default(),
+                )
+                .with_num_short_messages(1)
+                .build(),
+                iop_trace!("mock indexed round"),
+            );
+        }
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        Ok((0..Self::NUM_ROUNDS).all(|i| {
+            transcript_messages
+                .prover_round((namespace, i))
+                .short_message(0, iop_trace!("mock indexed round"))
+                == [F::from(i as u64)]
+        }))
+    }
+}
+
+/// Sends the short message `MESSAGE` in each of `NUM_ROUNDS` rounds.
+pub(crate) struct MockRepeatedShortMessageProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockRepeatedShortMessageProtocol<F> {
+    pub(crate) const NUM_ROUNDS: usize = 3;
+    const MESSAGE: [u64; 4] = [3, 1, 4, 1];
+
+    fn message() -> Vec<F> {
+        Self::MESSAGE.iter().map(|&x| F::from(x)).collect()
+    }
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockRepeatedShortMessageProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        for _ in 0..Self::NUM_ROUNDS {
+            transcript
+                .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+                .send_short_message(Self::message())
+                .submit(namespace, iop_trace!("mock repeated short message"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockRepeatedShortMessageProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        for _ in 0..Self::NUM_ROUNDS {
+            transcript.receive_prover_current_round(
+                namespace,
+                ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                    0,
+                    Localization::default(),
+                )
+                .with_num_short_messages(1)
+                .build(),
+                iop_trace!("mock repeated short message"),
+            );
+        }
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let message = Self::message();
+        Ok((0..Self::NUM_ROUNDS).all(|i| {
+            transcript_messages
+                .prover_round((namespace, i))
+                .short_message(0, iop_trace!("mock repeated short message"))
+                == message.as_slice()
+        }))
+    }
+}
+
+/// Parameter of `MockLDTQueryCountProtocol`.
+#[derive(Clone, Debug)]
+pub(crate) struct MockLDTQueryCountParameter {
+    pub(crate) num_ldt_queries: usize,
+}
+
+impl ProverParam for MockLDTQueryCountParameter {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// Sends one polynomial of degree bound 8, and takes the number of LDT
+/// queries from its parameter.
+pub(crate) struct MockLDTQueryCountProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockLDTQueryCountProtocol<F> {
+    type ProverParameter = MockLDTQueryCountParameter;
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let poly = DensePolynomial::from_coefficients_vec((1..=8u64).map(F::from).collect());
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_univariate_polynomial(&poly, 8)
+            .submit(namespace, iop_trace!("mock polynomial"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockLDTQueryCountProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = MockLDTQueryCountParameter;
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(transcript)
+            .with_reed_solomon_codes_degree_bounds(vec![8])
+            .build();
+        transcript.receive_prover_current_round(
+            namespace,
+            expected_info,
+            iop_trace!("mock polynomial"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        _namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        _transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        Ok(true)
+    }
+
+    fn ldt_num_queries(verifier_parameter: &Self::VerifierParameter) -> Option<usize> {
+        Some(verifier_parameter.num_ldt_queries)
+    }
+}
+
+/// Sends an oracle of length 2, which is committed inline under
+/// `TranscriptVersion::V4`, and an oracle of length 64, which is not. Then
+/// queries both oracles.
+pub(crate) struct MockInlineOracleProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockInlineOracleProtocol<F> {
+    pub(crate) const TINY_ORACLE: [u64; 2] = [5, 7];
+    pub(crate) const TINY_QUERIES: [usize; 3] = [1, 0, 1];
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockInlineOracleProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(2, Localization::default())
+            .send_oracle_message_without_degree_bound(
+                Self::TINY_ORACLE.iter().map(|&x| F::from(x)).collect(),
+            )
+            .submit(namespace, iop_trace!("mock tiny oracle"))?;
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::new(1, 64)?)
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(4))
+            .submit(namespace, iop_trace!("mock large oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockInlineOracleProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                2,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock tiny oracle"),
+        );
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::new(1, 64).unwrap(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock large oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let tiny = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&Self::TINY_QUERIES, iop_trace!("mock tiny oracle query"));
+        let large = transcript_messages
+            .prover_round((namespace, 1))
+            .query_point(&[3, 40], iop_trace!("mock large oracle query"));
+        let expected_large = MockSubprotocol::oracle::<F>(4);
+        Ok(Self::TINY_QUERIES
+            .iter()
+            .zip(tiny)
+            .all(|(&position, leaf)| leaf == vec![F::from(Self::TINY_ORACLE[position])])
+            && large == vec![vec![expected_large[3]], vec![expected_large[40]]])
+    }
+}
+
+/// Parameter of `MockSiblingBindingProtocol`.
+#[derive(Clone, Debug)]
+pub(crate) struct MockSiblingBindingParameter<F: PrimeField> {
+    /// Commit phase digest of the sibling proof after its first round.
+    pub(crate) sibling_digest: F,
+}
+
+impl<F: PrimeField> ProverParam for MockSiblingBindingParameter<F> {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// Sends the private input as a short message, binds the sibling digest in
+/// its parameter, and sends back a challenge squeezed after binding.
+pub(crate) struct MockSiblingBindingProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockSiblingBindingProtocol<F> {
+    /// Send the first round, whose commit phase digest is bound by the
+    /// sibling proof.
+    pub(crate) fn send_message<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        transcript: &mut Transcript<MT, S, F>,
+        message: u64,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![F::from(message)])
+            .submit(namespace, iop_trace!("mock sibling message"))?;
+        Ok(())
+    }
+
+    fn short_message_info() -> ProverRoundMessageInfo {
+        ProverRoundMessageInfo::new_using_custom_length_and_localization(0, Localization::default())
+            .with_num_short_messages(1)
+            .build()
+    }
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockSiblingBindingProtocol<F> {
+    type ProverParameter = MockSiblingBindingParameter<F>;
+    type PublicInput = ();
+    type PrivateInput = u64;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        Self::send_message(namespace, transcript, *private_input)?;
+        transcript.bind_sibling(prover_parameter.sibling_digest);
+        let challenge = transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock bound challenge"));
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(challenge)
+            .submit(namespace, iop_trace!("mock bound challenge echo"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockSiblingBindingProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = MockSiblingBindingParameter<F>;
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            Self::short_message_info(),
+            iop_trace!("mock sibling message"),
+        );
+        transcript.bind_sibling(verifier_parameter.sibling_digest);
+        transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+        transcript.submit_verifier_current_round(namespace, iop_trace!("mock bound challenge"));
+        transcript.receive_prover_current_round(
+            namespace,
+            Self::short_message_info(),
+            iop_trace!("mock bound challenge echo"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let challenge = transcript_messages.verifier_round((namespace, 0))[0]
+            .as_field_elements()?
+            .to_vec();
+        Ok(transcript_messages
+            .prover_round((namespace, 1))
+            .short_message(0, iop_trace!("mock bound challenge echo"))
+            == challenge.as_slice())
+    }
+}
+
+/// Sends one oracle of length 64 with one element per leaf, and queries it at
+/// `POSITIONS`, which are adjacent.
+pub(crate) struct MockAdjacentQueryProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockAdjacentQueryProtocol<F> {
+    pub(crate) const POSITIONS: [usize; 4] = [20, 21, 22, 23];
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockAdjacentQueryProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(5))
+            .submit(namespace, iop_trace!("mock adjacent oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockAdjacentQueryProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock adjacent oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let oracle = MockSubprotocol::oracle::<F>(5);
+        let answers = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&Self::POSITIONS, iop_trace!("mock adjacent query"));
+        Ok(Self::POSITIONS
+            .iter()
+            .zip(answers)
+            .all(|(&position, answer)| answer == vec![oracle[position]]))
+    }
+}
+
+/// Samples `num` distinct positions `base + i * stride`, where `base` is
+/// squeezed from the sponge and `stride` is the domain size divided by `num`
+/// rounded up to a power of two.
+pub(crate) struct MockStridedSampler;
+
+impl QuerySampler for MockStridedSampler {
+    fn sample_positions<S: CryptographicSponge>(
+        &self,
+        sponge: &mut S,
+        num: usize,
+        domain_size: usize,
+    ) -> Result<Vec<usize>, Error> {
+        assert!(num <= domain_size, "too many positions");
+        let base = le_bits_to_usize(&sponge.squeeze_bits(ark_std::log2(domain_size) as usize));
+        let stride = domain_size >> ark_std::log2(num);
+        Ok((0..num)
+            .map(|i| (base + i * stride) % domain_size)
+            .collect())
+    }
+
+    fn samples_distinct_positions(&self) -> bool {
+        true
+    }
+}
+
+/// Sends one oracle of length 64 with one element per leaf, and queries it at
+/// `NUM_QUERIES` positions sampled using `MockStridedSampler`. Verifier
+/// outputs whether the answers are correct, and the sampled positions.
+pub(crate) struct MockSampledQueryProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockSampledQueryProtocol<F> {
+    pub(crate) const NUM_QUERIES: usize = 6;
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockSampledQueryProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+            .send_oracle_message_without_degree_bound(MockSubprotocol::oracle::<F>(6))
+            .submit(namespace, iop_trace!("mock sampled oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockSampledQueryProtocol<F>
+{
+    type VerifierOutput = (bool, Vec<usize>);
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                64,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("mock sampled oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let oracle = MockSubprotocol::oracle::<F>(6);
+        let positions = MockStridedSampler.sample_positions(sponge, Self::NUM_QUERIES, 64)?;
+        let answers = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&positions, iop_trace!("mock sampled query"));
+        let valid = positions
+            .iter()
+            .zip(answers)
+            .all(|(&position, answer)| answer == vec![oracle[position]]);
+        Ok((valid, positions))
+    }
+}
+
+/// Runs two identical instances of `MockSubprotocol` in forked transcripts,
+/// and then sends a low-degree polynomial, so that the proof is also checked
+/// by LDT.
+pub(crate) struct MockIdenticalForkProtocol<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> MockIdenticalForkProtocol<F> {
+    const SEED: u64 = 3;
+
+    fn polynomial() -> DensePolynomial<F> {
+        DensePolynomial::from_coefficients_vec((1..=8u64).map(F::from).collect())
+    }
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for MockIdenticalForkProtocol<F> {
+    type ProverParameter = ();
+    type PublicInput = ();
+    type PrivateInput = ();
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        _private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        _prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let ns_a = transcript.new_namespace(namespace, iop_trace!("identical fork a"));
+        let ns_b = transcript.new_namespace(namespace, iop_trace!("identical fork b"));
+        let mut fork_a = transcript.fork();
+        let mut fork_b = transcript.fork();
+        MockSubprotocol::prove(ns_a, &mut fork_a, Self::SEED)?;
+        MockSubprotocol::prove(ns_b, &mut fork_b, Self::SEED)?;
+        transcript.join(fork_a);
+        transcript.join(fork_b);
+
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_univariate_polynomial(&Self::polynomial(), 8)
+            .submit(namespace, iop_trace!("identical fork polynomial"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F>
+    for MockIdenticalForkProtocol<F>
+{
+    type VerifierOutput = bool;
+    type VerifierParameter = ();
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        _verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let ns_a = transcript.new_namespace(namespace, iop_trace!("identical fork a"));
+        let ns_b = transcript.new_namespace(namespace, iop_trace!("identical fork b"));
+        let mut fork_a = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+        let mut fork_b = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+        MockSubprotocol::register(ns_a, &mut fork_a);
+        MockSubprotocol::register(ns_b, &mut fork_b);
+        transcript.join(fork_a);
+        transcript.join(fork_b);
+
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(transcript)
+            .with_reed_solomon_codes_degree_bounds(vec![8])
+            .build();
+        transcript.receive_prover_current_round(
+            namespace,
+            expected_info,
+            iop_trace!("identical fork polynomial"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let ns_a = transcript_messages.get_subprotocol_namespace(namespace, 0);
+        let ns_b = transcript_messages.get_subprotocol_namespace(namespace, 1);
+        let a = MockSubprotocol::query_and_decide(ns_a, transcript_messages, Self::SEED);
+        let b = MockSubprotocol::query_and_decide(ns_b, transcript_messages, Self::SEED);
+        Ok(a && b)
+    }
+}
@@ -6,26 +6,66 @@ pub(crate) mod mock;
 
 use crate::{
     bcs::{
-        prover::BCSProof,
-        simulation_transcript::SimulationTranscript,
-        tests::mock::{MockTest1Verifier, MockTestProver},
-        verifier::BCSVerifier,
-        MTHashParameters,
+        aggregation::AggregatedBCSProof,
+        commitment::{commit_oracle, open_oracle, verify_opening},
+        config::{BCSConfig, BCS},
+        interning::InternedBCSProof,
+        prover::{BCSProof, DebugProof},
+        simulation_transcript::{ChallengeSource, RngChallengeSource, SimulationTranscript},
+        tests::mock::{
+            MockAdaptiveQueryProtocol, MockAdjacentQueryProtocol, MockDishonestForkProver,
+            MockDynamicQueryCountProtocol, MockEvaluationOutputProtocol,
+            MockFixedShortMessagesProtocol, MockForkProtocol, MockHidingProtocol,
+            MockIdenticalForkProtocol, MockInlineOracleProtocol, MockKnownRootParameter,
+            MockKnownRootProtocol, MockLDTQueryCountParameter, MockLDTQueryCountProtocol,
+            MockOptionalRoundParameter, MockOptionalRoundProtocol, MockOracleEqualityProtocol,
+            MockOracleReferenceProtocol, MockPrecommittedOracleProtocol,
+            MockRepeatedShortMessageProtocol, MockRoundIndexProtocol, MockSampledQueryProtocol,
+            MockSiblingBindingParameter, MockSiblingBindingProtocol, MockSplitOracleProtocol,
+            MockStridedSampler, MockSubprotocol, MockTest1Verifier, MockTestProver,
+        },
+        transcript::{
+            AbsorbOp, AbsorbedValue, HashToFieldConfig, LDTInfo, RoundMerkleTree, Transcript,
+            TranscriptVersion,
+        },
+        verifier::{BCSVerifier, ProofLimits, QueryComplexity},
+    },
+    iop::{
+        bookkeeper::{MessageBookkeeper, NameSpace, ROOT_NAMESPACE_DOMAIN_SEPARATOR},
+        message::{
+            DegreeBound, Localization, MessagesCollection, ProverRoundMessageInfo, VerifierMessage,
+        },
+        oracles::{RoundOracle, SuccinctRoundMessage},
+        prover::{IOPProver, SequentialProver},
+        verifier::{
+            squeeze_query_phase_challenge, IOPVerifier, QuerySampler, SequentialVerifier,
+            UniformUniqueSampler,
+        },
     },
-    iop::{bookkeeper::NameSpace, verifier::IOPVerifier},
     ldt::{
-        rl_ldt::{LinearCombinationLDT, LinearCombinationLDTParameters},
-        LDT,
+        rl_ldt::{
+            CoefficientStrategy, LinearCombinationLDT, LinearCombinationLDTParameters,
+            QueryStrategy,
+        },
+        NoLDT, LDT,
     },
-    test_utils::poseidon_parameters,
+    test_utils::{
+        commit_phase_transcript, mt_hash_parameters, new_transcript, new_transcript_with_version,
+        poseidon_parameters, prove_and_verify, record_squeeze_outputs, tamper_answer,
+    },
+    univariate_opening::{UnivariateOpening, UnivariateOpeningParameter},
+    BCSError, Error,
 };
 use ark_crypto_primitives::{
     crh::poseidon,
     merkle_tree::{Config, IdentityDigestConverter},
 };
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
 use ark_ldt::{domain::Radix2CosetDomain, fri::FRIParameters};
-use ark_sponge::{poseidon::PoseidonSponge, CryptographicSponge};
-use ark_std::{vec, One};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_sponge::{poseidon::PoseidonSponge, Absorb, CryptographicSponge, FieldElementSize};
+use ark_std::{boxed::Box, collections::BTreeSet, test_rng, vec, vec::Vec, One, UniformRand, Zero};
 
 pub(crate) type Fr = ark_bls12_381::Fr;
 pub(crate) type H = poseidon::CRH<Fr>;
@@ -53,12 +93,11 @@ fn test_bcs() {
     let ldt_parameters = LinearCombinationLDTParameters {
         fri_parameters,
         num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
     };
     let sponge = PoseidonSponge::new(&poseidon_parameters());
-    let mt_hash_param = MTHashParameters::<FieldMTConfig> {
-        leaf_hash_param: poseidon_parameters(),
-        inner_hash_param: poseidon_parameters(),
-    };
+    let mt_hash_param = mt_hash_parameters();
     let bcs_proof = BCSProof::generate::<
         MockTest1Verifier<Fr>,
         MockTestProver<Fr>,
@@ -103,3 +142,5129 @@ fn test_bcs() {
         "test verifier returns false"
     );
 }
+
+#[test]
+/// Test that two independent subprotocols simulated in forked transcripts
+/// verify.
+fn test_fork_and_join() {
+    let sponge = PoseidonSponge::new(&poseidon_parameters());
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockForkProtocol<Fr>,
+        MockForkProtocol<Fr>,
+        _,
+    >(sponge, &(), &(), &(), mt_hash_param.clone())
+    .expect("fail to prove");
+    assert_eq!(
+        bcs_proof.prover_iop_messages_by_round.len(),
+        2 * MockSubprotocol::NUM_PROVER_ROUNDS
+    );
+
+    let sponge = PoseidonSponge::new(&poseidon_parameters());
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockForkProtocol<Fr>, _>(
+            sponge,
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param
+        )
+        .expect("verification failed"),
+        "forked verifier returns false"
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+/// Test that sibling forks of a simulation transcript can be simulated on
+/// separate threads, starting with the last fork, and reconstruct the same
+/// verifier messages as prover.
+fn test_simulate_forks_on_threads() {
+    let proof =
+        BCSProof::generate_with_ldt_disabled::<MockForkProtocol<Fr>, MockForkProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            mt_hash_parameters(),
+        )
+        .expect("fail to prove");
+    // threads spawned by `std::thread::spawn` cannot borrow the proof
+    let proof: &'static BCSProof<FieldMTConfig, Fr> = Box::leak(Box::new(proof));
+    let mut transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test forks on threads"),
+    );
+    let root = NameSpace::root(iop_trace!("test forks on threads"));
+    let ns_a = transcript.new_namespace(root, iop_trace!("fork a"));
+    let ns_b = transcript.new_namespace(root, iop_trace!("fork b"));
+    let mut fork_a = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+    let mut fork_b = transcript.fork(MockSubprotocol::NUM_PROVER_ROUNDS);
+    let handle_b = std::thread::spawn(move || {
+        MockSubprotocol::register(ns_b, &mut fork_b);
+        fork_b
+    });
+    let handle_a = std::thread::spawn(move || {
+        MockSubprotocol::register(ns_a, &mut fork_a);
+        fork_a
+    });
+    let fork_b = handle_b.join().expect("fork b panicked");
+    let fork_a = handle_a.join().expect("fork a panicked");
+    transcript.join(fork_a);
+    transcript.join(fork_b);
+    transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+    transcript.submit_verifier_current_round(root, iop_trace!("after join"));
+
+    let prover_transcript =
+        commit_phase_transcript::<MockForkProtocol<Fr>, NoLDT<Fr>>(&(), &(), &(), &None)
+            .expect("fail to run commit phase");
+    assert_eq!(
+        transcript.reconstructed_verifier_messages,
+        prover_transcript.verifier_messages
+    );
+    assert_eq!(
+        transcript.num_prover_rounds_submitted(),
+        proof.prover_iop_messages_by_round.len()
+    );
+
+    // prover forks can be sent to other threads as well
+    fn assert_send<T: Send>() {}
+    assert_send::<Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr>>();
+}
+
+#[test]
+/// Test that sibling forks running the same subprotocol get different
+/// challenges, and that the protocol verifies with LDT.
+fn test_identical_forks() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let root = NameSpace::root(iop_trace!("test identical forks"));
+    MockIdenticalForkProtocol::prove(root, &(), &(), &mut transcript, &()).unwrap();
+    let challenges = (0..2)
+        .map(|index| {
+            let namespace = transcript.bookkeeper.get_subspace(root, index);
+            let round = transcript
+                .bookkeeper
+                .get_message_indices(namespace)
+                .verifier_messages[0];
+            transcript.verifier_messages[round.index].clone()
+        })
+        .collect::<Vec<_>>();
+    assert_ne!(challenges[0], challenges[1]);
+
+    assert!(prove_and_verify::<
+        MockIdenticalForkProtocol<Fr>,
+        MockIdenticalForkProtocol<Fr>,
+        LinearCombinationLDT<Fr>,
+    >(&(), &(), &(), &ldt_parameters)
+    .expect("round trip failed"));
+}
+
+#[test]
+/// Test that a localization parameter larger than `log2` of the oracle length
+/// is rejected.
+fn test_localization_out_of_range() {
+    assert_eq!(Localization::new(8, 256).unwrap().get(), 8);
+    let err = Localization::new(9, 256).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidLocalization {
+            localization_parameter: 9,
+            oracle_length: 256,
+        })
+    );
+    assert!(Localization::new(1, 0).is_err());
+}
+
+#[test]
+/// Test that a localization parameter is rejected for an oracle whose length
+/// is not a power of two, even if `2 ^ localization` fits in the length.
+fn test_localization_non_power_of_two_length() {
+    for localization_parameter in [0, 2, 3] {
+        let err = Localization::new(localization_parameter, 5).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<BCSError>(),
+            Some(&BCSError::InvalidOracleLength { oracle_length: 5 })
+        );
+    }
+    assert_eq!(Localization::new(2, 4).unwrap().get(), 2);
+    assert!(Localization::new(3, 4).is_err());
+}
+
+#[test]
+/// Test that `squeeze_verifier_bytes_as_field` matches squeezing bytes and
+/// converting them using `ToConstraintField`.
+fn test_squeeze_verifier_bytes_as_field() {
+    let bytes_per_element = ((Fr::MODULUS_BIT_SIZE - 1) / 8) as usize;
+    let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+    sponge.absorb(&ROOT_NAMESPACE_DOMAIN_SEPARATOR.to_vec());
+    sponge.absorb(
+        &TranscriptVersion::default()
+            .domain_separator()
+            .unwrap()
+            .to_vec(),
+    );
+    let expected: Vec<Fr> = sponge
+        .squeeze_bytes(3 * bytes_per_element)
+        .to_field_elements()
+        .unwrap();
+    assert_eq!(expected.len(), 3);
+
+    let mut transcript = new_transcript(None, None);
+    assert_eq!(transcript.squeeze_verifier_bytes_as_field(3), expected);
+
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test squeeze"),
+    );
+    assert_eq!(
+        simulation_transcript.squeeze_verifier_bytes_as_field(3),
+        expected
+    );
+}
+
+#[test]
+/// Test that a sparse oracle commits to the same merkle root as its dense
+/// equivalent, hashing only the nodes above non-default leaves, and that its
+/// authentication paths are valid.
+fn test_sparse_oracle_root() {
+    let length = 1 << 16;
+    let default = Fr::from(7u64);
+    let nonzeros = [
+        (0, Fr::from(1u64)),
+        (12345, Fr::from(2u64)),
+        (length - 1, Fr::from(3u64)),
+    ];
+    let mut dense = vec![default; length];
+    nonzeros
+        .iter()
+        .for_each(|&(position, value)| dense[position] = value);
+
+    let roots = (0..2)
+        .map(|i| {
+            let mut transcript = new_transcript(None, None);
+            let round = transcript.add_prover_round_with_custom_length_and_localization(
+                length,
+                Localization::new(1, length).unwrap(),
+            );
+            let round = if i == 0 {
+                round.send_oracle_message_without_degree_bound(dense.clone())
+            } else {
+                round.send_sparse_oracle_message_without_degree_bound(default, &nonzeros)
+            };
+            round
+                .submit(NameSpace::root(iop_trace!()), iop_trace!("sparse oracle"))
+                .unwrap();
+            if i == 1 {
+                let tree = match &transcript.merkle_tree_for_each_round[0] {
+                    Some(RoundMerkleTree::Sparse(tree)) => tree,
+                    _ => panic!("sparse round should be committed using a sparse merkle tree"),
+                };
+                // one all-default node per level, and at most one node per
+                // level above each of the 3 non-default leaves
+                let height = 15;
+                assert!(tree.num_hash_invocations <= height + 3 * height);
+                let cosets = &transcript.prover_message_oracles[0].all_coset_elements;
+                for leaf_index in [0, 1, 12345, 20000, length / 2 - 1] {
+                    let leaf = cosets[leaf_index].concat();
+                    assert!(tree
+                        .generate_proof(leaf_index)
+                        .verify(
+                            &transcript.hash_params.leaf_hash_param,
+                            &transcript.hash_params.inner_hash_param,
+                            &tree.root(),
+                            leaf.as_slice(),
+                        )
+                        .unwrap());
+                }
+            }
+            transcript.merkle_tree_roots()
+        })
+        .collect::<Vec<_>>();
+    assert!(roots[0][0].is_some());
+    assert_eq!(roots[0], roots[1]);
+}
+
+#[test]
+/// Test that prover and verifier derive the same valid permutation.
+fn test_squeeze_verifier_permutation() {
+    let mut transcript = new_transcript(None, None);
+    let permutation = transcript.squeeze_verifier_permutation(10);
+
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test permutation"),
+    );
+    assert_eq!(
+        simulation_transcript.squeeze_verifier_permutation(10),
+        permutation
+    );
+
+    let mut sorted = permutation;
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "timing")]
+#[test]
+/// Test that timing records a non-zero duration for each prover round.
+fn test_round_timings() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let root = NameSpace::root(iop_trace!("test timing"));
+    MockTestProver::prove(root, &(), &(), &mut transcript, &()).unwrap();
+
+    let timings = transcript.round_timings();
+    assert_eq!(timings.len(), transcript.prover_message_oracles.len());
+    timings
+        .iter()
+        .enumerate()
+        .for_each(|(i, &(namespace, index, duration))| {
+            assert_eq!(namespace, root);
+            assert_eq!(index, i);
+            assert!(duration.as_nanos() > 0);
+        });
+}
+
+#[test]
+/// Test that an optional round can be both present and absent, as long as
+/// prover and verifier parameters agree.
+fn test_optional_round() {
+    let mt_hash_param = mt_hash_parameters();
+    for &with_optional_round in &[true, false] {
+        let param = MockOptionalRoundParameter {
+            with_optional_round,
+        };
+        let sponge = PoseidonSponge::new(&poseidon_parameters());
+        let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+            MockOptionalRoundProtocol<Fr>,
+            MockOptionalRoundProtocol<Fr>,
+            _,
+        >(sponge, &(), &(), &param, mt_hash_param.clone())
+        .expect("fail to prove");
+        assert_eq!(
+            bcs_proof.prover_iop_messages_by_round.len(),
+            1 + with_optional_round as usize
+        );
+
+        let sponge = PoseidonSponge::new(&poseidon_parameters());
+        assert!(
+            BCSVerifier::verify_with_ldt_disabled::<MockOptionalRoundProtocol<Fr>, _>(
+                sponge,
+                &bcs_proof,
+                &(),
+                &param,
+                mt_hash_param.clone()
+            )
+            .expect("verification failed"),
+            "optional round verifier returns false"
+        );
+    }
+}
+
+#[test]
+/// Test that namespaces can be used as hash map keys, and that `to_bytes` does
+/// not depend on trace.
+fn test_namespace_hash_and_bytes() {
+    use hashbrown::HashMap;
+
+    let mut bookkeeper = MessageBookkeeper::new(iop_trace!("test namespace"));
+    let root = NameSpace::root(iop_trace!("root"));
+    let child = bookkeeper.new_namespace(iop_trace!("child"), root.id);
+    let grandchild = bookkeeper.new_namespace(iop_trace!("grandchild"), child.id);
+
+    let mut map = HashMap::new();
+    map.insert(root, "root");
+    map.insert(child, "child");
+    map.insert(grandchild, "grandchild");
+    // same namespace with a different trace
+    let child_elsewhere = NameSpace::from_bytes(&child.to_bytes(), iop_trace!()).unwrap();
+    assert_eq!(map.get(&child_elsewhere), Some(&"child"));
+    assert_eq!(map.len(), 3);
+
+    for ns in &[root, child, grandchild] {
+        let decoded = NameSpace::from_bytes(&ns.to_bytes(), iop_trace!("decoded")).unwrap();
+        assert_eq!(decoded, *ns);
+        assert_eq!(decoded.parent_id, ns.parent_id);
+        assert_eq!(decoded.to_bytes(), ns.to_bytes());
+    }
+    assert_eq!(NameSpace::root(iop_trace!("a")).to_bytes(), [0u8; 16]);
+    assert!(NameSpace::from_bytes(&[0u8; 15], iop_trace!()).is_none());
+}
+
+#[test]
+/// Test `test_bcs` through `BCS` and `BCSConfig`.
+fn test_bcs_config() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let config = BCSConfig::<_, _, _, LinearCombinationLDT<Fr>>::new(
+        PoseidonSponge::new(&poseidon_parameters()),
+        mt_hash_parameters(),
+        LinearCombinationLDTParameters {
+            fri_parameters,
+            num_queries: 7,
+            query_sampler: QueryStrategy::Independent,
+            coefficient_strategy: CoefficientStrategy::IndependentRandom,
+        },
+    );
+    let proof =
+        BCS::prove::<MockTest1Verifier<Fr>, MockTestProver<Fr>, _, _>(&(), &(), &(), &config)
+            .expect("fail to prove");
+    assert!(
+        BCS::verify::<MockTest1Verifier<Fr>, _, _>(&proof, &(), &(), &config)
+            .expect("verification failed")
+    );
+}
+
+#[test]
+/// Test that `squeeze_verifier_field_element_from_bits` is in
+/// `[0, 2^num_bits)` and matches between prover and verifier.
+fn test_squeeze_verifier_field_element_from_bits() {
+    let mut transcript = new_transcript(None, None);
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test squeeze bits"),
+    );
+    for _ in 0..16 {
+        let challenge = transcript.squeeze_verifier_field_element_from_bits(40);
+        assert!(challenge.into_bigint().num_bits() <= 40);
+        assert_eq!(
+            simulation_transcript.squeeze_verifier_field_element_from_bits(40),
+            challenge
+        );
+    }
+}
+
+#[test]
+/// Test that submitting prover rounds out of order gives the same transcript as
+/// submitting them in order.
+fn test_submit_at_out_of_order() {
+    let submission_orders = [vec![0, 1, 2], vec![2, 0, 1]];
+    let results = submission_orders
+        .iter()
+        .map(|order| {
+            let mut transcript = new_transcript(None, None);
+            let namespace = NameSpace::root(iop_trace!());
+            for &index in order {
+                let round_ref = transcript
+                    .add_prover_round_with_custom_length_and_localization(
+                        16,
+                        Localization::default(),
+                    )
+                    .send_oracle_message_without_degree_bound(
+                        (0..16u64)
+                            .map(|x| Fr::from(x + 16 * index as u64))
+                            .collect(),
+                    )
+                    .send_short_message(vec![Fr::from(index as u64)])
+                    .submit_at(index, namespace, iop_trace!("out of order round"))
+                    .unwrap();
+                assert_eq!(round_ref.index, index);
+            }
+            assert!(!transcript.is_pending_message_available());
+            let challenge =
+                transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full; 2]);
+            (transcript.merkle_tree_roots(), challenge)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results[0].0.len(), 3);
+    assert_eq!(results[0], results[1]);
+}
+
+/// A challenge source that returns fixed values.
+struct FixedChallengeSource {
+    field_element: Fr,
+    byte: u8,
+    bit: bool,
+}
+
+impl ChallengeSource<Fr> for FixedChallengeSource {
+    fn next_field_elements(&mut self, num_elements: usize) -> Vec<Fr> {
+        vec![self.field_element; num_elements]
+    }
+
+    fn next_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        vec![self.byte; num_bytes]
+    }
+
+    fn next_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        vec![self.bit; num_bits]
+    }
+}
+
+#[test]
+/// Test that simulation transcript takes verifier messages from the challenge
+/// source.
+fn test_challenge_source() {
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test challenge source"),
+    )
+    .with_challenge_source(Box::new(FixedChallengeSource {
+        field_element: Fr::from(42u64),
+        byte: 0x01,
+        bit: true,
+    }));
+    let namespace = NameSpace::root(iop_trace!());
+
+    transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full; 3]);
+    assert_eq!(transcript.squeeze_verifier_field_bytes(4), vec![0x01; 4]);
+    assert_eq!(transcript.squeeze_verifier_field_bits(5), vec![true; 5]);
+    assert_eq!(
+        transcript.squeeze_verifier_field_element_from_bits(5),
+        Fr::from(31u64)
+    );
+    transcript.submit_verifier_current_round(namespace, iop_trace!("fixed challenges"));
+
+    let messages = &transcript.reconstructed_verifier_messages[0];
+    assert_eq!(
+        messages[0].clone().try_into_field_elements().unwrap(),
+        vec![Fr::from(42u64); 3]
+    );
+    let mut sponge = PoseidonSponge::<Fr>::new(&poseidon_parameters());
+    sponge.absorb(&ROOT_NAMESPACE_DOMAIN_SEPARATOR.to_vec());
+    sponge.absorb(
+        &TranscriptVersion::default()
+            .domain_separator()
+            .unwrap()
+            .to_vec(),
+    );
+    assert_eq!(
+        transcript.sponge.squeeze_bytes(32),
+        sponge.squeeze_bytes(32)
+    );
+}
+
+#[test]
+/// Test that modular query reduces positions modulo oracle length.
+fn test_query_modular() {
+    let mut transcript = new_transcript(None, None);
+    transcript
+        .add_prover_round_with_custom_length_and_localization(
+            128,
+            Localization::new(2, 128).unwrap(),
+        )
+        .send_oracle_message_without_degree_bound((0..128u64).map(Fr::from).collect())
+        .submit(NameSpace::root(iop_trace!()), iop_trace!("oracle"))
+        .unwrap();
+    let oracle = &mut transcript.prover_message_oracles[0];
+    assert_eq!(oracle.query_modular(&[130]), vec![vec![Fr::from(2u64)]]);
+    assert_eq!(oracle.query_modular(&[130, 64]), oracle.query(&[2, 64]));
+}
+
+/// A logger that captures all records, used by `test_logging`.
+#[cfg(all(feature = "logging", feature = "std"))]
+struct CapturingLogger {
+    records: std::sync::Mutex<Vec<(log::Level, ark_std::string::String)>>,
+}
+
+#[cfg(all(feature = "logging", feature = "std"))]
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), ark_std::format!("{}", record.args())));
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(all(feature = "logging", feature = "std"))]
+static CAPTURING_LOGGER: CapturingLogger = CapturingLogger {
+    records: std::sync::Mutex::new(Vec::new()),
+};
+
+#[test]
+#[cfg(all(feature = "logging", feature = "std"))]
+/// Test that prover and verifier emit log records for rounds and queries.
+fn test_logging() {
+    log::set_logger(&CAPTURING_LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    assert!(
+        BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+
+    let records = CAPTURING_LOGGER.records.lock().unwrap();
+    let has_record = |level: log::Level, prefix: &str| {
+        records
+            .iter()
+            .any(|(l, message)| *l == level && message.starts_with(prefix))
+    };
+    assert!(has_record(log::Level::Debug, "prover round submitted"));
+    assert!(has_record(log::Level::Debug, "prover round received"));
+    assert!(has_record(log::Level::Trace, "query"));
+}
+
+#[test]
+/// Test that prover memory estimate covers oracles and merkle trees of the mock
+/// protocol.
+fn test_estimate_prover_memory() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let root_namespace = NameSpace::root(iop_trace!());
+    MockTestProver::<Fr>::prove(root_namespace, &(), &(), &mut transcript, &()).unwrap();
+    let round_infos = transcript
+        .prover_message_oracles
+        .iter()
+        .map(|oracle| oracle.info.clone())
+        .collect::<Vec<_>>();
+    let ldt_namespace = transcript.new_namespace(root_namespace, iop_trace!("LDT"));
+    let codewords = transcript.bookkeeper.dump_all_prover_messages_in_order();
+    LinearCombinationLDT::prove(ldt_namespace, &ldt_parameters, &mut transcript, &codewords)
+        .unwrap();
+
+    // approximate actual memory by oracle lengths and merkle tree sizes
+    let element_size = ark_std::mem::size_of::<Fr>();
+    let actual = transcript
+        .prover_message_oracles
+        .iter()
+        .map(|oracle| {
+            let info = &oracle.info;
+            let num_leaves = info.length >> info.localization_parameter;
+            let merkle_tree_nodes = if info.num_oracles() == 0 {
+                0
+            } else {
+                2 * num_leaves - 1
+            };
+            (info.length * info.num_oracles() + merkle_tree_nodes) * element_size
+        })
+        .sum::<usize>();
+    let estimate = LinearCombinationLDT::estimate_prover_memory::<FieldMTConfig>(
+        &ldt_parameters,
+        &round_infos,
+    );
+    assert!(estimate >= actual);
+    assert!(estimate <= 2 * actual);
+}
+
+#[test]
+/// Test that squeezing an array of challenges is the same as squeezing full
+/// size field elements.
+fn test_squeeze_verifier_field_elements_array() {
+    let expected =
+        new_transcript(None, None).squeeze_verifier_field_elements(&[FieldElementSize::Full; 3]);
+    let challenges = new_transcript(None, None).squeeze_verifier_field_elements_array::<3>();
+    assert_eq!(challenges.to_vec(), expected);
+
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test squeeze array"),
+    );
+    assert_eq!(
+        simulation_transcript.squeeze_verifier_field_elements_array::<3>(),
+        challenges
+    );
+}
+
+#[test]
+/// Test that verification metrics count the merkle tree checks of the mock
+/// protocol.
+fn test_verify_with_metrics() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    let (verifier_result, metrics) =
+        BCSVerifier::verify_with_metrics::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param,
+        )
+        .expect("verification failed");
+    assert!(verifier_result);
+
+    let paths = bcs_proof
+        .prover_oracles_mt_path
+        .iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let queried_cosets = bcs_proof
+        .prover_iop_messages_by_round
+        .iter()
+        .flat_map(|round| round.queried_cosets.iter())
+        .collect::<Vec<_>>();
+    assert!(!paths.is_empty());
+    assert_eq!(metrics.num_merkle_paths, paths.len());
+    // inner nodes shared by paths of the same round are hashed once
+    assert!(
+        metrics.num_hash_invocations
+            <= paths
+                .iter()
+                .map(|path| 2 + path.auth_path.len())
+                .sum::<usize>()
+    );
+    assert_eq!(
+        metrics.num_hashed_field_elements,
+        queried_cosets
+            .iter()
+            .map(|coset| coset.iter().map(|c| c.len()).sum::<usize>())
+            .sum::<usize>()
+    );
+    assert_eq!(
+        metrics.num_queried_positions,
+        queried_cosets
+            .iter()
+            .map(|coset| coset.get(0).map_or(0, |c| c.len()))
+            .sum::<usize>()
+    );
+}
+
+#[test]
+/// Test that one point query returns the tuple of all interleaved columns.
+fn test_interleaved_oracles() {
+    let columns = (0..3u64)
+        .map(|c| {
+            (0..16u64)
+                .map(|i| Fr::from(100 * c + i))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut transcript = new_transcript(None, None);
+    transcript
+        .add_prover_round_with_custom_length_and_localization(16, Localization::new(1, 16).unwrap())
+        .send_interleaved_oracles(columns.clone())
+        .submit(NameSpace::root(iop_trace!()), iop_trace!("interleaved"))
+        .unwrap();
+    let oracle = &mut transcript.prover_message_oracles[0];
+    assert_eq!(oracle.get_info().num_message_oracles, 3);
+    assert_eq!(
+        oracle.query(&[5]),
+        vec![vec![Fr::from(5u64), Fr::from(105u64), Fr::from(205u64)]]
+    );
+}
+
+#[test]
+/// Test remediation hints of triggered errors.
+fn test_error_describe() {
+    let err = Localization::new(9, 256).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>().unwrap().describe(),
+        "Localization parameter 9 groups 2^9 elements per leaf, but the oracle only has 256 \
+         elements; use a localization parameter of at most 8."
+    );
+
+    let err = LinearCombinationLDTParameters::new(
+        31,
+        vec![2, 2, 2],
+        Radix2CosetDomain::new_radix2_coset(64, Fr::one()),
+        1,
+    )
+    .validate()
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>().unwrap().describe(),
+        "Folding schedule [2, 2, 2] shrinks the codeword domain of size 64 by 2^6; use at least \
+         one localization parameter, and make them sum to less than 6 while keeping the final \
+         domain larger than tested degree 31 divided by the total folding factor."
+    );
+}
+
+#[test]
+/// Test that an oracle fed in chunks has the same merkle root as sending it at
+/// once, and that feeding a wrong number of elements is rejected.
+fn test_streamed_oracle() {
+    let oracle = (0..256u64).map(Fr::from).collect::<Vec<_>>();
+    let localization = Localization::new(2, 256).unwrap();
+    let namespace = NameSpace::root(iop_trace!());
+
+    let mut expected = new_transcript(None, None);
+    expected
+        .add_prover_round_with_custom_length_and_localization(256, localization)
+        .send_oracle_message_without_degree_bound(oracle.clone())
+        .submit(namespace, iop_trace!("one-shot oracle"))
+        .unwrap();
+
+    let mut transcript = new_transcript(None, None);
+    oracle
+        .chunks(64)
+        .fold(
+            transcript.begin_oracle(256, localization),
+            |round, chunk| round.feed_oracle_chunk(chunk),
+        )
+        .finish_oracle()
+        .unwrap()
+        .submit(namespace, iop_trace!("streamed oracle"))
+        .unwrap();
+    assert_eq!(transcript.merkle_tree_roots(), expected.merkle_tree_roots());
+
+    let err = new_transcript(None, None)
+        .begin_oracle(256, localization)
+        .feed_oracle_chunk(&oracle[..192])
+        .finish_oracle()
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::OracleLengthMismatch {
+            expected: 256,
+            actual: 192,
+        })
+    );
+}
+
+#[test]
+/// Test that a verifier challenge derived from query answers is the same for
+/// prover and verifier, and depends on the answers.
+fn test_query_phase_challenge() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockAdaptiveQueryProtocol<Fr>,
+        MockAdaptiveQueryProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    assert_eq!(bcs_proof.prover_oracles_mt_path[0].len(), 2);
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockAdaptiveQueryProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param
+        )
+        .expect("verification failed"),
+        "adaptive query verifier returns false"
+    );
+
+    let challenge = |answer: u64| {
+        squeeze_query_phase_challenge(
+            &mut PoseidonSponge::<Fr>::new(&poseidon_parameters()),
+            &[vec![Fr::from(answer)]],
+            &[FieldElementSize::Full],
+        )
+    };
+    assert_eq!(challenge(1), challenge(1));
+    assert_ne!(challenge(1), challenge(2));
+}
+
+#[test]
+/// Test that proving the same statement twice gives equal proofs.
+fn test_proof_equality() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let prove = |sponge: PoseidonSponge<Fr>| {
+        BCSProof::generate::<
+            MockTest1Verifier<Fr>,
+            MockTestProver<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            sponge,
+            &(),
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove")
+    };
+    let proof = prove(PoseidonSponge::new(&poseidon_parameters()));
+    assert!(proof == prove(PoseidonSponge::new(&poseidon_parameters())));
+    assert!(proof == proof.clone());
+
+    let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+    sponge.absorb(&Fr::one());
+    assert!(proof != prove(sponge));
+}
+
+#[test]
+/// Test that running the mock prover interactively with random challenges
+/// records the same structure as the non-interactive run.
+fn test_interactive_transcript() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let ldt_transcript = || {
+        new_transcript(
+            LinearCombinationLDT::codeword_domain(&ldt_parameters),
+            LinearCombinationLDT::localization_param(&ldt_parameters),
+        )
+    };
+    let mut interactive =
+        ldt_transcript().with_challenge_source(Box::new(RngChallengeSource::new(test_rng())));
+    let mut non_interactive = ldt_transcript();
+    let namespace = NameSpace::root(iop_trace!());
+    MockTestProver::<Fr>::prove(namespace, &(), &(), &mut interactive, &()).unwrap();
+    MockTestProver::<Fr>::prove(namespace, &(), &(), &mut non_interactive, &()).unwrap();
+
+    let round_infos = |transcript: &Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr>| {
+        transcript
+            .prover_message_oracles
+            .iter()
+            .map(|oracle| oracle.info.clone())
+            .collect::<Vec<_>>()
+    };
+    let verifier_message_shapes =
+        |transcript: &Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr>| {
+            transcript
+                .verifier_messages
+                .iter()
+                .map(|round| {
+                    round
+                        .iter()
+                        .map(|message| match message {
+                            VerifierMessage::FieldElements(x) => (0, x.len()),
+                            VerifierMessage::Bits(x) => (1, x.len()),
+                            VerifierMessage::Bytes(x) => (2, x.len()),
+                            VerifierMessage::Permutation(x) => (3, x.len()),
+                            VerifierMessage::Advance(x) => (4, *x),
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+    assert_eq!(round_infos(&interactive), round_infos(&non_interactive));
+    assert_eq!(
+        verifier_message_shapes(&interactive),
+        verifier_message_shapes(&non_interactive)
+    );
+    assert_ne!(
+        interactive.verifier_messages,
+        non_interactive.verifier_messages
+    );
+}
+
+/// Quadratic extension of `Fr`, used by `test_extension_field_oracle`.
+struct FrQuadraticConfig;
+
+impl ark_ff::Fp2Config for FrQuadraticConfig {
+    type Fp = Fr;
+    // 7 generates the multiplicative group of `Fr`, so it is not a square
+    const NONRESIDUE: Fr = ark_ff::MontFp!("7");
+    const FROBENIUS_COEFF_FP2_C1: &'static [Fr] = &[ark_ff::MontFp!("1"), ark_ff::MontFp!("-1")];
+}
+
+#[test]
+/// Test that an extension field oracle can be committed and queried.
+fn test_extension_field_oracle() {
+    type Fr2 = ark_ff::Fp2<FrQuadraticConfig>;
+    let mut rng = test_rng();
+    let oracle = (0..64).map(|_| Fr2::rand(&mut rng)).collect::<Vec<_>>();
+    let mut transcript = new_transcript(None, None);
+    let namespace = NameSpace::root(iop_trace!());
+    transcript
+        .add_prover_round_with_custom_length_and_localization(64, Localization::new(1, 64).unwrap())
+        .send_oracle_message_without_degree_bound(vec![Fr::one(); 64])
+        .send_extension_field_oracle_without_degree_bound(oracle.clone())
+        .submit(namespace, iop_trace!("extension field oracle"))
+        .unwrap();
+    assert_eq!(
+        transcript.prover_message_oracles[0]
+            .get_info()
+            .num_message_oracles,
+        3
+    );
+
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        Vec::new(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+    let queried = messages
+        .prover_round((namespace, 0))
+        .query_extension_field_point::<Fr2>(&[5, 40], 1, iop_trace!("query extension field"));
+    assert_eq!(queried, vec![oracle[5], oracle[40]]);
+}
+
+#[test]
+/// Test that structurally malformed proofs are rejected before verification.
+fn test_malformed_proof() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    assert!(proof.validate_structure().is_ok());
+
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+    };
+    let round = proof
+        .prover_oracles_mt_path
+        .iter()
+        .position(|paths| !paths.is_empty())
+        .unwrap();
+    let assert_malformed = |proof: &BCSProof<FieldMTConfig, Fr>, expected_round: usize| {
+        let err = verify(proof)
+            .err()
+            .expect("malformed proof should be rejected");
+        match err.downcast_ref::<BCSError>() {
+            Some(BCSError::MalformedProof { round, .. }) => assert_eq!(*round, expected_round),
+            _ => panic!("unexpected error: {}", err),
+        }
+    };
+
+    let mut zero_root = proof.clone();
+    zero_root.prover_messages_mt_root[round] = Some(Fr::zero());
+    assert_malformed(&zero_root, round);
+
+    let mut missing_root = proof.clone();
+    missing_root.prover_messages_mt_root[round] = None;
+    assert_malformed(&missing_root, round);
+
+    let mut missing_path = proof.clone();
+    missing_path.prover_oracles_mt_path[round].pop();
+    assert_malformed(&missing_path, round);
+
+    let mut empty_coset = proof.clone();
+    empty_coset.prover_iop_messages_by_round[round].queried_cosets[0][0].clear();
+    assert_malformed(&empty_coset, round);
+
+    let mut short_coset = proof.clone();
+    let last = short_coset.prover_iop_messages_by_round[round]
+        .queried_cosets
+        .len()
+        - 1;
+    short_coset.prover_iop_messages_by_round[round].queried_cosets[last][0].pop();
+    assert_malformed(&short_coset, round);
+
+    let num_rounds = proof.prover_iop_messages_by_round.len();
+    let mut missing_round = proof.clone();
+    missing_round.prover_messages_mt_root.pop();
+    assert_malformed(&missing_round, num_rounds);
+}
+
+#[test]
+/// Test that uniform verifier elements are squeezed identically by both
+/// transcripts, and are less biased than reducing squeezed bits modulo `p`.
+fn test_squeeze_verifier_uniform_element() {
+    let mut transcript = new_transcript(None, None);
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test uniform element"),
+    );
+    let mut sponge = PoseidonSponge::<Fr>::new(&poseidon_parameters());
+
+    // fraction of elements in the lower half of the field, which is 1/2 for
+    // uniform elements
+    let num_samples = 4000;
+    let mut half_modulus = Fr::MODULUS;
+    half_modulus.div2();
+    let (mut num_uniform_low, mut num_reduced_low) = (0, 0);
+    for _ in 0..num_samples {
+        let element = transcript.squeeze_verifier_uniform_element();
+        assert_eq!(
+            simulation_transcript.squeeze_verifier_uniform_element(),
+            element
+        );
+        if element.into_bigint() < half_modulus {
+            num_uniform_low += 1;
+        }
+        let bits = sponge.squeeze_bits(Fr::MODULUS_BIT_SIZE as usize);
+        let reduced = Fr::from_le_bytes_mod_order(
+            &<Fr as PrimeField>::BigInt::from_bits_le(&bits).to_bytes_le(),
+        );
+        if reduced.into_bigint() < half_modulus {
+            num_reduced_low += 1;
+        }
+    }
+    let uniform_bias = (num_uniform_low as i64 - num_samples / 2).abs();
+    let reduced_bias = (num_reduced_low as i64 - num_samples / 2).abs();
+    assert!(uniform_bias < reduced_bias);
+}
+
+#[test]
+/// Test that namespaces are domain separated, so that identical subprotocols
+/// forked from the same transcript get different challenges, and that both
+/// transcripts absorb the same domain separators.
+fn test_namespace_domain_separation() {
+    let mut transcript = new_transcript(None, None);
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test domain separation"),
+    );
+    let root = NameSpace::root(iop_trace!());
+
+    let ns_a = transcript.new_namespace(root, iop_trace!("subprotocol a"));
+    let challenge_a = transcript
+        .fork()
+        .squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+    let ns_b = transcript.new_namespace(root, iop_trace!("subprotocol b"));
+    let challenge_b = transcript
+        .fork()
+        .squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+    assert_ne!(challenge_a, challenge_b);
+    assert_ne!(
+        transcript.bookkeeper.domain_separator(ns_a.id),
+        transcript.bookkeeper.domain_separator(ns_b.id)
+    );
+    assert_eq!(
+        transcript.bookkeeper.domain_separator(root.id),
+        ROOT_NAMESPACE_DOMAIN_SEPARATOR.to_vec()
+    );
+
+    simulation_transcript.new_namespace(root, iop_trace!("subprotocol a"));
+    simulation_transcript.new_namespace(root, iop_trace!("subprotocol b"));
+    assert_eq!(
+        simulation_transcript.sponge.squeeze_bytes(32),
+        transcript.sponge.squeeze_bytes(32)
+    );
+}
+
+#[test]
+/// Test that two openings of the same committed polynomial are merged into a
+/// smaller aggregated proof, which still verifies both openings after being
+/// serialized.
+fn test_merge_proofs() {
+    let mut rng = test_rng();
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let param = UnivariateOpeningParameter {
+        degree_bound: 63,
+        codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap(),
+        num_queries: 7,
+    };
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let public_inputs = (0..2)
+        .map(|_| {
+            let point = Fr::rand(&mut rng);
+            (point, poly.evaluate(&point))
+        })
+        .collect::<Vec<_>>();
+    let proofs = public_inputs
+        .iter()
+        .map(|public_input| {
+            BCSProof::generate::<
+                UnivariateOpening<Fr>,
+                UnivariateOpening<Fr>,
+                LinearCombinationLDT<Fr>,
+                _,
+            >(
+                PoseidonSponge::new(&poseidon_parameters()),
+                public_input,
+                &poly,
+                &param,
+                &ldt_parameters,
+                mt_hash_param.clone(),
+            )
+            .expect("fail to prove")
+        })
+        .collect::<Vec<_>>();
+
+    let aggregated = BCSProof::merge_proofs(&proofs).unwrap();
+    // the committed polynomial is the first round of both proofs
+    assert_eq!(aggregated.shared_rounds.len(), 1);
+    assert_eq!(aggregated.shared_rounds[0].round, 0);
+    assert!(
+        aggregated.num_merkle_digests()
+            < proofs
+                .iter()
+                .map(|proof| proof.num_merkle_digests())
+                .sum::<usize>()
+    );
+    assert!(
+        aggregated.compressed_size()
+            < proofs
+                .iter()
+                .map(|proof| proof.compressed_size())
+                .sum::<usize>()
+    );
+    let mut bytes = Vec::new();
+    aggregated.serialize_compressed(&mut bytes).unwrap();
+    let aggregated =
+        AggregatedBCSProof::<FieldMTConfig, Fr>::deserialize_compressed(&*bytes).unwrap();
+    let recovered = aggregated.to_proofs().unwrap();
+    assert!(recovered[0] == proofs[0] && recovered[1] == proofs[1]);
+
+    let verify = |public_inputs: &[(Fr, Fr)]| {
+        BCSVerifier::verify_aggregated::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &aggregated,
+            public_inputs,
+            &param,
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+    };
+    assert_eq!(
+        verify(&public_inputs).expect("verification failed"),
+        vec![true, true]
+    );
+    let err = verify(&public_inputs[..1]).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::PublicInputCountMismatch {
+            expected: 2,
+            actual: 1
+        })
+    );
+}
+
+#[test]
+/// Test that prover and verifier transcripts with the same custom
+/// `HashToFieldConfig` derive identical field elements from the same bytes.
+fn test_hash_to_field_config() {
+    // wide reduction of big endian bytes, as in hash to field of RFC 9380
+    let config = HashToFieldConfig::<Fr> {
+        bytes_per_element: 48,
+        map: Fr::from_be_bytes_mod_order,
+    };
+    let mut transcript = new_transcript(None, None).with_hash_to_field_config(config);
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test hash to field"),
+    )
+    .with_hash_to_field_config(config);
+
+    let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+    sponge.absorb(&ROOT_NAMESPACE_DOMAIN_SEPARATOR.to_vec());
+    sponge.absorb(
+        &TranscriptVersion::default()
+            .domain_separator()
+            .unwrap()
+            .to_vec(),
+    );
+    let expected = sponge
+        .squeeze_bytes(2 * 48)
+        .chunks(48)
+        .map(Fr::from_be_bytes_mod_order)
+        .collect::<Vec<_>>();
+    let challenges = transcript.squeeze_verifier_bytes_as_field(2);
+    assert_eq!(challenges, expected);
+    assert_eq!(
+        simulation_transcript.squeeze_verifier_bytes_as_field(2),
+        challenges
+    );
+}
+
+#[test]
+/// Test that a protocol can query an oracle committed by a sibling namespace.
+fn test_referenced_oracle() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockOracleReferenceProtocol<Fr>,
+        MockOracleReferenceProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    // the queries are recorded in the round of the owner namespace
+    assert_eq!(
+        bcs_proof.prover_iop_messages_by_round[0]
+            .queried_cosets
+            .len(),
+        MockOracleReferenceProtocol::<Fr>::QUERY_POSITIONS.len()
+    );
+
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockOracleReferenceProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+}
+
+#[test]
+/// Test that identical oracles sent with `with_hiding` have different merkle
+/// roots, also across proofs with the same sponge, and still verify using the
+/// salts in the proof.
+fn test_hiding_merkle_root() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove = |seed: u64| {
+        BCSProof::generate_with_ldt_disabled::<MockHidingProtocol<Fr>, MockHidingProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &seed,
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove")
+    };
+    let verify = |bcs_proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify_with_ldt_disabled::<MockHidingProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            bcs_proof,
+            &(),
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("verification failed")
+    };
+    let bcs_proof = prove(0);
+    let roots = &bcs_proof.prover_messages_mt_root;
+    assert!(roots[0].is_some());
+    assert_ne!(roots[0], roots[1]);
+    let num_queries = MockHidingProtocol::<Fr>::QUERY_POSITIONS.len();
+    assert_eq!(
+        bcs_proof.prover_iop_messages_by_round[0]
+            .queried_salts
+            .len(),
+        num_queries
+    );
+    assert!(verify(&bcs_proof));
+
+    let other_proof = prove(1);
+    assert_ne!(other_proof.prover_messages_mt_root[0], roots[0]);
+    assert!(verify(&other_proof));
+
+    // a wrong or missing salt fails the authentication paths of the round
+    let paths_verify = |bcs_proof: &BCSProof<FieldMTConfig, Fr>| {
+        let (_, namespaces) =
+            BCSVerifier::verify_per_namespace::<MockHidingProtocol<Fr>, NoLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                bcs_proof,
+                &(),
+                &(),
+                &None,
+                mt_hash_param.clone(),
+            )
+            .unwrap();
+        namespaces[0].1
+    };
+    assert!(paths_verify(&bcs_proof));
+    let mut tampered = bcs_proof.clone();
+    tampered.prover_iop_messages_by_round[0].queried_salts[0] += Fr::one();
+    assert!(!paths_verify(&tampered));
+    let mut tampered = bcs_proof;
+    tampered.prover_iop_messages_by_round[0]
+        .queried_salts
+        .clear();
+    assert!(!paths_verify(&tampered));
+}
+
+#[test]
+/// Test that `try_build` rejects rounds whose oracles cannot be split into
+/// leaves.
+fn test_prover_round_message_info_try_build() {
+    let localization = Localization::new(3, 64).unwrap();
+    let info = ProverRoundMessageInfo::new_using_custom_length_and_localization(64, localization)
+        .with_num_message_oracles(1)
+        .try_build()
+        .unwrap();
+    assert_eq!(info.localization_parameter, 3);
+
+    // localization checked against 64, but used for a round of length 4
+    let err = ProverRoundMessageInfo::new_using_custom_length_and_localization(4, localization)
+        .with_num_message_oracles(1)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidLocalization {
+            localization_parameter: 3,
+            oracle_length: 4,
+        })
+    );
+
+    let err = ProverRoundMessageInfo::new_using_custom_length_and_localization(48, localization)
+        .with_num_message_oracles(1)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidOracleLength { oracle_length: 48 })
+    );
+
+    // rounds without oracles do not use length or localization
+    assert!(
+        ProverRoundMessageInfo::new_using_custom_length_and_localization(0, localization)
+            .with_num_short_messages(1)
+            .try_build()
+            .is_ok()
+    );
+}
+
+#[test]
+/// Test that `MessagesCollection::finalize_checks` reports all failed checks
+/// of a verifier at once.
+fn test_failed_checks_are_collected() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![1, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let namespace = NameSpace::root(iop_trace!("test failed checks"));
+    MockTestProver::prove(namespace, &(), &(), &mut transcript, &()).unwrap();
+    // break short messages of round 0 and round 2
+    transcript.prover_message_oracles[0].short_messages[0][0] += Fr::one();
+    transcript.prover_message_oracles[2].short_messages[0][0] += Fr::one();
+
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        transcript
+            .registered_virtual_oracles
+            .into_iter()
+            .map(|v| Some(v.0))
+            .collect(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+    let err = <MockTest1Verifier<Fr> as IOPVerifier<PoseidonSponge<Fr>, Fr>>::query_and_decide(
+        namespace,
+        &(),
+        &(),
+        &mut PoseidonSponge::new(&poseidon_parameters()),
+        &mut messages,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::FailedChecks {
+            messages: vec![
+                "short message of round 0".into(),
+                "short message of round 2".into()
+            ],
+        })
+    );
+    // failed checks are cleared once reported
+    assert!(messages.finalize_checks().is_ok());
+}
+
+#[test]
+/// Test that LDT proves and verifies using each query strategy, and that
+/// `CosetStructured` queries are spread over the codeword domain.
+fn test_ldt_query_strategy() {
+    let mut rng = test_rng();
+    let hash_params = mt_hash_parameters();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let point = Fr::rand(&mut rng);
+    let value = poly.evaluate(&point);
+    for query_strategy in [QueryStrategy::Independent, QueryStrategy::CosetStructured] {
+        let ldt_params = LinearCombinationLDTParameters::new(
+            64,
+            vec![2, 2, 1],
+            Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+            7,
+        )
+        .with_query_sampler(query_strategy);
+        let param = UnivariateOpeningParameter {
+            degree_bound: 63,
+            codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_params).unwrap(),
+            num_queries: 7,
+        };
+        let proof = BCSProof::generate::<
+            UnivariateOpening<Fr>,
+            UnivariateOpening<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(point, value),
+            &poly,
+            &param,
+            &ldt_params,
+            hash_params.clone(),
+        )
+        .expect("fail to prove");
+        assert!(
+            BCSVerifier::verify::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &proof,
+                &(point, value),
+                &param,
+                &ldt_params,
+                hash_params.clone(),
+            )
+            .expect("verification failed")
+        );
+    }
+
+    // 32 cosets and 7 queries: offsets step by 32 / 8
+    let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+    let indices = QueryStrategy::CosetStructured
+        .sample_positions(&mut sponge, 7, 32)
+        .unwrap();
+    assert_eq!(
+        indices.iter().map(|i| i ^ indices[0]).collect::<Vec<_>>(),
+        vec![0, 4, 8, 12, 16, 20, 24]
+    );
+}
+
+#[test]
+/// Test that prover and verifier transcripts record the same absorption
+/// script, including the scripts of forks.
+fn test_absorption_script() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof =
+        BCSProof::generate_with_ldt_disabled::<MockForkProtocol<Fr>, MockForkProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+
+    let mut transcript = new_transcript(None, None);
+    MockForkProtocol::<Fr>::prove(
+        NameSpace::root(iop_trace!("test absorption script")),
+        &(),
+        &(),
+        &mut transcript,
+        &(),
+    )
+    .unwrap();
+
+    let mut simulation_transcript = SimulationTranscript::new_transcript(
+        &bcs_proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test absorption script"),
+    );
+    MockForkProtocol::<Fr>::register_iop_structure(
+        NameSpace::root(iop_trace!("test absorption script")),
+        &mut simulation_transcript,
+        &(),
+    );
+
+    let script = transcript.absorption_script();
+    assert_eq!(script, simulation_transcript.absorption_script());
+    assert_eq!(
+        script[0],
+        AbsorbOp::Absorb {
+            value: AbsorbedValue::DomainSeparator,
+            bytes: ROOT_NAMESPACE_DOMAIN_SEPARATOR
+                .to_vec()
+                .to_sponge_bytes_as_vec(),
+        }
+    );
+    let joined = script
+        .iter()
+        .filter_map(|op| match op {
+            AbsorbOp::Join(fork) => Some(fork),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(joined.len(), 2);
+    // sibling forks start from the same parent state
+    assert_eq!(joined[0][0], joined[1][0]);
+    assert_eq!(joined[0].last(), Some(&AbsorbOp::SqueezeBytes(32)));
+    assert_eq!(
+        script.last(),
+        Some(&AbsorbOp::SqueezeFieldElements(vec![
+            FieldElementSize::Full
+        ]))
+    );
+}
+
+#[test]
+/// Test that a round with fewer short messages than
+/// `with_fixed_short_messages` is padded, and still verifies.
+fn test_fixed_short_messages() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockFixedShortMessagesProtocol<Fr>,
+        MockFixedShortMessagesProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    bcs_proof.prover_iop_messages_by_round[..2]
+        .iter()
+        .for_each(|round| {
+            assert_eq!(
+                round.short_messages.len(),
+                MockFixedShortMessagesProtocol::<Fr>::NUM_SHORT_MESSAGES
+            )
+        });
+
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockFixedShortMessagesProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+/// Test that `MockTestProver` proves and verifies using BLAKE3 merkle trees.
+fn test_bcs_with_blake3_merkle_tree() {
+    use crate::bcs::{blake3_mt::Blake3MTConfig, MTHashParameters};
+
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = MTHashParameters::<Blake3MTConfig<Fr>> {
+        leaf_hash_param: (),
+        inner_hash_param: (),
+    };
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+
+    assert!(
+        BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+/// Test that `MockTestProver` commits its oracles using BLAKE3 merkle trees
+/// under a Poseidon sponge: the root of each round is absorbed as its digest
+/// bytes, and a proof with a changed root is rejected.
+fn test_blake3_merkle_tree_with_poseidon_sponge() {
+    use crate::bcs::{
+        blake3_mt::{Blake3Digest, Blake3MTConfig},
+        MTHashParameters,
+    };
+
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = MTHashParameters::<Blake3MTConfig<Fr>> {
+        leaf_hash_param: (),
+        inner_hash_param: (),
+    };
+    let mut transcript = Transcript::<Blake3MTConfig<Fr>, _, Fr>::new(
+        PoseidonSponge::new(&poseidon_parameters()),
+        mt_hash_param.clone(),
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+        iop_trace!("test blake3 merkle tree with poseidon sponge"),
+    );
+    MockTestProver::<Fr>::prove(
+        NameSpace::root(iop_trace!("test blake3 merkle tree with poseidon sponge")),
+        &(),
+        &(),
+        &mut transcript,
+        &(),
+    )
+    .unwrap();
+    let roots = transcript.merkle_tree_roots();
+    let absorbed_roots = transcript
+        .absorption_script()
+        .iter()
+        .filter_map(|op| match op {
+            AbsorbOp::Absorb {
+                value: AbsorbedValue::MerkleRoot,
+                bytes,
+            } => Some(bytes.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(absorbed_roots.len(), roots.len());
+    assert!(roots.iter().any(Option::is_some));
+    for (root, bytes) in roots.iter().zip(&absorbed_roots) {
+        assert_eq!(bytes, &root.to_sponge_bytes_as_vec());
+        if let Some(root) = root {
+            assert!(bytes.ends_with(&root.0));
+        }
+    }
+
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    assert_eq!(
+        &bcs_proof.prover_messages_mt_root[..roots.len()],
+        &roots[..]
+    );
+    let verify = |proof: &BCSProof<Blake3MTConfig<Fr>, Fr>| {
+        BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+    };
+    assert!(verify(&bcs_proof).expect("verification failed"));
+
+    let mut tampered = bcs_proof.clone();
+    let round = roots.iter().position(Option::is_some).unwrap();
+    tampered.prover_messages_mt_root[round] = Some(Blake3Digest([1; 32]));
+    assert!(!matches!(verify(&tampered), Ok(true)));
+}
+
+#[test]
+/// Test that `DebugProof` renders each round of the mock proof.
+fn test_debug_proof() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param,
+    )
+    .expect("fail to prove");
+
+    let output = ark_std::format!("{:?}", DebugProof(&bcs_proof));
+    assert!(output.starts_with(&ark_std::format!(
+        "BCSProof: {} prover rounds\n",
+        bcs_proof.prover_iop_messages_by_round.len()
+    )));
+    // round 0 has 2 oracles and a short message of 4 elements
+    let round_0 = output
+        .split("  round 1:")
+        .next()
+        .unwrap()
+        .split("  round 0:")
+        .nth(1)
+        .unwrap();
+    assert!(!round_0.contains("merkle root: none"));
+    assert!(round_0.contains(&ark_std::format!(
+        "queried cosets: {} (2 oracles x ",
+        bcs_proof.prover_iop_messages_by_round[0]
+            .queried_cosets
+            .len()
+    )));
+    assert!(round_0.contains("short messages: 1\n      [0] 4 elements: "));
+    assert!(round_0.trim_end().ends_with(" .."));
+    assert_eq!(
+        output.matches("  round ").count(),
+        bcs_proof.prover_iop_messages_by_round.len()
+    );
+}
+
+#[test]
+/// Test that `finalize` consumes the transcript and returns its sponge in the
+/// final state.
+fn test_finalized_transcript() {
+    let mut transcript = new_transcript(None, None);
+    let namespace = NameSpace::root(iop_trace!("test finalized transcript"));
+    transcript
+        .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+        .send_short_message(vec![Fr::one()])
+        .submit(namespace, iop_trace!("before finalize"))
+        .unwrap();
+    let digest = transcript.commit_phase_digest();
+    let mut sponge = transcript.finalize();
+    assert_eq!(sponge.squeeze_field_elements::<Fr>(1)[0], digest);
+}
+
+#[test]
+/// Test that an oracle committed by `commit_oracle` can be opened and
+/// verified without any sponge.
+fn test_oracle_commitment() {
+    let mut rng = test_rng();
+    let hash_params = mt_hash_parameters();
+    let oracle = (0..128).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let commitment = commit_oracle(&hash_params, oracle.clone()).unwrap();
+    let root = commitment.root();
+
+    let positions = [1, 2];
+    let proof = open_oracle(&commitment, &positions).unwrap();
+    let answers = [oracle[1], oracle[2]];
+    assert!(verify_opening(&hash_params, &root, &positions, &answers, &proof).unwrap());
+
+    let wrong_answers = [oracle[1], oracle[2] + Fr::one()];
+    assert!(!verify_opening(&hash_params, &root, &positions, &wrong_answers, &proof).unwrap());
+    assert!(!verify_opening(&hash_params, &root, &[2, 1], &answers, &proof).unwrap());
+
+    let err = commit_oracle(&hash_params, oracle[..100].to_vec())
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidOracleLength { oracle_length: 100 })
+    );
+}
+
+#[test]
+/// Test that the LDT of the mock proof, which tests a degree 8 oracle, can be
+/// verified without the consistency checks of the protocol.
+fn test_verify_ldt_only() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+
+    assert!(
+        BCSVerifier::verify_ldt_only::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &ldt_parameters,
+            mt_hash_param,
+        )
+        .expect("LDT verification failed")
+    );
+}
+
+#[cfg(feature = "bench")]
+#[test]
+/// Test that a proof generated with fixed queries answers exactly the supplied
+/// positions, and verifies with the same positions.
+fn test_fixed_queries() {
+    let mt_hash_param = mt_hash_parameters();
+    let positions = [3, 17, 42];
+    let bcs_proof = BCSProof::generate_with_fixed_queries::<
+        MockAdaptiveQueryProtocol<Fr>,
+        MockAdaptiveQueryProtocol<Fr>,
+        NoLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &None,
+        mt_hash_param.clone(),
+        &positions,
+    )
+    .expect("fail to prove");
+
+    // both queries of the protocol are replaced by `positions`
+    let queried = bcs_proof.prover_oracles_mt_path[0]
+        .iter()
+        .map(|path| path.leaf_index)
+        .collect::<Vec<_>>();
+    assert_eq!(queried, [positions, positions].concat());
+
+    BCSVerifier::verify_with_fixed_queries::<MockAdaptiveQueryProtocol<Fr>, NoLDT<Fr>, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &bcs_proof,
+        &(),
+        &(),
+        &None,
+        mt_hash_param,
+        &positions,
+    )
+    .expect("verification failed");
+}
+
+#[test]
+/// Test that `BCSProof::try_from_bytes` parses a serialized proof, and rejects
+/// truncated, over-long and inconsistent bytes with the offset of the failing
+/// item.
+fn test_proof_try_from_bytes() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockAdaptiveQueryProtocol<Fr>,
+        MockAdaptiveQueryProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param,
+    )
+    .expect("fail to prove");
+    let mut bytes = Vec::new();
+    bcs_proof.serialize_compressed(&mut bytes).unwrap();
+    let parse = |bytes: &[u8]| BCSProof::<FieldMTConfig, Fr>::try_from_bytes(bytes);
+    let parse_error = |bytes: &[u8]| {
+        parse(bytes)
+            .err()
+            .unwrap()
+            .downcast_ref::<BCSError>()
+            .cloned()
+            .unwrap()
+    };
+    assert!(parse(&bytes).unwrap() == bcs_proof);
+
+    assert_eq!(
+        parse_error(&[]),
+        BCSError::ProofParseError {
+            offset: 0,
+            reason: "unexpected end of input"
+        }
+    );
+    // the last item is the last authentication path
+    let last_path_size = bcs_proof.prover_oracles_mt_path[0]
+        .last()
+        .unwrap()
+        .compressed_size();
+    assert_eq!(
+        parse_error(&bytes[..bytes.len() - 1]),
+        BCSError::ProofParseError {
+            offset: bytes.len() - last_path_size,
+            reason: "unexpected end of input"
+        }
+    );
+
+    let mut over_long = bytes.clone();
+    over_long.push(0);
+    assert_eq!(
+        parse_error(&over_long),
+        BCSError::ProofParseError {
+            offset: bytes.len(),
+            reason: "trailing bytes after proof"
+        }
+    );
+
+    // claim one more merkle root than prover rounds
+    let roots_offset = bcs_proof.prover_iop_messages_by_round.compressed_size();
+    let mut inconsistent = bytes.clone();
+    inconsistent[roots_offset..roots_offset + 8].copy_from_slice(&2u64.to_le_bytes());
+    assert_eq!(
+        parse_error(&inconsistent),
+        BCSError::ProofParseError {
+            offset: roots_offset,
+            reason: "length is inconsistent with previous items"
+        }
+    );
+}
+
+#[test]
+/// Test that LDT using powers of one challenge as coefficients verifies, and
+/// squeezes one challenge instead of one per oracle.
+fn test_ldt_coefficient_strategy() {
+    let mut rng = test_rng();
+    let hash_params = mt_hash_parameters();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let point = Fr::rand(&mut rng);
+    let value = poly.evaluate(&point);
+
+    // returns the number of field elements squeezed in commit phase
+    let num_squeezed = |coefficient_strategy| {
+        let ldt_params = LinearCombinationLDTParameters::new(
+            64,
+            vec![2, 2, 1],
+            Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+            7,
+        )
+        .with_coefficient_strategy(coefficient_strategy);
+        let param = UnivariateOpeningParameter {
+            degree_bound: 63,
+            codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_params).unwrap(),
+            num_queries: 7,
+        };
+        let proof = BCSProof::generate::<
+            UnivariateOpening<Fr>,
+            UnivariateOpening<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(point, value),
+            &poly,
+            &param,
+            &ldt_params,
+            hash_params.clone(),
+        )
+        .expect("fail to prove");
+        assert!(
+            BCSVerifier::verify::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &proof,
+                &(point, value),
+                &param,
+                &ldt_params,
+                hash_params.clone(),
+            )
+            .expect("verification failed")
+        );
+
+        let mut transcript = SimulationTranscript::new_transcript(
+            &proof,
+            PoseidonSponge::new(&poseidon_parameters()),
+            LinearCombinationLDT::codeword_domain(&ldt_params),
+            LinearCombinationLDT::localization_param(&ldt_params),
+            iop_trace!("test coefficient strategy"),
+        );
+        let namespace = NameSpace::root(iop_trace!("BCS Test"));
+        UnivariateOpening::register_iop_structure(namespace, &mut transcript, &param);
+        let ldt_namespace = transcript.new_namespace(namespace, iop_trace!("LDT"));
+        LinearCombinationLDT::register_iop_structure(
+            ldt_namespace,
+            &ldt_params,
+            2,
+            &mut transcript,
+        );
+        transcript
+            .absorption_script()
+            .iter()
+            .map(|op| match op {
+                AbsorbOp::SqueezeFieldElements(sizes) => sizes.len(),
+                _ => 0,
+            })
+            .sum::<usize>()
+    };
+    assert_eq!(
+        num_squeezed(CoefficientStrategy::IndependentRandom),
+        num_squeezed(CoefficientStrategy::PowersOfChallenge) + 1
+    );
+}
+
+#[test]
+/// Test that proofs exceeding `ProofLimits` are rejected when decoding,
+/// before reading the declared items, and when verifying.
+fn test_proof_limits() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockAdaptiveQueryProtocol<Fr>,
+        MockAdaptiveQueryProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    let mut bytes = Vec::new();
+    bcs_proof.serialize_compressed(&mut bytes).unwrap();
+    let limits = ProofLimits {
+        max_rounds: 10,
+        max_queries: 10,
+        max_elements: 100,
+    };
+    let decode_error = |bytes: &[u8]| {
+        BCSProof::<FieldMTConfig, Fr>::try_from_bytes_with_limits(bytes, &limits)
+            .err()
+            .unwrap()
+            .downcast_ref::<BCSError>()
+            .cloned()
+            .unwrap()
+    };
+    assert!(BCSProof::try_from_bytes_with_limits(&bytes, &limits).unwrap() == bcs_proof);
+
+    // 20 rounds, followed by enough bytes to pass the input length check
+    let mut too_many_rounds = 20u64.to_le_bytes().to_vec();
+    too_many_rounds.resize(8 + 20, 0);
+    assert_eq!(
+        decode_error(&too_many_rounds),
+        BCSError::ResourceLimitExceeded {
+            resource: "prover rounds",
+            limit: 10,
+            actual: 20
+        }
+    );
+
+    // one round with one queried coset of one oracle declaring 1000 elements
+    let mut too_many_elements = [1u64, 1, 1, 1000]
+        .iter()
+        .flat_map(|length| length.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    too_many_elements.resize(too_many_elements.len() + 1000, 0);
+    assert_eq!(
+        decode_error(&too_many_elements),
+        BCSError::ResourceLimitExceeded {
+            resource: "field elements",
+            limit: 100,
+            actual: 1000
+        }
+    );
+
+    let err = BCSVerifier::verify_with_limits::<MockAdaptiveQueryProtocol<Fr>, NoLDT<Fr>, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &bcs_proof,
+        &(),
+        &(),
+        &None,
+        mt_hash_param,
+        &ProofLimits {
+            max_queries: 1,
+            ..limits
+        },
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::ResourceLimitExceeded {
+            resource: "queried cosets",
+            limit: 1,
+            actual: 2
+        })
+    );
+}
+
+#[test]
+/// Test that `iter_query_answers` yields the namespace, round and position of
+/// each answer in the mock proofs, with one item per queried position, and
+/// includes the cosets of a round committed inline.
+fn test_iter_query_answers() {
+    let root = NameSpace::root(iop_trace!("test iter query answers"));
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockAdaptiveQueryProtocol<Fr>,
+        MockAdaptiveQueryProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_parameters(),
+    )
+    .expect("fail to prove");
+    let transcript =
+        commit_phase_transcript::<MockAdaptiveQueryProtocol<Fr>, NoLDT<Fr>>(&(), &(), &(), &None)
+            .unwrap();
+
+    let answers = bcs_proof
+        .iter_query_answers(&transcript)
+        .collect::<Vec<_>>();
+    // the protocol queries its only round twice, and each coset has one
+    // element
+    assert_eq!(answers.len(), 2);
+    for (query, (namespace, round, position, answer)) in answers.into_iter().enumerate() {
+        assert_eq!((namespace, round), (root, 0));
+        assert_eq!(
+            position,
+            bcs_proof.prover_oracles_mt_path[0][query].leaf_index
+        );
+        assert_eq!(
+            answer,
+            vec![bcs_proof.prover_iop_messages_by_round[0].queried_cosets[query][0][0]]
+        );
+    }
+
+    // the first round is committed inline, so both of its positions are
+    // answers, while the second round has two queried cosets of 2 elements
+    let bcs_proof = BCSProof::generate_with_version::<
+        MockInlineOracleProtocol<Fr>,
+        MockInlineOracleProtocol<Fr>,
+        NoLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &None,
+        mt_hash_parameters(),
+        TranscriptVersion::V4,
+    )
+    .expect("fail to prove");
+    let mut transcript = new_transcript_with_version(None, None, TranscriptVersion::V4);
+    MockInlineOracleProtocol::<Fr>::prove(root, &(), &(), &mut transcript, &()).unwrap();
+
+    let answers = bcs_proof
+        .iter_query_answers(&transcript)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        answers[..2],
+        [
+            (root, 0, 0, vec![Fr::from(5u64)]),
+            (root, 0, 1, vec![Fr::from(7u64)])
+        ]
+    );
+    let paths = &bcs_proof.prover_oracles_mt_path[1];
+    let cosets = &bcs_proof.prover_iop_messages_by_round[1].queried_cosets;
+    let expected = paths
+        .iter()
+        .zip(cosets)
+        .flat_map(|(path, coset)| {
+            (0..2).map(move |element_index| {
+                (
+                    root,
+                    1,
+                    path.leaf_index + element_index * 32,
+                    vec![coset[0][element_index]],
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(expected.len(), 4);
+    assert_eq!(answers[2..], expected[..]);
+}
+
+#[test]
+/// Test that the merkle root of a round binds the order of its oracles: a
+/// queried coset whose two oracles are swapped does not match the
+/// authentication path.
+fn test_swapped_oracles_do_not_verify() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+
+    // the first round of the mock protocol has two oracles
+    let coset = &bcs_proof.prover_iop_messages_by_round[0].queried_cosets[0];
+    assert_eq!(coset.len(), 2);
+    let path = &bcs_proof.prover_oracles_mt_path[0][0];
+    let root = bcs_proof.prover_messages_mt_root[0].as_ref().unwrap();
+    let verify = |coset: &[Vec<Fr>]| {
+        path.verify(
+            &mt_hash_param.leaf_hash_param,
+            &mt_hash_param.inner_hash_param,
+            root,
+            coset
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+        .unwrap()
+    };
+    assert!(verify(coset));
+    assert!(!verify(&[coset[1].clone(), coset[0].clone()]));
+}
+
+#[test]
+/// Test that a proof generated with LDT dev mode verifies, and queries each
+/// FRI oracle `DEV_MODE_NUM_QUERIES` times.
+fn test_ldt_dev_mode() {
+    let mut rng = test_rng();
+    let ldt_params = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    )
+    .dev_mode();
+    assert_eq!(
+        ldt_params.num_queries,
+        LinearCombinationLDTParameters::<Fr>::DEV_MODE_NUM_QUERIES
+    );
+    let hash_params = mt_hash_parameters();
+    let param = UnivariateOpeningParameter {
+        degree_bound: 63,
+        codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_params).unwrap(),
+        num_queries: 7,
+    };
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let point = Fr::rand(&mut rng);
+    let value = poly.evaluate(&point);
+    let proof = BCSProof::generate::<
+        UnivariateOpening<Fr>,
+        UnivariateOpening<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(point, value),
+        &poly,
+        &param,
+        &ldt_params,
+        hash_params.clone(),
+    )
+    .expect("fail to prove");
+
+    // rounds 0 and 1 are sent by the protocol, and rounds 2 and 3 are FRI
+    // oracles
+    for message in &proof.prover_iop_messages_by_round[2..4] {
+        assert_eq!(
+            message.queried_cosets.len(),
+            LinearCombinationLDTParameters::<Fr>::DEV_MODE_NUM_QUERIES
+        );
+    }
+    assert!(
+        BCSVerifier::verify::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &proof,
+            &(point, value),
+            &param,
+            &ldt_params,
+            hash_params,
+        )
+        .expect("verification failed")
+    );
+}
+
+#[test]
+/// Test that sending an oracle with the merkle tree of a previous transcript
+/// gives the same merkle root as building the tree, and that a tree of another
+/// oracle or of another height is rejected.
+fn test_precomputed_tree() {
+    let mut rng = test_rng();
+    let namespace = NameSpace::root(iop_trace!("test precomputed tree"));
+    let oracle = (0..64).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let localization = Localization::new(2, 64).unwrap();
+
+    let mut fresh = new_transcript(None, None);
+    fresh
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_without_degree_bound(oracle.clone())
+        .submit(namespace, iop_trace!("fresh tree"))
+        .unwrap();
+    let tree = match fresh.merkle_tree_for_each_round[0].clone() {
+        Some(RoundMerkleTree::Dense(tree)) => tree,
+        _ => panic!("round should be committed using a dense merkle tree"),
+    };
+
+    let mut precomputed = new_transcript(None, None);
+    precomputed
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_with_precomputed_tree(oracle.clone(), tree.clone())
+        .submit(namespace, iop_trace!("precomputed tree"))
+        .unwrap();
+    assert_eq!(
+        precomputed.merkle_tree_for_each_round[0]
+            .as_ref()
+            .unwrap()
+            .root(),
+        tree.root()
+    );
+
+    let other_oracle = (0..64).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let err = new_transcript(None, None)
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_with_precomputed_tree(other_oracle, tree.clone())
+        .submit(namespace, iop_trace!("mismatched tree"))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::PrecomputedTreeMismatch { leaf_index: 0 })
+    );
+
+    // the tree has 16 leaves, but an oracle of length 128 has 32 cosets
+    let longer_oracle = (0..128).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let err = new_transcript(None, None)
+        .add_prover_round_with_custom_length_and_localization(
+            128,
+            Localization::new(2, 128).unwrap(),
+        )
+        .send_oracle_message_with_precomputed_tree(longer_oracle, tree)
+        .submit(namespace, iop_trace!("tree of another height"))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::PrecomputedTreeHeightMismatch {
+            expected: 6,
+            actual: 5
+        })
+    );
+}
+
+#[test]
+/// Test that submitting a round with a precomputed merkle tree returns an
+/// error if the round sends a low-degree oracle, another oracle, or is hiding.
+fn test_precomputed_tree_unsupported_round() {
+    let mut rng = test_rng();
+    let namespace = NameSpace::root(iop_trace!("test precomputed tree unsupported round"));
+    let oracle = (0..64).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let localization = Localization::new(2, 64).unwrap();
+
+    let mut fresh = new_transcript(None, None);
+    fresh
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_without_degree_bound(oracle.clone())
+        .submit(namespace, iop_trace!("fresh tree"))
+        .unwrap();
+    let tree = match fresh.merkle_tree_for_each_round[0].clone() {
+        Some(RoundMerkleTree::Dense(tree)) => tree,
+        _ => panic!("round should be committed using a dense merkle tree"),
+    };
+    let unsupported = |err: Error| match err.downcast_ref::<BCSError>() {
+        Some(BCSError::UnsupportedPrecomputedTreeRound { reason }) => *reason,
+        _ => panic!("unexpected error: {}", err),
+    };
+
+    let poly = DensePolynomial::<Fr>::rand(7, &mut rng);
+    let err = new_transcript(
+        Some(Radix2CosetDomain::new_radix2_coset(64, Fr::one())),
+        Some(2),
+    )
+    .add_prover_round_with_codeword_domain()
+    .send_univariate_polynomial(&poly, 7)
+    .send_oracle_message_with_precomputed_tree(oracle.clone(), tree.clone())
+    .submit(
+        namespace,
+        iop_trace!("precomputed tree with low-degree oracle"),
+    )
+    .unwrap_err();
+    assert_eq!(unsupported(err), "sends a low-degree oracle");
+
+    let other_oracle = (0..64).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+    let err = new_transcript(None, None)
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_with_precomputed_tree(oracle.clone(), tree.clone())
+        .send_oracle_message_without_degree_bound(other_oracle)
+        .submit(namespace, iop_trace!("precomputed tree with two oracles"))
+        .unwrap_err();
+    assert_eq!(unsupported(err), "sends more than one oracle");
+
+    let err = new_transcript(None, None)
+        .add_prover_round_with_custom_length_and_localization(64, localization)
+        .send_oracle_message_with_precomputed_tree(oracle, tree)
+        .with_hiding(&mut rng)
+        .submit(namespace, iop_trace!("hiding precomputed tree"))
+        .unwrap_err();
+    assert_eq!(unsupported(err), "is hiding");
+}
+
+#[test]
+/// Test that prover and verifier derive the same folding coefficients.
+fn test_squeeze_folding_coefficients() {
+    let mut transcript = new_transcript(None, None);
+    let coefficients = transcript.squeeze_folding_coefficients(5);
+    assert_eq!(coefficients.len(), 5);
+
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test folding coefficients"),
+    );
+    assert_eq!(
+        simulation_transcript.squeeze_folding_coefficients(5),
+        coefficients
+    );
+}
+
+#[test]
+/// Test that `verify_oracle_equality` accepts equal oracles sent with different
+/// localization parameters, and rejects different oracles.
+fn test_verify_oracle_equality() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove_and_verify = |seed: u64| {
+        let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+            MockOracleEqualityProtocol<Fr>,
+            MockOracleEqualityProtocol<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &seed,
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+        BCSVerifier::verify_with_ldt_disabled::<MockOracleEqualityProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("verification failed")
+    };
+    assert!(prove_and_verify(4));
+    assert!(!prove_and_verify(5));
+}
+
+#[test]
+/// Test that the number of queries can be chosen by a verifier challenge, and
+/// that proofs with different query counts verify.
+fn test_dynamic_query_count() {
+    let mt_hash_param = mt_hash_parameters();
+    let mut query_counts = Vec::new();
+    for salt in 0..8u64 {
+        let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+        sponge.absorb(&salt);
+        let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+            MockDynamicQueryCountProtocol<Fr>,
+            MockDynamicQueryCountProtocol<Fr>,
+            _,
+        >(sponge.clone(), &(), &(), &(), mt_hash_param.clone())
+        .expect("fail to prove");
+        let num_queries = bcs_proof.prover_iop_messages_by_round[0]
+            .queried_cosets
+            .len();
+        assert!((MockDynamicQueryCountProtocol::<Fr>::MIN_QUERIES
+            ..=MockDynamicQueryCountProtocol::<Fr>::MAX_QUERIES)
+            .contains(&num_queries));
+        query_counts.push(num_queries);
+
+        assert!(
+            BCSVerifier::verify_with_ldt_disabled::<MockDynamicQueryCountProtocol<Fr>, _>(
+                sponge,
+                &bcs_proof,
+                &(),
+                &(),
+                mt_hash_param.clone()
+            )
+            .expect("verification failed")
+        );
+    }
+    query_counts.sort_unstable();
+    query_counts.dedup();
+    assert!(query_counts.len() > 1);
+}
+
+#[test]
+/// Test that `at_localization` reads single elements and smaller cosets out of
+/// a fetched coset.
+fn test_at_localization() {
+    // 4 cosets of 4 elements, where coset `j` is positions `j, j + 4, ..`
+    let info = ProverRoundMessageInfo::new_using_custom_length_and_localization(
+        16,
+        Localization::new(2, 16).unwrap(),
+    )
+    .with_num_message_oracles(1)
+    .build();
+    let message = SuccinctRoundMessage::<Fr> {
+        queried_cosets: vec![vec![[1u64, 5, 9, 13]
+            .iter()
+            .map(|&x| Fr::from(x))
+            .collect()]],
+        short_messages: Vec::new(),
+    };
+    let mut oracle = message.get_view(info);
+    oracle.query_coset(&[1]);
+
+    let view = oracle.at_localization(0);
+    assert_eq!(
+        view.query(&[9, 1]),
+        Some(vec![vec![Fr::from(9u64)], vec![Fr::from(1u64)]])
+    );
+    assert_eq!(view.query(&[2]), None);
+
+    // coset 5 of localization 1 is positions 5 and 13
+    let view = oracle.at_localization(1);
+    assert_eq!(
+        view.query_coset(&[5]).unwrap().0,
+        vec![vec![vec![Fr::from(5u64), Fr::from(13u64)]]]
+    );
+    assert!(view.query_coset(&[0]).is_none());
+}
+
+#[test]
+/// Test that `ldt_layer_roots` returns the roots of the FRI intermediate
+/// codewords.
+fn test_ldt_layer_roots() {
+    let mut rng = test_rng();
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    let param = UnivariateOpeningParameter {
+        degree_bound: 63,
+        codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap(),
+        num_queries: 7,
+    };
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let point = Fr::rand(&mut rng);
+    let proof = BCSProof::generate::<
+        UnivariateOpening<Fr>,
+        UnivariateOpening<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(point, poly.evaluate(&point)),
+        &poly,
+        &param,
+        &ldt_parameters,
+        mt_hash_param,
+    )
+    .expect("fail to prove");
+
+    // the last folding gives the final polynomial, which is not committed
+    let roots = proof.ldt_layer_roots(&ldt_parameters);
+    assert_eq!(
+        roots.len(),
+        ldt_parameters.fri_parameters.localization_parameters.len() - 1
+    );
+    // rounds 0 and 1 are sent by `UnivariateOpening`
+    assert!(roots
+        .iter()
+        .zip(proof.prover_messages_mt_root[2..4].iter())
+        .all(|(root, expected)| Some(root) == expected.as_ref()));
+}
+
+#[test]
+/// Test that `advance_sponge` changes later challenges, and that prover and
+/// verifier still derive the same challenges.
+fn test_advance_sponge() {
+    let namespace = NameSpace::root(iop_trace!("test advance sponge"));
+    let mut transcript = new_transcript(None, None);
+    transcript.advance_sponge(16);
+    transcript.submit_verifier_current_round(namespace, iop_trace!("advance"));
+    let challenge = transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full])[0];
+    assert_eq!(
+        transcript.verifier_messages[0],
+        vec![VerifierMessage::Advance(16)]
+    );
+
+    let mut transcript_without_advance = new_transcript(None, None);
+    assert_ne!(
+        transcript_without_advance.squeeze_verifier_field_elements(&[FieldElementSize::Full])[0],
+        challenge
+    );
+
+    let proof = BCSProof::<FieldMTConfig, Fr> {
+        prover_iop_messages_by_round: Vec::new(),
+        prover_messages_mt_root: Vec::new(),
+        prover_oracles_mt_path: Vec::new(),
+    };
+    let mut simulation_transcript = SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+        &proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test advance sponge"),
+    );
+    simulation_transcript.advance_sponge(16);
+    simulation_transcript.submit_verifier_current_round(namespace, iop_trace!("advance"));
+    assert_eq!(
+        simulation_transcript.squeeze_verifier_field_elements_array::<1>()[0],
+        challenge
+    );
+}
+
+#[test]
+/// Test that `verify_chain` accepts a chain where each proof is generated with
+/// the sponge of `next_chain_sponge`, and reports the index of a broken link.
+fn test_verify_chain() {
+    let mt_hash_param = mt_hash_parameters();
+    // `MockOracleEqualityProtocol` is rejected unless private input is 4
+    let prove_chain = |private_inputs: &[u64]| {
+        let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+        private_inputs
+            .iter()
+            .map(|private_input| {
+                let proof = BCSProof::generate_with_ldt_disabled::<
+                    MockOracleEqualityProtocol<Fr>,
+                    MockOracleEqualityProtocol<Fr>,
+                    _,
+                >(
+                    sponge.clone(),
+                    &(),
+                    private_input,
+                    &(),
+                    mt_hash_param.clone(),
+                )
+                .expect("fail to prove");
+                sponge = BCSVerifier::next_chain_sponge(&sponge, &proof);
+                proof
+            })
+            .collect::<Vec<_>>()
+    };
+    let verify_chain = |proofs: &[BCSProof<FieldMTConfig, Fr>]| {
+        BCSVerifier::verify_chain::<MockOracleEqualityProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proofs,
+            &vec![(); proofs.len()],
+            &(),
+            &None,
+            mt_hash_param.clone(),
+        )
+    };
+
+    let chain = prove_chain(&[4, 4]);
+    assert!(verify_chain(&chain).is_ok());
+
+    let err = verify_chain(&prove_chain(&[4, 5, 4])).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<BCSError>(),
+        Some(BCSError::ChainLinkFailed { index: 1, .. })
+    ));
+
+    let mut tampered = prove_chain(&[4, 4]);
+    tampered[1].prover_oracles_mt_path[0][0].leaf_sibling_hash += Fr::one();
+    let err = verify_chain(&tampered).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<BCSError>(),
+        Some(BCSError::ChainLinkFailed { index: 1, .. })
+    ));
+
+    let err = BCSVerifier::verify_chain::<MockOracleEqualityProtocol<Fr>, NoLDT<Fr>, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &chain,
+        &[()],
+        &(),
+        &None,
+        mt_hash_param.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::PublicInputCountMismatch {
+            expected: 2,
+            actual: 1,
+        })
+    );
+}
+
+#[test]
+/// Test that `query_as_matrix` returns query answers in column-major order.
+fn test_query_as_matrix() {
+    let mut transcript = new_transcript(None, None);
+    let namespace = NameSpace::root(iop_trace!("test query as matrix"));
+    let oracle = |offset: u64| (0..4u64).map(|i| Fr::from(offset + i)).collect::<Vec<_>>();
+    transcript
+        .add_prover_round_with_custom_length_and_localization(4, Localization::default())
+        .send_oracle_message_without_degree_bound(oracle(10))
+        .send_oracle_message_without_degree_bound(oracle(20))
+        .submit(namespace, iop_trace!("two oracles"))
+        .unwrap();
+
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        Vec::new(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+    let (matrix, dimensions) = messages
+        .prover_round((namespace, 0))
+        .query_as_matrix(&[1, 3], iop_trace!("query as matrix"));
+    assert_eq!(dimensions, (2, 2));
+    assert_eq!(
+        matrix,
+        [11u64, 13, 21, 23]
+            .iter()
+            .map(|&x| Fr::from(x))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+/// Test that degree bounds computed from verifier parameter in
+/// `register_iop_structure` reach the simulation transcript, and that proofs
+/// for different degree bounds verify.
+fn test_parameter_dependent_degree_bound() {
+    let mut rng = test_rng();
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    for degree_bound in [63, 31] {
+        let param = UnivariateOpeningParameter {
+            degree_bound,
+            codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap(),
+            num_queries: 7,
+        };
+        let poly = DensePolynomial::<Fr>::rand(degree_bound, &mut rng);
+        let point = Fr::rand(&mut rng);
+        let public_input = (point, poly.evaluate(&point));
+        let proof = BCSProof::generate::<
+            UnivariateOpening<Fr>,
+            UnivariateOpening<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &public_input,
+            &poly,
+            &param,
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+
+        let mut simulation_transcript =
+            SimulationTranscript::<FieldMTConfig, _, Fr>::new_transcript(
+                &proof,
+                PoseidonSponge::new(&poseidon_parameters()),
+                LinearCombinationLDT::codeword_domain(&ldt_parameters),
+                LinearCombinationLDT::localization_param(&ldt_parameters),
+                iop_trace!("test degree bound"),
+            );
+        <UnivariateOpening<Fr> as IOPVerifier<PoseidonSponge<Fr>, Fr>>::register_iop_structure(
+            NameSpace::root(iop_trace!("test degree bound")),
+            &mut simulation_transcript,
+            &param,
+        );
+        let degree_bounds = simulation_transcript
+            .expected_prover_messages_info
+            .iter()
+            .map(|info| info.reed_solomon_code_degree_bound.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            degree_bounds,
+            vec![vec![degree_bound], vec![degree_bound - 1]]
+        );
+
+        assert!(
+            BCSVerifier::verify::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &proof,
+                &public_input,
+                &param,
+                &ldt_parameters,
+                mt_hash_param.clone(),
+            )
+            .expect("verification failed")
+        );
+    }
+}
+
+#[test]
+/// Test that prover and verifier compute the same commit phase digest for the
+/// mock protocol, and that computing it twice gives the same value.
+fn test_commit_phase_digest() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let mt_hash_param = mt_hash_parameters();
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let namespace = NameSpace::root(iop_trace!("test commit phase digest"));
+    MockTestProver::prove(namespace, &(), &(), &mut transcript, &()).unwrap();
+    let digest = transcript.commit_phase_digest();
+    assert_eq!(transcript.commit_phase_digest(), digest);
+
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param,
+    )
+    .expect("fail to prove");
+    let mut simulation_transcript = SimulationTranscript::new_transcript(
+        &bcs_proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+        iop_trace!("test commit phase digest"),
+    );
+    MockTest1Verifier::register_iop_structure(namespace, &mut simulation_transcript, &());
+    assert_eq!(simulation_transcript.commit_phase_digest(), digest);
+}
+
+#[test]
+/// Test that a proof committing a root other than the one registered by
+/// `expect_known_root` is rejected.
+fn test_expect_known_root() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove = |seed: u64| {
+        BCSProof::generate_with_ldt_disabled::<
+            MockKnownRootProtocol<Fr>,
+            MockKnownRootProtocol<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &seed,
+            &MockKnownRootParameter {
+                known_root: Vec::new(),
+            },
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove")
+    };
+    let honest_proof = prove(3);
+    let mut known_root = Vec::new();
+    honest_proof.prover_messages_mt_root[0]
+        .clone()
+        .unwrap()
+        .serialize_compressed(&mut known_root)
+        .unwrap();
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify_with_ldt_disabled::<MockKnownRootProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &MockKnownRootParameter {
+                known_root: known_root.clone(),
+            },
+            mt_hash_param.clone(),
+        )
+    };
+
+    assert!(verify(&honest_proof).expect("honest proof should be accepted"));
+
+    let err = verify(&prove(5)).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnexpectedRoot { round: 0 })
+    );
+}
+
+#[test]
+/// Test that a precommitted oracle is tested once, and its cached root is
+/// reused by two different proofs.
+fn test_precommitted_oracle() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let poly = DensePolynomial::<Fr>::rand(60, &mut test_rng());
+    let evaluations = LinearCombinationLDT::codeword_domain(&ldt_parameters)
+        .unwrap()
+        .evaluate(&poly);
+    let precommitted = LinearCombinationLDT::precommit_oracle(
+        PoseidonSponge::new(&poseidon_parameters()),
+        evaluations.clone(),
+        64,
+        &ldt_parameters,
+        mt_hash_parameters(),
+    )
+    .expect("fail to precommit");
+    let mut known_root = Vec::new();
+    precommitted
+        .root()
+        .serialize_compressed(&mut known_root)
+        .unwrap();
+
+    // splicing the oracle reuses the cached tree
+    let mut transcript = new_transcript(
+        LinearCombinationLDT::codeword_domain(&ldt_parameters),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    transcript
+        .send_precommitted_oracle(
+            NameSpace::root(iop_trace!("test precommitted oracle")),
+            &precommitted,
+            iop_trace!("precommitted oracle"),
+        )
+        .unwrap();
+    assert_eq!(
+        transcript.merkle_tree_for_each_round[0]
+            .as_ref()
+            .unwrap()
+            .root(),
+        precommitted.root()
+    );
+
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>, statement: u64, known_root: &[u8]| {
+        BCSVerifier::verify::<MockPrecommittedOracleProtocol<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &statement,
+            &MockKnownRootParameter {
+                known_root: known_root.to_vec(),
+            },
+            &ldt_parameters,
+            mt_hash_parameters(),
+        )
+    };
+    let proofs = (0..2u64)
+        .map(|statement| {
+            BCSProof::generate::<
+                MockPrecommittedOracleProtocol<Fr>,
+                MockPrecommittedOracleProtocol<Fr>,
+                LinearCombinationLDT<Fr>,
+                _,
+            >(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &statement,
+                &evaluations,
+                &MockKnownRootParameter {
+                    known_root: known_root.clone(),
+                },
+                &ldt_parameters,
+                mt_hash_parameters(),
+            )
+            .expect("fail to prove")
+        })
+        .collect::<Vec<_>>();
+    for (statement, proof) in proofs.iter().enumerate() {
+        assert_eq!(proof.prover_messages_mt_root[0], Some(precommitted.root()));
+        assert!(verify(proof, statement as u64, &known_root).expect("verification failed"));
+    }
+    assert_eq!(
+        proofs[0].prover_messages_mt_root[0],
+        proofs[1].prover_messages_mt_root[0]
+    );
+
+    // the cached low-degree proof is checked once for both proofs
+    let sponge = PoseidonSponge::new(&poseidon_parameters());
+    assert!(precommitted
+        .verify(
+            sponge.clone(),
+            &precommitted.root(),
+            &ldt_parameters,
+            mt_hash_parameters()
+        )
+        .expect("verification failed"));
+    let wrong_root = precommitted.root() + Fr::one();
+    assert!(!precommitted
+        .verify(sponge, &wrong_root, &ldt_parameters, mt_hash_parameters())
+        .unwrap());
+
+    let mut wrong_known_root = Vec::new();
+    wrong_root
+        .serialize_compressed(&mut wrong_known_root)
+        .unwrap();
+    let err = verify(&proofs[0], 0, &wrong_known_root).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnexpectedRoot { round: 0 })
+    );
+}
+
+#[test]
+/// Test that `UnivariateOpening` proves the evaluation of a polynomial at a
+/// random point, and rejects a wrong value.
+fn test_univariate_opening() {
+    let mut rng = test_rng();
+    let ldt_params = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let hash_params = mt_hash_parameters();
+    let param = UnivariateOpeningParameter {
+        degree_bound: 63,
+        codeword_domain: LinearCombinationLDT::codeword_domain(&ldt_params).unwrap(),
+        num_queries: 7,
+    };
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let point = Fr::rand(&mut rng);
+    let value = poly.evaluate(&point);
+
+    let proof = BCSProof::generate::<
+        UnivariateOpening<Fr>,
+        UnivariateOpening<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(point, value),
+        &poly,
+        &param,
+        &ldt_params,
+        hash_params.clone(),
+    )
+    .expect("fail to prove");
+
+    let verify = |claimed_value| {
+        BCSVerifier::verify::<UnivariateOpening<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &proof,
+            &(point, claimed_value),
+            &param,
+            &ldt_params,
+            hash_params.clone(),
+        )
+        .expect("verification failed")
+    };
+    assert!(verify(value));
+    assert!(!verify(value + Fr::one()));
+}
+
+#[test]
+/// Test that sending a polynomial in evaluation form gives the same oracle as
+/// sending it in coefficient form.
+fn test_send_univariate_polynomial_evaluations() {
+    let mut rng = test_rng();
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let domain = LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap();
+    let poly = DensePolynomial::<Fr>::rand(63, &mut rng);
+    let evaluations = domain.evaluate(&poly);
+    let commit = |by_evaluations: bool| {
+        let mut transcript = new_transcript(
+            Some(domain),
+            LinearCombinationLDT::localization_param(&ldt_parameters),
+        );
+        let round = transcript.add_prover_round_with_codeword_domain();
+        let round = if by_evaluations {
+            round.send_univariate_polynomial_evaluations(63, &evaluations, domain)
+        } else {
+            round.send_univariate_polynomial(&poly, 63)
+        };
+        round
+            .submit(NameSpace::root(iop_trace!()), iop_trace!("polynomial"))
+            .unwrap();
+        (
+            transcript.prover_message_oracles[0]
+                .reed_solomon_codes()
+                .clone(),
+            transcript.merkle_tree_for_each_round[0]
+                .as_ref()
+                .unwrap()
+                .root(),
+        )
+    };
+    assert_eq!(commit(true), commit(false));
+}
+
+#[test]
+/// Test that reading a verifier message as a wrong type returns
+/// `BCSError::UnexpectedVerifierMessageType`.
+fn test_verifier_message_accessors() {
+    let message = VerifierMessage::<Fr>::Bytes(vec![1, 2, 3]);
+    assert_eq!(message.as_bytes().unwrap(), &[1, 2, 3]);
+    let err = message.as_field_elements().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnexpectedVerifierMessageType {
+            expected: "field elements",
+            found: "bytes",
+        })
+    );
+    let err = VerifierMessage::<Fr>::FieldElements(vec![Fr::one()])
+        .as_bits()
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnexpectedVerifierMessageType {
+            expected: "bits",
+            found: "field elements",
+        })
+    );
+}
+
+#[test]
+/// Test that polynomials sent using `send_low_degree_batch` are committed
+/// under one merkle tree, and that one query returns all polynomials' values.
+fn test_send_low_degree_batch() {
+    let mut rng = test_rng();
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let domain = LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap();
+    let mut transcript = new_transcript(
+        Some(domain),
+        LinearCombinationLDT::localization_param(&ldt_parameters),
+    );
+    let polys = (0..3)
+        .map(|_| DensePolynomial::<Fr>::rand(63, &mut rng))
+        .collect::<Vec<_>>();
+    transcript
+        .add_prover_round_with_codeword_domain()
+        .send_low_degree_batch(63, &polys)
+        .submit(NameSpace::root(iop_trace!()), iop_trace!("batch"))
+        .unwrap();
+
+    assert_eq!(transcript.merkle_tree_for_each_round.len(), 1);
+    let oracle = &mut transcript.prover_message_oracles[0];
+    assert_eq!(oracle.num_reed_solomon_codes_oracles(), 3);
+    assert_eq!(
+        oracle.query(&[5]),
+        vec![polys
+            .iter()
+            .map(|poly| poly.evaluate(&domain.element(5)))
+            .collect::<Vec<_>>()]
+    );
+    assert_eq!(oracle.queried_coset_index.len(), 1);
+}
+
+#[test]
+/// Test that a proof generated with an older transcript version is verified
+/// when the same version is given to verifier.
+fn test_transcript_version() {
+    let mt_hash_param = mt_hash_parameters();
+    assert!(
+        new_transcript_with_version(None, None, TranscriptVersion::V1)
+            .absorption_script()
+            .is_empty()
+    );
+    assert_eq!(TranscriptVersion::default(), TranscriptVersion::V3);
+    assert_eq!(
+        new_transcript_with_version(None, None, TranscriptVersion::V2).absorption_script()[1],
+        AbsorbOp::Absorb {
+            value: AbsorbedValue::TranscriptVersion,
+            bytes: TranscriptVersion::V2
+                .domain_separator()
+                .unwrap()
+                .to_vec()
+                .to_sponge_bytes_as_vec(),
+        }
+    );
+
+    let proof = BCSProof::generate_with_version::<
+        MockOracleEqualityProtocol<Fr>,
+        MockOracleEqualityProtocol<Fr>,
+        NoLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &4,
+        &(),
+        &None,
+        mt_hash_param.clone(),
+        TranscriptVersion::V1,
+    )
+    .expect("fail to prove");
+    assert!(
+        BCSVerifier::verify_with_version::<MockOracleEqualityProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &proof,
+            &(),
+            &(),
+            &None,
+            mt_hash_param,
+            TranscriptVersion::V1,
+        )
+        .expect("fail to verify")
+    );
+}
+
+#[test]
+/// Test that `TranscriptVersion::V1` reproduces the challenges of the baseline
+/// transcript, which absorbs only the merkle root and short messages of each
+/// prover round, even when namespaces are created.
+fn test_transcript_version_v1_matches_baseline() {
+    let mut rng = test_rng();
+    let mut transcript = new_transcript_with_version(None, None, TranscriptVersion::V1);
+    let root = NameSpace::root(iop_trace!("test transcript version v1"));
+    let namespace = transcript.new_namespace(root, iop_trace!("subprotocol"));
+    let short_message = vec![Fr::rand(&mut rng), Fr::rand(&mut rng)];
+    transcript
+        .add_prover_round_with_custom_length_and_localization(64, Localization::default())
+        .send_oracle_message_without_degree_bound((0..64).map(|_| Fr::rand(&mut rng)))
+        .send_short_message(short_message.clone())
+        .submit(namespace, iop_trace!("round"))
+        .unwrap();
+    let challenge = transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full]);
+    transcript.submit_verifier_current_round(namespace, iop_trace!("challenge"));
+
+    // replay the operations of the baseline transcript on a fresh sponge
+    let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+    sponge.absorb(
+        &transcript.merkle_tree_for_each_round[0]
+            .as_ref()
+            .map(|tree| tree.root()),
+    );
+    sponge.absorb(&short_message);
+    assert_eq!(
+        challenge,
+        sponge.squeeze_field_elements_with_sizes::<Fr>(&[FieldElementSize::Full])
+    );
+    assert!(!transcript.absorption_script().iter().any(|op| matches!(
+        op,
+        AbsorbOp::Absorb {
+            value: AbsorbedValue::DomainSeparator,
+            ..
+        }
+    )));
+}
+
+#[test]
+/// Test that `TranscriptVersion::V1` squeezes LDT queries independently, as
+/// baseline LDT does, whatever the query sampler is: a proof generated with
+/// the baseline sampling verifies using `UniformUniqueSampler` under `V1`.
+fn test_transcript_version_v1_ldt_queries() {
+    // 32 queries of 32 cosets, so independent queries repeat and unique ones
+    // do not
+    let baseline_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        32,
+    );
+    let unique_parameters = baseline_parameters
+        .clone()
+        .with_query_sampler(UniformUniqueSampler);
+    let to_bytes = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    let prove_baseline = |version| {
+        BCSProof::generate_with_version::<
+            MockTest1Verifier<Fr>,
+            MockTestProver<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            &baseline_parameters,
+            mt_hash_parameters(),
+            version,
+        )
+        .expect("fail to prove")
+    };
+    let prove_unique = |version| {
+        BCSProof::generate_with_version::<
+            MockTest1Verifier<Fr>,
+            MockTestProver<Fr>,
+            LinearCombinationLDT<Fr, UniformUniqueSampler>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            &unique_parameters,
+            mt_hash_parameters(),
+            version,
+        )
+        .expect("fail to prove")
+    };
+
+    let baseline_proof = prove_baseline(TranscriptVersion::V1);
+    assert_eq!(
+        to_bytes(&prove_unique(TranscriptVersion::V1)),
+        to_bytes(&baseline_proof)
+    );
+    assert!(BCSVerifier::verify_with_version::<
+        MockTest1Verifier<Fr>,
+        LinearCombinationLDT<Fr, UniformUniqueSampler>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &baseline_proof,
+        &(),
+        &(),
+        &unique_parameters,
+        mt_hash_parameters(),
+        TranscriptVersion::V1,
+    )
+    .expect("fail to verify"));
+
+    // later versions use the sampler
+    assert_ne!(
+        to_bytes(&prove_unique(TranscriptVersion::V3)),
+        to_bytes(&prove_baseline(TranscriptVersion::V3))
+    );
+}
+
+#[test]
+/// Test that `query_complexity` reports the positions queried by
+/// `MockOracleEqualityProtocol`, which queries both oracles at 3 positions,
+/// without any proof.
+fn test_query_complexity() {
+    let query_complexity = BCSVerifier::<FieldMTConfig, Fr>::query_complexity::<
+        MockOracleEqualityProtocol<Fr>,
+        NoLDT<Fr>,
+        _,
+    >(PoseidonSponge::new(&poseidon_parameters()), &(), &(), &None)
+    .unwrap();
+    // 3 cosets of size 2 in round 0, and 3 cosets of size 4 in round 1
+    assert_eq!(
+        query_complexity,
+        QueryComplexity {
+            positions_per_round: vec![6, 12],
+        }
+    );
+    assert_eq!(query_complexity.total(), 18);
+}
+
+#[test]
+/// Test that `prove_and_verify` returns the verifier output of a round trip.
+fn test_prove_and_verify() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    assert!(
+        prove_and_verify::<MockTestProver<Fr>, MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>>(
+            &(),
+            &(),
+            &(),
+            &ldt_parameters
+        )
+        .expect("round trip failed")
+    );
+    // `MockOracleEqualityProtocol` is rejected unless private input is 4
+    assert!(!prove_and_verify::<
+        MockOracleEqualityProtocol<Fr>,
+        MockOracleEqualityProtocol<Fr>,
+        NoLDT<Fr>,
+    >(&(), &5, &(), &None)
+    .expect("round trip failed"));
+}
+
+#[test]
+/// Test that challenges depend on the oracle length declared by verifier, so
+/// that a round cannot be interpreted with a different length.
+fn test_round_structure_absorbed() {
+    let proof = BCSProof::generate_with_ldt_disabled::<
+        MockDynamicQueryCountProtocol<Fr>,
+        MockDynamicQueryCountProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_parameters(),
+    )
+    .expect("fail to prove");
+    let challenge_after_round = |length: usize, version: TranscriptVersion| {
+        let mut transcript = SimulationTranscript::new_transcript_with_version(
+            &proof,
+            PoseidonSponge::new(&poseidon_parameters()),
+            None,
+            None,
+            version,
+            iop_trace!("test round structure absorbed"),
+        );
+        transcript.receive_prover_current_round(
+            NameSpace::root(iop_trace!()),
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                length,
+                Localization::default(),
+            )
+            .with_num_message_oracles(1)
+            .build(),
+            iop_trace!("oracle"),
+        );
+        transcript.squeeze_verifier_field_elements_array::<1>()[0]
+    };
+    assert_ne!(
+        challenge_after_round(64, TranscriptVersion::V3),
+        challenge_after_round(128, TranscriptVersion::V3)
+    );
+    assert_eq!(
+        challenge_after_round(64, TranscriptVersion::V2),
+        challenge_after_round(128, TranscriptVersion::V2)
+    );
+}
+
+#[test]
+/// Test that `verify` returns a non-`bool` verifier output, and that
+/// `verify_accepted` turns a `false` output into `BCSError::ProofRejected`.
+fn test_structured_verifier_output() {
+    let mt_hash_param = mt_hash_parameters();
+    let proof = BCSProof::generate_with_ldt_disabled::<
+        MockEvaluationOutputProtocol<Fr>,
+        MockEvaluationOutputProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &2,
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    let evaluations = BCSVerifier::verify_with_ldt_disabled::<MockEvaluationOutputProtocol<Fr>, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &proof,
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to verify");
+    assert_eq!(evaluations, vec![Fr::from(2003u64), Fr::from(2040u64)]);
+
+    // `MockOracleEqualityProtocol` is rejected unless private input is 4
+    let verify_equality = |private_input: u64| {
+        let proof = BCSProof::generate_with_ldt_disabled::<
+            MockOracleEqualityProtocol<Fr>,
+            MockOracleEqualityProtocol<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &private_input,
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+        BCSVerifier::verify_accepted::<MockOracleEqualityProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &proof,
+            &(),
+            &(),
+            &None,
+            mt_hash_param.clone(),
+        )
+    };
+    assert!(verify_equality(4).is_ok());
+    assert_eq!(
+        verify_equality(5).unwrap_err().downcast_ref::<BCSError>(),
+        Some(&BCSError::ProofRejected)
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+/// Test that the proof of `MockTestProver` does not depend on the number of
+/// threads used to build merkle trees and evaluate polynomials.
+fn test_proof_independent_of_thread_count() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let prove_with_threads = |num_threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let proof = pool.install(|| {
+            BCSProof::generate::<
+                MockTest1Verifier<Fr>,
+                MockTestProver<Fr>,
+                LinearCombinationLDT<Fr>,
+                _,
+            >(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &(),
+                &(),
+                &(),
+                &ldt_parameters,
+                mt_hash_parameters(),
+            )
+            .expect("fail to prove")
+        });
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+    assert_eq!(prove_with_threads(1), prove_with_threads(8));
+}
+
+#[test]
+/// Test that `MessagesCollection::check_relation` checks the relations of
+/// `MockTestProver` declaratively, and reports each failing position.
+fn test_check_relation() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![1, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let transcript = commit_phase_transcript::<MockTestProver<Fr>, LinearCombinationLDT<Fr>>(
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+    )
+    .unwrap();
+    let namespace = NameSpace::root(iop_trace!("test check relation"));
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        transcript
+            .registered_virtual_oracles
+            .into_iter()
+            .map(|v| Some(v.0))
+            .collect(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+
+    // short message of round 1 squares the first verifier message
+    assert!(messages.check_relation(
+        "square",
+        &[0, 1, 2],
+        |q, i| q.short_message((namespace, 1), 0)[i]
+            == q.verifier_element((namespace, 0), 0, i).square(),
+        iop_trace!("square relation"),
+    ));
+    // oracle of round 1 is `x + r` for some `r`
+    assert!(messages.check_relation(
+        "shift",
+        &[19, 29, 39],
+        |q, p| q.oracle((namespace, 1), (0, false).into(), p)
+            - q.oracle((namespace, 1), (0, false).into(), 0)
+            == Fr::from(p as u64),
+        iop_trace!("shift relation"),
+    ));
+    assert!(messages.finalize_checks().is_ok());
+
+    assert!(!messages.check_relation(
+        "identity",
+        &[0, 1, 2],
+        |q, i| q.short_message((namespace, 1), 0)[i] == q.verifier_element((namespace, 0), 0, i),
+        iop_trace!("wrong relation"),
+    ));
+    let err = messages.finalize_checks().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::FailedChecks {
+            messages: vec![
+                "relation identity at position 0".into(),
+                "relation identity at position 1".into(),
+                "relation identity at position 2".into(),
+            ],
+        })
+    );
+}
+
+#[test]
+/// Test that `BCSVerifier::verify_streaming` verifies a serialized proof read
+/// through a reader that returns a few bytes at a time, and that it reads the
+/// paths one at a time after the query phase: a truncated proof is only
+/// rejected at the start of its last path, and a path with an invalid
+/// encoding is rejected without reading the paths after it. Messages and
+/// queried cosets are read in full before the paths, so this does not check
+/// that memory is bounded.
+fn test_verify_streaming() {
+    /// A reader that returns at most 3 bytes per read, and leaves the bytes
+    /// it has not returned in `self.0`.
+    struct TinyReader<'a, 'b>(&'a mut &'b [u8]);
+    impl ark_std::io::Read for TinyReader<'_, '_> {
+        fn read(&mut self, buf: &mut [u8]) -> ark_std::io::Result<usize> {
+            let num_bytes = buf.len().min(3).min(self.0.len());
+            buf[..num_bytes].copy_from_slice(&self.0[..num_bytes]);
+            *self.0 = &self.0[num_bytes..];
+            Ok(num_bytes)
+        }
+    }
+
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockOracleEqualityProtocol<Fr>,
+        MockOracleEqualityProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &4,
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    let mut bytes = Vec::new();
+    bcs_proof.serialize_compressed(&mut bytes).unwrap();
+
+    // returns the verification result and the number of bytes not read
+    let verify = |bytes: &[u8]| {
+        let mut remaining = bytes;
+        let result = BCSVerifier::verify_streaming::<MockOracleEqualityProtocol<Fr>, NoLDT<Fr>, _, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            TinyReader(&mut remaining),
+            &(),
+            &(),
+            &None,
+            mt_hash_param.clone(),
+        );
+        (result, remaining.len())
+    };
+    let parse_error = |bytes: &[u8]| {
+        let (result, num_remaining_bytes) = verify(bytes);
+        let err = result.unwrap_err();
+        match err.downcast_ref::<BCSError>() {
+            Some(BCSError::ProofParseError { offset, reason }) => {
+                (*offset, *reason, num_remaining_bytes)
+            },
+            _ => panic!("unexpected error: {}", err),
+        }
+    };
+    let (result, num_remaining_bytes) = verify(&bytes);
+    assert!(result.expect("verification failed"));
+    assert_eq!(num_remaining_bytes, 0);
+
+    // a truncated proof is rejected when its last path is read
+    let last_path = bcs_proof
+        .prover_oracles_mt_path
+        .last()
+        .and_then(|paths| paths.last())
+        .expect("last round has paths");
+    let last_path_offset = bytes.len() - last_path.compressed_size();
+    assert_eq!(
+        parse_error(&bytes[..bytes.len() - 1]),
+        (last_path_offset, "unexpected end of input", 0)
+    );
+
+    // the first path comes after the number of path lists and the number of
+    // paths of round 0, and starts with the sibling hash of its leaf
+    assert!(!bcs_proof.prover_oracles_mt_path[0].is_empty());
+    let first_path_offset = bcs_proof.prover_iop_messages_by_round.compressed_size()
+        + bcs_proof.prover_messages_mt_root.compressed_size()
+        + 16;
+    let hash_size = Fr::zero().compressed_size();
+    let mut invalid_path = bytes.clone();
+    invalid_path[first_path_offset..first_path_offset + hash_size].fill(0xff);
+    // the paths after the invalid one are not read
+    assert_eq!(
+        parse_error(&invalid_path),
+        (
+            first_path_offset,
+            "invalid encoding",
+            bytes.len() - first_path_offset - hash_size
+        )
+    );
+
+    // a path that decodes but does not verify is rejected with an error
+    let mut tampered = bcs_proof.clone();
+    tampered.prover_oracles_mt_path[0][0].leaf_sibling_hash += Fr::one();
+    let mut tampered_bytes = Vec::new();
+    tampered.serialize_compressed(&mut tampered_bytes).unwrap();
+    let err = verify(&tampered_bytes).0.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidAuthenticationPath { round: 0 })
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+/// Known-answer test of the sponge squeezes of `MockTestProver`, so that any
+/// change to absorption order or hashing fails. The golden vector is kept in
+/// `mock_test_prover_squeezes.txt` next to this file as one hex line per
+/// squeeze. If the file does not exist, it is recorded from the current run;
+/// delete it to record a new golden vector after an intended change.
+fn test_squeeze_outputs_match_golden_vector() {
+    use ark_std::{format, string::String};
+
+    let golden_path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/bcs/tests/mock_test_prover_squeezes.txt"
+    );
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let record = || {
+        record_squeeze_outputs::<MockTestProver<Fr>, LinearCombinationLDT<Fr>>(
+            &(),
+            &(),
+            &(),
+            &ldt_parameters,
+        )
+        .expect("fail to run commit phase")
+    };
+    let outputs = record();
+    assert_eq!(outputs, record());
+    let recorded = outputs
+        .iter()
+        .map(|bytes| {
+            let line = bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+            line + "\n"
+        })
+        .collect::<String>();
+
+    match std::fs::read_to_string(golden_path) {
+        Ok(golden) => assert!(
+            golden == recorded,
+            "sponge squeeze outputs of `MockTestProver` differ from {}",
+            golden_path
+        ),
+        Err(_) => std::fs::write(golden_path, recorded).expect("fail to record golden vector"),
+    }
+}
+
+#[test]
+/// Test that `BCSVerifier::verify_per_namespace` reports the intact
+/// subprotocol of `MockForkProtocol` as passed when the other one commits to
+/// wrong answers, and that `MessagesCollection::decide_namespace` rejects a
+/// decision that returns `false`, an error or panics without aborting.
+fn test_verify_per_namespace() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove = |seed: u64| {
+        BCSProof::generate_with_ldt_disabled::<
+            MockForkProtocol<Fr>,
+            MockDishonestForkProver<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &seed,
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove")
+    };
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        let (output, namespaces) =
+            BCSVerifier::verify_per_namespace::<MockForkProtocol<Fr>, NoLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                proof,
+                &(),
+                &(),
+                &None,
+                mt_hash_param.clone(),
+            )
+            .expect("verification failed");
+        let namespaces = namespaces
+            .into_iter()
+            .map(|(namespace, passed)| (namespace.id, passed))
+            .collect::<Vec<_>>();
+        (output, namespaces)
+    };
+    // namespaces are root, the two subprotocols and LDT
+    assert_eq!(
+        verify(&prove(2)),
+        (true, vec![(0, true), (1, true), (2, true), (3, true)])
+    );
+
+    // the second subprotocol answers with the oracle of another seed, whose
+    // paths still verify
+    let dishonest = prove(7);
+    assert!(
+        !BCSVerifier::verify_with_ldt_disabled::<MockForkProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &dishonest,
+            &(),
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("verification failed")
+    );
+    assert_eq!(
+        verify(&dishonest),
+        (false, vec![(0, false), (1, true), (2, false), (3, true)])
+    );
+
+    let transcript =
+        commit_phase_transcript::<MockForkProtocol<Fr>, NoLDT<Fr>>(&(), &(), &(), &None).unwrap();
+    let root = NameSpace::root(iop_trace!("test decide namespace"));
+    let ns_a = transcript.bookkeeper.get_subspace(root, 0);
+    let ns_b = transcript.bookkeeper.get_subspace(root, 1);
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        Vec::new(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+    assert!(!messages.decide_namespace(ns_a, |_| Ok(false)));
+    assert!(!messages.decide_namespace(ns_a, |_| {
+        Err(Box::new(BCSError::ProofRejected) as Error)
+    }));
+    #[cfg(feature = "std")]
+    assert!(!messages.decide_namespace(ns_a, |_| panic!("decision panics")));
+    assert!(messages.decide_namespace(ns_b, |messages| {
+        Ok(MockSubprotocol::query_and_decide(ns_b, messages, 2))
+    }));
+    assert_eq!(
+        messages.namespace_decisions.into_iter().collect::<Vec<_>>(),
+        vec![(ns_a.id, false), (ns_b.id, true)]
+    );
+}
+
+#[test]
+/// Test that `BCSConfig::new_with_poseidon` rejects Poseidon parameters
+/// without rate or with too little capacity for the security level.
+fn test_invalid_sponge_parameters() {
+    let new_config = |sponge_params: ark_sponge::poseidon::PoseidonConfig<Fr>, security_bits| {
+        BCSConfig::<FieldMTConfig, _, Fr, NoLDT<Fr>>::new_with_poseidon(
+            &sponge_params,
+            security_bits,
+            mt_hash_parameters(),
+            None,
+        )
+        .map(|_| ())
+    };
+    assert!(new_config(poseidon_parameters(), 100).is_ok());
+
+    // one capacity element of a 255-bit field gives less than 128 bits
+    let err = new_config(poseidon_parameters(), 128).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidSpongeParameters {
+            rate: 2,
+            capacity: 1,
+            field_bits: 255,
+            security_bits: 128,
+        })
+    );
+
+    let mut no_rate = poseidon_parameters();
+    no_rate.rate = 0;
+    let err = new_config(no_rate, 100).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidSpongeParameters {
+            rate: 0,
+            capacity: 1,
+            field_bits: 255,
+            security_bits: 100,
+        })
+    );
+}
+
+#[test]
+/// Test that an oracle of length 512 sent by `Transcript::send_split_oracle`
+/// is committed as two segments of length 256, and that queries across the
+/// boundary are answered by the right segment.
+fn test_split_oracle() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockSplitOracleProtocol<Fr>,
+        MockSplitOracleProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    // positions 7 and 255 are in the first segment, and 256, 300 and 511 are
+    // in the second one
+    assert_eq!(
+        bcs_proof
+            .prover_iop_messages_by_round
+            .iter()
+            .map(|round| round.queried_cosets.len())
+            .collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert_eq!(
+        bcs_proof.prover_iop_messages_by_round[1].queried_cosets[0],
+        vec![vec![Fr::from(3 * 256 + 1u64)]]
+    );
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockSplitOracleProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param,
+        )
+        .expect("verification failed")
+    );
+}
+
+#[test]
+/// Test that `absorb_public_field_elements` prefixes the elements by their
+/// number, so that `[a, b]` and `[a, b, 0]` lead to different sponge states,
+/// and that prover and verifier transcripts absorb them identically.
+fn test_absorb_public_field_elements() {
+    let bcs_proof =
+        BCSProof::generate_with_ldt_disabled::<MockForkProtocol<Fr>, MockForkProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            mt_hash_parameters(),
+        )
+        .expect("fail to prove");
+    let mut rng = test_rng();
+    let (a, b) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+
+    let prover_state = |public_input: &[Fr]| {
+        let mut transcript = new_transcript(None, None);
+        transcript.absorb_public_field_elements(public_input);
+        let script = transcript.absorption_script().to_vec();
+        (script, transcript.sponge.squeeze_bytes(32))
+    };
+    let verifier_state = |public_input: &[Fr]| {
+        let mut transcript = SimulationTranscript::new_transcript(
+            &bcs_proof,
+            PoseidonSponge::new(&poseidon_parameters()),
+            None,
+            None,
+            iop_trace!("test absorb public input"),
+        );
+        transcript.absorb_public_field_elements(public_input);
+        let script = transcript.absorption_script().to_vec();
+        (script, transcript.sponge.squeeze_bytes(32))
+    };
+
+    let (script, state) = prover_state(&[a, b]);
+    assert_eq!(
+        script.last(),
+        Some(&AbsorbOp::absorb(
+            AbsorbedValue::PublicInput,
+            &vec![Fr::from(2u64), a, b]
+        ))
+    );
+    assert_eq!((script, state.clone()), verifier_state(&[a, b]));
+    assert_ne!(state, prover_state(&[a, b, Fr::zero()]).1);
+}
+
+#[test]
+/// Test that stopping FRI early at a final polynomial of degree 4 gives a
+/// valid proof that is smaller than folding all the way to a constant.
+fn test_final_poly_degree() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove_and_verify = |final_poly_degree| {
+        let ldt_parameters = LinearCombinationLDTParameters::with_final_poly_degree(
+            63,
+            1,
+            final_poly_degree,
+            Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+            7,
+        );
+        ldt_parameters.validate().unwrap();
+        let bcs_proof = BCSProof::generate::<
+            MockTest1Verifier<Fr>,
+            MockTestProver<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+        assert!(
+            BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &bcs_proof,
+                &(),
+                &(),
+                &ldt_parameters,
+                mt_hash_param.clone()
+            )
+            .expect("verification failed")
+        );
+        (ldt_parameters, bcs_proof.compressed_size())
+    };
+
+    let (early_parameters, early_size) = prove_and_verify(4);
+    assert_eq!(
+        early_parameters.fri_parameters.localization_parameters,
+        vec![1, 1, 1, 1]
+    );
+    assert!(early_parameters.final_poly_degree() <= 4);
+
+    let (constant_parameters, constant_size) = prove_and_verify(0);
+    assert_eq!(constant_parameters.final_poly_degree(), 0);
+    assert!(early_size < constant_size);
+}
+
+#[test]
+/// Test that `current_round_index` increments with each round submitted in a
+/// namespace, on both prover and verifier side.
+fn test_current_round_index() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockRoundIndexProtocol<Fr>,
+        MockRoundIndexProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    assert_eq!(
+        bcs_proof.prover_iop_messages_by_round.len(),
+        MockRoundIndexProtocol::<Fr>::NUM_ROUNDS
+    );
+    assert!(
+        BCSVerifier::verify_with_ldt_disabled::<MockRoundIndexProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &bcs_proof,
+            &(),
+            &(),
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+}
+
+#[test]
+/// Test that interning three identical short messages gives a smaller proof
+/// that still verifies.
+fn test_intern_short_messages() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockRepeatedShortMessageProtocol<Fr>,
+        MockRepeatedShortMessageProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+
+    let interned = bcs_proof.intern_short_messages();
+    assert_eq!(interned.short_messages.len(), 1);
+    assert_eq!(interned.short_message_indices, vec![vec![0]; 3]);
+    assert!(interned.compressed_size() < bcs_proof.compressed_size());
+    assert!(interned.to_proof().unwrap() == bcs_proof);
+
+    let mut bytes = Vec::new();
+    interned.serialize_compressed(&mut bytes).unwrap();
+    let decoded = InternedBCSProof::<FieldMTConfig, Fr>::deserialize_compressed(&*bytes).unwrap();
+    assert!(
+        BCSVerifier::verify_interned::<MockRepeatedShortMessageProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &decoded,
+            &(),
+            &(),
+            &None,
+            mt_hash_param
+        )
+        .expect("verification failed")
+    );
+
+    let mut malformed = interned;
+    malformed.short_message_indices[2][0] = 1;
+    let err = malformed.to_proof().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::MalformedProof {
+            round: 2,
+            reason: "missing short message",
+        })
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "verifier sponge diverges from prover at checkpoint `after public input`"
+)]
+/// Test that `assert_sponge_matches` passes at a checkpoint before verifier
+/// diverges from prover, and reports the first checkpoint after it.
+fn test_assert_sponge_matches() {
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockRoundIndexProtocol<Fr>,
+        MockRoundIndexProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_parameters(),
+    )
+    .expect("fail to prove");
+
+    let mut transcript = new_transcript(None, None);
+    transcript.checkpoint_sponge("start");
+    transcript.absorb_public_field_elements(&[Fr::one(), Fr::from(2u64)]);
+    transcript.checkpoint_sponge("after public input");
+    let checkpoints = transcript.sponge_checkpoints().clone();
+    assert!(checkpoints.get("start").is_some());
+    assert_ne!(
+        checkpoints.get("start"),
+        checkpoints.get("after public input")
+    );
+
+    let mut simulation_transcript = SimulationTranscript::new_transcript(
+        &bcs_proof,
+        PoseidonSponge::new(&poseidon_parameters()),
+        None,
+        None,
+        iop_trace!("test sponge checkpoints"),
+    )
+    .with_sponge_checkpoints(checkpoints);
+    simulation_transcript.assert_sponge_matches("start");
+    // verifier absorbs a different public input
+    simulation_transcript.absorb_public_field_elements(&[Fr::one(), Fr::from(3u64)]);
+    simulation_transcript.assert_sponge_matches("after public input");
+}
+
+#[test]
+/// Test that `query_at_points` queries the oracles of `MockTestProver` at
+/// elements of the codeword domain, and rejects a point outside the domain.
+fn test_query_at_points() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![1, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let transcript = commit_phase_transcript::<MockTestProver<Fr>, LinearCombinationLDT<Fr>>(
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+    )
+    .unwrap();
+    let namespace = NameSpace::root(iop_trace!("test query at points"));
+    let mut messages = MessagesCollection::new(
+        transcript.prover_message_oracles,
+        transcript
+            .registered_virtual_oracles
+            .into_iter()
+            .map(|v| Some(v.0))
+            .collect(),
+        transcript.verifier_messages,
+        transcript.bookkeeper,
+    );
+
+    let domain = LinearCombinationLDT::codeword_domain(&ldt_parameters).unwrap();
+    let points = [domain.element(5), domain.element(100)];
+    let answers = messages
+        .prover_round((namespace, 2))
+        .query_at_points(&points, domain, iop_trace!("query at points"))
+        .unwrap();
+    assert_eq!(
+        answers,
+        messages
+            .prover_round((namespace, 2))
+            .query_point(&[5, 100], iop_trace!("query at indices"))
+    );
+    // the oracle of round 2 is the evaluations of this polynomial
+    let poly = DensePolynomial::from_coefficients_vec(
+        [0x12345u128, 0x23456, 0x34567, 0x45678, 0x56789]
+            .iter()
+            .map(|&c| Fr::from(c))
+            .collect(),
+    );
+    assert_eq!(answers[1][0], poly.evaluate(&points[1]));
+
+    let err = messages
+        .prover_round((namespace, 2))
+        .query_at_points(
+            &[points[0], Fr::from(2u64)],
+            domain,
+            iop_trace!("query outside domain"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::PointNotInDomain {
+            index: 1,
+            domain_size: 128,
+        })
+    );
+}
+
+#[test]
+/// Test that `verify_short_message_equals_evaluation` passes when a short
+/// message is the evaluation of an oracle, and reports the altered message.
+fn test_verify_short_message_equals_evaluation() {
+    let namespace = NameSpace::root(iop_trace!("test short message evaluation"));
+    let oracle = (0..16u64).map(|i| Fr::from(i * i)).collect::<Vec<_>>();
+    let messages = |short_message: Fr| {
+        let mut transcript = new_transcript(None, None);
+        transcript
+            .add_prover_round_with_custom_length_and_localization(16, Localization::default())
+            .send_oracle_message_without_degree_bound(oracle.clone())
+            .submit(namespace, iop_trace!("oracle"))
+            .unwrap();
+        transcript
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
+            .send_short_message(vec![Fr::from(7u64), short_message])
+            .submit(namespace, iop_trace!("claimed evaluation"))
+            .unwrap();
+        MessagesCollection::new(
+            transcript.prover_message_oracles,
+            Vec::new(),
+            transcript.verifier_messages,
+            transcript.bookkeeper,
+        )
+    };
+
+    let mut consistent = messages(oracle[5]);
+    assert!(consistent.verify_short_message_equals_evaluation(
+        namespace,
+        1,
+        1,
+        0,
+        5,
+        iop_trace!("check claimed evaluation")
+    ));
+    assert!(consistent.finalize_checks().is_ok());
+
+    let mut altered = messages(oracle[5] + Fr::one());
+    assert!(!altered.verify_short_message_equals_evaluation(
+        namespace,
+        1,
+        1,
+        0,
+        5,
+        iop_trace!("check claimed evaluation")
+    ));
+    let err = altered.finalize_checks().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::FailedChecks {
+            messages: vec![
+                "short message 1 of round 1 does not equal round 0 at position 5".into()
+            ],
+        })
+    );
+}
+
+#[test]
+/// Test that the same protocol runs at 1 and 40 LDT queries by changing only
+/// its parameter, which overrides the number of queries of LDT parameters.
+fn test_ldt_num_queries_from_verifier_parameter() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let mt_hash_param = mt_hash_parameters();
+    for &num_ldt_queries in &[1, 40] {
+        let param = MockLDTQueryCountParameter { num_ldt_queries };
+        let bcs_proof = BCSProof::generate::<
+            MockLDTQueryCountProtocol<Fr>,
+            MockLDTQueryCountProtocol<Fr>,
+            LinearCombinationLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &param,
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+        // LDT queries one coset of the polynomial per query
+        assert_eq!(
+            bcs_proof.prover_iop_messages_by_round[0]
+                .queried_cosets
+                .len(),
+            num_ldt_queries
+        );
+        assert!(
+            BCSVerifier::verify::<MockLDTQueryCountProtocol<Fr>, LinearCombinationLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                &bcs_proof,
+                &(),
+                &param,
+                &ldt_parameters,
+                mt_hash_param.clone(),
+            )
+            .expect("verification failed")
+        );
+    }
+}
+
+#[test]
+/// Test that `TranscriptVersion::V4` commits a length-2 oracle inline, with no
+/// merkle root and paths, and that verifier reads queries to it directly.
+fn test_inline_oracle() {
+    let mt_hash_param = mt_hash_parameters();
+    let prove = |version| {
+        BCSProof::generate_with_version::<
+            MockInlineOracleProtocol<Fr>,
+            MockInlineOracleProtocol<Fr>,
+            NoLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            &None,
+            mt_hash_param.clone(),
+            version,
+        )
+        .expect("fail to prove")
+    };
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify_with_version::<MockInlineOracleProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &(),
+            &None,
+            mt_hash_param.clone(),
+            TranscriptVersion::V4,
+        )
+    };
+
+    let proof = prove(TranscriptVersion::V4);
+    assert!(proof.prover_messages_mt_root[0].is_none());
+    assert!(proof.prover_oracles_mt_path[0].is_empty());
+    assert_eq!(
+        proof.prover_iop_messages_by_round[0].queried_cosets,
+        vec![vec![vec![Fr::from(5u64)]], vec![vec![Fr::from(7u64)]]]
+    );
+    // the length-64 oracle is above the threshold, so it still uses a merkle tree
+    assert!(proof.prover_messages_mt_root[1].is_some());
+    assert_eq!(proof.prover_oracles_mt_path[1].len(), 2);
+    assert!(proof.validate_structure().is_ok());
+    assert!(verify(&proof).expect("fail to verify"));
+
+    // verifier reads the inline elements sent in the proof
+    let mut tampered = proof.clone();
+    tampered.prover_iop_messages_by_round[0].queried_cosets[0][0][0] = Fr::from(6u64);
+    assert!(!matches!(verify(&tampered), Ok(true)));
+
+    // the default version does not commit inline
+    let proof = prove(TranscriptVersion::default());
+    assert!(proof.prover_messages_mt_root[0].is_some());
+    assert_eq!(
+        proof.prover_oracles_mt_path[0].len(),
+        MockInlineOracleProtocol::<Fr>::TINY_QUERIES.len()
+    );
+}
+
+#[test]
+/// Test that submitting a round committed inline by `TranscriptVersion::V4`
+/// returns an error if it is hiding or uses a precomputed merkle tree.
+fn test_inline_oracle_unsupported_round() {
+    let mut rng = test_rng();
+    let v4_transcript = || new_transcript_with_version(None, None, TranscriptVersion::V4);
+    let namespace = NameSpace::root(iop_trace!("test inline oracle unsupported round"));
+    let oracle = vec![Fr::from(5u64), Fr::from(7u64)];
+
+    let err = v4_transcript()
+        .add_prover_round_with_custom_length_and_localization(2, Localization::default())
+        .send_oracle_message_without_degree_bound(oracle.clone())
+        .with_hiding(&mut rng)
+        .submit(namespace, iop_trace!("hiding inline round"))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnsupportedInlineRound {
+            reason: "is hiding"
+        })
+    );
+
+    // the default version commits the same round using a merkle tree
+    let mut committed = new_transcript(None, None);
+    committed
+        .add_prover_round_with_custom_length_and_localization(2, Localization::default())
+        .send_oracle_message_without_degree_bound(oracle.clone())
+        .submit(namespace, iop_trace!("round with merkle tree"))
+        .unwrap();
+    let tree = match committed.merkle_tree_for_each_round[0].clone() {
+        Some(RoundMerkleTree::Dense(tree)) => tree,
+        _ => panic!("round should be committed using a dense merkle tree"),
+    };
+    let err = v4_transcript()
+        .add_prover_round_with_custom_length_and_localization(2, Localization::default())
+        .send_oracle_message_with_precomputed_tree(oracle, tree)
+        .submit(namespace, iop_trace!("inline round with precomputed tree"))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::UnsupportedInlineRound {
+            reason: "uses a precomputed merkle tree"
+        })
+    );
+}
+
+#[test]
+/// Test that `LinearCombinationLDT::derive_query_positions` gives the coset
+/// indices queried by verifier in the polynomial round and the FRI rounds.
+fn test_derive_query_positions() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    let param = MockLDTQueryCountParameter { num_ldt_queries: 7 };
+
+    // run commit phase only, including the one of LDT
+    let mut transcript = commit_phase_transcript::<
+        MockLDTQueryCountProtocol<Fr>,
+        LinearCombinationLDT<Fr>,
+    >(&(), &(), &param, &ldt_parameters)
+    .unwrap();
+    let namespace = NameSpace::root(iop_trace!("test derive query positions"));
+    let ldt_namespace = transcript.new_namespace(namespace, iop_trace!("LDT"));
+    let codewords = transcript.bookkeeper.dump_all_prover_messages_in_order();
+    LinearCombinationLDT::prove(ldt_namespace, &ldt_parameters, &mut transcript, &codewords)
+        .unwrap();
+    let positions = LinearCombinationLDT::derive_query_positions(
+        &transcript.commit_phase_sponge(),
+        &ldt_parameters,
+        TranscriptVersion::default(),
+    )
+    .unwrap();
+    assert_eq!(positions.len(), 3);
+    assert!(positions.iter().all(|layer| layer.len() == 7));
+
+    let bcs_proof = BCSProof::generate::<
+        MockLDTQueryCountProtocol<Fr>,
+        MockLDTQueryCountProtocol<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &param,
+        &ldt_parameters,
+        mt_hash_parameters(),
+    )
+    .expect("fail to prove");
+    // round 0 sends the polynomial, and rounds 1 and 2 are FRI rounds
+    for (round, layer) in positions.iter().enumerate() {
+        let queried = bcs_proof.prover_oracles_mt_path[round]
+            .iter()
+            .map(|path| path.leaf_index)
+            .collect::<Vec<_>>();
+        assert_eq!(&queried, layer);
+    }
+}
+
+#[test]
+/// Test that a proof serialized using `serialize_minimal` is smaller than the
+/// full encoding, and that `deserialize_minimal` recovers a proof that
+/// verifies like the original one.
+fn test_serialize_minimal() {
+    let fri_parameters = FRIParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+    );
+    let ldt_parameters = LinearCombinationLDTParameters {
+        fri_parameters,
+        num_queries: 7,
+        query_sampler: QueryStrategy::Independent,
+        coefficient_strategy: CoefficientStrategy::IndependentRandom,
+    };
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate::<
+        MockTest1Verifier<Fr>,
+        MockTestProver<Fr>,
+        LinearCombinationLDT<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        &ldt_parameters,
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+
+    let mut full = Vec::new();
+    bcs_proof.serialize_compressed(&mut full).unwrap();
+    let mut minimal = Vec::new();
+    bcs_proof
+        .serialize_minimal(&mt_hash_param, &mut minimal)
+        .unwrap();
+    // the last FRI round has 4 leaves and 7 queries, so some siblings are queried
+    assert!(minimal.len() < full.len());
+
+    let decoded = BCSProof::deserialize_minimal(minimal.as_slice(), &mt_hash_param).unwrap();
+    assert!(decoded == bcs_proof);
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify::<MockTest1Verifier<Fr>, LinearCombinationLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &(),
+            &ldt_parameters,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to verify")
+    };
+    assert_eq!(verify(&decoded), verify(&bcs_proof));
+    assert!(verify(&decoded));
+}
+
+#[test]
+/// Test that two mock proofs bound to each other using `bind_sibling`
+/// verify, and that a proof bound to another sibling does not.
+fn test_bind_sibling() {
+    let mt_hash_param = mt_hash_parameters();
+    let namespace = NameSpace::root(iop_trace!("test bind sibling"));
+    // each prover computes its digest before binding, and sends it to the other
+    let digest_before_binding = |message: u64| {
+        let mut transcript = new_transcript(None, None);
+        MockSiblingBindingProtocol::send_message(namespace, &mut transcript, message).unwrap();
+        transcript.commit_phase_digest()
+    };
+    let prove = |message: u64, sibling_digest: Fr| {
+        BCSProof::generate::<
+            MockSiblingBindingProtocol<Fr>,
+            MockSiblingBindingProtocol<Fr>,
+            NoLDT<Fr>,
+            _,
+        >(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &message,
+            &MockSiblingBindingParameter { sibling_digest },
+            &None,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove")
+    };
+    // each verifier computes the digest of the sibling proof at the same point
+    let digest_of_proof = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        let mut transcript = SimulationTranscript::<_, _, Fr>::new_transcript(
+            proof,
+            PoseidonSponge::new(&poseidon_parameters()),
+            None,
+            None,
+            iop_trace!("test bind sibling"),
+        );
+        transcript.receive_prover_current_round(
+            namespace,
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
+            iop_trace!("mock sibling message"),
+        );
+        transcript.commit_phase_digest()
+    };
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>, sibling: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify::<MockSiblingBindingProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &MockSiblingBindingParameter {
+                sibling_digest: digest_of_proof(sibling),
+            },
+            &None,
+            mt_hash_param.clone(),
+        )
+        .expect("fail to verify")
+    };
+
+    let main_proof = prove(1, digest_before_binding(2));
+    let range_proof = prove(2, digest_before_binding(1));
+    assert_eq!(digest_of_proof(&main_proof), digest_before_binding(1));
+    assert!(verify(&main_proof, &range_proof));
+    assert!(verify(&range_proof, &main_proof));
+
+    // a range proof for the same message, bound to another main proof
+    let unbound_range_proof = prove(2, digest_before_binding(3));
+    assert!(!verify(&unbound_range_proof, &main_proof));
+    assert!(!verify(&main_proof, &prove(3, digest_before_binding(1))));
+}
+
+#[test]
+/// Test that verifying paths of adjacent positions hashes their shared inner
+/// nodes once, and that a path disagreeing with a cached node is rejected.
+fn test_path_prefix_cache() {
+    let mt_hash_param = mt_hash_parameters();
+    let bcs_proof = BCSProof::generate_with_ldt_disabled::<
+        MockAdjacentQueryProtocol<Fr>,
+        MockAdjacentQueryProtocol<Fr>,
+        _,
+    >(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &(),
+        &(),
+        &(),
+        mt_hash_param.clone(),
+    )
+    .expect("fail to prove");
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        BCSVerifier::verify_with_metrics::<MockAdjacentQueryProtocol<Fr>, NoLDT<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            proof,
+            &(),
+            &(),
+            &None,
+            mt_hash_param.clone(),
+        )
+    };
+    let (verifier_result, metrics) = verify(&bcs_proof).expect("fail to verify");
+    assert!(verifier_result);
+
+    let paths = &bcs_proof.prover_oracles_mt_path[0];
+    assert_eq!(paths.len(), 4);
+    assert_eq!(paths[0].auth_path.len(), 5);
+    let naive = paths
+        .iter()
+        .map(|path| 2 + path.auth_path.len())
+        .sum::<usize>();
+    // position 20 hashes up to the root, 21 shares its parent with 20, and
+    // 22 and 23 share their grandparent with 20 and 21
+    assert_eq!(metrics.num_hash_invocations, 7 + 2 + 3 + 2);
+    assert!(metrics.num_hash_invocations < naive);
+
+    let mut tampered = bcs_proof.clone();
+    tampered.prover_iop_messages_by_round[0].queried_cosets[1][0][0] += Fr::one();
+    assert!(!matches!(verify(&tampered), Ok((true, _))));
+}
+
+#[test]
+/// Test that a degree bound given as a rate resolves to the absolute degree
+/// bound over the codeword domain, and keeps the rate when the domain is
+/// resized.
+fn test_degree_bound_rate() {
+    let transcript_over = |domain_size: usize| {
+        new_transcript(
+            Some(Radix2CosetDomain::new_radix2_coset(domain_size, Fr::one())),
+            Some(1),
+        )
+    };
+    let transcript = transcript_over(128);
+    assert_eq!(
+        transcript
+            .resolve_degree_bound(DegreeBound::Rate(1, 16))
+            .unwrap(),
+        8
+    );
+    assert_eq!(
+        transcript
+            .resolve_degree_bound(DegreeBound::Absolute(8))
+            .unwrap(),
+        8
+    );
+    assert_eq!(transcript.resolve_degree_bound(8.into()).unwrap(), 8);
+    let transcript = transcript_over(256);
+    assert_eq!(
+        transcript
+            .resolve_degree_bound(DegreeBound::Rate(1, 16))
+            .unwrap(),
+        16
+    );
+
+    for rate in [
+        DegreeBound::Rate(1, 0),
+        DegreeBound::Rate(2, 2),
+        DegreeBound::Rate(1, 512),
+    ] {
+        let err = transcript.resolve_degree_bound(rate).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BCSError>(),
+            Some(BCSError::InvalidRate {
+                domain_size: 256,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+/// Test that `tamper_answer` changes the answer to a queried position of a
+/// subprotocol round, and that the tampered proof is rejected when verifying
+/// the paths of that round only.
+fn test_tamper_answer() {
+    let mt_hash_param = mt_hash_parameters();
+    let transcript =
+        commit_phase_transcript::<MockForkProtocol<Fr>, NoLDT<Fr>>(&(), &(), &(), &None).unwrap();
+    let root = NameSpace::root(iop_trace!("test tamper answer"));
+    let bcs_proof =
+        BCSProof::generate_with_ldt_disabled::<MockForkProtocol<Fr>, MockForkProtocol<Fr>, _>(
+            PoseidonSponge::new(&poseidon_parameters()),
+            &(),
+            &(),
+            &(),
+            mt_hash_param.clone(),
+        )
+        .expect("fail to prove");
+    let verify = |proof: &BCSProof<FieldMTConfig, Fr>| {
+        let (output, namespaces) =
+            BCSVerifier::verify_per_namespace::<MockForkProtocol<Fr>, NoLDT<Fr>, _>(
+                PoseidonSponge::new(&poseidon_parameters()),
+                proof,
+                &(),
+                &(),
+                &None,
+                mt_hash_param.clone(),
+            )
+            .expect("verification failed");
+        let namespaces = namespaces
+            .into_iter()
+            .map(|(namespace, passed)| (namespace.id, passed))
+            .collect::<Vec<_>>();
+        (output, namespaces)
+    };
+    assert_eq!(
+        verify(&bcs_proof),
+        (true, vec![(0, true), (1, true), (2, true), (3, true)])
+    );
+
+    // the second subprotocol queries position 40, which is element 1 of coset 8
+    let ns_b = transcript.bookkeeper.get_subspace(root, 1);
+    let tampered = tamper_answer(&bcs_proof, &transcript, ns_b, 0, 40, &[Fr::zero()]);
+    let index = MockSubprotocol::NUM_PROVER_ROUNDS;
+    let (answers, paths) = (
+        &tampered.prover_iop_messages_by_round[index].queried_cosets,
+        &bcs_proof.prover_oracles_mt_path[index],
+    );
+    assert_eq!(paths[1].leaf_index, 8);
+    assert_eq!(answers[1][0], vec![Fr::from(2008u64), Fr::zero()]);
+    assert!(answers[0] == bcs_proof.prover_iop_messages_by_round[index].queried_cosets[0]);
+    assert_eq!(
+        verify(&tampered),
+        (false, vec![(0, false), (1, true), (2, false), (3, true)])
+    );
+    let err = BCSVerifier::verify::<MockForkProtocol<Fr>, NoLDT<Fr>, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &tampered,
+        &(),
+        &(),
+        &None,
+        mt_hash_param,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::InvalidAuthenticationPath { round: index })
+    );
+}
+
+#[test]
+/// Test that query samplers squeeze positions from the sponge: a custom
+/// sampler and `UniformUniqueSampler` give distinct positions, and a
+/// protocol using the custom sampler opens the positions it samples.
+fn test_query_sampler() {
+    fn sample<Q: QuerySampler>(sampler: &Q, num: usize, domain_size: usize) -> Vec<usize> {
+        let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+        let positions = sampler
+            .sample_positions(&mut sponge, num, domain_size)
+            .unwrap();
+        let mut same_sponge = PoseidonSponge::new(&poseidon_parameters());
+        assert_eq!(
+            sampler
+                .sample_positions(&mut same_sponge, num, domain_size)
+                .unwrap(),
+            positions
+        );
+        assert_eq!(positions.len(), num);
+        assert!(positions.iter().all(|&position| position < domain_size));
+        positions
+    }
+    let is_distinct =
+        |positions: &[usize]| positions.iter().collect::<BTreeSet<_>>().len() == positions.len();
+
+    for num in [1, 6, 32] {
+        assert!(is_distinct(&sample(&MockStridedSampler, num, 32)));
+        assert!(is_distinct(&sample(&UniformUniqueSampler, num, 32)));
+    }
+    // every position is sampled once
+    let mut positions = sample(&UniformUniqueSampler, 32, 32);
+    positions.sort_unstable();
+    assert_eq!(positions, (0..32).collect::<Vec<_>>());
+    // independent queries repeat positions when there are more than cosets
+    assert!(!is_distinct(&sample(&QueryStrategy::Independent, 33, 32)));
+    // but distinct ones cannot be sampled
+    let err = UniformUniqueSampler
+        .sample_positions(&mut PoseidonSponge::new(&poseidon_parameters()), 33, 32)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BCSError>(),
+        Some(&BCSError::TooManyDistinctQueries {
+            num_queries: 33,
+            domain_size: 32,
+        })
+    );
+
+    let (valid, positions) = prove_and_verify::<
+        MockSampledQueryProtocol<Fr>,
+        MockSampledQueryProtocol<Fr>,
+        NoLDT<Fr>,
+    >(&(), &(), &(), &None)
+    .expect("round trip failed");
+    assert!(valid);
+    assert_eq!(positions.len(), MockSampledQueryProtocol::<Fr>::NUM_QUERIES);
+    assert!(is_distinct(&positions));
+}
+
+#[test]
+/// Test that `SequentialProver` and `SequentialVerifier` compose two
+/// instances of a mock protocol, passing each instance its own inputs.
+fn test_sequential_composition() {
+    let ldt_parameters = LinearCombinationLDTParameters::new(
+        64,
+        vec![2, 2, 1],
+        Radix2CosetDomain::new_radix2_coset(128, Fr::one()),
+        7,
+    );
+    assert_eq!(
+        prove_and_verify::<
+            SequentialProver<MockTestProver<Fr>, MockTestProver<Fr>>,
+            SequentialVerifier<MockTest1Verifier<Fr>, MockTest1Verifier<Fr>>,
+            LinearCombinationLDT<Fr>,
+        >(&((), ()), &((), ()), &((), ()), &ldt_parameters)
+        .expect("round trip failed"),
+        (true, true)
+    );
+
+    // an instance proves equal oracles only with private input 4
+    let prove_and_verify_equality = |private_input: (u64, u64)| {
+        prove_and_verify::<
+            SequentialProver<MockOracleEqualityProtocol<Fr>, MockOracleEqualityProtocol<Fr>>,
+            SequentialVerifier<MockOracleEqualityProtocol<Fr>, MockOracleEqualityProtocol<Fr>>,
+            NoLDT<Fr>,
+        >(&((), ()), &private_input, &((), ()), &None)
+        .expect("round trip failed")
+    };
+    assert_eq!(prove_and_verify_equality((4, 4)), (true, true));
+    assert_eq!(prove_and_verify_equality((4, 5)), (true, false));
+    assert_eq!(prove_and_verify_equality((5, 4)), (false, true));
+}
@@ -1,17 +1,24 @@
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
-use ark_ff::PrimeField;
+use ark_ff::{BigInteger, Field, PrimeField};
 use ark_sponge::{Absorb, CryptographicSponge, FieldElementSize};
-use ark_std::{boxed::Box, collections::BTreeSet, vec, vec::Vec};
+use ark_std::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    rand::RngCore,
+    vec,
+    vec::Vec,
+    UniformRand,
+};
 use tracing::info;
 
 use crate::{
-    bcs::MTHashParameters,
+    bcs::{simulation_transcript::ChallengeSource, sparse_mt::SparseMerkleTree, MTHashParameters},
     iop::{
         bookkeeper::{MessageBookkeeper, NameSpace, ToMsgRoundRef},
         message::{
-            LeavesType,
+            DegreeBound, LeavesType,
             LeavesType::{Custom, UseCodewordDomain},
-            MsgRoundRef, OracleIndex, ProverRoundMessageInfo, VerifierMessage,
+            Localization, MsgRoundRef, OracleIndex, ProverRoundMessageInfo, VerifierMessage,
         },
         oracles::{
             RecordingRoundOracle, RoundOracle, SuccinctRoundMessage, VirtualOracle,
@@ -19,12 +26,14 @@ use crate::{
         },
     },
     tracer::TraceInfo,
-    Error,
+    BCSError, Error,
 };
-use ark_crypto_primitives::MerkleTree;
+use ark_crypto_primitives::{CRHScheme, MerkleTree, Path};
 use ark_ldt::domain::Radix2CosetDomain;
 use ark_poly::{univariate::DensePolynomial, Polynomial};
 use ark_std::mem::take;
+#[cfg(feature = "timing")]
+use ark_std::time::{Duration, Instant};
 
 #[allow(variant_size_differences)]
 /// Pending message for current transcript. We allow `variant_size_differences`
@@ -60,7 +69,7 @@ where
     /// Each element `merkle_tree_for_each_round[i]` corresponds to the merkle
     /// tree for `prover_message_oracles[i]`. If no oracle messages in this
     /// round, merkle tree will be `None`.
-    pub merkle_tree_for_each_round: Vec<Option<MerkleTree<P>>>,
+    pub merkle_tree_for_each_round: Vec<Option<RoundMerkleTree<P>>>,
     /// Sampled Message sent by verifier in commit phase. In each round,
     /// verifier can send multiple messages.
     pub verifier_messages: Vec<Vec<VerifierMessage<F>>>,
@@ -71,6 +80,331 @@ where
     pending_message_for_current_round: PendingMessage<F>,
     pub(crate) ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
     pub(crate) ldt_localization_parameter: Option<usize>,
+    /// Wall-clock duration of each prover round, from the time the round is
+    /// added to the time it is submitted.
+    #[cfg(feature = "timing")]
+    round_timings: Vec<(NameSpace, usize, Duration)>,
+    /// Prover rounds submitted by `PendingProverMessage::submit_at` that are
+    /// waiting for rounds with smaller index, keyed by round index.
+    staged_prover_rounds: BTreeMap<usize, StagedProverRound<P, F>>,
+    /// If set, verifier messages are taken from this source instead of the
+    /// sponge.
+    challenge_source: Option<Box<dyn ChallengeSource<F> + Send>>,
+    /// Mapping used by `squeeze_verifier_bytes_as_field`.
+    hash_to_field: HashToFieldConfig<F>,
+    /// Operations on `sponge`, in order.
+    absorption_script: Vec<AbsorbOp>,
+    /// Number of forks created by `fork`.
+    num_forks: u64,
+    /// Version of the absorption order.
+    pub(crate) version: TranscriptVersion,
+    /// If set, query phase uses these positions instead of the ones derived
+    /// from the sponge.
+    #[cfg(feature = "bench")]
+    pub(crate) fixed_queries: Option<Vec<usize>>,
+    /// Sponge states recorded by `checkpoint_sponge`.
+    sponge_checkpoints: SpongeCheckpoints,
+}
+
+/// What is absorbed by `AbsorbOp::Absorb`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbsorbedValue {
+    /// Domain separator of a namespace, given by its path from root namespace.
+    DomainSeparator,
+    /// Merkle root of a prover round, as `Option<InnerDigest>`, which is
+    /// `None` if the round has no oracle.
+    MerkleRoot,
+    /// A short message of a prover round.
+    ShortMessage,
+    /// 32 bytes squeezed from the sponge of a joined fork.
+    ForkDigest,
+    /// Domain separator of the transcript version; see `TranscriptVersion`.
+    TranscriptVersion,
+    /// Oracle length and localization parameter of a prover round with
+    /// oracles, as two field elements.
+    RoundStructure,
+    /// Public field elements prefixed by their number; see
+    /// `Transcript::absorb_public_field_elements`.
+    PublicInput,
+    /// All cosets of a prover round committed inline, concatenated in coset
+    /// order; see `TranscriptVersion::V4`.
+    InlineOracles,
+    /// Commit phase digest of another proof; see `Transcript::bind_sibling`.
+    SiblingDigest,
+    /// Separator of a fork, given by the number of forks created before it
+    /// by the same transcript; see `Transcript::fork`.
+    ForkSeparator,
+}
+
+/// Version of the order in which transcripts absorb messages. A proof can
+/// only be verified using the version it is generated with, so verifier can
+/// still check proofs of an older version after the default version changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptVersion {
+    /// Sponge absorbs no domain separator, neither of namespaces nor of the
+    /// version, as transcripts did before namespaces were separated, and
+    /// `LinearCombinationLDT` squeezes independent queries whatever its query
+    /// sampler is, as it did before samplers were configurable, so that
+    /// their proofs can still be verified.
+    V1,
+    /// Sponge absorbs the domain separator of root namespace, then the one of
+    /// this version, and the one of each namespace when it is created.
+    V2,
+    /// Like `V2`, and sponge also absorbs the oracle length and localization
+    /// parameter of each prover round with oracles before its merkle root, so
+    /// that later challenges depend on the declared structure.
+    V3,
+    /// Like `V3`, but each prover round whose oracles have at most
+    /// `INLINE_ORACLE_MAX_LENGTH` elements is committed inline: prover sends
+    /// all cosets in the clear with no merkle tree, and sponge absorbs all
+    /// elements in the place of the merkle root. Rounds committed inline
+    /// cannot be hiding or use a precomputed merkle tree.
+    ///
+    /// This version is opt-in: it is not the default, so prover and verifier
+    /// should select it explicitly, e.g. using
+    /// `BCSProof::generate_with_version` and `BCSVerifier::verify_with_version`.
+    V4,
+}
+
+/// Maximum oracle length of a prover round committed inline by
+/// `TranscriptVersion::V4`. A merkle tree of such a round is larger than
+/// the oracles themselves.
+pub const INLINE_ORACLE_MAX_LENGTH: usize = 4;
+
+impl TranscriptVersion {
+    /// Domain separator absorbed after the one of root namespace, if any.
+    pub fn domain_separator(self) -> Option<&'static [u8]> {
+        match self {
+            TranscriptVersion::V1 => None,
+            TranscriptVersion::V2 => Some(b"BCS transcript v2"),
+            TranscriptVersion::V3 => Some(b"BCS transcript v3"),
+            TranscriptVersion::V4 => Some(b"BCS transcript v4"),
+        }
+    }
+
+    /// Whether sponge absorbs the structure of each prover round.
+    pub fn absorbs_round_structure(self) -> bool {
+        self == TranscriptVersion::V3 || self == TranscriptVersion::V4
+    }
+
+    /// Whether the oracles of a prover round with `info` are committed inline
+    /// instead of using a merkle tree.
+    pub fn inlines_oracles(self, info: &ProverRoundMessageInfo) -> bool {
+        self == TranscriptVersion::V4
+            && info.num_oracles() > 0
+            && info.length <= INLINE_ORACLE_MAX_LENGTH
+    }
+
+    /// Whether sponge absorbs the domain separator of each namespace.
+    pub fn separates_namespaces(self) -> bool {
+        self != TranscriptVersion::V1
+    }
+
+    /// Whether LDT queries are sampled by the query sampler of the LDT
+    /// parameters, instead of being squeezed independently.
+    pub fn uses_ldt_query_sampler(self) -> bool {
+        self != TranscriptVersion::V1
+    }
+
+    /// Absorbs the domain separators of root namespace of `bookkeeper` and of
+    /// this version, as a new transcript does, and returns the recorded
+    /// operations.
+    pub(crate) fn absorb_into<S: CryptographicSponge>(
+        self,
+        sponge: &mut S,
+        bookkeeper: &MessageBookkeeper,
+    ) -> Vec<AbsorbOp> {
+        let mut absorption_script = Vec::new();
+        if self.separates_namespaces() {
+            let domain_separator = bookkeeper.domain_separator(0);
+            sponge.absorb(&domain_separator);
+            absorption_script.push(AbsorbOp::absorb(
+                AbsorbedValue::DomainSeparator,
+                &domain_separator,
+            ));
+        }
+        if let Some(separator) = self.domain_separator() {
+            let separator = separator.to_vec();
+            sponge.absorb(&separator);
+            absorption_script.push(AbsorbOp::absorb(
+                AbsorbedValue::TranscriptVersion,
+                &separator,
+            ));
+        }
+        absorption_script
+    }
+}
+
+impl Default for TranscriptVersion {
+    fn default() -> Self {
+        TranscriptVersion::V3
+    }
+}
+
+/// Oracle length and localization parameter of a prover round, as absorbed
+/// with `AbsorbedValue::RoundStructure`.
+pub(crate) fn round_structure<F: PrimeField>(info: &ProverRoundMessageInfo) -> Vec<F> {
+    vec![
+        F::from(info.length as u64),
+        F::from(info.localization_parameter as u64),
+    ]
+}
+
+/// All cosets of a prover round concatenated in coset order, as absorbed with
+/// `AbsorbedValue::InlineOracles`.
+pub(crate) fn inline_elements<F: PrimeField>(cosets: &[Vec<Vec<F>>]) -> Vec<F> {
+    cosets.iter().flatten().flatten().copied().collect()
+}
+
+/// `elements` prefixed by their number, as absorbed with
+/// `AbsorbedValue::PublicInput`.
+pub(crate) fn length_prefixed<F: PrimeField>(elements: &[F]) -> Vec<F> {
+    ark_std::iter::once(F::from(elements.len() as u64))
+        .chain(elements.iter().copied())
+        .collect()
+}
+
+/// An operation on the sponge of a transcript, as recorded by
+/// `Transcript::absorption_script` and
+/// `SimulationTranscript::absorption_script`. Prover and verifier transcripts
+/// of the same proof record the same script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbsorbOp {
+    /// Absorb a value. `bytes` is `Absorb::to_sponge_bytes` of the value,
+    /// while the sponge absorbs the value using its own `Absorb`
+    /// implementation.
+    Absorb {
+        /// What the value is.
+        value: AbsorbedValue,
+        /// Canonical bytes of the value.
+        bytes: Vec<u8>,
+    },
+    /// Squeeze field elements of given sizes.
+    SqueezeFieldElements(Vec<FieldElementSize>),
+    /// Squeeze given number of bytes.
+    SqueezeBytes(usize),
+    /// Squeeze given number of bits.
+    SqueezeBits(usize),
+    /// First operation of a forked transcript: the sponge is a clone of the
+    /// parent sponge after the first `parent_position` operations in the
+    /// script of the parent.
+    Fork {
+        /// Number of operations in the script of the parent at the time of
+        /// forking.
+        parent_position: usize,
+    },
+    /// Script of a fork joined into this transcript. The joined script ends
+    /// with squeezing the fork digest, which is absorbed next.
+    Join(Vec<AbsorbOp>),
+}
+
+impl AbsorbOp {
+    pub(crate) fn absorb(value: AbsorbedValue, item: &impl Absorb) -> Self {
+        AbsorbOp::Absorb {
+            value,
+            bytes: item.to_sponge_bytes_as_vec(),
+        }
+    }
+}
+
+/// Mapping from squeezed verifier bytes to field elements, used by
+/// `squeeze_verifier_bytes_as_field`. The default maps every
+/// `(MODULUS_BIT_SIZE - 1) / 8` bytes to a field element in little endian,
+/// which is the mapping of `ToConstraintField`. Prover and verifier
+/// transcripts must use the same config.
+#[derive(Clone, Copy)]
+pub struct HashToFieldConfig<F: PrimeField> {
+    /// Number of squeezed bytes mapped to each field element.
+    pub bytes_per_element: usize,
+    /// Map `bytes_per_element` bytes to a field element, e.g. the hash to
+    /// field method of an external specification.
+    pub map: fn(&[u8]) -> F,
+}
+
+impl<F: PrimeField> Default for HashToFieldConfig<F> {
+    fn default() -> Self {
+        Self {
+            bytes_per_element: bytes_per_field_element::<F>(),
+            map: F::from_le_bytes_mod_order,
+        }
+    }
+}
+
+impl<F: PrimeField> HashToFieldConfig<F> {
+    /// Map every `bytes_per_element` bytes to a field element.
+    pub(crate) fn map_bytes(&self, bytes: &[u8]) -> Vec<F> {
+        bytes.chunks(self.bytes_per_element).map(self.map).collect()
+    }
+}
+
+/// Sponge states recorded by `Transcript::checkpoint_sponge` at labeled
+/// points of the protocol, and checked by
+/// `SimulationTranscript::assert_sponge_matches`. This is only meant for
+/// finding where the simulation of a verifier diverges from prover.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpongeCheckpoints {
+    states: BTreeMap<&'static str, Vec<u8>>,
+}
+
+impl SpongeCheckpoints {
+    /// Returns the sponge state recorded at `label`.
+    pub fn get(&self, label: &str) -> Option<&[u8]> {
+        self.states.get(label).map(Vec::as_slice)
+    }
+}
+
+/// Separator absorbed by the sponge of the fork created after `fork_index`
+/// other forks of the same transcript.
+pub(crate) fn fork_separator(fork_index: u64) -> Vec<u8> {
+    let mut separator = b"BCS fork".to_vec();
+    separator.extend_from_slice(&fork_index.to_le_bytes());
+    separator
+}
+
+/// State of `sponge` compared by sponge checkpoints: 32 bytes squeezed from a
+/// copy of `sponge`.
+pub(crate) fn sponge_snapshot<S: CryptographicSponge>(sponge: &S) -> Vec<u8> {
+    sponge.clone().squeeze_bytes(32)
+}
+
+/// Merkle tree committing to the oracles of one prover round.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "P: MTConfig"))]
+pub enum RoundMerkleTree<P: MTConfig> {
+    /// Tree storing all nodes.
+    Dense(MerkleTree<P>),
+    /// Tree of a round whose oracles are all sent using
+    /// `send_sparse_oracle_message_without_degree_bound`.
+    Sparse(SparseMerkleTree<P>),
+}
+
+impl<P: MTConfig> RoundMerkleTree<P> {
+    /// Merkle root of the tree.
+    pub fn root(&self) -> P::InnerDigest {
+        match self {
+            Self::Dense(tree) => tree.root(),
+            Self::Sparse(tree) => tree.root(),
+        }
+    }
+
+    /// Authentication path of leaf `index`.
+    pub fn generate_proof(&self, index: usize) -> Result<Path<P>, Error> {
+        match self {
+            Self::Dense(tree) => tree.generate_proof(index),
+            Self::Sparse(tree) => Ok(tree.generate_proof(index)),
+        }
+    }
+}
+
+/// A prover round whose merkle tree is built, but is not yet absorbed by the
+/// sponge.
+struct StagedProverRound<P: MTConfig, F: PrimeField> {
+    namespace: NameSpace,
+    trace: TraceInfo,
+    merkle_tree: Option<RoundMerkleTree<P>>,
+    oracle: RecordingRoundOracle<F>,
+    #[cfg(feature = "timing")]
+    duration: Duration,
 }
 
 impl<P, S, F> Transcript<P, S, F>
@@ -80,7 +414,9 @@ where
     F: PrimeField + Absorb,
     P::InnerDigest: Absorb,
 {
-    /// Return a new BCS transcript.
+    /// Return a new BCS transcript using the default `TranscriptVersion`.
+    /// `sponge` absorbs the domain separators of root namespace and of the
+    /// version before anything else.
     pub fn new(
         sponge: S,
         hash_params: MTHashParameters<P>,
@@ -88,23 +424,170 @@ where
         ldt_localization_parameter: Option<usize>,
         trace: TraceInfo,
     ) -> Self {
+        Self::new_with_version(
+            sponge,
+            hash_params,
+            ldt_codeword_domain,
+            ldt_localization_parameter,
+            TranscriptVersion::default(),
+            trace,
+        )
+    }
+
+    /// Return a new BCS transcript like `new`, but using `version`.
+    pub fn new_with_version(
+        mut sponge: S,
+        hash_params: MTHashParameters<P>,
+        ldt_codeword_domain: Option<Radix2CosetDomain<F>>,
+        ldt_localization_parameter: Option<usize>,
+        version: TranscriptVersion,
+        trace: TraceInfo,
+    ) -> Self {
+        let bookkeeper = MessageBookkeeper::new(trace);
+        let absorption_script = version.absorb_into(&mut sponge, &bookkeeper);
         Self {
             prover_message_oracles: Vec::new(),
             merkle_tree_for_each_round: Vec::new(),
             verifier_messages: Vec::new(),
-            bookkeeper: MessageBookkeeper::new(trace),
+            bookkeeper,
             sponge,
             hash_params,
             pending_message_for_current_round: PendingMessage::default(),
             ldt_codeword_domain,
             ldt_localization_parameter,
             registered_virtual_oracles: Vec::new(),
+            #[cfg(feature = "timing")]
+            round_timings: Vec::new(),
+            staged_prover_rounds: BTreeMap::new(),
+            challenge_source: None,
+            hash_to_field: HashToFieldConfig::default(),
+            absorption_script,
+            num_forks: 0,
+            version,
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
+            sponge_checkpoints: SpongeCheckpoints::default(),
         }
     }
 
-    /// Create a new namespace in bookkeeper.
+    /// Create a new namespace in bookkeeper. Unless the transcript uses
+    /// `TranscriptVersion::V1`, the sponge absorbs the domain separator of the
+    /// new namespace, which encodes its path from root namespace.
     pub fn new_namespace(&mut self, current_namespace: NameSpace, trace: TraceInfo) -> NameSpace {
-        self.bookkeeper.new_namespace(trace, current_namespace.id)
+        let namespace = self.bookkeeper.new_namespace(trace, current_namespace.id);
+        if self.version.separates_namespaces() {
+            let domain_separator = self.bookkeeper.domain_separator(namespace.id);
+            self.absorb(AbsorbedValue::DomainSeparator, &domain_separator);
+        }
+        namespace
+    }
+
+    /// Fork this transcript to run an independent subprotocol. The fork
+    /// starts with a clone of the current sponge, so challenges squeezed in
+    /// the fork do not depend on anything sent in a sibling fork. The fork
+    /// then absorbs a separator containing the number of forks created
+    /// before it, so that sibling forks running the same subprotocol get
+    /// different challenges. Use `join` to merge the fork back after the
+    /// subprotocol finishes.
+    ///
+    /// Contract:
+    /// * all namespaces used by the fork must be created before forking.
+    /// * the fork cannot register virtual rounds.
+    /// * `MsgRoundRef` returned by the fork is only valid within the fork.
+    /// * sibling forks are created from the same parent state, and joined in
+    ///   the order they are created. The parent does not send prover rounds
+    ///   between creating forks and joining them.
+    /// * the verifier creates the forks using `SimulationTranscript::fork` in
+    ///   the same order, declaring the number of prover rounds of each fork,
+    ///   so that it can simulate them independently, e.g. on separate threads.
+    ///
+    /// # Panics
+    /// Panics if there is a pending verifier message, or if the transcript uses
+    /// a challenge source.
+    pub fn fork(&mut self) -> Self {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot fork a transcript with pending message"
+        );
+        assert!(
+            self.challenge_source.is_none(),
+            "cannot fork a transcript with challenge source"
+        );
+        let fork_index = self.num_forks;
+        self.num_forks += 1;
+        let mut fork = Self {
+            prover_message_oracles: Vec::new(),
+            merkle_tree_for_each_round: Vec::new(),
+            verifier_messages: Vec::new(),
+            bookkeeper: self.bookkeeper.fork(),
+            sponge: self.sponge.clone(),
+            hash_params: self.hash_params.clone(),
+            pending_message_for_current_round: PendingMessage::default(),
+            ldt_codeword_domain: self.ldt_codeword_domain,
+            ldt_localization_parameter: self.ldt_localization_parameter,
+            registered_virtual_oracles: Vec::new(),
+            #[cfg(feature = "timing")]
+            round_timings: Vec::new(),
+            staged_prover_rounds: BTreeMap::new(),
+            challenge_source: None,
+            hash_to_field: self.hash_to_field,
+            absorption_script: vec![AbsorbOp::Fork {
+                parent_position: self.absorption_script.len(),
+            }],
+            num_forks: 0,
+            version: self.version,
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
+            sponge_checkpoints: SpongeCheckpoints::default(),
+        };
+        fork.absorb(AbsorbedValue::ForkSeparator, &fork_separator(fork_index));
+        fork
+    }
+
+    /// Merge a fork created by `fork` back into this transcript. After
+    /// merging, the sponge of `self` absorbs 32 bytes squeezed from the
+    /// sponge of `fork`, so that later challenges are bound to all messages
+    /// sent in the fork.
+    ///
+    /// # Panics
+    /// Panics if the fork has a pending message, or if the fork violates the
+    /// contract described in `fork`.
+    pub fn join(&mut self, mut fork: Self) {
+        assert!(
+            !fork.is_pending_message_available(),
+            "cannot join a transcript with pending message"
+        );
+        assert!(
+            fork.registered_virtual_oracles.is_empty(),
+            "forked transcript cannot register virtual rounds"
+        );
+        #[cfg(feature = "timing")]
+        {
+            let prover_round_offset = self.prover_message_oracles.len();
+            self.round_timings.extend(
+                fork.round_timings
+                    .iter()
+                    .map(|&(ns, index, duration)| (ns, index + prover_round_offset, duration)),
+            );
+        }
+        self.bookkeeper.join(
+            fork.bookkeeper,
+            self.prover_message_oracles.len(),
+            self.verifier_messages.len(),
+        );
+        self.prover_message_oracles
+            .extend(fork.prover_message_oracles);
+        self.merkle_tree_for_each_round
+            .extend(fork.merkle_tree_for_each_round);
+        self.verifier_messages.extend(fork.verifier_messages);
+        self.sponge_checkpoints
+            .states
+            .extend(fork.sponge_checkpoints.states);
+        let fork_digest = fork.sponge.squeeze_bytes(32);
+        fork.absorption_script.push(AbsorbOp::SqueezeBytes(32));
+        self.absorption_script
+            .push(AbsorbOp::Join(fork.absorption_script));
+        self.absorb(AbsorbedValue::ForkDigest, &fork_digest);
     }
 
     /// Add a prover round, using codeword domain.
@@ -120,6 +603,13 @@ where
             leaves_type: UseCodewordDomain,
             oracle_length,
             localization_parameter,
+            sparse_nonzero_positions: Some(BTreeSet::new()),
+            streamed_oracle: None,
+            salts: None,
+            fixed_short_messages: None,
+            precomputed_tree: None,
+            #[cfg(feature = "timing")]
+            start_time: Instant::now(),
         }
     }
 
@@ -128,7 +618,7 @@ where
     pub fn add_prover_round_with_custom_length_and_localization(
         &mut self,
         length: usize,
-        localization_parameter: usize,
+        localization: Localization,
     ) -> PendingProverMessage<P, S, F> {
         PendingProverMessage {
             reed_solomon_codes: Vec::new(),
@@ -137,10 +627,65 @@ where
             transcript: self,
             leaves_type: Custom,
             oracle_length: length,
-            localization_parameter,
+            localization_parameter: localization.get(),
+            sparse_nonzero_positions: Some(BTreeSet::new()),
+            streamed_oracle: None,
+            salts: None,
+            fixed_short_messages: None,
+            precomputed_tree: None,
+            #[cfg(feature = "timing")]
+            start_time: Instant::now(),
         }
     }
 
+    /// Add a prover round using custom length and localization, and begin a
+    /// message oracle of `total_length` elements that is fed in chunks. See
+    /// `PendingProverMessage::begin_oracle`.
+    pub fn begin_oracle(
+        &mut self,
+        total_length: usize,
+        localization: Localization,
+    ) -> PendingProverMessage<P, S, F> {
+        self.add_prover_round_with_custom_length_and_localization(total_length, localization)
+            .begin_oracle()
+    }
+
+    /// Send `evaluations` as one logical message oracle split into segments of
+    /// `segment_length` elements, each sent in its own prover round with
+    /// `localization`, so that the oracle can be longer than a single round.
+    /// Verifier receives the segments using
+    /// `SimulationTranscript::receive_split_oracle`, and queries the logical
+    /// positions using `MessagesCollection::query_split_oracle`. Returns the
+    /// rounds of the segments in order.
+    ///
+    /// # Panics
+    /// Panics if the length of `evaluations` is not a multiple of
+    /// `segment_length`.
+    pub fn send_split_oracle(
+        &mut self,
+        namespace: NameSpace,
+        evaluations: Vec<F>,
+        segment_length: usize,
+        localization: Localization,
+        trace: TraceInfo,
+    ) -> Result<Vec<MsgRoundRef>, Error> {
+        assert!(
+            segment_length > 0 && evaluations.len() % segment_length == 0,
+            "oracle length should be a multiple of segment length"
+        );
+        evaluations
+            .chunks(segment_length)
+            .map(|segment| {
+                self.add_prover_round_with_custom_length_and_localization(
+                    segment_length,
+                    localization,
+                )
+                .send_oracle_message_without_degree_bound(segment.iter().copied())
+                .submit(namespace, trace)
+            })
+            .collect()
+    }
+
     /// Register a virtual oracle specfied by coset evaluator.
     /// * `coset_query_evaluator`: a function that takes a coset and constituent
     ///   oracles, and return query responses
@@ -200,6 +745,17 @@ where
         self.attach_latest_prover_round_to_namespace(ns, true, trace)
     }
 
+    /// Returns the index in `namespace` that the next prover round submitted
+    /// or virtual round registered in `namespace` will get, so that
+    /// `(namespace, index)` can be stored as a reference to that round before
+    /// it is sent.
+    pub fn current_round_index(&self, namespace: NameSpace) -> usize {
+        self.bookkeeper
+            .get_message_indices(namespace)
+            .prover_rounds
+            .len()
+    }
+
     /// Get low-degree oracle evaluations at index `x` or requested round.
     ///
     /// For example, if in requested round, prover send low-degree oracle `[p0,
@@ -296,13 +852,32 @@ where
     /// and decision phase.
     pub fn squeeze_verifier_field_elements(&mut self, field_size: &[FieldElementSize]) -> Vec<F> {
         // squeeze message
-        let msg = self.sponge.squeeze_field_elements_with_sizes(field_size);
+        let msg = self.squeeze_field_elements(field_size);
         // store the verifier message for later decision phase
         self.current_verifier_pending_message()
             .push(VerifierMessage::FieldElements(msg.clone()));
         msg
     }
 
+    /// Squeeze `K` full size verifier field elements as an array. This is the
+    /// same as calling `squeeze_verifier_field_elements` with `K`
+    /// `FieldElementSize::Full`.
+    pub fn squeeze_verifier_field_elements_array<const K: usize>(&mut self) -> [F; K] {
+        let msg = self.squeeze_verifier_field_elements(&[FieldElementSize::Full; K]);
+        let mut result = [F::zero(); K];
+        result.copy_from_slice(&msg);
+        result
+    }
+
+    /// Squeeze `num_claims` full size verifier field elements, used as
+    /// coefficients to fold `num_claims` claims into a single random linear
+    /// combination. This is the same as calling
+    /// `squeeze_verifier_field_elements` with `num_claims`
+    /// `FieldElementSize::Full`.
+    pub fn squeeze_folding_coefficients(&mut self, num_claims: usize) -> Vec<F> {
+        self.squeeze_verifier_field_elements(&vec![FieldElementSize::Full; num_claims])
+    }
+
     /// Squeeze sampled verifier message as bytes. The squeezed elements is
     /// attached to pending messages, and need to be submitted through
     /// `submit_verifier_current_round`. Submitted messages will be stored in
@@ -310,13 +885,31 @@ where
     /// phase.
     pub fn squeeze_verifier_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
         // squeeze message
-        let msg = self.sponge.squeeze_bytes(num_bytes);
+        let msg = self.squeeze_bytes(num_bytes);
         // store the verifier message for later decision phase
         self.current_verifier_pending_message()
             .push(VerifierMessage::Bytes(msg.clone()));
         msg
     }
 
+    /// Squeeze `num_elements` verifier field elements derived from squeezed
+    /// bytes. The result equals squeezing the bytes with
+    /// `squeeze_verifier_bytes` and converting them using the
+    /// `HashToFieldConfig` of this transcript (`ToConstraintField` by
+    /// default), but the message is stored as field elements, so verifier can
+    /// read it using `try_into_field_elements`. Submitted messages will be
+    /// stored in transcript and will be later given to verifier in query and
+    /// decision phase.
+    pub fn squeeze_verifier_bytes_as_field(&mut self, num_elements: usize) -> Vec<F> {
+        // squeeze message
+        let bytes = self.squeeze_bytes(num_elements * self.hash_to_field.bytes_per_element);
+        let msg = self.hash_to_field.map_bytes(&bytes);
+        // store the verifier message for later decision phase
+        self.current_verifier_pending_message()
+            .push(VerifierMessage::FieldElements(msg.clone()));
+        msg
+    }
+
     /// Squeeze sampled verifier message as bits. The squeezed elements is
     /// attached to pending messages, and need to be submitted through
     /// `submit_verifier_current_round`. Submitted messages will be stored in
@@ -324,16 +917,300 @@ where
     /// phase.
     pub fn squeeze_verifier_bits(&mut self, num_bits: usize) -> Vec<bool> {
         // squeeze message
-        let msg = self.sponge.squeeze_bits(num_bits);
+        let msg = self.squeeze_bits(num_bits);
         // store the verifier message for later decision phase
         self.current_verifier_pending_message()
             .push(VerifierMessage::Bits(msg.clone()));
         msg
     }
 
+    /// Squeeze a verifier field element in `[0, 2^num_bits)`, derived from
+    /// `num_bits` squeezed bits. The element is attached to pending messages
+    /// as field elements, and need to be submitted through
+    /// `submit_verifier_current_round`.
+    ///
+    /// # Panics
+    /// Panics if `num_bits` is not smaller than the modulus bit size of `F`.
+    pub fn squeeze_verifier_field_element_from_bits(&mut self, num_bits: usize) -> F {
+        // squeeze message
+        let msg = field_element_from_bits(&self.squeeze_bits(num_bits));
+        // store the verifier message for later decision phase
+        self.current_verifier_pending_message()
+            .push(VerifierMessage::FieldElements(vec![msg]));
+        msg
+    }
+
+    /// Squeeze an unbiased full size verifier field element, using rejection
+    /// sampling: `MODULUS_BIT_SIZE` bits are squeezed, and squeezed again
+    /// until they encode an integer smaller than the modulus. The element is
+    /// attached to pending messages as field elements, and need to be
+    /// submitted through `submit_verifier_current_round`.
+    pub fn squeeze_verifier_uniform_element(&mut self) -> F {
+        // squeeze message
+        let msg = sample_uniform_field_element(|num_bits| self.squeeze_bits(num_bits));
+        // store the verifier message for later decision phase
+        self.current_verifier_pending_message()
+            .push(VerifierMessage::FieldElements(vec![msg]));
+        msg
+    }
+
+    /// Squeeze a uniformly random permutation of `0..n` as verifier message,
+    /// using Fisher-Yates shuffle driven by squeezed bytes. The permutation is
+    /// attached to pending messages, and need to be submitted through
+    /// `submit_verifier_current_round`. Submitted messages will be stored in
+    /// transcript and will be later given to verifier in query and decision
+    /// phase.
+    pub fn squeeze_verifier_permutation(&mut self, n: usize) -> Vec<usize> {
+        // squeeze message
+        let msg = sample_permutation(n, |num_bytes| self.squeeze_bytes(num_bytes));
+        // store the verifier message for later decision phase
+        self.current_verifier_pending_message()
+            .push(VerifierMessage::Permutation(msg.clone()));
+        msg
+    }
+
+    /// Squeeze and discard `num_bytes` bytes, to advance the sponge without
+    /// sampling a usable challenge (e.g. as a domain separation step between
+    /// prover rounds). The step is attached to pending messages as
+    /// `VerifierMessage::Advance`, and need to be submitted through
+    /// `submit_verifier_current_round`, so that verifier advances its sponge
+    /// in the same way.
+    pub fn advance_sponge(&mut self, num_bytes: usize) {
+        self.squeeze_bytes(num_bytes);
+        self.current_verifier_pending_message()
+            .push(VerifierMessage::Advance(num_bytes));
+    }
+
+    /// Absorb public field elements, e.g. a public input of variable length,
+    /// prefixed by their number, so that vectors of different lengths such as
+    /// `[a, b]` and `[a, b, 0]` lead to different sponge states. Verifier
+    /// should call `SimulationTranscript::absorb_public_field_elements` with
+    /// the same elements at the same point.
+    pub fn absorb_public_field_elements(&mut self, elements: &[F]) {
+        self.absorb(AbsorbedValue::PublicInput, &length_prefixed(elements));
+    }
+
+    /// Absorb `other_digest`, the `commit_phase_digest` of another proof, so
+    /// that later challenges of this proof depend on the other one. Two
+    /// proofs are bound to each other if each absorbs the digest of the other
+    /// computed before binding; when binding several proofs, absorb their
+    /// digests in the same canonical order in all of them. Verifier should
+    /// call `SimulationTranscript::bind_sibling` with the same digest at the
+    /// same point.
+    pub fn bind_sibling(&mut self, other_digest: F) {
+        self.absorb(AbsorbedValue::SiblingDigest, &other_digest);
+    }
+
+    /// Returns the namespace, round index and wall-clock duration of each
+    /// submitted prover round, in order. The duration is measured from the
+    /// time the round is added to the time it is submitted, so it includes
+    /// computing the messages of the round and building its merkle tree.
+    #[cfg(feature = "timing")]
+    pub fn round_timings(&self) -> Vec<(NameSpace, usize, Duration)> {
+        self.round_timings.clone()
+    }
+
+    /// Use `config` to map squeezed bytes to field elements in
+    /// `squeeze_verifier_bytes_as_field`. The simulation transcript of
+    /// verifier should use the same config.
+    pub fn with_hash_to_field_config(mut self, config: HashToFieldConfig<F>) -> Self {
+        self.hash_to_field = config;
+        self
+    }
+
+    /// Take verifier messages from `source` instead of the sponge, so that
+    /// the protocol can be run interactively, e.g. with challenges sampled by
+    /// `RngChallengeSource`. Prover messages are still recorded in this
+    /// transcript, and its structure can be compared with a transcript using
+    /// sponge. This is **unsafe for production** and only meant for
+    /// debugging; see `ChallengeSource`.
+    pub fn with_challenge_source(mut self, source: Box<dyn ChallengeSource<F> + Send>) -> Self {
+        self.challenge_source = Some(source);
+        self
+    }
+
+    /// In query phase, query every prover round at `positions` instead of the
+    /// positions derived from the sponge. This is the prover counterpart of
+    /// `SimulationTranscript::with_fixed_queries`, and is **unsound**: it is
+    /// only meant for benchmarks.
+    #[cfg(feature = "bench")]
+    pub fn with_fixed_queries(mut self, positions: &[usize]) -> Self {
+        self.fixed_queries = Some(positions.to_vec());
+        self
+    }
+
+    /// Squeeze field elements from the challenge source if it is set, or the
+    /// sponge otherwise.
+    fn squeeze_field_elements(&mut self, field_size: &[FieldElementSize]) -> Vec<F> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_field_elements(field_size.len()),
+            None => {
+                self.absorption_script
+                    .push(AbsorbOp::SqueezeFieldElements(field_size.to_vec()));
+                self.sponge.squeeze_field_elements_with_sizes(field_size)
+            },
+        }
+    }
+
+    /// Squeeze bytes from the challenge source if it is set, or the sponge
+    /// otherwise.
+    fn squeeze_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_bytes(num_bytes),
+            None => {
+                self.absorption_script
+                    .push(AbsorbOp::SqueezeBytes(num_bytes));
+                self.sponge.squeeze_bytes(num_bytes)
+            },
+        }
+    }
+
+    /// Squeeze bits from the challenge source if it is set, or the sponge
+    /// otherwise.
+    fn squeeze_bits(&mut self, num_bits: usize) -> Vec<bool> {
+        match &mut self.challenge_source {
+            Some(source) => source.next_bits(num_bits),
+            None => {
+                self.absorption_script.push(AbsorbOp::SqueezeBits(num_bits));
+                self.sponge.squeeze_bits(num_bits)
+            },
+        }
+    }
+
+    /// Absorb `item` into the sponge, and record it in the absorption script.
+    fn absorb(&mut self, value: AbsorbedValue, item: &impl Absorb) {
+        self.sponge.absorb(item);
+        self.absorption_script.push(AbsorbOp::absorb(value, item));
+    }
+
+    /// Every operation on the sponge so far, in order, so that an external
+    /// implementation can reproduce the evolution of the sponge. Squeezes
+    /// answered by a challenge source are not recorded.
+    pub fn absorption_script(&self) -> &[AbsorbOp] {
+        &self.absorption_script
+    }
+
+    /// Record the state of the sponge at `label`, so that verifier can check
+    /// that its simulation reaches the same state at the same point using
+    /// `SimulationTranscript::assert_sponge_matches`. The state is squeezed
+    /// from a copy of the sponge, so it does not change later challenges.
+    /// Checkpoints recorded in a fork are kept when the fork is joined.
+    ///
+    /// # Panics
+    /// Panics if a state is already recorded at `label`.
+    pub fn checkpoint_sponge(&mut self, label: &'static str) {
+        let previous = self
+            .sponge_checkpoints
+            .states
+            .insert(label, sponge_snapshot(&self.sponge));
+        assert!(
+            previous.is_none(),
+            "sponge checkpoint `{}` is recorded twice",
+            label
+        );
+    }
+
+    /// Sponge states recorded by `checkpoint_sponge`, to be given to
+    /// `SimulationTranscript::with_sponge_checkpoints`.
+    pub fn sponge_checkpoints(&self) -> &SpongeCheckpoints {
+        &self.sponge_checkpoints
+    }
+
+    /// Finish this transcript, e.g. after the last prover round of a composed
+    /// protocol, and return its sponge in the final state. The transcript is
+    /// consumed, so sending or squeezing messages after finalizing it is a
+    /// compile error instead of silently reusing the final sponge.
+    ///
+    /// # Panics
+    /// Panics if there is a pending message.
+    pub fn finalize(self) -> S {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot finalize a transcript with pending message"
+        );
+        self.sponge
+    }
+
+    /// Squeeze a digest binding all messages sent so far: merkle roots, short
+    /// messages and verifier messages, in order. External systems can sign
+    /// or reference the digest. It is squeezed from a copy of the sponge, so
+    /// it does not change later challenges, and verifier gets the same value
+    /// from `SimulationTranscript::commit_phase_digest` at the same point of
+    /// the protocol. Verifier messages taken from a challenge source are not
+    /// bound.
+    ///
+    /// # Panics
+    /// Panics if there is a pending message.
+    pub fn commit_phase_digest(&self) -> F {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot compute commit phase digest with pending message"
+        );
+        self.sponge.clone().squeeze_field_elements::<F>(1)[0]
+    }
+
+    /// Return a copy of the sponge at this point, e.g. to derive the query
+    /// positions of LDT using `LinearCombinationLDT::derive_query_positions`
+    /// once commit phase, including the one of LDT, is over.
+    ///
+    /// # Panics
+    /// Panics if there is a pending message.
+    pub fn commit_phase_sponge(&self) -> S {
+        assert!(
+            !self.is_pending_message_available(),
+            "cannot copy commit phase sponge with pending message"
+        );
+        self.sponge.clone()
+    }
+
     /// Returns if there is a pending message for the transcript.
     pub fn is_pending_message_available(&self) -> bool {
         !matches!(self.pending_message_for_current_round, PendingMessage::None)
+            || !self.staged_prover_rounds.is_empty()
+    }
+
+    /// Absorb a prover round and attach it to its namespace.
+    fn commit_prover_round(&mut self, round: StagedProverRound<P, F>) -> MsgRoundRef {
+        // if this round prover message contains oracle messages, absorb its
+        // structure and merkle tree root
+        if self.version.absorbs_round_structure()
+            && (round.merkle_tree.is_some() || round.oracle.inline)
+        {
+            self.absorb(
+                AbsorbedValue::RoundStructure,
+                &round_structure::<F>(&round.oracle.info),
+            );
+        }
+        self.absorb(
+            AbsorbedValue::MerkleRoot,
+            &round.merkle_tree.as_ref().map(|x| x.root()),
+        );
+        // oracles committed inline are absorbed in entirety
+        if round.oracle.inline {
+            let elements = inline_elements(&round.oracle.all_coset_elements);
+            self.absorb(AbsorbedValue::InlineOracles, &elements);
+        }
+        // if this round prover message has non-oracle messages, absorb them in entirety
+        for msg in &round.oracle.short_messages {
+            self.absorb(AbsorbedValue::ShortMessage, msg);
+        }
+        #[cfg(feature = "timing")]
+        self.round_timings.push((
+            round.namespace,
+            self.prover_message_oracles.len(),
+            round.duration,
+        ));
+        #[cfg(feature = "logging")]
+        log::debug!(
+            "prover round submitted: namespace {}, round {}, oracle length {}",
+            round.namespace.id,
+            self.prover_message_oracles.len(),
+            round.oracle.info.length
+        );
+        self.prover_message_oracles.push(round.oracle);
+        self.merkle_tree_for_each_round.push(round.merkle_tree);
+
+        self.attach_latest_prover_round_to_namespace(round.namespace, false, round.trace)
     }
 
     #[allow(unused)]
@@ -357,6 +1234,10 @@ where
     /// become verifier message type. Panic if current pending message is
     /// not verifier message.
     fn current_verifier_pending_message(&mut self) -> &mut Vec<VerifierMessage<F>> {
+        assert!(
+            self.staged_prover_rounds.is_empty(),
+            "prover rounds submitted by `submit_at` are not complete"
+        );
         if let PendingMessage::None = &self.pending_message_for_current_round {
             self.pending_message_for_current_round = PendingMessage::VerifierMessage(Vec::new());
         }
@@ -414,6 +1295,20 @@ where
     leaves_type: LeavesType,
     oracle_length: usize,
     localization_parameter: usize,
+    /// Positions that are not equal to the default value in any oracle, if
+    /// all oracles of this round are sent using
+    /// `send_sparse_oracle_message_without_degree_bound`.
+    sparse_nonzero_positions: Option<BTreeSet<usize>>,
+    /// Message oracle that is being fed using `feed_oracle_chunk`.
+    streamed_oracle: Option<Vec<F>>,
+    /// Salt prefixed to each merkle tree leaf, sampled by `with_hiding`.
+    salts: Option<Vec<F>>,
+    /// If set, short messages are padded to this number when submitting.
+    fixed_short_messages: Option<usize>,
+    /// Merkle tree given by `send_oracle_message_with_precomputed_tree`.
+    precomputed_tree: Option<MerkleTree<P>>,
+    #[cfg(feature = "timing")]
+    start_time: Instant,
 }
 
 impl<'a, P, S, F> PendingProverMessage<'a, P, S, F>
@@ -441,6 +1336,7 @@ where
         let oracle = msg.into_iter().collect::<Vec<_>>();
         assert_eq!(oracle.len(), self.transcript.codeword_domain().size());
         self.reed_solomon_codes.push((oracle, degree_bound));
+        self.sparse_nonzero_positions = None;
         self
     }
 
@@ -458,6 +1354,164 @@ where
         let oracle: Vec<_> = msg.into_iter().collect();
         assert_eq!(oracle.len(), self.oracle_length);
         self.message_oracles.push(oracle);
+        self.sparse_nonzero_positions = None;
+        self
+    }
+
+    /// Send a prover message oracle over an extension field `E` of `F`. Each
+    /// element is stored as its `E::extension_degree()` base field
+    /// components, which are sent as interleaved message oracles using
+    /// `send_interleaved_oracles`, so one leaf holds all components of an
+    /// element. Use `AtProverRound::query_extension_field_point` to query it.
+    ///
+    /// Extension field oracles cannot have a degree bound, because LDT works
+    /// over the base field.
+    /// # Panics
+    /// Panics if the length of oracle message is not equal to length for
+    /// current round.
+    #[must_use]
+    pub fn send_extension_field_oracle_without_degree_bound<E: Field<BasePrimeField = F>>(
+        self,
+        msg: impl IntoIterator<Item = E>,
+    ) -> Self {
+        let mut columns =
+            vec![Vec::with_capacity(self.oracle_length); E::extension_degree() as usize];
+        msg.into_iter().for_each(|element| {
+            element
+                .to_base_prime_field_elements()
+                .zip(columns.iter_mut())
+                .for_each(|(component, column)| column.push(component))
+        });
+        self.send_interleaved_oracles(columns)
+    }
+
+    /// Send a prover message oracle like
+    /// `send_oracle_message_without_degree_bound`, and commit it using
+    /// `tree` instead of building a new merkle tree, e.g. when the same
+    /// oracle was committed in a previous transcript. `tree` should be the
+    /// merkle tree of a round that only has this oracle, with the same length
+    /// and localization parameter.
+    ///
+    /// When submitting, the height of `tree` is checked against the number
+    /// of cosets of `msg`, and `BCSError::PrecomputedTreeHeightMismatch` is
+    /// returned if they differ. Then only the first, middle and last leaves
+    /// of `tree` are checked against `msg`, and
+    /// `BCSError::PrecomputedTreeMismatch` is returned if one of them
+    /// differs; checking every leaf would cost as much as building the tree.
+    ///
+    /// **The caller must guarantee that every other leaf of `tree` commits
+    /// the corresponding coset of `msg`**, e.g. by building `tree` from `msg`
+    /// in a trusted computation. A tree with other leaves is not detected,
+    /// and the resulting proof is rejected by verifier.
+    ///
+    /// Submitting returns `BCSError::UnsupportedPrecomputedTreeRound` if the
+    /// round sends other oracles or uses `with_hiding`, whose salt changes
+    /// the leaves.
+    #[must_use]
+    pub fn send_oracle_message_with_precomputed_tree(
+        mut self,
+        msg: impl IntoIterator<Item = F>,
+        tree: MerkleTree<P>,
+    ) -> Self {
+        self.precomputed_tree = Some(tree);
+        self.send_oracle_message_without_degree_bound(msg)
+    }
+
+    /// Begin a message oracle whose elements are fed in chunks using
+    /// `feed_oracle_chunk`, and end it using `finish_oracle`. This is useful
+    /// when oracle elements arrive in a stream.
+    ///
+    /// Each merkle tree leaf is a coset whose elements are spread across the
+    /// whole oracle, so the chunks are buffered and the merkle tree is built
+    /// when this round is submitted.
+    /// # Panics
+    /// Panics if an oracle is already being fed.
+    #[must_use]
+    pub fn begin_oracle(mut self) -> Self {
+        assert!(
+            self.streamed_oracle.is_none(),
+            "previous oracle is not finished"
+        );
+        self.streamed_oracle = Some(Vec::with_capacity(self.oracle_length));
+        self
+    }
+
+    /// Append `chunk` to the oracle started by `begin_oracle`.
+    /// # Panics
+    /// Panics if no oracle is begun.
+    #[must_use]
+    pub fn feed_oracle_chunk(mut self, chunk: &[F]) -> Self {
+        self.streamed_oracle
+            .as_mut()
+            .expect("no oracle is begun")
+            .extend_from_slice(chunk);
+        self
+    }
+
+    /// Finish the oracle started by `begin_oracle`, and send it as a message
+    /// oracle without degree bound. Returns an error if the number of fed
+    /// elements is not equal to length for current round.
+    /// # Panics
+    /// Panics if no oracle is begun.
+    pub fn finish_oracle(mut self) -> Result<Self, Error> {
+        let oracle = self.streamed_oracle.take().expect("no oracle is begun");
+        if oracle.len() != self.oracle_length {
+            return Err(Box::new(BCSError::OracleLengthMismatch {
+                expected: self.oracle_length,
+                actual: oracle.len(),
+            }));
+        }
+        Ok(self.send_oracle_message_without_degree_bound(oracle))
+    }
+
+    /// Send columns of a table as prover message oracles, so that leaf `i` of
+    /// this round holds the tuple `(columns[0][i], columns[1][i], ...)`.
+    ///
+    /// All oracles in a round already share one merkle tree, so a single
+    /// merkle path authenticates the whole tuple, and a point query at `i`
+    /// returns the tuple as `Vec<F>` (followed by other oracles sent in this
+    /// round after `columns`).
+    /// # Panics
+    /// Panics if the length of any column is not equal to length for current
+    /// round.
+    #[must_use]
+    pub fn send_interleaved_oracles<I: IntoIterator<Item = F>>(
+        self,
+        columns: impl IntoIterator<Item = I>,
+    ) -> Self {
+        columns.into_iter().fold(self, |round, column| {
+            round.send_oracle_message_without_degree_bound(column)
+        })
+    }
+
+    /// Send a prover message oracle where all elements are `default` except
+    /// `nonzeros`, given as `(position, value)` pairs. The committed merkle
+    /// root is the same as sending the dense oracle using
+    /// `send_oracle_message_without_degree_bound`. If all oracles in this
+    /// round are sparse, the round is committed using a `SparseMerkleTree`:
+    /// leaves and subtrees that only contain default values are hashed once
+    /// per level, and only the nodes above non-default leaves are hashed.
+    /// Oracle elements are still stored in full to answer queries.
+    /// # Panics
+    /// Panics if any position is out of range for current round.
+    #[must_use]
+    pub fn send_sparse_oracle_message_without_degree_bound(
+        mut self,
+        default: F,
+        nonzeros: &[(usize, F)],
+    ) -> Self {
+        let mut oracle = vec![default; self.oracle_length];
+        nonzeros.iter().for_each(|&(position, value)| {
+            assert!(
+                position < self.oracle_length,
+                "sparse oracle position out of range"
+            );
+            oracle[position] = value;
+        });
+        self.message_oracles.push(oracle);
+        if let Some(positions) = &mut self.sparse_nonzero_positions {
+            positions.extend(nonzeros.iter().map(|(position, _)| *position));
+        }
         self
     }
 
@@ -471,6 +1525,19 @@ where
         self
     }
 
+    /// Pad short messages of this round with empty messages on submit, so
+    /// that the round has exactly `num` short messages, and verifier can
+    /// expect `with_num_short_messages(num)` no matter how many messages the
+    /// round sends. Empty messages are absorbed by the sponge as usual.
+    ///
+    /// # Panics
+    /// Submitting panics if the round sends more than `num` short messages.
+    #[must_use]
+    pub fn with_fixed_short_messages(mut self, num: usize) -> Self {
+        self.fixed_short_messages = Some(num);
+        self
+    }
+
     /// Send univariate polynomial with LDT.
     /// Evaluation domain and localization parameter is managed by LDT.
     ///
@@ -492,23 +1559,155 @@ where
         self.send_oracle_evaluations_with_degree_bound(evaluations, degree_bound)
     }
 
+    /// Send univariate polynomial with LDT, given its `evaluations` over
+    /// `domain`. This is the same as `send_univariate_polynomial`, but does
+    /// not convert evaluations back to coefficients. In debug builds, the
+    /// degree of the polynomial is checked by interpolating `evaluations`.
+    ///
+    /// # Panics
+    /// This function panics if `domain` is not the LDT codeword domain, or in
+    /// debug builds, if polynomial's degree is larger than degree bound.
+    #[must_use]
+    pub fn send_univariate_polynomial_evaluations(
+        self,
+        degree_bound: usize,
+        evaluations: &[F],
+        domain: Radix2CosetDomain<F>,
+    ) -> Self {
+        let codeword_domain = self.transcript.codeword_domain();
+        assert!(
+            domain.size() == codeword_domain.size() && domain.offset == codeword_domain.offset,
+            "evaluations should be over LDT codeword domain"
+        );
+        debug_assert!(
+            domain.interpolate(evaluations.to_vec()).degree() <= degree_bound,
+            "polynomial degree is larger than degree bound"
+        );
+        self.send_oracle_evaluations_with_degree_bound(evaluations.iter().copied(), degree_bound)
+    }
+
+    /// Send a batch of univariate polynomials with the same degree bound
+    /// using `send_univariate_polynomial`. As all oracles of a round share
+    /// one merkle tree, where the `i`-th leaf holds the `i`-th coset of
+    /// every oracle, one authentication path opens all polynomials at a
+    /// point. LDT tests each polynomial as a separate Reed-Solomon code.
+    ///
+    /// # Panics
+    /// This function panics if any polynomial's degree is larger than degree
+    /// bound.
+    #[must_use]
+    pub fn send_low_degree_batch(self, degree_bound: usize, polys: &[DensePolynomial<F>]) -> Self {
+        polys.iter().fold(self, |round, poly| {
+            round.send_univariate_polynomial(poly, degree_bound)
+        })
+    }
+
+    /// Prefix each merkle tree leaf of this round with a fresh salt sampled
+    /// from `rng`, so that the merkle root does not reveal whether two rounds
+    /// commit the same oracles. The salts of queried leaves are sent in the
+    /// proof next to the queried cosets, and verifier checks them if the
+    /// expected round info is built using `with_hiding`.
+    ///
+    /// `rng` should be a cryptographically secure source that is private to
+    /// prover: salts derived from public data, such as the sponge, do not
+    /// hide anything. Rounds with salts are never committed using a sparse
+    /// merkle tree, as no two leaves are the same, and submitting one that
+    /// `TranscriptVersion::V4` commits inline returns
+    /// `BCSError::UnsupportedInlineRound`.
+    #[must_use]
+    pub fn with_hiding<R: RngCore>(mut self, rng: &mut R) -> Self {
+        let num_leaves = self.oracle_length >> self.localization_parameter;
+        self.salts = Some((0..num_leaves).map(|_| F::rand(rng)).collect());
+        self.sparse_nonzero_positions = None;
+        self
+    }
+
     /// Submit current round to transcript.
+    ///
+    /// Each submitted round is committed using its own merkle tree, even if
+    /// no verifier message is sampled since the previous prover round. Rounds
+    /// are not merged automatically, because `MsgRoundRef` refers to all
+    /// oracles of a round, and merged rounds would change the query results
+    /// seen by verifier. There is deliberately no option to merge them. To
+    /// save a merkle root, send oracles with the same length and localization
+    /// parameter in one round instead.
     pub fn submit(self, namespace: NameSpace, trace: TraceInfo) -> Result<MsgRoundRef, Error> {
+        let (round, transcript) = self.into_staged_round(namespace, trace)?;
+        assert!(
+            transcript.staged_prover_rounds.is_empty(),
+            "prover rounds submitted by `submit_at` are not complete"
+        );
+        Ok(transcript.commit_prover_round(round))
+    }
+
+    /// Submit current round as the prover round at `index` of the transcript.
+    /// Rounds can be submitted in any order: a round is absorbed by the sponge
+    /// only after all rounds with smaller index are submitted, so the
+    /// resulting transcript is the same as submitting rounds in index order.
+    /// In a fork, `index` is relative to the start of the fork.
+    ///
+    /// The returned `MsgRoundRef` is valid once all rounds before `index` are
+    /// submitted. Verifier messages cannot be squeezed before that.
+    ///
+    /// # Panics
+    /// Panics if the round at `index` is already submitted.
+    pub fn submit_at(
+        self,
+        index: usize,
+        namespace: NameSpace,
+        trace: TraceInfo,
+    ) -> Result<MsgRoundRef, Error> {
+        let (round, transcript) = self.into_staged_round(namespace, trace)?;
+        assert!(
+            index >= transcript.prover_message_oracles.len()
+                && !transcript.staged_prover_rounds.contains_key(&index),
+            "prover round {} is already submitted",
+            index
+        );
+        transcript.staged_prover_rounds.insert(index, round);
+        while let Some(round) = transcript
+            .staged_prover_rounds
+            .remove(&transcript.prover_message_oracles.len())
+        {
+            transcript.commit_prover_round(round);
+        }
+        Ok(MsgRoundRef::new(index, trace, false))
+    }
+
+    /// Generate the merkle tree and recording oracle of current round.
+    fn into_staged_round(
+        mut self,
+        namespace: NameSpace,
+        trace: TraceInfo,
+    ) -> Result<(StagedProverRound<P, F>, &'a mut Transcript<P, S, F>), Error> {
+        assert!(
+            self.streamed_oracle.is_none(),
+            "oracle begun by `begin_oracle` is not finished"
+        );
+        if let Some(num) = self.fixed_short_messages {
+            assert!(
+                self.short_messages.len() <= num,
+                "round sends {} short messages, but it is fixed to {}. {}",
+                self.short_messages.len(),
+                num,
+                trace
+            );
+            self.short_messages.resize(num, Vec::new());
+        }
+        #[cfg(feature = "timing")]
+        let start_time = self.start_time;
         // generate merkle tree
         // extract short messages
-        let (mt, recording_oracle, transcript) = self.into_merkle_tree_and_recording_oracle()?;
-        // if this round prover message contains oracle messages, absorb merkle tree
-        // root
-        transcript.sponge.absorb(&mt.as_ref().map(|x| x.root()));
-        // if this round prover message has non-oracle messages, absorb them in entirety
-        recording_oracle
-            .short_messages
-            .iter()
-            .for_each(|msg| transcript.sponge.absorb(msg));
-        transcript.prover_message_oracles.push(recording_oracle);
-        transcript.merkle_tree_for_each_round.push(mt);
-
-        Ok(transcript.attach_latest_prover_round_to_namespace(namespace, false, trace))
+        let (merkle_tree, oracle, transcript) = self.into_merkle_tree_and_recording_oracle()?;
+        let round = StagedProverRound {
+            namespace,
+            trace,
+            merkle_tree,
+            oracle,
+            #[cfg(feature = "timing")]
+            duration: start_time.elapsed(),
+        };
+        Ok((round, transcript))
     }
 
     fn has_oracle(&self) -> bool {
@@ -521,29 +1720,15 @@ where
     /// oracle[1][3], oracle[1][6], oracle[1][9]]`
     #[allow(clippy::type_complexity)]
     fn into_merkle_tree_and_recording_oracle(
-        self, // all RS-codes, all message oracles
+        mut self, // all RS-codes, all message oracles
     ) -> Result<
         (
-            Option<MerkleTree<P>>,
+            Option<RoundMerkleTree<P>>,
             RecordingRoundOracle<F>,
             &'a mut Transcript<P, S, F>,
         ),
         Error,
     > {
-        let hash_params = &self.transcript.hash_params;
-        let all_coset_elements = self.generate_all_cosets();
-        let flattened_leaves = all_coset_elements
-            .iter()
-            .map(|oracles| oracles.iter().flatten().copied().collect::<Vec<_>>());
-        let mt = if self.has_oracle() {
-            Some(MerkleTree::new(
-                &hash_params.leaf_hash_param,
-                &hash_params.inner_hash_param,
-                flattened_leaves,
-            )?)
-        } else {
-            None
-        };
         let info = ProverRoundMessageInfo {
             reed_solomon_code_degree_bound: self
                 .reed_solomon_codes
@@ -555,14 +1740,151 @@ where
             leaves_type: self.leaves_type,
             length: self.oracle_length,
             localization_parameter: self.localization_parameter,
+            hiding: self.salts.is_some(),
+        };
+        let inline = self.transcript.version.inlines_oracles(&info);
+        if inline && self.salts.is_some() {
+            return Err(Box::new(BCSError::UnsupportedInlineRound {
+                reason: "is hiding",
+            }));
+        }
+        if inline && self.precomputed_tree.is_some() {
+            return Err(Box::new(BCSError::UnsupportedInlineRound {
+                reason: "uses a precomputed merkle tree",
+            }));
+        }
+        let salts = if self.has_oracle() {
+            self.salts.take().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let precomputed_tree = self.precomputed_tree.take();
+        let hash_params = &self.transcript.hash_params;
+        let all_coset_elements = self.generate_all_cosets();
+        let flattened_leaves =
+            all_coset_elements
+                .iter()
+                .enumerate()
+                .map(|(coset_index, oracles)| {
+                    salts
+                        .get(coset_index)
+                        .copied()
+                        .into_iter()
+                        .chain(oracles.iter().flatten().copied())
+                        .collect::<Vec<_>>()
+                });
+        let mt = match (&self.sparse_nonzero_positions, self.has_oracle()) {
+            _ if inline => None,
+            _ if precomputed_tree.is_some() => {
+                let unsupported = if !self.reed_solomon_codes.is_empty() {
+                    Some("sends a low-degree oracle")
+                } else if self.message_oracles.len() != 1 {
+                    Some("sends more than one oracle")
+                } else if !salts.is_empty() {
+                    Some("is hiding")
+                } else {
+                    None
+                };
+                if let Some(reason) = unsupported {
+                    return Err(Box::new(BCSError::UnsupportedPrecomputedTreeRound {
+                        reason,
+                    }));
+                }
+                let tree = precomputed_tree.unwrap();
+                let num_leaves = all_coset_elements.len();
+                // a tree with a different number of leaves commits another
+                // oracle, even if the checked leaves happen to match
+                let expected_height = ark_std::log2(num_leaves) as usize + 1;
+                if tree.height() != expected_height {
+                    return Err(Box::new(BCSError::PrecomputedTreeHeightMismatch {
+                        expected: expected_height,
+                        actual: tree.height(),
+                    }));
+                }
+                // only a few leaves are checked, so that the tree is not
+                // rehashed; the caller guarantees that the other leaves match
+                for leaf_index in [0, num_leaves / 2, num_leaves - 1] {
+                    let leaf = all_coset_elements[leaf_index]
+                        .iter()
+                        .flatten()
+                        .copied()
+                        .collect::<Vec<_>>();
+                    let matches = match tree.generate_proof(leaf_index) {
+                        Ok(path) => path
+                            .verify(
+                                &hash_params.leaf_hash_param,
+                                &hash_params.inner_hash_param,
+                                &tree.root(),
+                                leaf.as_slice(),
+                            )
+                            .unwrap_or(false),
+                        Err(_) => false,
+                    };
+                    if !matches {
+                        return Err(Box::new(BCSError::PrecomputedTreeMismatch { leaf_index }));
+                    }
+                }
+                Some(RoundMerkleTree::Dense(tree))
+            },
+            (_, false) => None,
+            (Some(positions), true)
+                if all_coset_elements.len() > 1 && positions.len() < all_coset_elements.len() =>
+            {
+                // cosets without any non-default element have the same leaf, so
+                // only leaves of other cosets and the nodes above them are hashed
+                let num_cosets = all_coset_elements.len();
+                let dirty_cosets = positions
+                    .iter()
+                    .map(|position| position % num_cosets)
+                    .collect::<BTreeSet<_>>();
+                let default_coset = (0..num_cosets)
+                    .find(|coset_index| !dirty_cosets.contains(coset_index))
+                    .unwrap();
+                let flattened_leaf = |coset_index: usize| {
+                    all_coset_elements[coset_index]
+                        .iter()
+                        .flatten()
+                        .copied()
+                        .collect::<Vec<_>>()
+                };
+                let default_leaf_digest = P::LeafHash::evaluate(
+                    &hash_params.leaf_hash_param,
+                    flattened_leaf(default_coset),
+                )?;
+                let leaf_digests = dirty_cosets
+                    .iter()
+                    .map(|&coset_index| {
+                        Ok((
+                            coset_index,
+                            P::LeafHash::evaluate(
+                                &hash_params.leaf_hash_param,
+                                flattened_leaf(coset_index),
+                            )?,
+                        ))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, Error>>()?;
+                Some(RoundMerkleTree::Sparse(SparseMerkleTree::new(
+                    hash_params,
+                    num_cosets,
+                    default_leaf_digest,
+                    leaf_digests,
+                )?))
+            },
+            (_, true) => Some(RoundMerkleTree::Dense(MerkleTree::new(
+                &hash_params.leaf_hash_param,
+                &hash_params.inner_hash_param,
+                flattened_leaves,
+            )?)),
         };
         let recording_oracle = RecordingRoundOracle {
             info,
+            inline,
             reed_solomon_codes: self.reed_solomon_codes,
             message_oracles: self.message_oracles,
             short_messages: self.short_messages,
             all_coset_elements,
             queried_coset_index: Vec::new(),
+            salts,
         };
         Ok((mt, recording_oracle, self.transcript))
     }
@@ -634,6 +1956,15 @@ pub trait LDTInfo<F: PrimeField> {
     /// not supported by LDT.
     fn codeword_localization_parameter(&self) -> usize;
 
+    /// Resolve `degree_bound` to an absolute degree bound over the codeword
+    /// domain; see `DegreeBound::resolve`.
+    ///
+    /// ## Panics
+    /// This function panics if LDT is not enabled.
+    fn resolve_degree_bound(&self, degree_bound: DegreeBound) -> Result<usize, Error> {
+        degree_bound.resolve(self.codeword_domain().size())
+    }
+
     /// Given the coset index, return the corresponding query coset of the LDT.
     ///
     /// For example, if the codeword domain is `{a,b,c,d,e,f,g,h}`, and
@@ -650,3 +1981,66 @@ pub trait LDTInfo<F: PrimeField> {
             .1
     }
 }
+
+/// Number of squeezed bytes used to derive one field element in
+/// `squeeze_verifier_bytes_as_field`. This is the largest chunk that
+/// `ToConstraintField` packs into a single field element.
+pub(crate) fn bytes_per_field_element<F: PrimeField>() -> usize {
+    ((F::MODULUS_BIT_SIZE - 1) / 8) as usize
+}
+
+/// Pack little endian `bits` into a field element in `[0, 2^bits.len())`.
+pub(crate) fn field_element_from_bits<F: PrimeField>(bits: &[bool]) -> F {
+    assert!(
+        bits.len() < F::MODULUS_BIT_SIZE as usize,
+        "number of bits should be smaller than modulus bit size"
+    );
+    let bytes = bits
+        .chunks(8)
+        .map(|bits| {
+            bits.iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << i))
+        })
+        .collect::<Vec<_>>();
+    F::from_le_bytes_mod_order(&bytes)
+}
+
+/// Sample a uniform field element from bits given by `squeeze_bits`, using
+/// rejection sampling so that the element is unbiased.
+pub(crate) fn sample_uniform_field_element<F: PrimeField>(
+    mut squeeze_bits: impl FnMut(usize) -> Vec<bool>,
+) -> F {
+    loop {
+        let bits = squeeze_bits(F::MODULUS_BIT_SIZE as usize);
+        // `from_bigint` returns `None` if the integer is not smaller than modulus
+        if let Some(element) = F::from_bigint(F::BigInt::from_bits_le(&bits)) {
+            break element;
+        }
+    }
+}
+
+/// Sample a permutation of `0..n` using Fisher-Yates shuffle. Each index is
+/// sampled from bytes given by `squeeze_bytes` using rejection sampling, so
+/// that the index is unbiased even if the range is not a power of two.
+pub(crate) fn sample_permutation(
+    n: usize,
+    mut squeeze_bytes: impl FnMut(usize) -> Vec<u8>,
+) -> Vec<usize> {
+    let mut permutation = (0..n).collect::<Vec<_>>();
+    for i in (1..n).rev() {
+        // sample j uniformly from 0..=i
+        let range = i as u64 + 1;
+        let mask = u64::MAX >> (i as u64).leading_zeros();
+        let j = loop {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&squeeze_bytes(8));
+            let candidate = u64::from_le_bytes(bytes) & mask;
+            if candidate < range {
+                break candidate as usize;
+            }
+        };
+        permutation.swap(i, j);
+    }
+    permutation
+}
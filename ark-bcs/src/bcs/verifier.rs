@@ -1,14 +1,115 @@
 use crate::{
-    bcs::{prover::BCSProof, simulation_transcript::SimulationTranscript, MTHashParameters},
-    iop::{bookkeeper::NameSpace, message::MessagesCollection, verifier::IOPVerifier},
-    ldt::{NoLDT, LDT},
-    Error,
+    bcs::{
+        aggregation::AggregatedBCSProof,
+        commitment::PathCache,
+        interning::InternedBCSProof,
+        prover::BCSProof,
+        simulation_transcript::{ChallengeSource, SimulationTranscript},
+        transcript::TranscriptVersion,
+        MTHashParameters,
+    },
+    iop::{
+        bookkeeper::{MessageBookkeeper, NameSpace},
+        message::{MessagesCollection, MsgRoundRef},
+        oracles::{CountingRoundOracle, SuccinctRoundOracle},
+        verifier::IOPVerifier,
+    },
+    ldt::{ldt_params_with_num_queries, NoLDT, LDT},
+    BCSError, Error,
 };
-use ark_crypto_primitives::merkle_tree::Config as MTConfig;
+use ark_crypto_primitives::{merkle_tree::Config as MTConfig, Path};
 use ark_ff::PrimeField;
 use ark_ldt::domain::Radix2CosetDomain;
+use ark_serialize::{CanonicalDeserialize, SerializationError};
 use ark_sponge::{Absorb, CryptographicSponge};
-use ark_std::{marker::PhantomData, vec::Vec};
+use ark_std::{
+    boxed::Box,
+    collections::BTreeMap,
+    io::Read,
+    marker::PhantomData,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Statistics collected while verifying a BCS proof.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VerificationMetrics {
+    /// Number of merkle tree authentication paths checked.
+    pub num_merkle_paths: usize,
+    /// Number of leaf hash and two-to-one hash invocations used to check
+    /// authentication paths. Inner nodes shared by paths of the same round
+    /// are hashed once.
+    pub num_hash_invocations: usize,
+    /// Number of field elements hashed as merkle tree leaves.
+    pub num_hashed_field_elements: usize,
+    /// Number of oracle positions revealed by all queried cosets.
+    pub num_queried_positions: usize,
+}
+
+/// Number of oracle positions queried by a verifier, as returned by
+/// `BCSVerifier::query_complexity`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QueryComplexity {
+    /// `positions_per_round[i]` is the number of positions revealed in prover
+    /// round `i`, including LDT rounds. A queried coset reveals all of its
+    /// positions.
+    pub positions_per_round: Vec<usize>,
+}
+
+impl QueryComplexity {
+    /// Total number of positions revealed in all prover rounds.
+    pub fn total(&self) -> usize {
+        self.positions_per_round.iter().sum()
+    }
+}
+
+/// Upper bounds on the size of a proof accepted by a verifier, so that a
+/// malicious proof cannot exhaust verifier resources.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProofLimits {
+    /// Maximum number of prover rounds.
+    pub max_rounds: usize,
+    /// Maximum number of queried cosets of all prover rounds.
+    pub max_queries: usize,
+    /// Maximum number of field elements in queried cosets and short messages
+    /// of all prover rounds.
+    pub max_elements: usize,
+}
+
+impl ProofLimits {
+    /// Limits that accept proofs of any size.
+    pub fn unlimited() -> Self {
+        Self {
+            max_rounds: usize::MAX,
+            max_queries: usize::MAX,
+            max_elements: usize::MAX,
+        }
+    }
+
+    fn check(resource: &'static str, limit: usize, actual: usize) -> Result<(), Error> {
+        if actual > limit {
+            return Err(Box::new(BCSError::ResourceLimitExceeded {
+                resource,
+                limit,
+                actual,
+            }));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_rounds(&self, num_rounds: usize) -> Result<(), Error> {
+        Self::check("prover rounds", self.max_rounds, num_rounds)
+    }
+
+    pub(crate) fn check_queries(&self, num_queries: usize) -> Result<(), Error> {
+        Self::check("queried cosets", self.max_queries, num_queries)
+    }
+
+    pub(crate) fn check_elements(&self, num_elements: usize) -> Result<(), Error> {
+        Self::check("field elements", self.max_elements, num_elements)
+    }
+}
 
 /// Verifier for BCS proof.
 pub struct BCSVerifier<MT, F>
@@ -29,7 +130,9 @@ where
 {
     /// Given a BCS transformed (RS-)IOP proof, verify the correctness of this
     /// proof. `sponge` should be the same state as in beginning of
-    /// `BCSProver::prove` function.
+    /// `BCSProver::prove` function. Returns
+    /// `BCSError::InvalidAuthenticationPath` if a queried answer does not
+    /// match the merkle root of its round.
     pub fn verify<V, L, S>(
         sponge: S,
         proof: &BCSProof<MT, F>,
@@ -43,23 +146,575 @@ where
         L: LDT<F>,
         S: CryptographicSponge,
     {
-        // simulate main prove: reconstruct verifier messages to restore verifier state
-        let mut transcript = SimulationTranscript::new_transcript(
+        Self::verify_with_optional_overrides::<V, L, S>(
+            sponge,
             proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            None,
+        )
+        .map(|(verifier_result, ..)| verifier_result)
+    }
+
+    /// Verify the proof like `verify` using a verifier with `bool` output,
+    /// and return `BCSError::ProofRejected` if the verifier returns `false`.
+    /// Verifiers with other outputs should use `verify`, which returns the
+    /// output as is.
+    pub fn verify_accepted<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<(), Error>
+    where
+        V: IOPVerifier<S, F, VerifierOutput = bool>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        if Self::verify::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+        )? {
+            Ok(())
+        } else {
+            Err(Box::new(BCSError::ProofRejected))
+        }
+    }
+
+    /// Verify the proof like `verify`, but first reject it with
+    /// `BCSError::ResourceLimitExceeded` if it is not within `limits`. Use
+    /// `BCSProof::try_from_bytes_with_limits` to enforce the limits when
+    /// decoding the proof.
+    pub fn verify_with_limits<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        limits: &ProofLimits,
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        proof.check_limits(limits)?;
+        Self::verify::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+        )
+    }
+
+    /// Decode a proof serialized using `serialize_compressed` from `reader`,
+    /// and verify it like `verify`. Only the authentication paths are
+    /// streamed: they come last in the proof, and are read one at a time, so
+    /// that each path is verified and dropped before the next one is read.
+    ///
+    /// Memory is **not** bounded by one path. `prover_iop_messages_by_round`,
+    /// i.e. the short messages, every queried coset and salt of all rounds,
+    /// and the merkle roots are decoded and held in full before the first path
+    /// is read, as the verifier needs the roots and short messages to
+    /// reconstruct the query positions, and the decision of `V` may read the
+    /// answers of any round. The saving is the size of the paths, which make
+    /// up most of a proof.
+    ///
+    /// Returns `BCSError::ProofParseError` with the offset of the item that
+    /// cannot be decoded.
+    pub fn verify_streaming<V, L, S, R>(
+        sponge: S,
+        reader: R,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+        R: Read,
+    {
+        let mut reader = CountingReader {
+            inner: reader,
+            num_bytes: 0,
+        };
+        let proof = BCSProof::<MT, F> {
+            prover_iop_messages_by_round: reader.read()?,
+            prover_messages_mt_root: reader.read()?,
+            prover_oracles_mt_path: Vec::new(),
+        };
+        let num_rounds = proof.prover_iop_messages_by_round.len();
+        if reader.read::<u64>()? != num_rounds as u64 {
+            return Err(Box::new(BCSError::MalformedProof {
+                round: num_rounds,
+                reason: "number of path lists is not number of rounds",
+            }));
+        }
+        proof.validate_structure_without_paths()?;
+        let run = Self::run_verification_with_paths::<V, L, S>(
+            sponge,
+            &proof,
+            Some(public_input),
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            None,
+            &mut ReaderPaths {
+                proof: &proof,
+                reader: &mut reader,
+                round: 0,
+            },
+        )?;
+        Ok(run.checked_output()?.0)
+    }
+
+    /// Verify the proof like `verify`, but instead of returning an error when
+    /// a queried answer does not match its merkle tree, report for each
+    /// namespace of the transcript whether it passed: a namespace passes if
+    /// the authentication paths of all prover rounds sent in it verify, if
+    /// its decision accepted when `V` runs it using
+    /// `MessagesCollection::decide_namespace`, and if all of its subprotocols
+    /// passed. This lets a caller accept the results of independent
+    /// subprotocols that are intact when another one is corrupted.
+    /// Namespaces are returned in the order they were created, starting with
+    /// root namespace.
+    ///
+    /// The verifier output is returned as is. A decision of `V` that is not
+    /// run using `decide_namespace` is only reflected in the output, and an
+    /// error it returns aborts the whole verification.
+    pub fn verify_per_namespace<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<(V::VerifierOutput, Vec<(NameSpace, bool)>), Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        let run = Self::run_verification::<V, L, S>(
+            sponge,
+            proof,
+            Some(public_input),
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            None,
+        )?;
+        let bookkeeper = &run.bookkeeper;
+        // subprotocols are created after their parent, so visiting namespaces
+        // in reverse order of id decides subprotocols first
+        let mut passed = BTreeMap::new();
+        for &id in bookkeeper.ns_details.keys().rev() {
+            let own_rounds_valid = bookkeeper.messages_store[&id]
+                .prover_rounds
+                .iter()
+                .filter(|round| !round.is_virtual)
+                .all(|round| run.valid_paths_per_round[round.index]);
+            let decision_accepted = run.namespace_decisions.get(&id).copied().unwrap_or(true);
+            let subprotocols_passed = bookkeeper.ns_map[&id].iter().all(|child| passed[child]);
+            passed.insert(
+                id,
+                own_rounds_valid && decision_accepted && subprotocols_passed,
+            );
+        }
+        let namespaces = bookkeeper
+            .ns_details
+            .iter()
+            .map(|(id, namespace)| (*namespace, passed[id]))
+            .collect();
+        Ok((run.output.expect("protocol is decided"), namespaces))
+    }
+
+    /// Verify each proof aggregated by `BCSProof::merge_proofs`, where the
+    /// `i`th proof is verified using `public_inputs[i]` and a clone of
+    /// `sponge`. Returns the verifier outputs in the order of the proofs.
+    ///
+    /// Returns `BCSError::PublicInputCountMismatch` if `public_inputs` and
+    /// aggregated proofs have different lengths.
+    pub fn verify_aggregated<V, L, S>(
+        sponge: S,
+        proof: &AggregatedBCSProof<MT, F>,
+        public_inputs: &[V::PublicInput],
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<Vec<V::VerifierOutput>, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        if proof.proofs.len() != public_inputs.len() {
+            return Err(Box::new(BCSError::PublicInputCountMismatch {
+                expected: proof.proofs.len(),
+                actual: public_inputs.len(),
+            }));
+        }
+        proof
+            .to_proofs()?
+            .iter()
+            .zip(public_inputs)
+            .map(|(proof, public_input)| {
+                Self::verify::<V, L, S>(
+                    sponge.clone(),
+                    proof,
+                    public_input,
+                    verifier_parameter,
+                    ldt_params,
+                    hash_params.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Verify a proof encoded by `BCSProof::intern_short_messages`, which is
+    /// first converted back to the original proof using
+    /// `InternedBCSProof::to_proof`.
+    pub fn verify_interned<V, L, S>(
+        sponge: S,
+        proof: &InternedBCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        Self::verify::<V, L, S>(
+            sponge,
+            &proof.to_proof()?,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+        )
+    }
+
+    /// Return the sponge for the proof after `proof` in a chain verified by
+    /// `verify_chain`: `sponge` (the sponge of `proof`) absorbing all merkle
+    /// roots and short messages of `proof`. Prover of the next proof should
+    /// start from the same sponge.
+    pub fn next_chain_sponge<S: CryptographicSponge>(sponge: &S, proof: &BCSProof<MT, F>) -> S {
+        let mut sponge = sponge.clone();
+        proof
+            .prover_messages_mt_root
+            .iter()
+            .flatten()
+            .for_each(|root| sponge.absorb(root));
+        proof
+            .prover_iop_messages_by_round
+            .iter()
+            .flat_map(|round| round.short_messages.iter())
+            .for_each(|message| sponge.absorb(message));
+        sponge
+    }
+
+    /// Verify a chain of proofs, where `proofs[0]` is verified using `sponge`,
+    /// and each following proof is verified using the sponge returned by
+    /// `next_chain_sponge` for the previous proof. `proofs[i]` is verified
+    /// with `public_inputs[i]`.
+    ///
+    /// Returns `BCSError::ChainLinkFailed` with the index of the first proof
+    /// that is rejected, has an invalid authentication path, or cannot be
+    /// verified, and `BCSError::PublicInputCountMismatch` if `proofs` and
+    /// `public_inputs` have different lengths.
+    pub fn verify_chain<V, L, S>(
+        sponge: S,
+        proofs: &[BCSProof<MT, F>],
+        public_inputs: &[V::PublicInput],
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<(), Error>
+    where
+        V: IOPVerifier<S, F, VerifierOutput = bool>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        if proofs.len() != public_inputs.len() {
+            return Err(Box::new(BCSError::PublicInputCountMismatch {
+                expected: proofs.len(),
+                actual: public_inputs.len(),
+            }));
+        }
+        let mut sponge = sponge;
+        for (index, (proof, public_input)) in proofs.iter().zip(public_inputs).enumerate() {
+            let reason = match Self::run_verification::<V, L, S>(
+                sponge.clone(),
+                proof,
+                Some(public_input),
+                verifier_parameter,
+                ldt_params,
+                hash_params.clone(),
+                TranscriptVersion::default(),
+                None,
+                None,
+            )
+            .and_then(VerificationRun::checked_output)
+            {
+                Ok((true, _)) => None,
+                Ok((false, _)) => Some(String::from("verifier rejects the proof")),
+                Err(err) => Some(err.to_string()),
+            };
+            if let Some(reason) = reason {
+                return Err(Box::new(BCSError::ChainLinkFailed { index, reason }));
+            }
+            sponge = Self::next_chain_sponge(&sponge, proof);
+        }
+        Ok(())
+    }
+
+    /// Verify the proof like `verify`, and also return statistics about the
+    /// merkle tree checks performed.
+    pub fn verify_with_metrics<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<(V::VerifierOutput, VerificationMetrics), Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        Self::verify_with_optional_overrides::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            None,
+        )
+    }
+
+    /// Returns the number of oracle positions revealed by the queries of `V`
+    /// and LDT in each prover round, without any proof. Commit phase is
+    /// simulated without prover messages, so challenges are squeezed from
+    /// `sponge` without absorbing any merkle root, and every query is
+    /// answered by zeros. Every oracle of a round is revealed at the same
+    /// positions. If the number of queries of `V` does not depend on the
+    /// challenges and answers, the result only depends on the parameters.
+    ///
+    /// The decisions of `V` and LDT on the zero answers are ignored. Short
+    /// messages are empty, so `V` should not read their elements.
+    pub fn query_complexity<V, L, S>(
+        sponge: S,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+    ) -> Result<QueryComplexity, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        let ldt_params = &ldt_params_with_num_queries::<F, L>(
+            ldt_params,
+            V::ldt_num_queries(verifier_parameter),
+        );
+        L::validate_parameters(ldt_params)?;
+        let mut transcript = SimulationTranscript::<MT, S, F>::new_transcript_without_proof(
             sponge,
             L::codeword_domain(ldt_params),
             L::localization_param(ldt_params),
-            iop_trace!("IOP Root: BCS proof verify"),
+            iop_trace!("IOP Root: BCS query complexity"),
         );
+        let (root_namespace, ldt_namespace, codewords) = Self::simulate_commit_phase::<V, L, S>(
+            &mut transcript,
+            verifier_parameter,
+            ldt_params,
+        )?;
 
+        let mut transcript_messages = MessagesCollection::new(
+            transcript
+                .expected_prover_messages_info
+                .into_iter()
+                .map(CountingRoundOracle::<F>::new)
+                .collect(),
+            transcript
+                .registered_virtual_oracles
+                .into_iter()
+                .map(Some)
+                .collect(),
+            transcript.reconstructed_verifier_messages,
+            transcript.bookkeeper,
+        );
+        let mut sponge = transcript.sponge;
+        // only the queries matter, so checks failing on zero answers are ignored
+        let _ = L::query_and_decide(
+            ldt_namespace,
+            ldt_params,
+            &mut sponge,
+            &codewords,
+            &mut transcript_messages,
+        );
+        let _ = V::query_and_decide(
+            root_namespace,
+            verifier_parameter,
+            public_input,
+            &mut sponge,
+            &mut transcript_messages,
+        );
+        Ok(QueryComplexity {
+            positions_per_round: transcript_messages
+                .real_oracles
+                .iter()
+                .map(|round_oracle| {
+                    round_oracle.coset_queries.len() << round_oracle.info.localization_parameter
+                })
+                .collect(),
+        })
+    }
+
+    /// Verify the proof like `verify`, but take commit phase verifier messages
+    /// from `challenge_source` instead of the sponge. This is **unsafe for
+    /// production** and only meant for testing a protocol under fixed or
+    /// adversarial challenges; see `ChallengeSource`.
+    pub fn verify_with_challenge_source<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        challenge_source: Box<dyn ChallengeSource<F> + Send>,
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        Self::verify_with_optional_overrides::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            Some(challenge_source),
+            None,
+        )
+        .map(|(verifier_result, ..)| verifier_result)
+    }
+
+    /// Verify the proof like `verify`, but use simulation transcript of
+    /// `version`, which should be the version the proof is generated with by
+    /// `BCSProof::generate_with_version`.
+    pub fn verify_with_version<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        Self::verify_with_optional_overrides::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            version,
+            None,
+            None,
+        )
+        .map(|(verifier_result, ..)| verifier_result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_with_optional_overrides<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+        challenge_source: Option<Box<dyn ChallengeSource<F> + Send>>,
+        fixed_queries: Option<&[usize]>,
+    ) -> Result<(V::VerifierOutput, VerificationMetrics), Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        let run = Self::run_verification::<V, L, S>(
+            sponge,
+            proof,
+            Some(public_input),
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            version,
+            challenge_source,
+            fixed_queries,
+        )?;
+        run.checked_output()
+    }
+
+    /// Simulate the commit phase of `V` and then LDT using `transcript`, to
+    /// reconstruct the verifier messages. Returns the namespaces of `V` and
+    /// LDT, and the prover rounds tested by LDT.
+    fn simulate_commit_phase<V, L, S>(
+        transcript: &mut SimulationTranscript<'_, MT, S, F>,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+    ) -> Result<(NameSpace, NameSpace, Vec<MsgRoundRef>), Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
         let root_namespace = NameSpace::root(iop_trace!("BCS Verify: commit phase"));
 
-        V::register_iop_structure::<MT>(root_namespace, &mut transcript, verifier_parameter);
+        V::register_iop_structure::<MT>(root_namespace, transcript, verifier_parameter);
         // sanity check: transcript has not pending message
         assert!(
             !transcript.is_pending_message_available(),
             "Sanity check failed: pending verifier message not submitted"
         );
+        if let Some(&round) = transcript.unexpected_roots.first() {
+            return Err(Box::new(BCSError::UnexpectedRoot { round }));
+        }
 
         let codewords = transcript.bookkeeper.dump_all_prover_messages_in_order();
 
@@ -82,7 +737,7 @@ where
             ldt_namespace,
             ldt_params,
             num_rs_oracles + num_virtual_oracles,
-            &mut transcript,
+            transcript,
         );
 
         debug_assert!(
@@ -90,6 +745,102 @@ where
             "Sanity check failed, pending verifier message not submitted"
         );
 
+        Ok((root_namespace, ldt_namespace, codewords))
+    }
+
+    /// Run the verifier like `verify_with_optional_overrides`, but record
+    /// whether the authentication paths of each prover round verify instead
+    /// of failing on the first invalid path. If `public_input` is `None`,
+    /// `V::query_and_decide` is skipped and only the queries of LDT are
+    /// checked.
+    #[allow(clippy::too_many_arguments)]
+    fn run_verification<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: Option<&V::PublicInput>,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+        challenge_source: Option<Box<dyn ChallengeSource<F> + Send>>,
+        fixed_queries: Option<&[usize]>,
+    ) -> Result<VerificationRun<V::VerifierOutput>, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        proof.validate_structure()?;
+        Self::run_verification_with_paths::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            version,
+            challenge_source,
+            fixed_queries,
+            &mut ProofPaths {
+                rounds: &proof.prover_oracles_mt_path,
+                paths: &[],
+            },
+        )
+    }
+
+    /// Run the verifier like `run_verification`, but take the authentication
+    /// paths from `paths` instead of `proof`, one at a time. Paths of each
+    /// round are read after `V::query_and_decide`, in the order of
+    /// `BCSProof::prover_oracles_mt_path`. `proof` should have passed
+    /// `BCSProof::validate_structure_without_paths`.
+    #[allow(clippy::too_many_arguments)]
+    fn run_verification_with_paths<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: Option<&V::PublicInput>,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        version: TranscriptVersion,
+        challenge_source: Option<Box<dyn ChallengeSource<F> + Send>>,
+        fixed_queries: Option<&[usize]>,
+        paths: &mut dyn PathSource<MT>,
+    ) -> Result<VerificationRun<V::VerifierOutput>, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        let ldt_params = &ldt_params_with_num_queries::<F, L>(
+            ldt_params,
+            V::ldt_num_queries(verifier_parameter),
+        );
+        L::validate_parameters(ldt_params)?;
+        // simulate main prove: reconstruct verifier messages to restore verifier state
+        let mut transcript = SimulationTranscript::new_transcript_with_version(
+            proof,
+            sponge,
+            L::codeword_domain(ldt_params),
+            L::localization_param(ldt_params),
+            version,
+            iop_trace!("IOP Root: BCS proof verify"),
+        );
+        if let Some(challenge_source) = challenge_source {
+            transcript = transcript.with_challenge_source(challenge_source);
+        }
+        #[cfg(feature = "bench")]
+        if let Some(positions) = fixed_queries {
+            transcript = transcript.with_fixed_queries(positions);
+        }
+        #[cfg(not(feature = "bench"))]
+        debug_assert!(fixed_queries.is_none());
+
+        let (root_namespace, ldt_namespace, codewords) = Self::simulate_commit_phase::<V, L, S>(
+            &mut transcript,
+            verifier_parameter,
+            ldt_params,
+        )?;
+
         // end commit phase
         // start query phase
 
@@ -103,7 +854,13 @@ where
             .prover_iop_messages_by_round
             .iter()
             .zip(transcript.expected_prover_messages_info.iter())
-            .map(|(m, info)| m.get_view(info.clone()))
+            .map(|(m, info)| {
+                if transcript.version.inlines_oracles(info) {
+                    m.get_inline_view(info.clone())
+                } else {
+                    m.get_view(info.clone())
+                }
+            })
             .collect::<Vec<_>>();
 
         let mut transcript_messages = MessagesCollection::new(
@@ -116,6 +873,11 @@ where
             transcript.reconstructed_verifier_messages,
             transcript.bookkeeper,
         );
+        transcript_messages.version = transcript.version;
+        #[cfg(feature = "bench")]
+        {
+            transcript_messages.fixed_queries = transcript.fixed_queries;
+        }
         let mut sponge = transcript.sponge;
 
         // verify LDT
@@ -129,71 +891,141 @@ where
         )?;
 
         // verify the protocol (we can use a new view)
-        let verifier_result = V::query_and_decide(
-            root_namespace,
-            verifier_parameter,
-            public_input,
-            &mut sponge,
-            &mut transcript_messages,
-        )?;
+        let verifier_result = match public_input {
+            Some(public_input) => Some(V::query_and_decide(
+                root_namespace,
+                verifier_parameter,
+                public_input,
+                &mut sponge,
+                &mut transcript_messages,
+            )?),
+            None => None,
+        };
 
         // verify all authentication paths
 
-        // we clone all the paths because we need to replace its leaf position with
-        // verifier calculated one
-        let all_paths = proof.prover_oracles_mt_path.clone();
         let all_mt_roots = &proof.prover_messages_mt_root;
-
-        assert_eq!(transcript_messages.real_oracles.len(), all_paths.len());
         assert_eq!(transcript_messages.real_oracles.len(), all_mt_roots.len());
 
-        transcript_messages
+        let rejected_rounds = transcript_messages.rounds_of_rejected_decisions();
+        let mut metrics = VerificationMetrics::default();
+        let mut valid_paths_per_round = Vec::with_capacity(all_mt_roots.len());
+        for (round, (round_oracle, mt_root)) in transcript_messages
             .real_oracles
             .iter()
-            .zip(all_paths)
             .zip(all_mt_roots)
-            .for_each(|((round_oracle, paths), mt_root)| {
-                assert_eq!(round_oracle.coset_queries.len(), paths.len());
-                assert_eq!(
-                    round_oracle.coset_queries.len(),
-                    round_oracle.underlying_message.queried_cosets.len(),
-                    "insufficient queries in verifier code"
-                );
-                let mt_root = if !round_oracle.coset_queries.is_empty() {
-                    mt_root
-                        .as_ref()
-                        .expect("round oracle has query but has no mt_root")
+            .enumerate()
+        {
+            let num_paths = paths.next_round()?;
+            let queries = &round_oracle.coset_queries;
+            // whether the round is valid so far, and the cache and salts to
+            // verify its paths with, or `None` if they need not be verified
+            let (mut valid, mut checked) = if round_oracle.inline {
+                // oracles committed inline are absorbed in entirety, so they
+                // have no paths to verify
+                (true, None)
+            } else {
+                if verifier_result.is_some() && !rejected_rounds.contains(&round) {
+                    assert_eq!(queries.len(), num_paths);
+                    assert_eq!(
+                        queries.len(),
+                        round_oracle.underlying_message.queried_cosets.len(),
+                        "insufficient queries in verifier code"
+                    );
+                }
+                if queries.len() > num_paths {
+                    // LDT queries come first, so the cosets queried by LDT
+                    // are a prefix of the cosets and paths of each round
+                    (false, None)
+                } else if queries.is_empty() {
+                    (true, None)
                 } else {
-                    return;
+                    let mt_root = mt_root
+                        .as_ref()
+                        .expect("round oracle has query but has no mt_root");
+                    // paths of this round share upper nodes, which are hashed once
+                    match queried_salts(round_oracle) {
+                        Some(salts) => (true, Some((PathCache::new(mt_root), salts))),
+                        None => (false, None),
+                    }
+                }
+            };
+            // paths that are not verified are still read, so that `paths` is
+            // at the start of the next round
+            for query in 0..num_paths {
+                let mut path = paths.next_path()?;
+                let (cache, salts) = match checked.as_mut() {
+                    Some(checked) if query < queries.len() => checked,
+                    _ => continue,
                 };
-                round_oracle
-                    .coset_queries
-                    .iter()
-                    .zip(round_oracle.underlying_message.queried_cosets.iter())
-                    .zip(paths.into_iter())
-                    .for_each(|((index, coset), mut path)| {
-                        debug_assert_eq!(path.leaf_index, *index);
-                        path.leaf_index = *index;
-                        assert!(
-                            path.verify(
-                                &hash_params.leaf_hash_param,
-                                &hash_params.inner_hash_param,
-                                mt_root,
-                                // flatten by concatenating cosets of all oracles
-                                coset
-                                    .clone()
-                                    .into_iter()
-                                    .flatten()
-                                    .collect::<Vec<_>>()
-                                    .as_slice()
-                            )
-                            .expect("cannot verify"),
-                            "merkle tree verification failed"
-                        )
-                    })
-            });
-
-        Ok(verifier_result)
+                let index = queries[query];
+                let coset = &round_oracle.underlying_message.queried_cosets[query];
+                debug_assert_eq!(path.leaf_index, index);
+                // replace leaf position of the path with verifier calculated one
+                path.leaf_index = index;
+                metrics.num_merkle_paths += 1;
+                metrics.num_hashed_field_elements += coset.iter().map(|c| c.len()).sum::<usize>();
+                metrics.num_queried_positions += coset.get(0).map_or(0, |c| c.len());
+                valid &= cache
+                    .verify(
+                        &hash_params,
+                        &path,
+                        // flatten by concatenating salt and cosets of all oracles
+                        salts[query]
+                            .into_iter()
+                            .chain(coset.iter().flatten().copied())
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                    )
+                    .unwrap_or(false);
+            }
+            if let Some((cache, _)) = checked {
+                metrics.num_hash_invocations += cache.num_hash_invocations;
+            }
+            valid_paths_per_round.push(valid);
+        }
+
+        Ok(VerificationRun {
+            output: verifier_result,
+            metrics,
+            valid_paths_per_round,
+            bookkeeper: transcript_messages.bookkeeper,
+            namespace_decisions: transcript_messages.namespace_decisions,
+        })
+    }
+
+    /// Verify a proof generated by `BCSProof::generate_with_fixed_queries`,
+    /// answering every query to a prover round at `positions`; see
+    /// `SimulationTranscript::with_fixed_queries`.
+    ///
+    /// This is **unsound** and only meant for benchmarks.
+    #[cfg(feature = "bench")]
+    pub fn verify_with_fixed_queries<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        public_input: &V::PublicInput,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+        positions: &[usize],
+    ) -> Result<V::VerifierOutput, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        Self::verify_with_optional_overrides::<V, L, S>(
+            sponge,
+            proof,
+            public_input,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            Some(positions),
+        )
+        .map(|(verifier_result, ..)| verifier_result)
     }
 
     /// Verify without LDT. If verifier tries to get a low-degree oracle, this
@@ -245,4 +1077,182 @@ where
             hash_params,
         )
     }
+    /// Verify only the low-degree test of `proof`, skipping
+    /// `V::query_and_decide`. `V` is only used to register the IOP structure,
+    /// so that the LDT challenges can be reconstructed. Returns `false` if an
+    /// authentication path of a coset queried by LDT does not verify.
+    ///
+    /// ** Warning **: This function does not check any consistency relation
+    /// of the protocol, so the result alone does not mean that the proof is
+    /// valid.
+    pub fn verify_ldt_only<V, L, S>(
+        sponge: S,
+        proof: &BCSProof<MT, F>,
+        verifier_parameter: &V::VerifierParameter,
+        ldt_params: &L::LDTParameters,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<bool, Error>
+    where
+        V: IOPVerifier<S, F>,
+        L: LDT<F>,
+        S: CryptographicSponge,
+    {
+        let run = Self::run_verification::<V, L, S>(
+            sponge,
+            proof,
+            None,
+            verifier_parameter,
+            ldt_params,
+            hash_params,
+            TranscriptVersion::default(),
+            None,
+            None,
+        )?;
+        Ok(run.valid_paths_per_round.iter().all(|&valid| valid))
+    }
+}
+
+/// Salt of the merkle tree leaf of each queried coset of `round_oracle`, in
+/// query order, where rounds that are not hiding have no salt. Returns `None`
+/// if the proof does not have exactly one salt per queried coset for a hiding
+/// round, and no salt for other rounds.
+fn queried_salts<F: PrimeField>(
+    round_oracle: &SuccinctRoundOracle<'_, F>,
+) -> Option<Vec<Option<F>>> {
+    let salts = &round_oracle.underlying_message.queried_salts;
+    let num_cosets = round_oracle.underlying_message.queried_cosets.len();
+    match (round_oracle.info.hiding, salts.len()) {
+        (true, num_salts) if num_salts == num_cosets => {
+            Some(salts.iter().copied().map(Some).collect())
+        },
+        (false, 0) => Some(vec![None; num_cosets]),
+        _ => None,
+    }
+}
+
+/// Outcome of `BCSVerifier::run_verification`.
+struct VerificationRun<O> {
+    /// `None` if `V::query_and_decide` is skipped.
+    output: Option<O>,
+    metrics: VerificationMetrics,
+    /// Whether all authentication paths of each prover round verify.
+    valid_paths_per_round: Vec<bool>,
+    bookkeeper: MessageBookkeeper,
+    /// Whether each decision run by `MessagesCollection::decide_namespace`
+    /// accepted, keyed by namespace id.
+    namespace_decisions: BTreeMap<u64, bool>,
+}
+
+impl<O> VerificationRun<O> {
+    /// Return the verifier output and metrics, or
+    /// `BCSError::InvalidAuthenticationPath` for the first prover round with
+    /// an invalid path.
+    fn checked_output(self) -> Result<(O, VerificationMetrics), Error> {
+        if let Some(round) = self.valid_paths_per_round.iter().position(|&valid| !valid) {
+            return Err(Box::new(BCSError::InvalidAuthenticationPath { round }));
+        }
+        Ok((self.output.expect("protocol is decided"), self.metrics))
+    }
+}
+
+/// Counts the bytes read from `inner`, so that `BCSVerifier::verify_streaming`
+/// can report where decoding stopped.
+struct CountingReader<R> {
+    inner: R,
+    num_bytes: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> ark_std::io::Result<usize> {
+        let num_bytes = self.inner.read(buf)?;
+        self.num_bytes += num_bytes;
+        Ok(num_bytes)
+    }
+}
+
+impl<R: Read> CountingReader<R> {
+    /// Decode a `T` serialized using `serialize_compressed`, or return
+    /// `BCSError::ProofParseError` with the offset where `T` starts.
+    fn read<T: CanonicalDeserialize>(&mut self) -> Result<T, Error> {
+        let offset = self.num_bytes;
+        T::deserialize_compressed(&mut *self).map_err(|e| -> Error {
+            Box::new(BCSError::ProofParseError {
+                offset,
+                reason: match e {
+                    SerializationError::IoError(_) => "unexpected end of input",
+                    _ => "invalid encoding",
+                },
+            })
+        })
+    }
+}
+
+/// Authentication paths of a proof, given one round at a time in the order of
+/// `BCSProof::prover_oracles_mt_path`.
+trait PathSource<MT: MTConfig> {
+    /// Move to the next round, and return its number of paths.
+    fn next_round(&mut self) -> Result<usize, Error>;
+
+    /// Return the next path of the current round.
+    fn next_path(&mut self) -> Result<Path<MT>, Error>;
+}
+
+/// Paths of a proof that is already decoded.
+struct ProofPaths<'a, MT: MTConfig> {
+    /// Path lists of the rounds after the current one.
+    rounds: &'a [Vec<Path<MT>>],
+    /// Paths of the current round that are not returned yet.
+    paths: &'a [Path<MT>],
+}
+
+impl<MT: MTConfig> PathSource<MT> for ProofPaths<'_, MT> {
+    fn next_round(&mut self) -> Result<usize, Error> {
+        let (paths, rounds) = self
+            .rounds
+            .split_first()
+            .expect("proof has one path list per round");
+        self.rounds = rounds;
+        self.paths = paths;
+        Ok(paths.len())
+    }
+
+    fn next_path(&mut self) -> Result<Path<MT>, Error> {
+        let (path, paths) = self.paths.split_first().expect("round has no more paths");
+        self.paths = paths;
+        Ok(path.clone())
+    }
+}
+
+/// Paths decoded from `reader`, which is at the first round of
+/// `BCSProof::prover_oracles_mt_path` of `proof`. The number of paths of
+/// each round is checked using `BCSProof::validate_round` as it is read.
+struct ReaderPaths<'a, MT, F, R>
+where
+    MT: MTConfig,
+    F: PrimeField,
+    MT::InnerDigest: Absorb,
+{
+    proof: &'a BCSProof<MT, F>,
+    reader: &'a mut CountingReader<R>,
+    /// Index of the next round.
+    round: usize,
+}
+
+impl<MT, F, R> PathSource<MT> for ReaderPaths<'_, MT, F, R>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+    R: Read,
+{
+    fn next_round(&mut self) -> Result<usize, Error> {
+        let num_paths = self.reader.read::<u64>()? as usize;
+        self.proof.validate_round(self.round, Some(num_paths))?;
+        self.round += 1;
+        Ok(num_paths)
+    }
+
+    fn next_path(&mut self) -> Result<Path<MT>, Error> {
+        self.reader.read()
+    }
 }
@@ -0,0 +1,462 @@
+use ark_std::{fmt, format, string::String, vec::Vec};
+
+/// Errors raised by `ark-bcs` itself. Functions return them boxed as
+/// [`crate::Error`], so callers can recover the variant with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BCSError {
+    /// `2 ^ localization_parameter` is larger than the length of the oracle.
+    InvalidLocalization {
+        /// The requested localization parameter.
+        localization_parameter: usize,
+        /// The length of the oracle it is applied to.
+        oracle_length: usize,
+    },
+    /// A round with oracles has an oracle length that is not a power of two.
+    InvalidOracleLength {
+        /// The oracle length of the round.
+        oracle_length: usize,
+    },
+    /// FRI localization parameters do not fold the codeword domain down to a
+    /// final domain that can hold the final polynomial.
+    InvalidFoldingSchedule {
+        /// Size of the codeword domain.
+        domain_size: usize,
+        /// Degree tested by FRI.
+        tested_degree: u64,
+        /// Localization parameter (log of folding factor) of each FRI round.
+        localization_parameters: Vec<u64>,
+    },
+    /// Number of elements sent in an oracle is not equal to the oracle length
+    /// of its round.
+    OracleLengthMismatch {
+        /// Oracle length of the round.
+        expected: usize,
+        /// Number of elements sent.
+        actual: usize,
+    },
+    /// The proof is structurally invalid, and is rejected before running
+    /// verifier.
+    MalformedProof {
+        /// Index of the malformed prover round in the proof.
+        round: usize,
+        /// What is malformed.
+        reason: &'static str,
+    },
+    /// Bytes given to `BCSProof::try_from_bytes` do not encode a proof.
+    ProofParseError {
+        /// Offset in bytes of the item that cannot be parsed.
+        offset: usize,
+        /// Why the item cannot be parsed.
+        reason: &'static str,
+    },
+    /// The proof exceeds a limit of `ProofLimits`.
+    ResourceLimitExceeded {
+        /// What is limited.
+        resource: &'static str,
+        /// The limit.
+        limit: usize,
+        /// The amount in the proof, counted up to the point of rejection.
+        actual: usize,
+    },
+    /// A merkle tree given to
+    /// `PendingProverMessage::send_oracle_message_with_precomputed_tree` does
+    /// not match the sent oracle.
+    PrecomputedTreeMismatch {
+        /// Index of the first checked leaf that does not match.
+        leaf_index: usize,
+    },
+    /// Checks recorded by `MessagesCollection::check` failed.
+    FailedChecks {
+        /// Message of each failed check, in the order they were recorded.
+        messages: Vec<String>,
+    },
+    /// A proof in a chain given to `BCSVerifier::verify_chain` is rejected.
+    ChainLinkFailed {
+        /// Index of the first rejected proof in the chain.
+        index: usize,
+        /// Why the proof is rejected.
+        reason: String,
+    },
+    /// Prover commits a root different from the one registered by
+    /// `SimulationTranscript::expect_known_root`.
+    UnexpectedRoot {
+        /// Index of the prover round.
+        round: usize,
+    },
+    /// A verifier message is read as a type different from the one it is
+    /// sampled as.
+    UnexpectedVerifierMessageType {
+        /// Type the verifier message is read as.
+        expected: &'static str,
+        /// Type of the verifier message.
+        found: &'static str,
+    },
+    /// A verifier with `bool` output returns `false`.
+    ProofRejected,
+    /// Poseidon sponge parameters given to `BCSConfig::new_with_poseidon`
+    /// have no rate, or too little capacity for the security level.
+    InvalidSpongeParameters {
+        /// Number of field elements absorbed per permutation.
+        rate: usize,
+        /// Number of field elements kept as capacity.
+        capacity: usize,
+        /// Bit size of the field.
+        field_bits: usize,
+        /// Required security level in bits.
+        security_bits: usize,
+    },
+    /// A point given to `AtProverRound::query_at_points` is not an element
+    /// of the given domain.
+    PointNotInDomain {
+        /// Index of the point in the queried points.
+        index: usize,
+        /// Size of the domain.
+        domain_size: usize,
+    },
+    /// A degree bound given as `DegreeBound::Rate` is not below 1, or does
+    /// not resolve to an integer degree bound over the codeword domain.
+    InvalidRate {
+        /// Numerator of the rate.
+        numerator: usize,
+        /// Denominator of the rate.
+        denominator: usize,
+        /// Size of the codeword domain.
+        domain_size: usize,
+    },
+    /// The number of public inputs given to `BCSVerifier::verify_aggregated`
+    /// or `BCSVerifier::verify_chain` is not the number of proofs.
+    PublicInputCountMismatch {
+        /// Number of aggregated or chained proofs.
+        expected: usize,
+        /// Number of public inputs.
+        actual: usize,
+    },
+    /// A merkle tree given to
+    /// `PendingProverMessage::send_oracle_message_with_precomputed_tree` does
+    /// not have one leaf per coset of the sent oracle.
+    PrecomputedTreeHeightMismatch {
+        /// Height of a tree with one leaf per coset, counting the leaf level.
+        expected: usize,
+        /// Height of the given tree.
+        actual: usize,
+    },
+    /// An authentication path of a prover round does not verify against its
+    /// merkle root, e.g. because a queried answer was changed after commit.
+    InvalidAuthenticationPath {
+        /// Index of the prover round in the proof.
+        round: usize,
+    },
+    /// A query sampler giving distinct positions is asked for more positions
+    /// than the domain has.
+    TooManyDistinctQueries {
+        /// Number of positions asked for.
+        num_queries: usize,
+        /// Size of the domain positions are sampled from.
+        domain_size: usize,
+    },
+    /// A prover round committed using
+    /// `PendingProverMessage::send_oracle_message_with_precomputed_tree` does
+    /// not consist of that oracle alone.
+    UnsupportedPrecomputedTreeRound {
+        /// What the round has besides the oracle.
+        reason: &'static str,
+    },
+    /// A prover round committed inline by `TranscriptVersion::V4` uses a
+    /// feature that needs a merkle tree.
+    UnsupportedInlineRound {
+        /// The feature the round uses.
+        reason: &'static str,
+    },
+}
+
+impl BCSError {
+    /// Returns a user-facing sentence that explains how to fix this error.
+    pub fn describe(&self) -> String {
+        match self {
+            BCSError::InvalidLocalization {
+                localization_parameter,
+                oracle_length,
+            } => format!(
+                "Localization parameter {} groups 2^{} elements per leaf, but the oracle only \
+                 has {} elements; use a localization parameter of at most {}.",
+                localization_parameter,
+                localization_parameter,
+                oracle_length,
+                ark_std::log2(*oracle_length)
+            ),
+            BCSError::InvalidOracleLength { oracle_length } => format!(
+                "The round has oracles of length {}, but merkle tree leaves are cosets of a \
+                 radix-2 domain; use an oracle length that is a power of two, such as {}.",
+                oracle_length,
+                oracle_length.next_power_of_two()
+            ),
+            BCSError::InvalidFoldingSchedule {
+                domain_size,
+                tested_degree,
+                localization_parameters,
+            } => {
+                let total_shrink_factor = localization_parameters.iter().sum::<u64>();
+                format!(
+                    "Folding schedule {:?} shrinks the codeword domain of size {} by 2^{}; use \
+                     at least one localization parameter, and make them sum to less than {} \
+                     while keeping the final domain larger than tested degree {} divided by \
+                     the total folding factor.",
+                    localization_parameters,
+                    domain_size,
+                    total_shrink_factor,
+                    ark_std::log2(*domain_size),
+                    tested_degree
+                )
+            },
+            BCSError::OracleLengthMismatch { expected, actual } => format!(
+                "The oracle has {} elements, but its round has oracle length {}; feed exactly {} \
+                 elements before finishing the oracle.",
+                actual, expected, expected
+            ),
+            BCSError::MalformedProof { round, reason } => format!(
+                "Prover round {} of the proof is malformed ({}); the proof was truncated or \
+                 not generated by `BCSProof::generate`, so it is rejected.",
+                round, reason
+            ),
+            BCSError::ProofParseError { offset, reason } => format!(
+                "The proof bytes cannot be parsed at offset {} ({}); the bytes are corrupted, \
+                 truncated or padded, or were not produced by `serialize_compressed` of a proof \
+                 with the same merkle tree config.",
+                offset, reason
+            ),
+            BCSError::ResourceLimitExceeded {
+                resource,
+                limit,
+                actual,
+            } => format!(
+                "The proof has at least {} {}, but the verifier accepts at most {}; the proof is \
+                 rejected before it is decoded or verified. Raise the limit in `ProofLimits` \
+                 only if such proofs are expected.",
+                actual, resource, limit
+            ),
+            BCSError::PrecomputedTreeMismatch { leaf_index } => format!(
+                "Leaf {} of the precomputed merkle tree does not match the sent oracle; pass \
+                 the tree built for exactly this oracle, with the same length and localization \
+                 parameter, or send the oracle without a precomputed tree.",
+                leaf_index
+            ),
+            BCSError::FailedChecks { messages } => format!(
+                "{} verifier checks failed: {}. Each message names a check in \
+                 `query_and_decide` whose queried values are inconsistent.",
+                messages.len(),
+                messages.join("; ")
+            ),
+            BCSError::ChainLinkFailed { index, reason } => format!(
+                "Proof {} of the chain is rejected ({}); check that it is generated with the \
+                 sponge returned by `BCSVerifier::next_chain_sponge` for the previous proof, and \
+                 with the public input at the same index.",
+                index, reason
+            ),
+            BCSError::UnexpectedRoot { round } => format!(
+                "Prover round {} commits a merkle root different from the known one; check that \
+                 prover sends the same oracle as the one used to compute the known root.",
+                round
+            ),
+            BCSError::UnexpectedVerifierMessageType { expected, found } => format!(
+                "Verifier message contains {} but is read as {}; check that verifier reads \
+                 each message using the type it is squeezed as.",
+                found, expected
+            ),
+            BCSError::ProofRejected => String::from(
+                "Verifier rejects the proof; check that prover and verifier use the same public \
+                 input, parameters and sponge state.",
+            ),
+            BCSError::InvalidSpongeParameters {
+                rate,
+                capacity,
+                field_bits,
+                security_bits,
+            } => format!(
+                "Poseidon parameters with rate {} and capacity {} elements of a {}-bit field do \
+                 not give {} bits of security; use a rate of at least 1 and a capacity of at \
+                 least {} bits.",
+                rate,
+                capacity,
+                field_bits,
+                security_bits,
+                2 * security_bits
+            ),
+            BCSError::PointNotInDomain { index, domain_size } => format!(
+                "Queried point {} is not in the domain of size {}; query only elements of the \
+                 domain the oracle is evaluated over.",
+                index, domain_size
+            ),
+            BCSError::InvalidRate {
+                numerator,
+                denominator,
+                domain_size,
+            } => format!(
+                "The degree bound is given as rate {}/{} of a codeword domain of size {}, but \
+                 the rate should be below 1 and give an integer degree bound; use a rate whose \
+                 denominator divides {} times the numerator.",
+                numerator, denominator, domain_size, domain_size
+            ),
+            BCSError::PublicInputCountMismatch { expected, actual } => format!(
+                "{} proofs are verified, but {} public inputs are given; pass one public input \
+                 per proof, in the order of the chain or of `BCSProof::merge_proofs`.",
+                expected, actual
+            ),
+            BCSError::PrecomputedTreeHeightMismatch { expected, actual } => format!(
+                "The precomputed merkle tree has height {}, but a tree committing the sent oracle \
+                 has height {}; pass the tree built for exactly this oracle, with the same length \
+                 and localization parameter.",
+                actual, expected
+            ),
+            BCSError::InvalidAuthenticationPath { round } => format!(
+                "A queried answer of prover round {} does not match the merkle root of the \
+                 round; the proof was modified after it was generated, or was generated with \
+                 other merkle tree parameters, so it is rejected.",
+                round
+            ),
+            BCSError::TooManyDistinctQueries {
+                num_queries,
+                domain_size,
+            } => format!(
+                "{} distinct positions are sampled from a domain of {} positions; use at most {}                  queries, or a sampler whose positions may repeat, such as                  `QueryStrategy::Independent`.",
+                num_queries, domain_size, domain_size
+            ),
+            BCSError::UnsupportedPrecomputedTreeRound { reason } => format!(
+                "The round is committed using a precomputed merkle tree, but it {}, so its \
+                 leaves differ from the ones of the tree; send the oracle alone in its round \
+                 without hiding, or send it without a precomputed tree.",
+                reason
+            ),
+            BCSError::UnsupportedInlineRound { reason } => format!(
+                "The prover round has oracles of at most {} elements, so \
+                 `TranscriptVersion::V4` commits it inline with no merkle tree, but the round \
+                 {}; send the round without hiding or a precomputed tree, or use a transcript \
+                 version that commits the round with a merkle tree.",
+                crate::bcs::transcript::INLINE_ORACLE_MAX_LENGTH,
+                reason
+            ),
+        }
+    }
+}
+
+impl fmt::Display for BCSError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BCSError::InvalidLocalization {
+                localization_parameter,
+                oracle_length,
+            } => write!(
+                f,
+                "localization parameter {} is too large for oracle of length {}",
+                localization_parameter, oracle_length
+            ),
+            BCSError::InvalidOracleLength { oracle_length } => {
+                write!(f, "oracle length {} is not a power of two", oracle_length)
+            },
+            BCSError::InvalidFoldingSchedule {
+                domain_size,
+                tested_degree,
+                localization_parameters,
+            } => write!(
+                f,
+                "folding schedule {:?} is invalid for domain of size {} and tested degree {}",
+                localization_parameters, domain_size, tested_degree
+            ),
+            BCSError::OracleLengthMismatch { expected, actual } => write!(
+                f,
+                "oracle has {} elements, but oracle length is {}",
+                actual, expected
+            ),
+            BCSError::MalformedProof { round, reason } => {
+                write!(f, "malformed proof at prover round {}: {}", round, reason)
+            },
+            BCSError::ProofParseError { offset, reason } => {
+                write!(f, "cannot parse proof at offset {}: {}", offset, reason)
+            },
+            BCSError::ResourceLimitExceeded {
+                resource,
+                limit,
+                actual,
+            } => write!(f, "proof has {} {}, limit is {}", actual, resource, limit),
+            BCSError::PrecomputedTreeMismatch { leaf_index } => {
+                write!(
+                    f,
+                    "precomputed merkle tree does not match leaf {}",
+                    leaf_index
+                )
+            },
+            BCSError::FailedChecks { messages } => {
+                write!(f, "failed checks: {}", messages.join("; "))
+            },
+            BCSError::ChainLinkFailed { index, reason } => {
+                write!(f, "proof {} of the chain is rejected: {}", index, reason)
+            },
+            BCSError::UnexpectedRoot { round } => {
+                write!(f, "prover round {} has unexpected merkle root", round)
+            },
+            BCSError::UnexpectedVerifierMessageType { expected, found } => write!(
+                f,
+                "expected verifier message of {}, found {}",
+                expected, found
+            ),
+            BCSError::ProofRejected => write!(f, "proof is rejected"),
+            BCSError::InvalidSpongeParameters {
+                rate,
+                capacity,
+                field_bits,
+                security_bits,
+            } => write!(
+                f,
+                "sponge with rate {} and capacity {} over {}-bit field is too weak for {}-bit \
+                 security",
+                rate, capacity, field_bits, security_bits
+            ),
+            BCSError::PointNotInDomain { index, domain_size } => write!(
+                f,
+                "queried point {} is not in domain of size {}",
+                index, domain_size
+            ),
+            BCSError::InvalidRate {
+                numerator,
+                denominator,
+                domain_size,
+            } => write!(
+                f,
+                "rate {}/{} is invalid for codeword domain of size {}",
+                numerator, denominator, domain_size
+            ),
+            BCSError::PublicInputCountMismatch { expected, actual } => write!(
+                f,
+                "{} proofs are verified, but {} public inputs are given",
+                expected, actual
+            ),
+            BCSError::PrecomputedTreeHeightMismatch { expected, actual } => write!(
+                f,
+                "precomputed merkle tree has height {}, expected {}",
+                actual, expected
+            ),
+            BCSError::InvalidAuthenticationPath { round } => write!(
+                f,
+                "authentication path of prover round {} does not verify",
+                round
+            ),
+            BCSError::TooManyDistinctQueries {
+                num_queries,
+                domain_size,
+            } => write!(
+                f,
+                "cannot sample {} distinct positions from a domain of size {}",
+                num_queries, domain_size
+            ),
+            BCSError::UnsupportedPrecomputedTreeRound { reason } => write!(
+                f,
+                "prover round committed using a precomputed merkle tree {}",
+                reason
+            ),
+            BCSError::UnsupportedInlineRound { reason } => {
+                write!(f, "prover round committed inline {}", reason)
+            },
+        }
+    }
+}
+
+impl ark_std::error::Error for BCSError {}
@@ -3,19 +3,33 @@ use ark_std::collections::BTreeMap;
 use crate::{iop::message::MsgRoundRef, tracer::TraceInfo};
 use ark_std::vec::Vec;
 
+/// Domain separator of the root namespace, which transcripts absorb into the
+/// sponge when they are created.
+pub(crate) const ROOT_NAMESPACE_DOMAIN_SEPARATOR: &[u8] = b"ark-bcs/namespace";
+
 /// Namespace is a unique id of the protocol in a transcript.
 /// `Namespace{id=0}` is always reserved for root namespace.
 #[derive(Copy, Clone, Debug, Derivative)]
-#[derivative(PartialEq, PartialOrd, Ord, Eq)]
+#[derivative(PartialEq, PartialOrd, Ord, Eq, Hash)]
 pub struct NameSpace {
     /// The global id of current namespace in the transcript.
     pub id: u64,
     /// Trace for the current namespace
-    #[derivative(PartialEq = "ignore", PartialOrd = "ignore", Ord = "ignore")]
+    #[derivative(
+        PartialEq = "ignore",
+        PartialOrd = "ignore",
+        Ord = "ignore",
+        Hash = "ignore"
+    )]
     pub trace: TraceInfo,
     /// The protocol id of the parent protocol in current transcript.
     /// if `self.id==0`, then this field should be 0.
-    #[derivative(PartialEq = "ignore", PartialOrd = "ignore", Ord = "ignore")]
+    #[derivative(
+        PartialEq = "ignore",
+        PartialOrd = "ignore",
+        Ord = "ignore",
+        Hash = "ignore"
+    )]
     pub parent_id: u64,
 }
 
@@ -29,6 +43,34 @@ impl NameSpace {
         }
     }
 
+    /// Canonical encoding of this namespace: `id` followed by `parent_id`,
+    /// both as little-endian `u64`. The encoding does not depend on `trace`,
+    /// so namespaces from the same transcript are equal if and only if their
+    /// encodings are equal.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.id.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.parent_id.to_le_bytes());
+        bytes
+    }
+
+    /// Decode a namespace encoded by `to_bytes`, attaching `trace` to it.
+    /// Returns `None` if `bytes` is not 16 bytes long.
+    pub fn from_bytes(bytes: &[u8], trace: TraceInfo) -> Option<Self> {
+        if bytes.len() != 16 {
+            return None;
+        }
+        let mut id = [0u8; 8];
+        let mut parent_id = [0u8; 8];
+        id.copy_from_slice(&bytes[..8]);
+        parent_id.copy_from_slice(&bytes[8..]);
+        Some(Self::new(
+            u64::from_le_bytes(id),
+            trace,
+            u64::from_le_bytes(parent_id),
+        ))
+    }
+
     /// Returns a Namespace
     pub(crate) const fn new(id: u64, trace: TraceInfo, parent_id: u64) -> Self {
         Self {
@@ -92,6 +134,73 @@ impl MessageBookkeeper {
         ns
     }
 
+    /// Canonical encoding of the path from root namespace to namespace
+    /// `namespace_id`: `ROOT_NAMESPACE_DOMAIN_SEPARATOR`, followed by the ids of
+    /// non-root namespaces on the path as little-endian `u64`. Transcripts
+    /// absorb it into the sponge when the namespace is created, so that
+    /// structurally identical subprotocols get different challenges.
+    pub(crate) fn domain_separator(&self, namespace_id: u64) -> Vec<u8> {
+        let mut path = Vec::new();
+        let mut id = namespace_id;
+        while id != 0 {
+            path.push(id);
+            id = self.ns_details[&id].parent_id;
+        }
+        let mut result = ROOT_NAMESPACE_DOMAIN_SEPARATOR.to_vec();
+        path.iter()
+            .rev()
+            .for_each(|id| result.extend_from_slice(&id.to_le_bytes()));
+        result
+    }
+
+    /// Return a bookkeeper that knows all namespaces of `self`, but contains
+    /// no messages. Used by forked transcripts, so that only messages sent in
+    /// the fork are recorded.
+    pub(crate) fn fork(&self) -> Self {
+        let mut result = self.clone();
+        result
+            .messages_store
+            .values_mut()
+            .for_each(|indices| *indices = MessageIndices::default());
+        result
+    }
+
+    /// Merge messages recorded by a forked bookkeeper into `self`. Prover
+    /// round indices of the fork are shifted by `prover_round_offset` and
+    /// verifier round indices are shifted by `verifier_round_offset`.
+    ///
+    /// # Panics
+    /// Panics if the fork created new namespaces or registered virtual rounds.
+    pub(crate) fn join(
+        &mut self,
+        fork: Self,
+        prover_round_offset: usize,
+        verifier_round_offset: usize,
+    ) {
+        assert_eq!(
+            fork.next_namespace_index, self.next_namespace_index,
+            "forked transcript cannot create new namespaces"
+        );
+        fork.messages_store.into_iter().for_each(|(id, indices)| {
+            let node = self
+                .messages_store
+                .get_mut(&id)
+                .expect("namespace not found");
+            node.prover_rounds
+                .extend(indices.prover_rounds.into_iter().map(|round| {
+                    assert!(
+                        !round.is_virtual,
+                        "forked transcript cannot register virtual rounds"
+                    );
+                    MsgRoundRef::new(round.index + prover_round_offset, round.trace, false)
+                }));
+            node.verifier_messages
+                .extend(indices.verifier_messages.into_iter().map(|round| {
+                    MsgRoundRef::new(round.index + verifier_round_offset, round.trace, false)
+                }));
+        });
+    }
+
     /// Return all prover message reference sent at this point, in order.
     pub(crate) fn dump_all_prover_messages_in_order(&self) -> Vec<MsgRoundRef> {
         self.messages_store
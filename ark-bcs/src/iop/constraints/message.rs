@@ -162,6 +162,30 @@ impl<'a, 'b, F: PrimeField> AtProverRoundVar<'a, 'b, F> {
         query_result
     }
 
+    /// Query the prover message as an evaluation oracle at the requested round
+    /// at a point, where each position is reduced modulo oracle length first.
+    /// Because oracle length is a power of two, reduction keeps the least
+    /// significant `log2(length)` bits of each little-endian position.
+    pub fn query_point_modular(
+        &mut self,
+        positions: &[Vec<Boolean<F>>],
+        tracer: TraceInfo,
+    ) -> Result<Vec<Vec<FpVar<F>>>, SynthesisError> {
+        let log_length =
+            ark_std::log2(self._self.get_prover_round_info(self.round).length) as usize;
+        let positions = positions
+            .iter()
+            .map(|position| {
+                position
+                    .iter()
+                    .take(log_length)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        self.query_point(&positions, tracer)
+    }
+
     /// Return the queried coset at `coset_index` of all oracles in this round.
     /// `result[i][j][k]` is coset index `i` -> oracle index `j` -> element `k`
     /// in this coset.
@@ -206,6 +230,8 @@ pub enum VerifierMessageVar<F: PrimeField> {
     Bits(Vec<Boolean<F>>),
     /// bytes
     Bytes(Vec<UInt8<F>>),
+    /// number of bytes squeezed and discarded by `advance_sponge`
+    Advance(usize),
 }
 
 impl<F: PrimeField> VerifierMessageVar<F> {
@@ -269,6 +295,15 @@ impl<F: PrimeField> AllocVar<VerifierMessage<F>, F> for VerifierMessageVar<F> {
                     .collect();
                 Ok(VerifierMessageVar::Bytes(var?))
             },
+            // a permutation is allocated as field elements of its indices
+            VerifierMessage::Permutation(permutation) => {
+                let var: Result<Vec<_>, _> = permutation
+                    .iter()
+                    .map(|x| FpVar::new_variable(cs.clone(), || Ok(F::from(*x as u64)), mode))
+                    .collect();
+                Ok(VerifierMessageVar::FieldElements(var?))
+            },
+            VerifierMessage::Advance(num_bytes) => Ok(VerifierMessageVar::Advance(*num_bytes)),
         }
     }
 }
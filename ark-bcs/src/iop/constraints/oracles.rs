@@ -27,6 +27,8 @@ pub struct SuccinctRoundMessageVar<F: PrimeField> {
     // note that queries will be provided by verifier instead
     /// Store the non-oracle IP messages in this round
     pub short_messages: Vec<Vec<FpVar<F>>>,
+    /// Salt of the merkle tree leaf of each queried coset, in query order.
+    pub queried_salts: Vec<FpVar<F>>,
 }
 
 impl<F: PrimeField> SuccinctRoundMessageVar<F> {
@@ -70,9 +72,12 @@ impl<F: PrimeField> AllocVar<SuccinctRoundMessage<F>, F> for SuccinctRoundMessag
                     .collect::<Result<Vec<_>, _>>()
             })
             .collect::<Result<Vec<_>, _>>()?;
+        let queried_salts =
+            Vec::new_variable(cs.clone(), || Ok(native.queried_salts.clone()), mode)?;
         Ok(Self {
             queried_cosets,
             short_messages,
+            queried_salts,
         })
     }
 }
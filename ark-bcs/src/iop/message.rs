@@ -1,10 +1,25 @@
-use crate::{iop::bookkeeper::MessageBookkeeper, tracer::TraceInfo};
-use ark_ff::PrimeField;
-use ark_std::{iter::FromIterator, vec, vec::Vec};
+use crate::{
+    iop::bookkeeper::{MessageBookkeeper, NameSpace},
+    tracer::TraceInfo,
+    util::le_bits_to_usize,
+};
+use ark_ff::{Field, PrimeField};
+use ark_ldt::domain::Radix2CosetDomain;
+use ark_sponge::CryptographicSponge;
+use ark_std::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    format,
+    iter::FromIterator,
+    string::String,
+    vec,
+    vec::Vec,
+};
 
 use crate::{
-    bcs::transcript::LDTInfo,
+    bcs::transcript::{LDTInfo, TranscriptVersion},
     iop::message::LeavesType::{Custom, UseCodewordDomain},
+    BCSError, Error,
 };
 use tracing::info;
 
@@ -86,6 +101,16 @@ pub struct MessagesCollection<F: PrimeField, O: RoundOracle<F>> {
     pub(crate) virtual_oracles: Vec<Option<VirtualOracleWithInfo<F>>>,
     pub(crate) verifier_messages: Vec<Vec<VerifierMessage<F>>>,
     pub(crate) bookkeeper: MessageBookkeeper,
+    /// Messages of failed checks recorded by `check`.
+    failed_checks: Vec<String>,
+    /// Whether each decision run by `decide_namespace` accepted, keyed by
+    /// namespace id.
+    pub(crate) namespace_decisions: BTreeMap<u64, bool>,
+    /// Version of the transcript the messages are sent in.
+    pub(crate) version: TranscriptVersion,
+    /// If set, every query to a prover round uses these positions instead.
+    #[cfg(feature = "bench")]
+    pub(crate) fixed_queries: Option<Vec<usize>>,
 }
 
 impl<F: PrimeField, O: RoundOracle<F>> MessagesCollection<F, O> {
@@ -100,7 +125,149 @@ impl<F: PrimeField, O: RoundOracle<F>> MessagesCollection<F, O> {
             virtual_oracles,
             verifier_messages,
             bookkeeper,
+            failed_checks: Vec::new(),
+            namespace_decisions: BTreeMap::new(),
+            version: TranscriptVersion::default(),
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
+        }
+    }
+
+    /// Run `decide`, the decision of a subprotocol in `namespace`, and record
+    /// whether it accepted, so that `BCSVerifier::verify_per_namespace` can
+    /// report the subprotocol on its own. The decision rejects if `decide`
+    /// returns `false` or an error, or, when `std` is enabled, if it panics,
+    /// and the verifier continues with its next decision either way. Returns
+    /// whether the decision accepted.
+    pub fn decide_namespace(
+        &mut self,
+        namespace: NameSpace,
+        decide: impl FnOnce(&mut Self) -> Result<bool, Error>,
+    ) -> bool {
+        #[cfg(feature = "std")]
+        let decision = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| decide(self)))
+            .unwrap_or(Ok(false));
+        #[cfg(not(feature = "std"))]
+        let decision = decide(self);
+        let accepted = decision.unwrap_or(false);
+        self.namespace_decisions.insert(namespace.id, accepted);
+        accepted
+    }
+
+    /// Indices of the prover rounds sent in a namespace whose decision is
+    /// rejected by `decide_namespace`, or in one of its subprotocols. A
+    /// rejected decision may have stopped before making all of its queries.
+    pub(crate) fn rounds_of_rejected_decisions(&self) -> BTreeSet<usize> {
+        let mut rounds = BTreeSet::new();
+        let mut namespaces = self
+            .namespace_decisions
+            .iter()
+            .filter(|(_, &accepted)| !accepted)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        while let Some(id) = namespaces.pop() {
+            rounds.extend(
+                self.bookkeeper.messages_store[&id]
+                    .prover_rounds
+                    .iter()
+                    .filter(|round| !round.is_virtual)
+                    .map(|round| round.index),
+            );
+            namespaces.extend(self.bookkeeper.ns_map[&id].iter().copied());
         }
+        rounds
+    }
+
+    /// Record a check made by verifier. If `condition` is false, `message` is
+    /// kept until `finalize_checks`, so that verifier can continue and report
+    /// all failed checks at once instead of stopping at the first one.
+    /// Returns `condition`.
+    pub fn check(&mut self, condition: bool, message: impl Into<String>) -> bool {
+        if !condition {
+            self.failed_checks.push(message.into());
+        }
+        condition
+    }
+
+    /// Return `BCSError::FailedChecks` containing messages of all checks failed
+    /// since last call, or `Ok` if all of them passed.
+    pub fn finalize_checks(&mut self) -> Result<(), Error> {
+        if self.failed_checks.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(BCSError::FailedChecks {
+                messages: ark_std::mem::take(&mut self.failed_checks),
+            }))
+        }
+    }
+
+    /// Check that `relation` holds at each of `positions`, and record each
+    /// position where it fails as a failed check named after `name`, which is
+    /// reported by `finalize_checks`. `relation` reads the messages it needs
+    /// from the given `RelationQuery`, which registers the oracle queries, so
+    /// verifier does not need to query the oracles beforehand. Returns whether
+    /// `relation` holds at all positions.
+    ///
+    /// For example, the relation `a[p] * c == b[p]` between oracle `a` of
+    /// round 0, oracle `b` of round 1 and challenge `c` can be checked by
+    /// ```ignore
+    /// messages.check_relation("product", &positions, |q, p| {
+    ///     q.oracle((namespace, 0), (0, false).into(), p) * q.verifier_element((namespace, 0), 0, 0)
+    ///         == q.oracle((namespace, 1), (0, false).into(), p)
+    /// }, iop_trace!("product"));
+    /// ```
+    pub fn check_relation(
+        &mut self,
+        name: &str,
+        positions: &[usize],
+        mut relation: impl FnMut(&mut RelationQuery<F, O>, usize) -> bool,
+        tracer: TraceInfo,
+    ) -> bool {
+        let mut all_hold = true;
+        for &position in positions {
+            let holds = relation(
+                &mut RelationQuery {
+                    messages: self,
+                    tracer,
+                },
+                position,
+            );
+            all_hold &= self.check(holds, format!("relation {} at position {}", name, position));
+        }
+        all_hold
+    }
+
+    /// Check that short message `short_message_index` of prover round `round`
+    /// in `namespace` equals the oracles of prover round `oracle_round` in
+    /// `namespace` evaluated at `position`, i.e. the leaf at `position`,
+    /// which has one element per oracle with RS codes first. The oracle is
+    /// queried at `position`. If they differ, the failure is recorded as in
+    /// `check` and reported by `finalize_checks`. Returns whether they are
+    /// equal.
+    pub fn verify_short_message_equals_evaluation(
+        &mut self,
+        namespace: NameSpace,
+        round: usize,
+        short_message_index: usize,
+        oracle_round: usize,
+        position: usize,
+        tracer: TraceInfo,
+    ) -> bool {
+        let short_message = self
+            .prover_round((namespace, round))
+            .short_message(short_message_index, tracer)
+            .to_vec();
+        let evaluation = self
+            .prover_round((namespace, oracle_round))
+            .query_point(&[position], tracer)
+            .remove(0);
+        self.check(
+            short_message == evaluation,
+            format!(
+                "short message {} of round {} does not equal round {} at position {}",
+                short_message_index, round, oracle_round, position
+            ),
+        )
     }
 
     /// Given a `MsgRoundRef`, return the corresponding verifier message.
@@ -115,6 +282,110 @@ impl<F: PrimeField, O: RoundOracle<F>> MessagesCollection<F, O> {
         AtProverRound { _self: self, round }
     }
 
+    /// Given a `MsgRoundRef` of a prover round and the index of one of its
+    /// oracles, return a handle to query only that oracle. The round can be
+    /// sent in any namespace, so that a protocol can query oracles committed
+    /// by another namespace. As in
+    /// `Transcript::get_previously_sent_prover_oracle`, `oracle.idx` counts
+    /// oracles with degree bound if `oracle.bounded`, and oracles without
+    /// degree bound otherwise.
+    ///
+    /// # Panics
+    /// Panics if the round does not have the oracle.
+    pub fn referenced_oracle(
+        &mut self,
+        at: impl ToMsgRoundRef,
+        oracle: OracleIndex,
+    ) -> AtReferencedOracle<F, O> {
+        let round = at.to_prover_msg_round_ref(&self.bookkeeper);
+        let info = self.get_prover_round_info(round);
+        let position = if oracle.bounded {
+            assert!(
+                oracle.idx < info.num_reed_solomon_codes_oracles(),
+                "round {} does not have oracle {} with degree bound",
+                round.index,
+                oracle.idx
+            );
+            oracle.idx
+        } else {
+            assert!(
+                oracle.idx < info.num_message_oracles,
+                "round {} does not have oracle {} without degree bound",
+                round.index,
+                oracle.idx
+            );
+            info.num_reed_solomon_codes_oracles() + oracle.idx
+        };
+        AtReferencedOracle {
+            round: AtProverRound { _self: self, round },
+            position,
+        }
+    }
+
+    /// Check that two oracles of the same length are equal, by querying both
+    /// of them at `num_queries` positions squeezed from `sponge`. Each oracle
+    /// is given as a prover round and an oracle index, as in
+    /// `referenced_oracle`, so the two oracles can be sent in different rounds
+    /// with different localization parameters.
+    ///
+    /// Prover does not need to do anything for this check: as for all other
+    /// queries, the answers are recorded into the proof when prover runs
+    /// `query_and_decide`.
+    ///
+    /// # Panics
+    /// Panics if the two oracles have different lengths.
+    pub fn verify_oracle_equality<S: CryptographicSponge>(
+        &mut self,
+        sponge: &mut S,
+        first: (impl ToMsgRoundRef, OracleIndex),
+        second: (impl ToMsgRoundRef, OracleIndex),
+        num_queries: usize,
+        tracer: TraceInfo,
+    ) -> bool {
+        let first_round = first.0.to_prover_msg_round_ref(&self.bookkeeper);
+        let second_round = second.0.to_prover_msg_round_ref(&self.bookkeeper);
+        let length = self.get_prover_round_info(first_round).length;
+        assert_eq!(
+            length,
+            self.get_prover_round_info(second_round).length,
+            "oracles compared for equality should have the same length"
+        );
+        let num_bits = ark_std::log2(length) as usize;
+        let positions = (0..num_queries)
+            .map(|_| le_bits_to_usize(&sponge.squeeze_bits(num_bits)) % length)
+            .collect::<Vec<_>>();
+        let first_answers = self
+            .referenced_oracle(first_round, first.1)
+            .query(&positions, tracer);
+        let second_answers = self
+            .referenced_oracle(second_round, second.1)
+            .query(&positions, tracer);
+        first_answers == second_answers
+    }
+
+    /// Return the elements at logical `positions` of a message oracle sent by
+    /// `Transcript::send_split_oracle`, whose segments are `segments`. Logical
+    /// position `p` is answered by segment `p / segment_length` at position
+    /// `p % segment_length`, and the query is recorded in that segment.
+    ///
+    /// # Panics
+    /// Panics if a position is not less than the total length of the segments.
+    pub fn query_split_oracle(
+        &mut self,
+        segments: &[MsgRoundRef],
+        positions: &[usize],
+        tracer: TraceInfo,
+    ) -> Vec<F> {
+        let segment_length = self.get_prover_round_info(segments[0]).length;
+        positions
+            .iter()
+            .map(|&position| {
+                self.prover_round(segments[position / segment_length])
+                    .query_point(&[position % segment_length], tracer)[0][0]
+            })
+            .collect()
+    }
+
     /// Get metadata of current prover round message.
     pub fn get_prover_round_info(&self, at: impl ToMsgRoundRef) -> ProverRoundMessageInfo {
         let at = at.to_prover_msg_round_ref(&self.bookkeeper);
@@ -151,6 +422,9 @@ impl<F: PrimeField, O: RoundOracle<F>> MessagesCollection<F, O> {
             real_oracles: ark_std::mem::take(&mut self.real_oracles),
             virtual_oracles: ark_std::mem::take(&mut self.virtual_oracles),
             verifier_messages: ark_std::mem::take(&mut self.verifier_messages),
+            failed_checks: Vec::new(),
+            #[cfg(feature = "bench")]
+            fixed_queries: None,
         };
 
         (virtual_round, shadow_self)
@@ -185,8 +459,19 @@ impl<'a, F: PrimeField, O: RoundOracle<F>> AtProverRound<'a, F, O> {
     /// Return the leaves of at `position` of all oracle in this round.
     /// `result[i][j]` is leaf `i` at oracle `j`.
     pub fn query_point(&mut self, positions: &[usize], tracer: TraceInfo) -> Vec<Vec<F>> {
+        #[cfg(feature = "bench")]
+        let fixed_queries = self._self.fixed_queries.clone();
+        #[cfg(feature = "bench")]
+        let positions = fixed_queries.as_deref().unwrap_or(positions);
         let _self = &mut self._self;
         let round = self.round;
+        #[cfg(feature = "logging")]
+        log::trace!(
+            "query point: round {}, virtual {}, positions {:?}",
+            round.index,
+            round.is_virtual,
+            positions
+        );
         if !round.is_virtual {
             info!("Query Real Oracle point at {:?} by {}", positions, tracer);
             return _self.real_oracles[round.index].query(positions);
@@ -208,12 +493,111 @@ impl<'a, F: PrimeField, O: RoundOracle<F>> AtProverRound<'a, F, O> {
         query_result
     }
 
+    /// Query an extension field oracle sent using
+    /// `send_extension_field_oracle_without_degree_bound` at `positions`.
+    /// `first_oracle` is the index of the first base field component of the
+    /// oracle among all oracles of this round. `result[i]` is the element at
+    /// `positions[i]`.
+    pub fn query_extension_field_point<E: Field<BasePrimeField = F>>(
+        &mut self,
+        positions: &[usize],
+        first_oracle: usize,
+        tracer: TraceInfo,
+    ) -> Vec<E> {
+        let degree = E::extension_degree() as usize;
+        self.query_point(positions, tracer)
+            .into_iter()
+            .map(|leaf| {
+                E::from_base_prime_field_elems(&leaf[first_oracle..first_oracle + degree])
+                    .expect("invalid number of extension field components")
+            })
+            .collect()
+    }
+
+    /// Return the leaves at `positions` of all oracles in this round as a
+    /// column-major matrix with dimensions `(num_positions, num_oracles)`:
+    /// element at row `i` and column `j` is oracle `j` at `positions[i]`,
+    /// stored at index `j * num_positions + i` of the returned vector.
+    pub fn query_as_matrix(
+        &mut self,
+        positions: &[usize],
+        tracer: TraceInfo,
+    ) -> (Vec<F>, (usize, usize)) {
+        let leaves = self.query_point(positions, tracer);
+        let num_oracles = self._self.get_prover_round_info(self.round).num_oracles();
+        let matrix = (0..num_oracles)
+            .flat_map(|oracle| leaves.iter().map(move |leaf| leaf[oracle]))
+            .collect();
+        (matrix, (leaves.len(), num_oracles))
+    }
+
+    /// Return the leaves at `position` of all oracle in this round, where
+    /// each position is reduced modulo oracle length first.
+    /// `result[i][j]` is leaf `i` at oracle `j`.
+    pub fn query_point_modular(&mut self, positions: &[usize], tracer: TraceInfo) -> Vec<Vec<F>> {
+        let length = self._self.get_prover_round_info(self.round).length;
+        let positions = positions.iter().map(|&p| p % length).collect::<Vec<_>>();
+        self.query_point(&positions, tracer)
+    }
+
+    /// Return the leaves at `points` of all oracle in this round, where each
+    /// point is an element of `domain`, the domain the oracles are evaluated
+    /// over, and is queried at its index in `domain`. Returns
+    /// `BCSError::PointNotInDomain` if a point is not in `domain`.
+    /// `result[i][j]` is leaf `i` at oracle `j`.
+    ///
+    /// # Panics
+    /// Panics if the size of `domain` is not the oracle length of this round.
+    pub fn query_at_points(
+        &mut self,
+        points: &[F],
+        domain: Radix2CosetDomain<F>,
+        tracer: TraceInfo,
+    ) -> Result<Vec<Vec<F>>, Error> {
+        let length = self._self.get_prover_round_info(self.round).length;
+        assert_eq!(
+            domain.size(),
+            length,
+            "domain size should be the oracle length of the round"
+        );
+        let gen = domain.gen();
+        let positions = points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| -> Result<usize, Error> {
+                let mut element = domain.offset;
+                for position in 0..length {
+                    if element == *point {
+                        return Ok(position);
+                    }
+                    element *= gen;
+                }
+                Err(Box::new(BCSError::PointNotInDomain {
+                    index,
+                    domain_size: length,
+                }))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(self.query_point(&positions, tracer))
+    }
+
     /// Return the queried coset at `coset_index` of all oracles in this round.
     /// `result[i][j][k]` is coset index `i` -> oracle index `j` -> element `k`
     /// in this coset.
     pub fn query_coset(&mut self, positions: &[usize], tracer: TraceInfo) -> CosetQueryResult<F> {
+        #[cfg(feature = "bench")]
+        let fixed_queries = self._self.fixed_queries.clone();
+        #[cfg(feature = "bench")]
+        let positions = fixed_queries.as_deref().unwrap_or(positions);
         let _self = &mut self._self;
         let round = self.round;
+        #[cfg(feature = "logging")]
+        log::trace!(
+            "query coset: round {}, virtual {}, positions {:?}",
+            round.index,
+            round.is_virtual,
+            positions
+        );
         if !round.is_virtual {
             info!("Query Real Oracle coset at {:?} by {}", positions, tracer);
             return _self.real_oracles[round.index].query_coset(positions);
@@ -246,6 +630,76 @@ impl<'a, F: PrimeField, O: RoundOracle<F>> AtProverRound<'a, F, O> {
     }
 }
 
+/// A temporary struct for querying one oracle of a prover round, returned by
+/// `MessagesCollection::referenced_oracle`.
+pub struct AtReferencedOracle<'a, F: PrimeField, O: RoundOracle<F>> {
+    round: AtProverRound<'a, F, O>,
+    /// Position of the oracle among all oracles of the round.
+    position: usize,
+}
+
+impl<'a, F: PrimeField, O: RoundOracle<F>> AtReferencedOracle<'a, F, O> {
+    /// Return the elements of this oracle at `positions`. The queries are
+    /// recorded in the round that sent the oracle, like
+    /// `AtProverRound::query_point`.
+    pub fn query(&mut self, positions: &[usize], tracer: TraceInfo) -> Vec<F> {
+        let position = self.position;
+        self.round
+            .query_point(positions, tracer)
+            .into_iter()
+            .map(|leaf| leaf[position])
+            .collect()
+    }
+
+    /// Return the cosets of this oracle at `coset_indices`. `result[i][k]` is
+    /// coset index `i` -> element `k` in this coset.
+    pub fn query_coset(&mut self, coset_indices: &[usize], tracer: TraceInfo) -> Vec<Vec<F>> {
+        self.round
+            .query_coset(coset_indices, tracer)
+            .at_oracle_index_owned(self.position)
+            .collect()
+    }
+}
+
+/// Messages available to a relation checked by
+/// `MessagesCollection::check_relation`.
+pub struct RelationQuery<'a, F: PrimeField, O: RoundOracle<F>> {
+    messages: &'a mut MessagesCollection<F, O>,
+    tracer: TraceInfo,
+}
+
+impl<'a, F: PrimeField, O: RoundOracle<F>> RelationQuery<'a, F, O> {
+    /// Return the element of oracle `oracle` of prover round `at` at
+    /// `position`, and record the query in that round. `oracle` is given as in
+    /// `MessagesCollection::referenced_oracle`.
+    pub fn oracle(&mut self, at: impl ToMsgRoundRef, oracle: OracleIndex, position: usize) -> F {
+        let tracer = self.tracer;
+        self.messages
+            .referenced_oracle(at, oracle)
+            .query(&[position], tracer)[0]
+    }
+
+    /// Return short message `index` of prover round `at`.
+    pub fn short_message(&mut self, at: impl ToMsgRoundRef, index: usize) -> Vec<F> {
+        let tracer = self.tracer;
+        self.messages
+            .prover_round(at)
+            .short_message(index, tracer)
+            .to_vec()
+    }
+
+    /// Return field element `index` of message `message` of verifier round
+    /// `at`.
+    ///
+    /// # Panics
+    /// Panics if the message does not contain field elements.
+    pub fn verifier_element(&self, at: impl ToMsgRoundRef, message: usize, index: usize) -> F {
+        self.messages.verifier_round(at)[message]
+            .as_field_elements()
+            .expect("relation expects a verifier message of field elements")[index]
+    }
+}
+
 /// The result of a coset query. `result[i][j][k]` is coset index `i` -> oracle
 /// index `j` -> element `k`
 #[repr(transparent)]
@@ -339,6 +793,37 @@ impl<T: Clone> IntoIterator for CosetQueryResult<T> {
     }
 }
 
+/// Localization parameter of an oracle: each merkle tree leaf is a coset of
+/// `2 ^ log_leaf_size` elements.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub struct Localization(usize);
+
+impl Localization {
+    /// Returns the localization parameter `log_leaf_size` for an oracle of
+    /// length `oracle_length`. Returns `BCSError::InvalidOracleLength` if
+    /// `oracle_length` is not a power of two, or
+    /// `BCSError::InvalidLocalization` if `2 ^ log_leaf_size` is larger than
+    /// `oracle_length`.
+    pub fn new(log_leaf_size: usize, oracle_length: usize) -> Result<Self, Error> {
+        if !oracle_length.is_power_of_two() {
+            return Err(Box::new(BCSError::InvalidOracleLength { oracle_length }));
+        }
+        // `oracle_length` is a power of two, so this is its exact `log2`
+        if log_leaf_size > oracle_length.trailing_zeros() as usize {
+            return Err(Box::new(BCSError::InvalidLocalization {
+                localization_parameter: log_leaf_size,
+                oracle_length,
+            }));
+        }
+        Ok(Self(log_leaf_size))
+    }
+
+    /// Localization parameter as a bare `usize`.
+    pub fn get(self) -> usize {
+        self.0
+    }
+}
+
 /// If the leaf is coset, the info also contains information about the stride of
 /// coset, and each leaf will be a flattened 2d array where first axis is oracle
 /// index, and second axis is leaf positions.
@@ -370,6 +855,9 @@ pub struct ProverRoundMessageInfo {
     /// parameter is 1, then the serialized merkle tree leaves will be
     /// `[H(1,5), H(2,6), H(3,7), H(4,8)]`.
     pub localization_parameter: usize,
+    /// Whether each merkle tree leaf of this round is prefixed with a salt
+    /// squeezed from the sponge before hashing.
+    pub hiding: bool,
 }
 
 /// Builds a `ProverRoundMessageInfo` from a `ProverRoundMessageInfoBuilder`.
@@ -380,17 +868,18 @@ pub struct ProverRoundMessageInfoBuilder {
     length: usize,
     localization_parameter: usize,
     leaves_type: LeavesType,
+    hiding: bool,
 }
 
 impl ProverRoundMessageInfo {
     /// Create a builder for prover round message info.
     /// * `leaves_options`: `UseCodewordDomain | Custom`
     /// * `length`: length of codeword
-    /// * `localization_parameter`: localization parameter
+    /// * `localization`: localization parameter
     pub fn make(
         leaves_options: LeavesType,
         length: usize,
-        localization_parameter: usize,
+        localization: Localization,
     ) -> ProverRoundMessageInfoBuilder {
         ProverRoundMessageInfoBuilder {
             reed_solomon_code_degree_bound: vec![],
@@ -398,7 +887,8 @@ impl ProverRoundMessageInfo {
             num_short_messages: 0,
             leaves_type: leaves_options,
             length,
-            localization_parameter,
+            localization_parameter: localization.get(),
+            hiding: false,
         }
     }
 
@@ -410,16 +900,16 @@ impl ProverRoundMessageInfo {
         F: PrimeField,
     {
         let length = transcript.codeword_domain().size();
-        let localization_parameter = transcript.codeword_localization_parameter();
-        Self::make(UseCodewordDomain, length, localization_parameter)
+        let localization = Localization(transcript.codeword_localization_parameter());
+        Self::make(UseCodewordDomain, length, localization)
     }
 
     /// Builds prover round message info using custom length and localization.
     pub fn new_using_custom_length_and_localization(
         length: usize,
-        localization_parameter: usize,
+        localization: Localization,
     ) -> ProverRoundMessageInfoBuilder {
-        Self::make(Custom, length, localization_parameter)
+        Self::make(Custom, length, localization)
     }
 }
 
@@ -448,6 +938,13 @@ impl ProverRoundMessageInfoBuilder {
         self
     }
 
+    /// Whether the round is sent with `PendingProverMessage::with_hiding`.
+    #[must_use]
+    pub fn with_hiding(mut self, hiding: bool) -> Self {
+        self.hiding = hiding;
+        self
+    }
+
     /// Builds a `ProverRoundMessageInfo` from this builder.
     pub fn build(self) -> ProverRoundMessageInfo {
         ProverRoundMessageInfo {
@@ -457,10 +954,70 @@ impl ProverRoundMessageInfoBuilder {
             leaves_type: self.leaves_type,
             length: self.length,
             localization_parameter: self.localization_parameter,
+            hiding: self.hiding,
+        }
+    }
+
+    /// Builds a `ProverRoundMessageInfo` from this builder, and checks that the
+    /// oracle length is a power of two and `2 ^ localization_parameter` is
+    /// at most the oracle length, if the round has oracles.
+    ///
+    /// Without this check, those mistakes are only caught when the verifier
+    /// receives the round.
+    pub fn try_build(self) -> Result<ProverRoundMessageInfo, Error> {
+        let info = self.build();
+        if info.num_oracles() > 0 {
+            if !info.length.is_power_of_two() {
+                return Err(Box::new(BCSError::InvalidOracleLength {
+                    oracle_length: info.length,
+                }));
+            }
+            Localization::new(info.localization_parameter, info.length)?;
+        }
+        Ok(info)
+    }
+}
+
+/// Degree bound of an oracle, either absolute or as a rate of the codeword
+/// domain size. For example, over a codeword domain of size 128,
+/// `Rate(1, 16)` and `Absolute(8)` are the same bound, but only `Rate(1, 16)`
+/// keeps the rate when the domain is resized.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum DegreeBound {
+    /// Degree bound independent of the domain.
+    Absolute(usize),
+    /// `Rate(numerator, denominator)` is the degree bound
+    /// `domain_size * numerator / denominator`.
+    Rate(usize, usize),
+}
+
+impl DegreeBound {
+    /// Resolve to an absolute degree bound over a codeword domain of
+    /// `domain_size` elements. Returns `BCSError::InvalidRate` if the rate is
+    /// not below 1, or does not give an integer degree bound.
+    pub fn resolve(self, domain_size: usize) -> Result<usize, Error> {
+        match self {
+            DegreeBound::Absolute(degree_bound) => Ok(degree_bound),
+            DegreeBound::Rate(numerator, denominator) => {
+                if numerator >= denominator || (domain_size * numerator) % denominator != 0 {
+                    return Err(Box::new(BCSError::InvalidRate {
+                        numerator,
+                        denominator,
+                        domain_size,
+                    }));
+                }
+                Ok(domain_size * numerator / denominator)
+            },
         }
     }
 }
 
+impl From<usize> for DegreeBound {
+    fn from(degree_bound: usize) -> Self {
+        DegreeBound::Absolute(degree_bound)
+    }
+}
+
 /// Specify the length and localization parameter of an oracle.
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
 pub enum LeavesType {
@@ -492,6 +1049,10 @@ pub enum VerifierMessage<F: PrimeField> {
     Bits(Vec<bool>),
     /// bytes
     Bytes(Vec<u8>),
+    /// a permutation of `0..n`
+    Permutation(Vec<usize>),
+    /// number of bytes squeezed and discarded by `advance_sponge`
+    Advance(usize),
 }
 
 impl<F: PrimeField> VerifierMessage<F> {
@@ -522,4 +1083,58 @@ impl<F: PrimeField> VerifierMessage<F> {
             None
         }
     }
+
+    /// If `self` contains a permutation, return the permutation. Otherwise
+    /// return `None`.
+    pub fn try_into_permutation(self) -> Option<Vec<usize>> {
+        if let Self::Permutation(x) = self {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the field elements in `self`, or
+    /// `BCSError::UnexpectedVerifierMessageType` if `self` does not contain
+    /// field elements.
+    pub fn as_field_elements(&self) -> Result<&[F], Error> {
+        if let Self::FieldElements(x) = self {
+            Ok(x)
+        } else {
+            Err(self.unexpected_type("field elements"))
+        }
+    }
+
+    /// Returns the bytes in `self`, or
+    /// `BCSError::UnexpectedVerifierMessageType` if `self` does not contain
+    /// bytes.
+    pub fn as_bytes(&self) -> Result<&[u8], Error> {
+        if let Self::Bytes(x) = self {
+            Ok(x)
+        } else {
+            Err(self.unexpected_type("bytes"))
+        }
+    }
+
+    /// Returns the bits in `self`, or
+    /// `BCSError::UnexpectedVerifierMessageType` if `self` does not contain
+    /// bits.
+    pub fn as_bits(&self) -> Result<&[bool], Error> {
+        if let Self::Bits(x) = self {
+            Ok(x)
+        } else {
+            Err(self.unexpected_type("bits"))
+        }
+    }
+
+    fn unexpected_type(&self, expected: &'static str) -> Error {
+        let found = match self {
+            Self::FieldElements(_) => "field elements",
+            Self::Bits(_) => "bits",
+            Self::Bytes(_) => "bytes",
+            Self::Permutation(_) => "permutation",
+            Self::Advance(_) => "advance",
+        };
+        Box::new(BCSError::UnexpectedVerifierMessageType { expected, found })
+    }
 }
@@ -30,6 +30,14 @@ impl ProverParam for () {
     }
 }
 
+impl<A: ProverParam, B: ProverParam> ProverParam for (A, B) {
+    type VerifierParameter = (A::VerifierParameter, B::VerifierParameter);
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        (self.0.to_verifier_param(), self.1.to_verifier_param())
+    }
+}
+
 /// Parameter used by the IOP Verifier.
 pub trait VerifierParam: Clone + Debug {}
 impl<T: Clone + Debug> VerifierParam for T {}
@@ -4,7 +4,10 @@
 
 use ark_ff::PrimeField;
 use ark_ldt::domain::Radix2CosetDomain;
-use ark_std::{collections::BTreeSet, mem::take};
+use ark_std::{
+    collections::{BTreeMap, BTreeSet},
+    mem::take,
+};
 
 use super::message::{MessagesCollection, ProverRoundMessageInfo};
 use crate::{
@@ -42,6 +45,15 @@ pub trait RoundOracle<F: PrimeField>: Sized {
         coset_query_response_to_point_query_response(queried_coset, element_index_in_coset)
     }
 
+    /// Return the leaves at `position` of all oracle, where each position is
+    /// reduced modulo oracle length first. This allows querying positions like
+    /// `p + length / 2` without reducing them manually.
+    fn query_modular(&mut self, position: &[usize]) -> Vec<Vec<F>> {
+        let length = self.oracle_length();
+        let position = position.iter().map(|&p| p % length).collect::<Vec<_>>();
+        self.query(&position)
+    }
+
     /// Return the queried coset at `coset_index` of all oracles.
     /// `result[i][j][k]` is coset index `i` -> oracle index `j` -> element `k`
     /// in this coset.
@@ -69,6 +81,94 @@ pub trait RoundOracle<F: PrimeField>: Sized {
     fn get_degree_bound(&self) -> Vec<usize> {
         self.get_info().reed_solomon_code_degree_bound
     }
+
+    /// Return the cosets queried so far as `(coset_index, coset)` pairs, in
+    /// query order. `coset[j][k]` is oracle index `j` -> element `k` in this
+    /// coset.
+    fn fetched_cosets(&self) -> Vec<(usize, Vec<Vec<F>>)>;
+
+    /// Return a read-only view of the cosets queried so far, as if the oracles
+    /// were committed with localization parameter `localization_parameter`.
+    /// For example, `at_localization(0)` allows reading single elements of
+    /// the fetched cosets. The view does not record queries and does not
+    /// change the commitment.
+    ///
+    /// # Panics
+    /// Panics if `localization_parameter` is larger than the localization
+    /// parameter of this round.
+    fn at_localization(&self, localization_parameter: usize) -> LocalizedOracleView<F> {
+        let info = self.get_info();
+        assert!(
+            localization_parameter <= info.localization_parameter,
+            "view localization parameter should not exceed the committed one"
+        );
+        LocalizedOracleView {
+            fetched_cosets: self.fetched_cosets().into_iter().collect(),
+            num_cosets: info.length >> info.localization_parameter,
+            committed_localization: info.localization_parameter,
+            localization: localization_parameter,
+        }
+    }
+}
+
+/// A read-only view of the cosets fetched from a round oracle, as if the
+/// oracles were committed with a smaller localization parameter. Returned by
+/// `RoundOracle::at_localization`.
+#[derive(Clone, Debug)]
+pub struct LocalizedOracleView<F: PrimeField> {
+    /// Fetched cosets of the committed oracles, by coset index.
+    fetched_cosets: BTreeMap<usize, Vec<Vec<F>>>,
+    /// Number of cosets of the committed oracles.
+    num_cosets: usize,
+    /// Localization parameter of the committed oracles.
+    committed_localization: usize,
+    /// Localization parameter of this view.
+    localization: usize,
+}
+
+impl<F: PrimeField> LocalizedOracleView<F> {
+    /// Return the elements at `positions` of all oracles, or `None` if a
+    /// position is in a coset that has not been fetched. `result[i][j]` is
+    /// oracle `j` at `positions[i]`.
+    pub fn query(&self, positions: &[usize]) -> Option<Vec<Vec<F>>> {
+        positions
+            .iter()
+            .map(|&position| {
+                let coset = self.fetched_cosets.get(&(position % self.num_cosets))?;
+                let element_index = position / self.num_cosets;
+                Some(coset.iter().map(|oracle| oracle[element_index]).collect())
+            })
+            .collect()
+    }
+
+    /// Return the cosets at `coset_indices` in the localization of this view,
+    /// or `None` if a coset is not part of a fetched coset.
+    /// `result[i][j][k]` is coset index `i` -> oracle index `j` -> element `k`
+    /// in this coset.
+    pub fn query_coset(&self, coset_indices: &[usize]) -> Option<CosetQueryResult<F>> {
+        // a coset of this view is a strided part of a committed coset
+        let stride = 1 << (self.committed_localization - self.localization);
+        let num_view_cosets = self.num_cosets * stride;
+        let cosets = coset_indices
+            .iter()
+            .map(|&coset_index| {
+                let coset_index = coset_index % num_view_cosets;
+                let coset = self.fetched_cosets.get(&(coset_index % self.num_cosets))?;
+                let offset = coset_index / self.num_cosets;
+                Some(
+                    coset
+                        .iter()
+                        .map(|oracle| {
+                            (0..1 << self.localization)
+                                .map(|k| oracle[offset + k * stride])
+                                .collect()
+                        })
+                        .collect(),
+                )
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(cosets.into())
+    }
 }
 
 /// Given point indices, return coset index and element index in coset.
@@ -130,6 +230,12 @@ pub struct RecordingRoundOracle<F: PrimeField> {
     pub(crate) message_oracles: Vec<Vec<F>>,
     /// Store the non-oracle IP messages in this round
     pub(crate) short_messages: Vec<Vec<F>>,
+    /// Whether the oracles are committed inline, so that all cosets are sent
+    /// in the clear; see `TranscriptVersion::V4`.
+    pub(crate) inline: bool,
+    /// Salt prefixed to each merkle tree leaf, in coset order, if the round
+    /// is hiding.
+    pub(crate) salts: Vec<F>,
 }
 
 impl<F: PrimeField> RecordingRoundOracle<F> {
@@ -146,16 +252,30 @@ impl<F: PrimeField> RecordingRoundOracle<F> {
         &self.short_messages
     }
 
-    /// Return a succinct oracle, which only contains queried responses.
+    /// Return a succinct oracle, which only contains queried responses and
+    /// their salts, or all cosets in coset order if the oracles are committed
+    /// inline.
     pub fn get_succinct(&self) -> SuccinctRoundMessage<F> {
-        let queried_cosets = self
-            .queried_coset_index
-            .iter()
-            .map(|coset_index| self.all_coset_elements[*coset_index].clone())
-            .collect::<Vec<_>>();
+        let queried_cosets = if self.inline {
+            self.all_coset_elements.clone()
+        } else {
+            self.queried_coset_index
+                .iter()
+                .map(|coset_index| self.all_coset_elements[*coset_index].clone())
+                .collect::<Vec<_>>()
+        };
+        let queried_salts = if self.salts.is_empty() {
+            Vec::new()
+        } else {
+            self.queried_coset_index
+                .iter()
+                .map(|coset_index| self.salts[*coset_index])
+                .collect()
+        };
         SuccinctRoundMessage {
             queried_cosets,
             short_messages: self.short_messages.clone(),
+            queried_salts,
         }
     }
 }
@@ -188,10 +308,21 @@ impl<F: PrimeField> RoundOracle<F> for RecordingRoundOracle<F> {
     fn get_info(&self) -> ProverRoundMessageInfo {
         self.info.clone()
     }
+
+    fn fetched_cosets(&self) -> Vec<(usize, Vec<Vec<F>>)> {
+        let num_cosets = self.all_coset_elements.len();
+        self.queried_coset_index
+            .iter()
+            .map(|coset_index| {
+                let coset_index = coset_index % num_cosets;
+                (coset_index, self.all_coset_elements[coset_index].clone())
+            })
+            .collect()
+    }
 }
 
 /// Succinct Round message that is going to be included in the proof.
-#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SuccinctRoundMessage<F: PrimeField> {
     /// Queried cosets. Axes `[query order, oracle index (RS-code first),
     /// element position in coset]`
@@ -199,6 +330,9 @@ pub struct SuccinctRoundMessage<F: PrimeField> {
     // note that we do not store query position here, as they will be calculated in verifier
     /// Store the non-oracle IP messages in this round
     pub short_messages: Vec<Vec<F>>,
+    /// Salt of the merkle tree leaf of each queried coset, in query order.
+    /// Empty if the round is not hiding.
+    pub queried_salts: Vec<F>,
 }
 
 impl<F: PrimeField> SuccinctRoundMessage<F> {
@@ -210,6 +344,16 @@ impl<F: PrimeField> SuccinctRoundMessage<F> {
             underlying_message: self,
             coset_queries: Vec::new(),
             current_query_pos: 0,
+            inline: false,
+        }
+    }
+
+    /// Like `get_view`, but `self` contains all cosets in coset order because
+    /// the oracles are committed inline.
+    pub(crate) fn get_inline_view(&self, info: ProverRoundMessageInfo) -> SuccinctRoundOracle<F> {
+        SuccinctRoundOracle {
+            inline: true,
+            ..self.get_view(info)
         }
     }
 }
@@ -224,6 +368,9 @@ pub struct SuccinctRoundOracle<'a, F: PrimeField> {
     /// Supposed queries of the verifier in order.
     pub coset_queries: Vec<usize>,
     current_query_pos: usize,
+    /// Whether `underlying_message` contains all cosets in coset order, and
+    /// is queried by coset index.
+    pub(crate) inline: bool,
 }
 
 impl<'a, F: PrimeField> RoundOracle<F> for SuccinctRoundOracle<'a, F> {
@@ -233,6 +380,19 @@ impl<'a, F: PrimeField> RoundOracle<F> for SuccinctRoundOracle<'a, F> {
 
     fn query_coset_without_tracer(&mut self, coset_index: &[usize]) -> CosetQueryResult<F> {
         self.coset_queries.extend_from_slice(coset_index);
+        if self.inline {
+            let num_cosets = self.info.length >> self.info.localization_parameter;
+            return coset_index
+                .iter()
+                .map(|coset_index| {
+                    self.underlying_message
+                        .queried_cosets
+                        .get(coset_index % num_cosets)
+                        .expect("inline oracle has too few cosets")
+                        .clone()
+                })
+                .collect();
+        }
         assert!(
             self.current_query_pos + coset_index.len()
                 <= self.underlying_message.queried_cosets.len(),
@@ -256,6 +416,84 @@ impl<'a, F: PrimeField> RoundOracle<F> for SuccinctRoundOracle<'a, F> {
     fn get_info(&self) -> ProverRoundMessageInfo {
         self.info.clone()
     }
+
+    fn fetched_cosets(&self) -> Vec<(usize, Vec<Vec<F>>)> {
+        let num_cosets = self.info.length >> self.info.localization_parameter;
+        if self.inline {
+            return self
+                .coset_queries
+                .iter()
+                .map(|coset_index| {
+                    let coset_index = coset_index % num_cosets;
+                    (
+                        coset_index,
+                        self.underlying_message.queried_cosets[coset_index].clone(),
+                    )
+                })
+                .collect();
+        }
+        self.coset_queries
+            .iter()
+            .zip(self.underlying_message.queried_cosets.iter())
+            .map(|(coset_index, coset)| (coset_index % num_cosets, coset.clone()))
+            .collect()
+    }
+}
+
+/// A round oracle without any prover message, which answers every query with
+/// zeros and records the queried cosets. Used to count the queries of a
+/// verifier without a proof. Short messages are empty.
+pub(crate) struct CountingRoundOracle<F: PrimeField> {
+    pub(crate) info: ProverRoundMessageInfo,
+    /// Queried cosets in order.
+    pub(crate) coset_queries: Vec<usize>,
+    short_messages: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> CountingRoundOracle<F> {
+    pub(crate) fn new(info: ProverRoundMessageInfo) -> Self {
+        let short_messages = vec![Vec::new(); info.num_short_messages];
+        Self {
+            info,
+            coset_queries: Vec::new(),
+            short_messages,
+        }
+    }
+
+    fn zero_coset(&self) -> Vec<Vec<F>> {
+        vec![vec![F::zero(); 1 << self.info.localization_parameter]; self.info.num_oracles()]
+    }
+}
+
+impl<F: PrimeField> RoundOracle<F> for CountingRoundOracle<F> {
+    fn get_short_message(&self, index: usize) -> &Vec<F> {
+        &self.short_messages[index]
+    }
+
+    fn query_coset_without_tracer(&mut self, coset_index: &[usize]) -> CosetQueryResult<F> {
+        self.coset_queries.extend_from_slice(coset_index);
+        coset_index.iter().map(|_| self.zero_coset()).collect()
+    }
+
+    fn num_reed_solomon_codes_oracles(&self) -> usize {
+        self.info.reed_solomon_code_degree_bound.len()
+    }
+
+    fn oracle_length(&self) -> usize {
+        self.info.length
+    }
+
+    fn get_info(&self) -> ProverRoundMessageInfo {
+        self.info.clone()
+    }
+
+    fn fetched_cosets(&self) -> Vec<(usize, Vec<Vec<F>>)> {
+        let num_cosets = self.info.length >> self.info.localization_parameter;
+        self.coset_queries
+            .iter()
+            .map(|coset_index| (coset_index % num_cosets, self.zero_coset()))
+            .collect()
+    }
 }
 
 /// A virtual oracle who make query to other virtual or non-virtual oracles.
@@ -378,8 +616,9 @@ impl<F: PrimeField> VirtualOracleWithInfo<F> {
 }
 
 /// evaluator for virtual oracle
-/// It is enforced that implementors do not contain any reference with lifetime.
-pub trait VirtualOracle<F: PrimeField>: 'static {
+/// It is enforced that implementors do not contain any reference with lifetime,
+/// and can be sent to another thread together with their transcript.
+pub trait VirtualOracle<F: PrimeField>: Send + 'static {
     /// query constituent oracles as a message round handle, and the indices of
     /// oracles needed in that round
     fn constituent_oracle_handles(&self) -> Vec<(MsgRoundRef, Vec<OracleIndex>)>;
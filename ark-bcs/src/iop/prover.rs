@@ -2,6 +2,7 @@ use crate::{bcs::transcript::Transcript, iop::ProverParam};
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
 use ark_ff::PrimeField;
 use ark_sponge::{Absorb, CryptographicSponge};
+use ark_std::marker::PhantomData;
 
 use super::bookkeeper::NameSpace;
 
@@ -30,3 +31,57 @@ pub trait IOPProver<F: PrimeField + Absorb> {
     where
         MT::InnerDigest: Absorb;
 }
+
+/// Prover running `A` and then `B` in two child namespaces of its namespace,
+/// created in this order. Parameters and inputs are pairs of the ones of `A`
+/// and `B`. Verify using `SequentialVerifier<A, B>`.
+///
+/// `B` runs after all rounds of `A` are sent, but does not know the round
+/// references of `A`. If `B` needs to query oracles of `A`, compose them by
+/// hand instead.
+pub struct SequentialProver<A, B> {
+    _protocols: PhantomData<(A, B)>,
+}
+
+impl<F, A, B> IOPProver<F> for SequentialProver<A, B>
+where
+    F: PrimeField + Absorb,
+    A: IOPProver<F>,
+    B: IOPProver<F>,
+    A::PublicInput: Sized,
+    B::PublicInput: Sized,
+    A::PrivateInput: Sized,
+    B::PrivateInput: Sized,
+{
+    type ProverParameter = (A::ProverParameter, B::ProverParameter);
+    type PublicInput = (A::PublicInput, B::PublicInput);
+    type PrivateInput = (A::PrivateInput, B::PrivateInput);
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), crate::Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let namespace_a = transcript.new_namespace(namespace, iop_trace!("sequential first"));
+        A::prove(
+            namespace_a,
+            &public_input.0,
+            &private_input.0,
+            transcript,
+            &prover_parameter.0,
+        )?;
+        let namespace_b = transcript.new_namespace(namespace, iop_trace!("sequential second"));
+        B::prove(
+            namespace_b,
+            &public_input.1,
+            &private_input.1,
+            transcript,
+            &prover_parameter.1,
+        )
+    }
+}
@@ -1,14 +1,19 @@
 use ark_ff::PrimeField;
-use ark_sponge::{Absorb, CryptographicSponge};
+use ark_sponge::{Absorb, CryptographicSponge, FieldElementSize};
+use ark_std::{boxed::Box, collections::BTreeSet, marker::PhantomData, vec::Vec};
 
 use crate::{
     bcs::simulation_transcript::SimulationTranscript,
     iop::{message::MessagesCollection, prover::IOPProver, ProverParam, VerifierParam},
-    Error,
+    util::le_bits_to_usize,
+    BCSError, Error,
 };
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
 
-use super::{bookkeeper::NameSpace, oracles::RoundOracle};
+use super::{
+    bookkeeper::{BookkeeperContainer, NameSpace},
+    oracles::RoundOracle,
+};
 
 /// The verifier for public coin IOP has two phases.  This is intended to be
 /// used as an endpoint protocol. Any subprotocol does not need to implement
@@ -62,6 +67,15 @@ pub trait IOPVerifier<S: CryptographicSponge, F: PrimeField + Absorb> {
         sponge: &mut S,
         transcript_messages: &mut MessagesCollection<F, O>,
     ) -> Result<Self::VerifierOutput, Error>;
+
+    /// Number of LDT queries set by `verifier_parameter`, which replaces the
+    /// number of queries in LDT parameters when it is `Some`, so that the
+    /// same verifier can run at different security levels by changing only
+    /// its parameter. Prover uses the value of the verifier parameter derived
+    /// from its prover parameter. Returns `None` by default.
+    fn ldt_num_queries(_verifier_parameter: &Self::VerifierParameter) -> Option<usize> {
+        None
+    }
 }
 
 /// `IOPVerifierForProver` is an auto-implemented trait. User does not
@@ -95,3 +109,175 @@ where
     >,
 {
 }
+
+/// Verifier of `SequentialProver<A, B>`, which verifies `A` and then `B` in two
+/// child namespaces of its namespace, and outputs the pair of their outputs.
+/// Parameters and public inputs are pairs of the ones of `A` and `B`.
+pub struct SequentialVerifier<A, B> {
+    _protocols: PhantomData<(A, B)>,
+}
+
+impl<S, F, A, B> IOPVerifier<S, F> for SequentialVerifier<A, B>
+where
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+    A: IOPVerifier<S, F>,
+    B: IOPVerifier<S, F>,
+    A::PublicInput: Sized,
+    B::PublicInput: Sized,
+{
+    type VerifierOutput = (A::VerifierOutput, B::VerifierOutput);
+    type VerifierParameter = (A::VerifierParameter, B::VerifierParameter);
+    type PublicInput = (A::PublicInput, B::PublicInput);
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let namespace_a = transcript.new_namespace(namespace, iop_trace!("sequential first"));
+        A::register_iop_structure(namespace_a, transcript, &verifier_parameter.0);
+        let namespace_b = transcript.new_namespace(namespace, iop_trace!("sequential second"));
+        B::register_iop_structure(namespace_b, transcript, &verifier_parameter.1);
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        verifier_parameter: &Self::VerifierParameter,
+        public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let namespace_a = transcript_messages.get_subprotocol_namespace(namespace, 0);
+        let namespace_b = transcript_messages.get_subprotocol_namespace(namespace, 1);
+        let output_a = A::query_and_decide(
+            namespace_a,
+            &verifier_parameter.0,
+            &public_input.0,
+            sponge,
+            transcript_messages,
+        )?;
+        let output_b = B::query_and_decide(
+            namespace_b,
+            &verifier_parameter.1,
+            &public_input.1,
+            sponge,
+            transcript_messages,
+        )?;
+        Ok((output_a, output_b))
+    }
+
+    /// The larger number of LDT queries set by `A` and `B`, so that LDT is
+    /// at least as sound as both of them require.
+    fn ldt_num_queries(verifier_parameter: &Self::VerifierParameter) -> Option<usize> {
+        A::ldt_num_queries(&verifier_parameter.0).max(B::ldt_num_queries(&verifier_parameter.1))
+    }
+}
+
+/// Squeeze a verifier challenge in query and decision phase that depends on
+/// `query_answers`, the answers of previous queries in this phase. This allows
+/// protocols whose later queries adapt to earlier query answers.
+///
+/// The answers are absorbed into `sponge` before squeezing. Prover runs the
+/// same `query_and_decide` on the same answers when generating the proof, so
+/// verifier derives the same challenge. Because the answers are bound by
+/// merkle roots absorbed in commit phase, prover cannot choose them after
+/// seeing the challenge.
+pub fn squeeze_query_phase_challenge<S: CryptographicSponge, F: PrimeField + Absorb>(
+    sponge: &mut S,
+    query_answers: &[Vec<F>],
+    field_size: &[FieldElementSize],
+) -> Vec<F> {
+    query_answers
+        .iter()
+        .for_each(|answer| sponge.absorb(answer));
+    sponge.squeeze_field_elements_with_sizes(field_size)
+}
+
+/// Returns a number of queries in `min_queries..=max_queries` derived from
+/// `challenge`, so that `query_and_decide` can make a number of queries chosen
+/// by a verifier message instead of a fixed one. Prover runs the same
+/// `query_and_decide` when generating the proof, so the proof contains exactly
+/// the answers of the queries made.
+///
+/// The count is close to uniform, so the expected number of queries is about
+/// `(min_queries + max_queries) / 2`. If each query catches a cheating prover
+/// with probability `p`, the soundness error of the round is the average of
+/// `(1 - p) ^ k` over all counts `k`, which is at most
+/// `(1 - p) ^ min_queries`.
+///
+/// # Panics
+/// Panics if `min_queries > max_queries`.
+pub fn num_queries_from_challenge<F: PrimeField>(
+    challenge: F,
+    min_queries: usize,
+    max_queries: usize,
+) -> usize {
+    assert!(
+        min_queries <= max_queries,
+        "minimum number of queries should not exceed the maximum"
+    );
+    let num_choices = (max_queries - min_queries) as u64 + 1;
+    // the lowest limb has 64 bits, so the bias of this reduction is negligible
+    min_queries + (challenge.into_bigint().as_ref()[0] % num_choices) as usize
+}
+
+/// Samples the positions queried by a verifier from the sponge, so that
+/// protocols and LDT can choose how their queries are distributed. Prover
+/// samples from the same sponge when generating the proof, so a sampler
+/// should be deterministic given the sponge state.
+pub trait QuerySampler {
+    /// Squeeze `num` positions in `0..domain_size` from `sponge`, where
+    /// `domain_size` is a power of two.
+    fn sample_positions<S: CryptographicSponge>(
+        &self,
+        sponge: &mut S,
+        num: usize,
+        domain_size: usize,
+    ) -> Result<Vec<usize>, Error>;
+
+    /// Whether sampled positions are distinct, so that at most `domain_size`
+    /// positions can be sampled.
+    fn samples_distinct_positions(&self) -> bool {
+        false
+    }
+}
+
+/// Samples positions uniformly without replacement, by squeezing positions
+/// until `num` of them are distinct. Positions are returned in the order they
+/// are first squeezed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UniformUniqueSampler;
+
+impl QuerySampler for UniformUniqueSampler {
+    /// Returns `BCSError::TooManyDistinctQueries` if `num > domain_size`.
+    fn sample_positions<S: CryptographicSponge>(
+        &self,
+        sponge: &mut S,
+        num: usize,
+        domain_size: usize,
+    ) -> Result<Vec<usize>, Error> {
+        if num > domain_size {
+            return Err(Box::new(BCSError::TooManyDistinctQueries {
+                num_queries: num,
+                domain_size,
+            }));
+        }
+        let log_domain_size = ark_std::log2(domain_size) as usize;
+        let mut sampled = BTreeSet::new();
+        let mut positions = Vec::with_capacity(num);
+        while positions.len() < num {
+            let position = le_bits_to_usize(&sponge.squeeze_bits(log_domain_size));
+            if sampled.insert(position) {
+                positions.push(position);
+            }
+        }
+        Ok(positions)
+    }
+
+    fn samples_distinct_positions(&self) -> bool {
+        true
+    }
+}
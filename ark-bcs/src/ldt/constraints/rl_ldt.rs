@@ -3,9 +3,12 @@ use crate::{
     iop::{
         bookkeeper::{BookkeeperContainer, NameSpace},
         constraints::message::MessagesCollectionVar,
-        message::{MsgRoundRef, ProverRoundMessageInfo},
+        message::{Localization, MsgRoundRef, ProverRoundMessageInfo},
+    },
+    ldt::{
+        constraints::LDTWithGadget,
+        rl_ldt::{CoefficientStrategy, LinearCombinationLDT, QueryStrategy},
     },
-    ldt::{constraints::LDTWithGadget, rl_ldt::LinearCombinationLDT},
 };
 use ark_crypto_primitives::merkle_tree::{constraints::ConfigGadget, Config};
 use ark_ff::PrimeField;
@@ -23,7 +26,9 @@ use ark_sponge::{
 };
 use ark_std::vec::Vec;
 
-impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F> {
+/// The gadget re-derives query positions in the constraint system, so it only
+/// supports samplers of `QueryStrategy`.
+impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F, QueryStrategy> {
     fn register_iop_structure_var<MT, MTG, S>(
         namespace: NameSpace,
         param: &Self::LDTParameters,
@@ -37,7 +42,9 @@ impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F> {
         MT::InnerDigest: Absorb,
         MTG::InnerDigest: AbsorbGadget<F>,
     {
-        transcript.squeeze_verifier_field_elements(num_rs_oracles)?;
+        transcript.squeeze_verifier_field_elements(
+            param.coefficient_strategy.num_challenges(num_rs_oracles),
+        )?;
         transcript.submit_verifier_current_round(namespace, iop_trace!());
 
         let mut current_domain = param.fri_parameters.domain;
@@ -57,7 +64,8 @@ impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F> {
                         namespace,
                         ProverRoundMessageInfo::new_using_custom_length_and_localization(
                             next_domain.size(),
-                            localization_next as usize,
+                            Localization::new(localization_next as usize, next_domain.size())
+                                .expect("invalid FRI localization parameters"),
                         )
                         .with_num_message_oracles(1)
                         .build(),
@@ -75,9 +83,12 @@ impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F> {
         transcript.submit_verifier_current_round(namespace, iop_trace!());
         transcript.receive_prover_current_round(
             namespace,
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(0, 0)
-                .with_num_short_messages(1)
-                .build(),
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
             iop_trace!("LDT final polynomial"),
         )?;
 
@@ -97,15 +108,51 @@ impl<F: PrimeField + Absorb> LDTWithGadget<F> for LinearCombinationLDT<F> {
         let codeword_log_num_cosets = param.fri_parameters.domain.dim()
             - param.fri_parameters.localization_parameters[0] as usize;
 
-        let query_indices = (0..param.num_queries)
-            .map(|_| sponge.squeeze_bits(codeword_log_num_cosets))
-            .collect::<Result<Vec<_>, _>>()?;
+        let query_indices = match param.query_sampler {
+            QueryStrategy::Independent => (0..param.num_queries)
+                .map(|_| sponge.squeeze_bits(codeword_log_num_cosets))
+                .collect::<Result<Vec<_>, _>>()?,
+            QueryStrategy::CosetStructured => {
+                let base = sponge.squeeze_bits(codeword_log_num_cosets)?;
+                QueryStrategy::coset_offsets(codeword_log_num_cosets, param.num_queries)
+                    .into_iter()
+                    .map(|offset| {
+                        // little-endian bits of `base ^ offset`
+                        base.iter()
+                            .enumerate()
+                            .map(|(pos, bit)| bit.xor(&Boolean::constant((offset >> pos) & 1 == 1)))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            },
+        };
 
         // restore random coefficients and alphas
-        let random_coefficients = transcript_messages.verifier_round((namespace, 0))[0]
+        let challenges = transcript_messages.verifier_round((namespace, 0))[0]
             .clone()
             .try_into_field_elements()
             .unwrap();
+        let random_coefficients = match param.coefficient_strategy {
+            CoefficientStrategy::IndependentRandom => challenges,
+            CoefficientStrategy::PowersOfChallenge => {
+                let num_oracles = codewords
+                    .iter()
+                    .map(|round| {
+                        transcript_messages
+                            .get_prover_round_info(*round)
+                            .num_reed_solomon_codes_oracles()
+                    })
+                    .sum::<usize>();
+                match challenges.first() {
+                    Some(r) => {
+                        ark_std::iter::successors(Some(FpVar::one()), |power| Some(power * r))
+                            .take(num_oracles)
+                            .collect()
+                    },
+                    None => Vec::new(),
+                }
+            },
+        };
 
         let alphas = (1..param.fri_parameters.localization_parameters.len() + 1)
             .map(|idx| {
@@ -0,0 +1,139 @@
+use ark_crypto_primitives::merkle_tree::Config as MTConfig;
+use ark_ff::PrimeField;
+use ark_ldt::{domain::Radix2CosetDomain, fri::prover::FRIProver};
+use ark_sponge::{Absorb, CryptographicSponge};
+use ark_std::vec::Vec;
+
+use crate::{
+    bcs::{commitment::commit_oracle, MTHashParameters},
+    Error,
+};
+
+/// Fold `codeword`, the evaluations of a polynomial over `domain`, by a
+/// factor of `2 ^ log_folding_factor` using `challenge`. This is the folding
+/// step of FRI used by `LinearCombinationLDT`. Returns the folded domain and
+/// the evaluations of the folded polynomial over it.
+///
+/// Each coset `{i, i + n / k, i + 2n / k, ...}` of `domain`, where `k` is the
+/// folding factor, is interpolated and evaluated at `challenge`, giving
+/// element `i` of the folded codeword. For `k = 2`, if the polynomial is
+/// `p_0(X^2) + X * p_1(X^2)`, the folded codeword is the evaluations of
+/// `p_0 + challenge * p_1`.
+///
+/// # Panics
+/// Panics if the length of `codeword` is not the size of `domain`.
+pub fn fold<F: PrimeField>(
+    domain: Radix2CosetDomain<F>,
+    codeword: Vec<F>,
+    log_folding_factor: u64,
+    challenge: F,
+) -> (Radix2CosetDomain<F>, Vec<F>) {
+    assert_eq!(
+        codeword.len(),
+        domain.size(),
+        "codeword should have one element per element of the domain"
+    );
+    FRIProver::interactive_phase_single_round(domain, codeword, log_folding_factor, challenge)
+}
+
+/// Fold `codeword` over `domain` once for each of `localization_parameters`
+/// except the last one, using a challenge squeezed from `sponge` for each
+/// folding, and commit to each folded codeword using `commit_oracle`. Each
+/// root is absorbed into `sponge` before the next challenge is squeezed.
+/// Returns the roots in folding order. As in `LinearCombinationLDT`, the last
+/// localization parameter folds to the final polynomial, which has no root.
+///
+/// This is meant to benchmark and test folding without running the whole
+/// transform. The roots differ from the ones in a proof: proofs commit each
+/// coset of a folded codeword as one leaf, and absorb other messages between
+/// the foldings.
+pub fn commit_layers<MT, S, F>(
+    domain: Radix2CosetDomain<F>,
+    codeword: Vec<F>,
+    localization_parameters: &[u64],
+    sponge: &mut S,
+    hash_params: &MTHashParameters<MT>,
+) -> Result<Vec<MT::InnerDigest>, Error>
+where
+    MT: MTConfig<Leaf = [F]>,
+    MT::InnerDigest: Absorb,
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+{
+    let mut current_domain = domain;
+    let mut current_codeword = codeword;
+    let num_layers = localization_parameters.len().saturating_sub(1);
+    localization_parameters[..num_layers]
+        .iter()
+        .map(|&localization| {
+            let challenge = sponge.squeeze_field_elements::<F>(1)[0];
+            let (next_domain, next_codeword) = fold(
+                current_domain,
+                current_codeword.clone(),
+                localization,
+                challenge,
+            );
+            let root = commit_oracle(hash_params, next_codeword.clone())?.root();
+            sponge.absorb(&root);
+            current_domain = next_domain;
+            current_codeword = next_codeword;
+            Ok(root)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ldt::fri::{commit_layers, fold},
+        test_utils::{mt_hash_parameters, poseidon_parameters},
+    };
+    use ark_bls12_381::Fr;
+    use ark_ldt::domain::Radix2CosetDomain;
+    use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+    use ark_sponge::{poseidon::PoseidonSponge, CryptographicSponge};
+    use ark_std::{test_rng, vec::Vec, UniformRand};
+
+    #[test]
+    fn test_fold_by_two() {
+        let mut rng = test_rng();
+        let domain = Radix2CosetDomain::new_radix2_coset(32, Fr::from(7u64));
+        let poly = DensePolynomial::<Fr>::rand(15, &mut rng);
+        let challenge = Fr::rand(&mut rng);
+        let codeword = domain.evaluate(&poly);
+
+        let (folded_domain, folded) = fold(domain, codeword, 1, challenge);
+        // p(X) = p_0(X^2) + X * p_1(X^2) folds to p_0 + challenge * p_1
+        let even = poly.coeffs.iter().step_by(2).copied().collect::<Vec<_>>();
+        let odd = poly.coeffs.iter().skip(1).step_by(2).copied();
+        let expected_poly = DensePolynomial::from_coefficients_vec(
+            even.into_iter()
+                .zip(odd)
+                .map(|(e, o)| e + challenge * o)
+                .collect(),
+        );
+        assert_eq!(folded_domain.size(), 16);
+        assert_eq!(folded.len(), 16);
+        assert_eq!(folded, folded_domain.evaluate(&expected_poly));
+        assert_eq!(folded[3], expected_poly.evaluate(&folded_domain.element(3)));
+    }
+
+    #[test]
+    fn test_commit_layers() {
+        let mut rng = test_rng();
+        let domain = Radix2CosetDomain::new_radix2_coset(64, Fr::from(7u64));
+        let codeword = domain.evaluate(&DensePolynomial::<Fr>::rand(31, &mut rng));
+        let hash_params = mt_hash_parameters();
+        let commit = |codeword: Vec<Fr>| {
+            let mut sponge = PoseidonSponge::new(&poseidon_parameters());
+            commit_layers(domain, codeword, &[1, 2, 1], &mut sponge, &hash_params).unwrap()
+        };
+
+        let roots = commit(codeword.clone());
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots, commit(codeword.clone()));
+        let mut other_codeword = codeword;
+        other_codeword[0] += Fr::from(1u64);
+        assert_ne!(roots[0], commit(other_codeword)[0]);
+    }
+}
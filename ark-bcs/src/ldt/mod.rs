@@ -18,6 +18,11 @@ use crate::{
 #[cfg(feature = "r1cs")]
 /// R1CS constraints for LDT.
 pub mod constraints;
+/// FRI folding and layer commitment as standalone operations.
+pub mod fri;
+/// Oracles whose commitment and low-degree proof are computed once and reused
+/// across proofs.
+pub mod precommitted;
 /// LDT that runs FRI on a random linear combination.
 pub mod rl_ldt;
 
@@ -33,6 +38,19 @@ pub trait LDT<F: PrimeField + Absorb> {
     /// used by this LDT.
     fn localization_param(param: &Self::LDTParameters) -> Option<usize>;
 
+    /// Check that `param` is consistent. BCS calls this before proving and
+    /// verifying, so that misconfiguration is caught up front.
+    fn validate_parameters(_param: &Self::LDTParameters) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Return `param` using `num_queries` queries, for verifiers whose
+    /// `IOPVerifier::ldt_num_queries` sets the number of LDT queries. LDTs
+    /// without a number of queries return `param` unchanged.
+    fn with_num_queries(param: &Self::LDTParameters, _num_queries: usize) -> Self::LDTParameters {
+        param.clone()
+    }
+
     /// Given the list of message round references along with its degree bound,
     /// generate a low degree test proof all reed solomon codes in each
     /// reference.
@@ -73,6 +91,18 @@ pub trait LDT<F: PrimeField + Absorb> {
     ) -> Result<(), Error>;
 }
 
+/// Return `param`, using `num_queries` queries if it is `Some`. This is how
+/// BCS applies `IOPVerifier::ldt_num_queries`.
+pub(crate) fn ldt_params_with_num_queries<F: PrimeField + Absorb, L: LDT<F>>(
+    param: &L::LDTParameters,
+    num_queries: Option<usize>,
+) -> L::LDTParameters {
+    match num_queries {
+        Some(num_queries) => L::with_num_queries(param, num_queries),
+        None => param.clone(),
+    }
+}
+
 /// A placeholder LDT, which does nothing.
 pub struct NoLDT<F: PrimeField + Absorb> {
     _do_nothing: PhantomData<F>,
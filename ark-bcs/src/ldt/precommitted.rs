@@ -0,0 +1,289 @@
+use ark_crypto_primitives::merkle_tree::{Config as MTConfig, MerkleTree};
+use ark_ff::PrimeField;
+use ark_sponge::{Absorb, CryptographicSponge};
+use ark_std::{marker::PhantomData, vec, vec::Vec};
+
+use crate::{
+    bcs::{
+        prover::BCSProof,
+        simulation_transcript::SimulationTranscript,
+        transcript::{RoundMerkleTree, Transcript},
+        verifier::BCSVerifier,
+        MTHashParameters,
+    },
+    iop::{
+        bookkeeper::NameSpace,
+        message::{MessagesCollection, MsgRoundRef, ProverRoundMessageInfo},
+        oracles::RoundOracle,
+        prover::IOPProver,
+        verifier::IOPVerifier,
+        ProverParam,
+    },
+    ldt::{
+        rl_ldt::{LinearCombinationLDT, LinearCombinationLDTParameters, QuerySampler},
+        LDT,
+    },
+    tracer::TraceInfo,
+    Error,
+};
+
+/// An oracle on the LDT codeword domain whose merkle tree and low-degree proof
+/// are computed once, and can be reused by every proof that refers to the
+/// same oracle (e.g. a fixed lookup table).
+///
+/// Each proof commits the oracle as a round of its own using
+/// `Transcript::send_precommitted_oracle`, which reuses the cached tree
+/// instead of hashing the oracle again. The oracle is not tested by the LDT
+/// of that proof: verifier registers the round using
+/// `SimulationTranscript::receive_precommitted_oracle` with the root it
+/// knows in advance, and checks the cached low-degree proof once using
+/// `verify`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "MT: MTConfig, F: PrimeField"))]
+pub struct PrecommittedOracle<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    evaluations: Vec<F>,
+    tree: MerkleTree<MT>,
+    degree_bound: usize,
+    proof: BCSProof<MT, F>,
+}
+
+impl<MT, F> PrecommittedOracle<MT, F>
+where
+    MT: MTConfig<Leaf = [F]>,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    /// Merkle root of the precommitted oracle.
+    pub fn root(&self) -> MT::InnerDigest {
+        self.tree.root()
+    }
+
+    /// Evaluations of the precommitted oracle over the codeword domain.
+    pub fn evaluations(&self) -> &[F] {
+        &self.evaluations
+    }
+
+    /// Degree bound tested for the precommitted oracle.
+    pub fn degree_bound(&self) -> usize {
+        self.degree_bound
+    }
+
+    /// The cached low-degree proof of the precommitted oracle.
+    pub fn proof(&self) -> &BCSProof<MT, F> {
+        &self.proof
+    }
+
+    /// Check that the cached low-degree proof is valid and commits to
+    /// `known_root`. `sponge` should be the same state as the one given to
+    /// `LinearCombinationLDT::precommit_oracle`. It is enough to call this once
+    /// for all proofs that use the oracle.
+    pub fn verify<S: CryptographicSponge, Q: QuerySampler + Clone>(
+        &self,
+        sponge: S,
+        known_root: &MT::InnerDigest,
+        ldt_params: &LinearCombinationLDTParameters<F, Q>,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<bool, Error> {
+        if self.proof.prover_messages_mt_root.first() != Some(&Some(known_root.clone())) {
+            return Ok(false);
+        }
+        BCSVerifier::verify::<PrecommitIOP<F>, LinearCombinationLDT<F, Q>, S>(
+            sponge,
+            &self.proof,
+            &(),
+            &PrecommitParameter {
+                degree_bound: self.degree_bound,
+            },
+            ldt_params,
+            hash_params,
+        )
+    }
+}
+
+impl<F: PrimeField + Absorb, Q: QuerySampler + Clone> LinearCombinationLDT<F, Q> {
+    /// Commit to `evaluations` over the LDT codeword domain and run the
+    /// low-degree test on it once. The returned `PrecommittedOracle` can be
+    /// shared by all proofs that use this oracle.
+    pub fn precommit_oracle<MT, S>(
+        sponge: S,
+        evaluations: Vec<F>,
+        degree_bound: usize,
+        ldt_params: &LinearCombinationLDTParameters<F, Q>,
+        hash_params: MTHashParameters<MT>,
+    ) -> Result<PrecommittedOracle<MT, F>, Error>
+    where
+        MT: MTConfig<Leaf = [F]>,
+        MT::InnerDigest: Absorb,
+        S: CryptographicSponge,
+    {
+        // the oracle is the only one in its round, so the tree of the round
+        // does not depend on whether the oracle is tested by LDT
+        let mut transcript = Transcript::new(
+            sponge.clone(),
+            hash_params.clone(),
+            Self::codeword_domain(ldt_params),
+            Self::localization_param(ldt_params),
+            iop_trace!("precommit oracle"),
+        );
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_oracle_message_without_degree_bound(evaluations.clone())
+            .submit(
+                NameSpace::root(iop_trace!("precommit oracle")),
+                iop_trace!("precommitted oracle"),
+            )?;
+        let tree = match transcript.merkle_tree_for_each_round.pop() {
+            Some(Some(RoundMerkleTree::Dense(tree))) => tree,
+            _ => unreachable!("precommitted oracle round should have a dense merkle tree"),
+        };
+
+        let proof = BCSProof::generate::<PrecommitIOP<F>, PrecommitIOP<F>, Self, S>(
+            sponge,
+            &(),
+            &evaluations,
+            &PrecommitParameter { degree_bound },
+            ldt_params,
+            hash_params,
+        )?;
+        debug_assert!(proof.prover_messages_mt_root[0] == Some(tree.root()));
+        Ok(PrecommittedOracle {
+            evaluations,
+            tree,
+            degree_bound,
+            proof,
+        })
+    }
+}
+
+impl<P, S, F> Transcript<P, S, F>
+where
+    P: MTConfig<Leaf = [F]>,
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+    P::InnerDigest: Absorb,
+{
+    /// Submit a prover round containing only `oracle` over the codeword
+    /// domain, reusing its cached merkle tree. The oracle is not tested by the
+    /// LDT of this proof.
+    pub fn send_precommitted_oracle(
+        &mut self,
+        namespace: NameSpace,
+        oracle: &PrecommittedOracle<P, F>,
+        trace: TraceInfo,
+    ) -> Result<MsgRoundRef, Error> {
+        self.add_prover_round_with_codeword_domain()
+            .send_oracle_message_with_precomputed_tree(
+                oracle.evaluations.iter().copied(),
+                oracle.tree.clone(),
+            )
+            .submit(namespace, trace)
+    }
+}
+
+impl<'a, P, S, F> SimulationTranscript<'a, P, S, F>
+where
+    P: MTConfig<Leaf = [F]>,
+    S: CryptographicSponge,
+    F: PrimeField + Absorb,
+    P::InnerDigest: Absorb,
+{
+    /// Receive the round sent by `Transcript::send_precommitted_oracle`, and
+    /// require its merkle root to be `known_root`.
+    pub fn receive_precommitted_oracle(
+        &mut self,
+        namespace: NameSpace,
+        known_root: P::InnerDigest,
+        trace: TraceInfo,
+    ) -> MsgRoundRef {
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(self)
+            .with_num_message_oracles(1)
+            .build();
+        let round = self.receive_prover_current_round(namespace, expected_info, trace);
+        self.expect_known_root(round, known_root);
+        round
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrecommitParameter {
+    degree_bound: usize,
+}
+
+impl ProverParam for PrecommitParameter {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// An IOP where prover sends out the precommitted oracle, and verifier only
+/// relies on LDT.
+struct PrecommitIOP<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for PrecommitIOP<F> {
+    type ProverParameter = PrecommitParameter;
+    type PublicInput = ();
+    type PrivateInput = Vec<F>;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        _public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_oracle_evaluations_with_degree_bound(
+                private_input.clone(),
+                prover_parameter.degree_bound,
+            )
+            .submit(namespace, iop_trace!("precommitted oracle"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for PrecommitIOP<F> {
+    type VerifierOutput = bool;
+    type VerifierParameter = PrecommitParameter;
+    type PublicInput = ();
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(transcript)
+            .with_reed_solomon_codes_degree_bounds(vec![verifier_parameter.degree_bound])
+            .build();
+        transcript.receive_prover_current_round(
+            namespace,
+            expected_info,
+            iop_trace!("precommitted oracle"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        _namespace: NameSpace,
+        _verifier_parameter: &Self::VerifierParameter,
+        _public_input: &Self::PublicInput,
+        _sponge: &mut S,
+        _transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        // low-degreeness is checked by LDT
+        Ok(true)
+    }
+}
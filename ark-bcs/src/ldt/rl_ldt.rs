@@ -1,23 +1,29 @@
 use crate::{
-    bcs::{simulation_transcript::SimulationTranscript, transcript::Transcript},
+    bcs::{
+        prover::BCSProof,
+        simulation_transcript::SimulationTranscript,
+        transcript::{Transcript, TranscriptVersion},
+    },
     iop::{
         bookkeeper::{BookkeeperContainer, NameSpace},
-        message::{MessagesCollection, MsgRoundRef, ProverRoundMessageInfo},
+        message::{Localization, MessagesCollection, MsgRoundRef, ProverRoundMessageInfo},
         oracles::RoundOracle,
+        verifier::QuerySampler,
     },
-    ldt::LDT,
-    Error,
+    ldt::{fri::fold, LDT},
+    util::le_bits_to_usize,
+    BCSError, Error,
 };
 use ark_crypto_primitives::merkle_tree::Config as MTConfig;
 use ark_ff::PrimeField;
 use ark_ldt::{
     direct::DirectLDT,
     domain::Radix2CosetDomain,
-    fri::{prover::FRIProver, verifier::FRIVerifier, FRIParameters},
+    fri::{verifier::FRIVerifier, FRIParameters},
 };
 use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
 use ark_sponge::{Absorb, CryptographicSponge, FieldElementSize};
-use ark_std::{marker::PhantomData, vec::Vec};
+use ark_std::{boxed::Box, marker::PhantomData, mem::size_of, vec, vec::Vec};
 use tracing::Level;
 
 /// Implementation of LDT using FRI protocol. When taking multiple oracles, this
@@ -27,22 +33,138 @@ use tracing::Level;
 /// bound <= tested_degree in FRI parameter. To enforce individual bound, this protocol follows [SCRSVP19](https://eprint.iacr.org/2018/) section 8, such that we
 /// multiply each oracle by monimial x^{degree_to_raise} and take random linear
 /// combination.
-pub struct LinearCombinationLDT<F: PrimeField + Absorb> {
+///
+/// Coset indices of FRI queries are sampled by `Q`, which defaults to
+/// `QueryStrategy`, whose default is `QueryStrategy::Independent`. The
+/// constraint gadget of this LDT only supports `QueryStrategy`.
+pub struct LinearCombinationLDT<F: PrimeField + Absorb, Q: QuerySampler = QueryStrategy> {
     _field: PhantomData<F>,
+    _sampler: PhantomData<Q>,
+}
+
+/// Query samplers of `LinearCombinationLDT` that its constraint gadget can
+/// also verify.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryStrategy {
+    /// Each query squeezes its own random coset index.
+    Independent,
+    /// Only one random coset index `base` is squeezed, and query `i` uses
+    /// coset index `base ^ (i << shift)`, where `2 ^ shift` is the number of
+    /// cosets divided by `num_queries` rounded up to a power of two. The
+    /// queried positions form a coset of a subgroup of coset indices under
+    /// XOR, so they are spread evenly over the codeword domain, but are not
+    /// independent: soundness analysis using this strategy needs to account
+    /// for the correlation.
+    CosetStructured,
+}
+
+impl Default for QueryStrategy {
+    fn default() -> Self {
+        QueryStrategy::Independent
+    }
+}
+
+impl QueryStrategy {
+    /// Offsets XORed into the base coset index of each query when using
+    /// `CosetStructured`.
+    pub(crate) fn coset_offsets(log_num_cosets: usize, num_queries: usize) -> Vec<usize> {
+        let shift = log_num_cosets - ark_std::log2(num_queries).min(log_num_cosets as u32) as usize;
+        let mask = (1 << log_num_cosets) - 1;
+        (0..num_queries).map(|i| (i << shift) & mask).collect()
+    }
+}
+
+impl QuerySampler for QueryStrategy {
+    /// Squeeze coset indices of `num` queries, in a domain of `domain_size`
+    /// cosets.
+    fn sample_positions<S: CryptographicSponge>(
+        &self,
+        sponge: &mut S,
+        num: usize,
+        domain_size: usize,
+    ) -> Result<Vec<usize>, Error> {
+        let log_num_cosets = ark_std::log2(domain_size) as usize;
+        Ok(match self {
+            QueryStrategy::Independent => (0..num)
+                .map(|_| le_bits_to_usize(&sponge.squeeze_bits(log_num_cosets)))
+                .collect(),
+            QueryStrategy::CosetStructured => {
+                let base = le_bits_to_usize(&sponge.squeeze_bits(log_num_cosets));
+                Self::coset_offsets(log_num_cosets, num)
+                    .into_iter()
+                    .map(|offset| base ^ offset)
+                    .collect()
+            },
+        })
+    }
+}
+
+/// How `LinearCombinationLDT` samples the coefficients of the linear
+/// combination of its oracles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoefficientStrategy {
+    /// Each oracle has its own random coefficient.
+    IndependentRandom,
+    /// Only one random challenge `r` is squeezed, and oracle `i` has
+    /// coefficient `r ^ i`. The combination is a polynomial in `r` of degree
+    /// one less than the number of oracles, so the soundness error of the
+    /// combination grows by this factor.
+    PowersOfChallenge,
+}
+
+impl Default for CoefficientStrategy {
+    fn default() -> Self {
+        CoefficientStrategy::IndependentRandom
+    }
+}
+
+impl CoefficientStrategy {
+    /// Number of field elements squeezed for the coefficients of
+    /// `num_oracles` oracles.
+    pub(crate) fn num_challenges(self, num_oracles: usize) -> usize {
+        match self {
+            CoefficientStrategy::IndependentRandom => num_oracles,
+            CoefficientStrategy::PowersOfChallenge => num_oracles.min(1),
+        }
+    }
+
+    /// Coefficients of `num_oracles` oracles, given the squeezed
+    /// `challenges`.
+    pub(crate) fn coefficients<F: PrimeField>(
+        self,
+        challenges: Vec<F>,
+        num_oracles: usize,
+    ) -> Vec<F> {
+        match self {
+            CoefficientStrategy::IndependentRandom => challenges,
+            CoefficientStrategy::PowersOfChallenge => match challenges.first() {
+                Some(&r) => ark_std::iter::successors(Some(F::one()), |power| Some(*power * r))
+                    .take(num_oracles)
+                    .collect(),
+                None => Vec::new(),
+            },
+        }
+    }
 }
 
 #[derive(Clone)]
 /// Parameter for Linear combination LDT, which includes parameter for FRI and
 /// number of queries.
-pub struct LinearCombinationLDTParameters<F: PrimeField + Absorb> {
+pub struct LinearCombinationLDTParameters<F: PrimeField + Absorb, Q: QuerySampler = QueryStrategy> {
     /// FRI parameter for the linearly combined polynomial
     pub fri_parameters: FRIParameters<F>,
     /// Number of FRI queries
     pub num_queries: usize,
+    /// Sampler of the coset indices of FRI queries.
+    pub query_sampler: Q,
+    /// How coefficients of the linear combination are sampled.
+    pub coefficient_strategy: CoefficientStrategy,
 }
 
 impl<F: PrimeField + Absorb> LinearCombinationLDTParameters<F> {
-    /// Create a new parameter for Linear Combination LDT
+    /// Create a new parameter for Linear Combination LDT, which samples
+    /// independent queries using `QueryStrategy::Independent`. Use
+    /// `with_query_sampler` to sample queries differently.
     pub fn new(
         max_degree_bound: u64,
         localization_param: Vec<u64>,
@@ -56,12 +178,252 @@ impl<F: PrimeField + Absorb> LinearCombinationLDTParameters<F> {
                 codeword_domain,
             ),
             num_queries,
+            query_sampler: QueryStrategy::default(),
+            coefficient_strategy: CoefficientStrategy::default(),
+        }
+    }
+
+    /// Create a new parameter for Linear Combination LDT, where FRI folds by
+    /// `2 ^ localization` in every round and stops as soon as the final
+    /// polynomial has degree at most `final_poly_degree`. Prover sends the
+    /// final polynomial in the clear, so a larger `final_poly_degree` trades
+    /// a few field elements for the oracles and authentication paths of the
+    /// skipped rounds.
+    ///
+    /// # Panics
+    /// Panics if `localization` is 0.
+    pub fn with_final_poly_degree(
+        max_degree_bound: u64,
+        localization: u64,
+        final_poly_degree: u64,
+        codeword_domain: Radix2CosetDomain<F>,
+        num_queries: usize,
+    ) -> Self {
+        assert!(localization > 0, "FRI should fold in every round");
+        let mut localization_param = vec![localization];
+        while max_degree_bound >> (localization * localization_param.len() as u64)
+            > final_poly_degree
+        {
+            localization_param.push(localization);
         }
+        Self::new(
+            max_degree_bound,
+            localization_param,
+            codeword_domain,
+            num_queries,
+        )
     }
 }
 
-impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
-    type LDTParameters = LinearCombinationLDTParameters<F>;
+impl<F: PrimeField + Absorb, Q: QuerySampler> LinearCombinationLDTParameters<F, Q> {
+    /// Number of FRI queries used by `dev_mode`.
+    pub const DEV_MODE_NUM_QUERIES: usize = 2;
+
+    /// Degree bound of the final polynomial, which prover sends in the clear
+    /// after the last folding.
+    pub fn final_poly_degree(&self) -> u64 {
+        let total_shrink_factor = self
+            .fri_parameters
+            .localization_parameters
+            .iter()
+            .sum::<u64>();
+        self.fri_parameters.tested_degree >> total_shrink_factor
+    }
+
+    /// Use `query_sampler` to sample FRI queries.
+    #[must_use]
+    pub fn with_query_sampler<Q2: QuerySampler>(
+        self,
+        query_sampler: Q2,
+    ) -> LinearCombinationLDTParameters<F, Q2> {
+        LinearCombinationLDTParameters {
+            fri_parameters: self.fri_parameters,
+            num_queries: self.num_queries,
+            query_sampler,
+            coefficient_strategy: self.coefficient_strategy,
+        }
+    }
+
+    /// Reduce the number of FRI queries to `DEV_MODE_NUM_QUERIES`, so that
+    /// proving and verifying run faster during development. The proof format
+    /// does not change, and prover and verifier agree on the number of
+    /// queries as long as both use the returned parameters.
+    ///
+    /// This is **insecure**: the soundness error of LDT is far too large for
+    /// any real use, so **never** use it in production.
+    #[must_use]
+    pub fn dev_mode(mut self) -> Self {
+        tracing::warn!(
+            "LDT dev mode is on: using {} FRI queries instead of {}, proofs are NOT SOUND",
+            Self::DEV_MODE_NUM_QUERIES,
+            self.num_queries
+        );
+        self.num_queries = Self::DEV_MODE_NUM_QUERIES;
+        self
+    }
+
+    /// Use `coefficient_strategy` to sample coefficients of the linear
+    /// combination.
+    #[must_use]
+    pub fn with_coefficient_strategy(mut self, coefficient_strategy: CoefficientStrategy) -> Self {
+        self.coefficient_strategy = coefficient_strategy;
+        self
+    }
+
+    /// Check that the FRI localization parameters fold the codeword domain
+    /// down to a final domain of at least 2 elements that is larger than
+    /// the degree bound of the final polynomial, and that a sampler of
+    /// distinct queries has enough cosets in the codeword domain to sample
+    /// `num_queries` of them.
+    pub fn validate(&self) -> Result<(), Error> {
+        let fri_parameters = &self.fri_parameters;
+        let total_shrink_factor = fri_parameters.localization_parameters.iter().sum::<u64>();
+        let is_valid = !fri_parameters.localization_parameters.is_empty()
+            && (total_shrink_factor as usize) < fri_parameters.domain.dim()
+            && (fri_parameters.tested_degree >> total_shrink_factor)
+                < (fri_parameters.domain.size() >> total_shrink_factor) as u64;
+        if !is_valid {
+            return Err(Box::new(BCSError::InvalidFoldingSchedule {
+                domain_size: fri_parameters.domain.size(),
+                tested_degree: fri_parameters.tested_degree,
+                localization_parameters: fri_parameters.localization_parameters.clone(),
+            }));
+        }
+        let codeword_num_cosets =
+            fri_parameters.domain.size() >> fri_parameters.localization_parameters[0];
+        if self.query_sampler.samples_distinct_positions() && self.num_queries > codeword_num_cosets
+        {
+            return Err(Box::new(BCSError::TooManyDistinctQueries {
+                num_queries: self.num_queries,
+                domain_size: codeword_num_cosets,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField + Absorb, Q: QuerySampler> LinearCombinationLDT<F, Q> {
+    /// Estimate the memory in bytes needed by the prover to commit to the
+    /// prover rounds described by `round_infos`, and to run this LDT on them.
+    ///
+    /// The estimate sums the size of all oracle evaluations (reed-solomon
+    /// codes are counted twice, as transcript keeps a copy for LDT), their
+    /// merkle trees, the linear combination codeword, and FRI intermediate
+    /// oracles with their merkle trees. Short messages and heap allocations
+    /// inside merkle tree digests are not counted, so the estimate is a lower
+    /// bound when digests are not plain values.
+    pub fn estimate_prover_memory<MT: MTConfig<Leaf = [F]>>(
+        param: &LinearCombinationLDTParameters<F, Q>,
+        round_infos: &[ProverRoundMessageInfo],
+    ) -> usize {
+        let iop_oracles = round_infos
+            .iter()
+            .map(|info| {
+                let num_elements =
+                    info.length * (info.num_oracles() + info.reed_solomon_code_degree_bound.len());
+                num_elements * size_of::<F>()
+                    + estimate_merkle_tree_memory::<MT>(
+                        info.length >> info.localization_parameter,
+                        info.num_oracles(),
+                    )
+            })
+            .sum::<usize>();
+
+        let fri_parameters = &param.fri_parameters;
+        let domain_size = fri_parameters.domain.size();
+        // linear combination codeword and degree raise polynomial evaluations
+        let linear_combination = 2 * domain_size * size_of::<F>();
+        // each FRI round except the last one sends an oracle of the folded codeword
+        let mut current_size = domain_size;
+        let fri_oracles = fri_parameters
+            .localization_parameters
+            .windows(2)
+            .map(|window| {
+                current_size >>= window[0];
+                current_size * size_of::<F>()
+                    + estimate_merkle_tree_memory::<MT>(current_size >> window[1], 1)
+            })
+            .sum::<usize>();
+
+        iop_oracles + linear_combination + fri_oracles
+    }
+
+    /// Derive the coset indices queried by this LDT, given the sponge at the
+    /// end of commit phase, e.g. from `Transcript::commit_phase_sponge`. The
+    /// result has axes `[layer, query]`: layer 0 is queried in every round
+    /// with reed-solomon codes, and layer `i > 0` in FRI round `i - 1` of
+    /// this LDT. A prover can use it to generate only the authentication
+    /// paths verifier needs.
+    ///
+    /// LDT queries are squeezed before any other query, and depend only on
+    /// the sponge and the transcript `version`. Queries of the protocol
+    /// verifier are not included: they are only known in advance if the
+    /// verifier is not adaptive, in which case they are squeezed after the
+    /// ones of LDT.
+    pub fn derive_query_positions<S: CryptographicSponge>(
+        commit_phase_sponge: &S,
+        param: &LinearCombinationLDTParameters<F, Q>,
+        version: TranscriptVersion,
+    ) -> Result<Vec<Vec<usize>>, Error> {
+        let coset_indices =
+            squeeze_query_coset_indices(&mut commit_phase_sponge.clone(), param, version)?;
+        let mut positions = vec![Vec::new(); param.fri_parameters.localization_parameters.len()];
+        for coset_index in coset_indices {
+            let (_, query_indices, _) =
+                FRIVerifier::prepare_query(coset_index, &param.fri_parameters);
+            positions
+                .iter_mut()
+                .zip(query_indices)
+                .for_each(|(layer, index)| layer.push(index));
+        }
+        Ok(positions)
+    }
+}
+
+impl<MT, F> BCSProof<MT, F>
+where
+    MT: MTConfig,
+    F: PrimeField + Absorb,
+    MT::InnerDigest: Absorb,
+{
+    /// Return the merkle roots of the intermediate codewords committed by
+    /// `LinearCombinationLDT` during FRI folding, in folding order, for a
+    /// proof generated with `ldt_params`. Each localization parameter except
+    /// the last one produces a committed codeword. The last folding produces
+    /// the final polynomial, which is sent in the clear and has no root.
+    ///
+    /// # Panics
+    /// Panics if the proof does not end with the rounds sent by this LDT.
+    pub fn ldt_layer_roots<Q: QuerySampler>(
+        &self,
+        ldt_params: &LinearCombinationLDTParameters<F, Q>,
+    ) -> Vec<MT::InnerDigest> {
+        let num_layers = ldt_params.fri_parameters.localization_parameters.len() - 1;
+        // LDT rounds are sent after the main protocol, and end with the final
+        // polynomial
+        let num_rounds = self.prover_messages_mt_root.len();
+        assert!(num_rounds > num_layers, "proof does not contain LDT rounds");
+        self.prover_messages_mt_root[num_rounds - 1 - num_layers..num_rounds - 1]
+            .iter()
+            .map(|root| {
+                root.clone()
+                    .expect("FRI intermediate codeword should have a merkle root")
+            })
+            .collect()
+    }
+}
+
+/// Bytes of a merkle tree with `num_leaves` leaves, or zero if the round has no
+/// oracle.
+fn estimate_merkle_tree_memory<MT: MTConfig>(num_leaves: usize, num_oracles: usize) -> usize {
+    if num_oracles == 0 || num_leaves == 0 {
+        return 0;
+    }
+    num_leaves * size_of::<MT::LeafDigest>() + (num_leaves - 1) * size_of::<MT::InnerDigest>()
+}
+
+impl<F: PrimeField + Absorb, Q: QuerySampler + Clone> LDT<F> for LinearCombinationLDT<F, Q> {
+    type LDTParameters = LinearCombinationLDTParameters<F, Q>;
 
     fn codeword_domain(param: &Self::LDTParameters) -> Option<Radix2CosetDomain<F>> {
         Some(param.fri_parameters.domain)
@@ -71,6 +433,17 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
         Some(param.fri_parameters.localization_parameters[0] as usize)
     }
 
+    fn validate_parameters(param: &Self::LDTParameters) -> Result<(), Error> {
+        param.validate()
+    }
+
+    fn with_num_queries(param: &Self::LDTParameters, num_queries: usize) -> Self::LDTParameters {
+        LinearCombinationLDTParameters {
+            num_queries,
+            ..param.clone()
+        }
+    }
+
     fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
         namespace: NameSpace,
         param: &Self::LDTParameters,
@@ -82,6 +455,8 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
     {
         let span = tracing::span!(Level::INFO, "LDT Prove");
         let _enter = span.enter();
+        let coefficient_strategy = param.coefficient_strategy;
+        let final_poly_degree_bound = param.final_poly_degree();
         let param = &param.fri_parameters;
         // get number of coefficients needed
         let num_oracles = codewords
@@ -93,12 +468,13 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
             })
             .sum::<usize>();
 
-        let random_coefficients = transcript.squeeze_verifier_field_elements(
-            &(0..num_oracles)
+        let challenges = transcript.squeeze_verifier_field_elements(
+            &(0..coefficient_strategy.num_challenges(num_oracles))
                 .map(|_| FieldElementSize::Full)
                 .collect::<Vec<_>>(),
         );
         transcript.submit_verifier_current_round(namespace, iop_trace!("ldt random coefficeints"));
+        let random_coefficients = coefficient_strategy.coefficients(challenges, num_oracles);
 
         let mut result_codewords = (0..param.domain.size())
             .map(|_| F::zero())
@@ -148,7 +524,7 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
                     let alpha =
                         transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full])[0];
                     transcript.submit_verifier_current_round(namespace, iop_trace!("ldt alpha"));
-                    let (next_domain, next_evaluations) = FRIProver::interactive_phase_single_round(
+                    let (next_domain, next_evaluations) = fold(
                         current_domain,
                         current_evaluations.clone(), /* TODO: change argument type to reference
                                                       * so we do not need to clone this */
@@ -166,7 +542,7 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
                     transcript
                         .add_prover_round_with_custom_length_and_localization(
                             next_evaluations.len(),
-                            localization_next as usize,
+                            Localization::new(localization_next as usize, next_evaluations.len())?,
                         )
                         .send_oracle_message_without_degree_bound(next_evaluations.clone())
                         .submit(namespace, iop_trace!("ldt fri oracle"))?;
@@ -179,17 +555,14 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
         // generate final polynomial
         let alpha = transcript.squeeze_verifier_field_elements(&[FieldElementSize::Full])[0];
         transcript.submit_verifier_current_round(namespace, iop_trace!("ldt final alpha"));
-        let (domain_final, final_polynomial_evaluations) =
-            FRIProver::interactive_phase_single_round(
-                current_domain,
-                current_evaluations,
-                *(param.localization_parameters.last().unwrap()),
-                alpha,
-            );
+        let (domain_final, final_polynomial_evaluations) = fold(
+            current_domain,
+            current_evaluations,
+            *(param.localization_parameters.last().unwrap()),
+            alpha,
+        );
         // send final polynomial, which is not an oracle.
         // We send interpolated final polynomial coefficients instead of evaluations.
-        let total_shrink_factor = param.localization_parameters.iter().sum::<u64>();
-        let final_poly_degree_bound = param.tested_degree >> total_shrink_factor;
         let sanity_check_point = final_polynomial_evaluations[1];
         let final_polynomial = DirectLDT::generate_low_degree_coefficients(
             domain_final,
@@ -207,7 +580,7 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
         //     .submit_prover_current_round(namespace, iop_trace!("ldt final poly
         // coefficients"))?;
         transcript
-            .add_prover_round_with_custom_length_and_localization(0, 0)
+            .add_prover_round_with_custom_length_and_localization(0, Localization::default())
             .send_short_message(final_polynomial.coeffs)
             .submit(namespace, iop_trace!("ldt final poly coefficients"))?;
 
@@ -225,7 +598,9 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
         let span = tracing::span!(Level::INFO, "ldt register");
         let _enter = span.enter();
         transcript.squeeze_verifier_field_elements(
-            &(0..num_codewords_oracles)
+            &(0..param
+                .coefficient_strategy
+                .num_challenges(num_codewords_oracles))
                 .map(|_| FieldElementSize::Full)
                 .collect::<Vec<_>>(),
         );
@@ -255,7 +630,8 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
                     // },
                     ProverRoundMessageInfo::new_using_custom_length_and_localization(
                         next_domain.size(),
-                        localization_next as usize,
+                        Localization::new(localization_next as usize, next_domain.size())
+                            .expect("invalid FRI localization parameters"),
                     )
                     .with_num_message_oracles(1)
                     .build(),
@@ -277,9 +653,12 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
             //     localization_parameter: 0, // ignored
             //     oracle_length: 0,          // ignored
             // },
-            ProverRoundMessageInfo::new_using_custom_length_and_localization(0, 0)
-                .with_num_short_messages(1)
-                .build(),
+            ProverRoundMessageInfo::new_using_custom_length_and_localization(
+                0,
+                Localization::default(),
+            )
+            .with_num_short_messages(1)
+            .build(),
             iop_trace!("LDT final polynomial"),
         );
     }
@@ -295,15 +674,24 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
         let span = tracing::span!(tracing::Level::INFO, "LDT Query");
         let _enter = span.enter();
         // calculate random coset indices for each query
-        let codeword_log_num_cosets = param.fri_parameters.domain.dim()
-            - param.fri_parameters.localization_parameters[0] as usize;
-        let query_indices = (0..param.num_queries)
-            .map(|_| le_bits_to_usize(&sponge.squeeze_bits(codeword_log_num_cosets)));
+        let query_indices =
+            squeeze_query_coset_indices(sponge, param, transcript_messages.version)?;
         // restore random coefficients and alphas
-        let random_coefficients = transcript_messages.verifier_round((namespace, 0))[0]
-            .clone()
-            .try_into_field_elements()
-            .unwrap();
+        let num_oracles = codewords
+            .iter()
+            .map(|round| {
+                transcript_messages
+                    .get_prover_round_info(*round)
+                    .num_reed_solomon_codes_oracles()
+            })
+            .sum::<usize>();
+        let random_coefficients = param.coefficient_strategy.coefficients(
+            transcript_messages.verifier_round((namespace, 0))[0]
+                .clone()
+                .try_into_field_elements()
+                .unwrap(),
+            num_oracles,
+        );
 
         // verifier message from index 1 to num_alphas are alphas
         let alphas = (1..param.fri_parameters.localization_parameters.len() + 1).map(|i|
@@ -403,13 +791,7 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
                         .short_message(0, iop_trace!("final poly coefficients"))
                 }
                 .to_vec();
-                let total_shrink_factor = param
-                    .fri_parameters
-                    .localization_parameters
-                    .iter()
-                    .sum::<u64>();
-                let final_poly_degree_bound =
-                    param.fri_parameters.tested_degree >> total_shrink_factor;
+                let final_poly_degree_bound = param.final_poly_degree();
                 // make sure final polynomial degree is valid
                 assert!(final_polynomial_coeffs.len() <= (final_poly_degree_bound + 1) as usize);
                 let final_polynomial =
@@ -434,11 +816,22 @@ impl<F: PrimeField + Absorb> LDT<F> for LinearCombinationLDT<F> {
     }
 }
 
-fn le_bits_to_usize(bits: &[bool]) -> usize {
-    bits.iter()
-        .enumerate()
-        .map(|(pos, bit)| (*bit as usize) << pos)
-        .sum()
+/// Squeeze the coset indices of all queries of `LinearCombinationLDT`, in the
+/// codeword domain, using the query sampler of `param` if `version` uses it.
+fn squeeze_query_coset_indices<F: PrimeField + Absorb, Q: QuerySampler, S: CryptographicSponge>(
+    sponge: &mut S,
+    param: &LinearCombinationLDTParameters<F, Q>,
+    version: TranscriptVersion,
+) -> Result<Vec<usize>, Error> {
+    let codeword_num_cosets =
+        param.fri_parameters.domain.size() >> param.fri_parameters.localization_parameters[0];
+    if version.uses_ldt_query_sampler() {
+        param
+            .query_sampler
+            .sample_positions(sponge, param.num_queries, codeword_num_cosets)
+    } else {
+        QueryStrategy::Independent.sample_positions(sponge, param.num_queries, codeword_num_cosets)
+    }
 }
 
 // return evaluation of x^{degree_to_raise} at domain
@@ -482,16 +875,17 @@ fn degree_raise_poly_query<F: PrimeField>(
 #[cfg(test)]
 mod tests {
     use crate::{
-        bcs::{tests::FieldMTConfig, transcript::Transcript, MTHashParameters},
-        iop::{bookkeeper::NameSpace, message::MessagesCollection},
+        bcs::transcript::Transcript,
+        iop::{bookkeeper::NameSpace, message::MessagesCollection, verifier::UniformUniqueSampler},
         ldt::{
             rl_ldt::{
-                degree_raise_poly_eval, degree_raise_poly_query, LinearCombinationLDT,
-                LinearCombinationLDTParameters,
+                degree_raise_poly_eval, degree_raise_poly_query, CoefficientStrategy,
+                LinearCombinationLDT, LinearCombinationLDTParameters, QueryStrategy,
             },
             LDT,
         },
-        test_utils::poseidon_parameters,
+        test_utils::{mt_hash_parameters, poseidon_parameters},
+        BCSError,
     };
     use ark_bls12_381::Fr;
     use ark_ldt::{domain::Radix2CosetDomain, fri::FRIParameters};
@@ -538,15 +932,14 @@ mod tests {
             let ldt_params = LinearCombinationLDTParameters {
                 fri_parameters,
                 num_queries: 1,
+                query_sampler: QueryStrategy::Independent,
+                coefficient_strategy: CoefficientStrategy::IndependentRandom,
             };
             let root_namespace = NameSpace::root(iop_trace!("ldt test"));
 
             let mut sponge = PoseidonSponge::new(&poseidon_parameters());
             sponge.absorb(&i);
-            let hash_params = MTHashParameters::<FieldMTConfig> {
-                inner_hash_param: poseidon_parameters(),
-                leaf_hash_param: poseidon_parameters(),
-            };
+            let hash_params = mt_hash_parameters();
             let mut transcript = Transcript::new(
                 sponge,
                 hash_params.clone(),
@@ -603,4 +996,42 @@ mod tests {
             // TODO: check restore
         }
     }
+
+    #[test]
+    fn test_invalid_folding_schedule() {
+        let domain = Radix2CosetDomain::new_radix2_coset(64, Fr::one());
+        let valid = LinearCombinationLDTParameters::new(31, vec![1, 2, 1], domain, 1);
+        assert!(valid.validate().is_ok());
+
+        // folds 64 elements to 1, which cannot hold the final polynomial
+        let invalid = LinearCombinationLDTParameters::new(31, vec![2, 2, 2], domain, 1);
+        let err = invalid.validate().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<BCSError>(),
+            Some(&BCSError::InvalidFoldingSchedule {
+                domain_size: 64,
+                tested_degree: 31,
+                localization_parameters: vec![2, 2, 2],
+            })
+        );
+    }
+
+    #[test]
+    fn test_too_many_distinct_queries() {
+        let domain = Radix2CosetDomain::new_radix2_coset(64, Fr::one());
+        // 32 cosets in the first FRI round
+        let param = LinearCombinationLDTParameters::new(31, vec![1, 2, 1], domain, 33);
+        assert!(param.validate().is_ok());
+        let err = param
+            .with_query_sampler(UniformUniqueSampler)
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<BCSError>(),
+            Some(&BCSError::TooManyDistinctQueries {
+                num_queries: 33,
+                domain_size: 32,
+            })
+        );
+    }
 }
@@ -30,10 +30,19 @@ pub mod bcs;
 /// defined in `ark-ldt` to an IOP.
 pub mod ldt;
 
+/// IOP proving an evaluation of a univariate polynomial, whose degree is
+/// checked by LDT.
+pub mod univariate_opening;
+
 /// Some handy imports for users.
 pub mod prelude;
 #[cfg(test)]
 pub(crate) mod test_utils;
+/// Helpers shared by IOP protocols, LDT and tests.
+pub(crate) mod util;
+
+mod error;
+pub use error::BCSError;
 
 use ark_std::boxed::Box;
 
@@ -2,4 +2,9 @@
 pub use crate::bcs::{simulation_transcript::SimulationTranscript, transcript::Transcript};
 
 // export queried message
-pub use crate::iop::message::{MessagesCollection, MsgRoundRef, ProverRoundMessageInfo};
+pub use crate::iop::message::{
+    Localization, MessagesCollection, MsgRoundRef, ProverRoundMessageInfo,
+};
+
+// export high-level API
+pub use crate::bcs::config::{BCSConfig, BCS};
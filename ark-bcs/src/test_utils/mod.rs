@@ -1,6 +1,30 @@
+use crate::{
+    bcs::{
+        prover::BCSProof,
+        tests::FieldMTConfig,
+        transcript::{Transcript, TranscriptVersion},
+        verifier::BCSVerifier,
+        MTHashParameters,
+    },
+    iop::{
+        bookkeeper::{NameSpace, ToMsgRoundRef},
+        message::VerifierMessage,
+        prover::IOPProver,
+        verifier::IOPVerifierForProver,
+        ProverParam,
+    },
+    ldt::LDT,
+    Error,
+};
 use ark_bls12_381::Fr;
 use ark_ff::PrimeField;
-use ark_sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig};
+use ark_ldt::domain::Radix2CosetDomain;
+use ark_serialize::CanonicalSerialize;
+use ark_sponge::{
+    poseidon::{find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge},
+    CryptographicSponge,
+};
+use ark_std::vec::Vec;
 
 pub(crate) fn poseidon_parameters() -> PoseidonConfig<Fr> {
     let full_rounds = 8;
@@ -27,5 +51,196 @@ pub(crate) fn poseidon_parameters() -> PoseidonConfig<Fr> {
     )
 }
 
+/// Hash parameters of `FieldMTConfig` merkle trees, using
+/// `poseidon_parameters` for both leaves and inner nodes.
+pub(crate) fn mt_hash_parameters() -> MTHashParameters<FieldMTConfig> {
+    MTHashParameters {
+        leaf_hash_param: poseidon_parameters(),
+        inner_hash_param: poseidon_parameters(),
+    }
+}
+
+/// A prover transcript with a fresh Poseidon sponge and `FieldMTConfig`
+/// merkle trees, using the codeword domain and localization parameter of LDT.
+pub(crate) fn new_transcript(
+    ldt_codeword_domain: Option<Radix2CosetDomain<Fr>>,
+    ldt_localization_parameter: Option<usize>,
+) -> Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr> {
+    new_transcript_with_version(
+        ldt_codeword_domain,
+        ldt_localization_parameter,
+        TranscriptVersion::default(),
+    )
+}
+
+/// A prover transcript like `new_transcript`, but using `version`.
+pub(crate) fn new_transcript_with_version(
+    ldt_codeword_domain: Option<Radix2CosetDomain<Fr>>,
+    ldt_localization_parameter: Option<usize>,
+    version: TranscriptVersion,
+) -> Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr> {
+    Transcript::new_with_version(
+        PoseidonSponge::new(&poseidon_parameters()),
+        mt_hash_parameters(),
+        ldt_codeword_domain,
+        ldt_localization_parameter,
+        version,
+        iop_trace!("test transcript"),
+    )
+}
+
+/// Run the commit phase of `P` in the root namespace of a transcript returned
+/// by `new_transcript` for `ldt_params`, and return the transcript. The LDT
+/// itself is not run.
+pub(crate) fn commit_phase_transcript<P, L>(
+    public_input: &P::PublicInput,
+    private_input: &P::PrivateInput,
+    prover_parameter: &P::ProverParameter,
+    ldt_params: &L::LDTParameters,
+) -> Result<Transcript<FieldMTConfig, PoseidonSponge<Fr>, Fr>, Error>
+where
+    P: IOPProver<Fr>,
+    L: LDT<Fr>,
+{
+    let mut transcript = new_transcript(
+        L::codeword_domain(ldt_params),
+        L::localization_param(ldt_params),
+    );
+    P::prove(
+        NameSpace::root(iop_trace!("commit phase transcript")),
+        public_input,
+        private_input,
+        &mut transcript,
+        prover_parameter,
+    )?;
+    Ok(transcript)
+}
+
+/// Generate a proof using `P` and verify it using `V`, with a fresh Poseidon
+/// sponge and `FieldMTConfig` merkle trees. Returns the verifier output, or
+/// the first error of proving or verifying.
+pub(crate) fn prove_and_verify<P, V, L>(
+    public_input: &P::PublicInput,
+    private_input: &P::PrivateInput,
+    prover_parameter: &P::ProverParameter,
+    ldt_params: &L::LDTParameters,
+) -> Result<V::VerifierOutput, Error>
+where
+    P: IOPProver<Fr>,
+    V: IOPVerifierForProver<PoseidonSponge<Fr>, Fr, P>,
+    L: LDT<Fr>,
+{
+    let hash_params = mt_hash_parameters();
+    let proof = BCSProof::generate::<V, P, L, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        public_input,
+        private_input,
+        prover_parameter,
+        ldt_params,
+        hash_params.clone(),
+    )?;
+    BCSVerifier::verify::<V, L, _>(
+        PoseidonSponge::new(&poseidon_parameters()),
+        &proof,
+        public_input,
+        &prover_parameter.to_verifier_param(),
+        ldt_params,
+        hash_params,
+    )
+}
+
+/// Run the commit phase of `P` with a fresh Poseidon sponge and
+/// `FieldMTConfig` merkle trees, and return the bytes of every sponge squeeze,
+/// in order. Each verifier message is one item, and the last item is 32 bytes
+/// squeezed after the commit phase, which seeds the query phase. Field
+/// elements are serialized with `serialize_compressed`, bits as one byte each,
+/// and permutation entries and discarded byte counts as little-endian `u64`.
+pub(crate) fn record_squeeze_outputs<P, L>(
+    public_input: &P::PublicInput,
+    private_input: &P::PrivateInput,
+    prover_parameter: &P::ProverParameter,
+    ldt_params: &L::LDTParameters,
+) -> Result<Vec<Vec<u8>>, Error>
+where
+    P: IOPProver<Fr>,
+    L: LDT<Fr>,
+{
+    let mut transcript =
+        commit_phase_transcript::<P, L>(public_input, private_input, prover_parameter, ldt_params)?;
+    let mut outputs = transcript
+        .verifier_messages
+        .iter()
+        .flatten()
+        .map(|message| match message {
+            VerifierMessage::FieldElements(elements) => {
+                let mut bytes = Vec::new();
+                for element in elements {
+                    element.serialize_compressed(&mut bytes).unwrap();
+                }
+                bytes
+            },
+            VerifierMessage::Bits(bits) => bits.iter().map(|&bit| bit as u8).collect(),
+            VerifierMessage::Bytes(bytes) => bytes.clone(),
+            VerifierMessage::Permutation(permutation) => permutation
+                .iter()
+                .flat_map(|&i| (i as u64).to_le_bytes())
+                .collect(),
+            VerifierMessage::Advance(num_bytes) => (*num_bytes as u64).to_le_bytes().to_vec(),
+        })
+        .collect::<Vec<_>>();
+    outputs.push(transcript.sponge.squeeze_bytes(32));
+    Ok(outputs)
+}
+
+/// Return a copy of `proof` where every answer to oracle position `position`
+/// of prover round `round` in namespace `ns` is replaced by `new_value`,
+/// which has one element per oracle of the round. This simulates a prover
+/// changing its oracles after committing to them: the merkle paths are kept,
+/// so `BCSVerifier::verify` returns `BCSError::InvalidAuthenticationPath` for
+/// the round, and `BCSVerifier::verify_per_namespace` rejects the namespace
+/// of the round.
+///
+/// Namespaces and oracle lengths are not part of the proof, so `transcript`
+/// should be a transcript the prover of `proof` has run its commit phase on.
+///
+/// # Panics
+/// Panics if `position` is not queried in the round, or if `new_value` does
+/// not have one element per oracle.
+pub(crate) fn tamper_answer<S: CryptographicSponge>(
+    proof: &BCSProof<FieldMTConfig, Fr>,
+    transcript: &Transcript<FieldMTConfig, S, Fr>,
+    ns: NameSpace,
+    round: usize,
+    position: usize,
+    new_value: &[Fr],
+) -> BCSProof<FieldMTConfig, Fr> {
+    let index = (ns, round)
+        .to_prover_msg_round_ref(&transcript.bookkeeper)
+        .index;
+    let info = &transcript.prover_message_oracles[index].info;
+    let num_cosets = info.length >> info.localization_parameter;
+    let (coset_index, element_index) = (position % num_cosets, position / num_cosets);
+    let mut tampered = proof.clone();
+    let mut num_tampered = 0;
+    for (coset, _) in tampered.prover_iop_messages_by_round[index]
+        .queried_cosets
+        .iter_mut()
+        .zip(proof.prover_oracles_mt_path[index].iter())
+        .filter(|(_, path)| path.leaf_index == coset_index)
+    {
+        assert_eq!(
+            coset.len(),
+            new_value.len(),
+            "one value per oracle is needed"
+        );
+        for (oracle, &value) in coset.iter_mut().zip(new_value) {
+            oracle[element_index] = value;
+        }
+        num_tampered += 1;
+    }
+    assert!(num_tampered > 0, "position {} is not queried", position);
+    tampered
+}
+
 #[cfg(feature = "r1cs")]
 mod constraints {}
@@ -0,0 +1,172 @@
+use ark_crypto_primitives::merkle_tree::Config as MTConfig;
+use ark_ff::PrimeField;
+use ark_ldt::domain::Radix2CosetDomain;
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_sponge::{Absorb, CryptographicSponge};
+use ark_std::{marker::PhantomData, vec, vec::Vec};
+
+use crate::{
+    bcs::{simulation_transcript::SimulationTranscript, transcript::Transcript},
+    iop::{
+        bookkeeper::NameSpace,
+        message::{MessagesCollection, ProverRoundMessageInfo},
+        oracles::RoundOracle,
+        prover::IOPProver,
+        verifier::IOPVerifier,
+        ProverParam,
+    },
+    util::le_bits_to_usize,
+    Error,
+};
+
+/// Parameter of `UnivariateOpening`.
+#[derive(Clone, Debug)]
+pub struct UnivariateOpeningParameter<F: PrimeField> {
+    /// Degree bound of the committed polynomial.
+    pub degree_bound: usize,
+    /// Codeword domain of LDT.
+    pub codeword_domain: Radix2CosetDomain<F>,
+    /// Number of points where verifier checks the quotient relation.
+    pub num_queries: usize,
+}
+
+impl<F: PrimeField> ProverParam for UnivariateOpeningParameter<F> {
+    type VerifierParameter = Self;
+
+    fn to_verifier_param(&self) -> Self::VerifierParameter {
+        self.clone()
+    }
+}
+
+/// An IOP proving that a univariate polynomial `p` evaluates to `value` at
+/// `point`.
+///
+/// This is not a polynomial commitment: `point` is known when `p` is sent, and
+/// there is no commitment that can be opened at points chosen later, so this
+/// IOP does not implement `PolynomialCommitment` of `ark-poly-commit`.
+///
+/// Prover sends `(point, value)` as a short message so that later challenges
+/// are bound to the claim, and sends `p` and the quotient
+/// `q = (p - value) / (X - point)` with `send_univariate_polynomial`, so that
+/// LDT checks their degree bounds.
+/// Verifier checks `p(x) - value = q(x) * (x - point)` at random positions
+/// of the codeword domain.
+///
+/// Public input is `(point, value)` and private input is `p`.
+pub struct UnivariateOpening<F: PrimeField + Absorb> {
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField + Absorb> UnivariateOpening<F> {
+    /// Returns `(p - value) / (X - point)`, where `value = p(point)`.
+    fn quotient(poly: &DensePolynomial<F>, point: F) -> DensePolynomial<F> {
+        // synthetic division by `X - point`
+        let mut quotient = vec![F::zero(); poly.coeffs.len().saturating_sub(1)];
+        let mut carry = F::zero();
+        for (i, coeff) in poly.coeffs.iter().enumerate().skip(1).rev() {
+            carry = *coeff + carry * point;
+            quotient[i - 1] = carry;
+        }
+        DensePolynomial::from_coefficients_vec(quotient)
+    }
+}
+
+impl<F: PrimeField + Absorb> IOPProver<F> for UnivariateOpening<F> {
+    type ProverParameter = UnivariateOpeningParameter<F>;
+    type PublicInput = (F, F);
+    type PrivateInput = DensePolynomial<F>;
+
+    fn prove<MT: MTConfig<Leaf = [F]>, S: CryptographicSponge>(
+        namespace: NameSpace,
+        public_input: &Self::PublicInput,
+        private_input: &Self::PrivateInput,
+        transcript: &mut Transcript<MT, S, F>,
+        prover_parameter: &Self::ProverParameter,
+    ) -> Result<(), Error>
+    where
+        MT::InnerDigest: Absorb,
+    {
+        let (point, value) = *public_input;
+        debug_assert_eq!(private_input.evaluate(&point), value);
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_univariate_polynomial(private_input, prover_parameter.degree_bound)
+            .send_short_message(vec![point, value])
+            .submit(namespace, iop_trace!("committed polynomial"))?;
+        transcript
+            .add_prover_round_with_codeword_domain()
+            .send_univariate_polynomial(
+                &Self::quotient(private_input, point),
+                prover_parameter.degree_bound.saturating_sub(1),
+            )
+            .submit(namespace, iop_trace!("quotient polynomial"))?;
+        Ok(())
+    }
+}
+
+impl<S: CryptographicSponge, F: PrimeField + Absorb> IOPVerifier<S, F> for UnivariateOpening<F> {
+    type VerifierOutput = bool;
+    type VerifierParameter = UnivariateOpeningParameter<F>;
+    type PublicInput = (F, F);
+
+    fn register_iop_structure<MT: MTConfig<Leaf = [F]>>(
+        namespace: NameSpace,
+        transcript: &mut SimulationTranscript<MT, S, F>,
+        verifier_parameter: &Self::VerifierParameter,
+    ) where
+        MT::InnerDigest: Absorb,
+    {
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(transcript)
+            .with_reed_solomon_codes_degree_bounds(vec![verifier_parameter.degree_bound])
+            .with_num_short_messages(1)
+            .build();
+        transcript.receive_prover_current_round(
+            namespace,
+            expected_info,
+            iop_trace!("committed polynomial"),
+        );
+        let expected_info = ProverRoundMessageInfo::new_using_codeword_domain(transcript)
+            .with_reed_solomon_codes_degree_bounds(vec![verifier_parameter
+                .degree_bound
+                .saturating_sub(1)])
+            .build();
+        transcript.receive_prover_current_round(
+            namespace,
+            expected_info,
+            iop_trace!("quotient polynomial"),
+        );
+    }
+
+    fn query_and_decide<O: RoundOracle<F>>(
+        namespace: NameSpace,
+        verifier_parameter: &Self::VerifierParameter,
+        public_input: &Self::PublicInput,
+        sponge: &mut S,
+        transcript_messages: &mut MessagesCollection<F, O>,
+    ) -> Result<Self::VerifierOutput, Error> {
+        let (point, value) = *public_input;
+        if transcript_messages
+            .prover_round((namespace, 0))
+            .short_message(0, iop_trace!("claimed evaluation"))
+            != [point, value]
+        {
+            return Ok(false);
+        }
+        let domain = verifier_parameter.codeword_domain;
+        let positions = (0..verifier_parameter.num_queries)
+            .map(|_| le_bits_to_usize(&sponge.squeeze_bits(domain.dim())))
+            .collect::<Vec<_>>();
+        let poly_evaluations = transcript_messages
+            .prover_round((namespace, 0))
+            .query_point(&positions, iop_trace!("query committed polynomial"));
+        let quotient_evaluations = transcript_messages
+            .prover_round((namespace, 1))
+            .query_point(&positions, iop_trace!("query quotient polynomial"));
+        Ok(positions
+            .iter()
+            .zip(poly_evaluations.iter().zip(quotient_evaluations.iter()))
+            .all(|(&position, (poly_eval, quotient_eval))| {
+                poly_eval[0] - value == quotient_eval[0] * (domain.element(position) - point)
+            }))
+    }
+}
@@ -0,0 +1,7 @@
+/// Interpret `bits` as an unsigned integer, least significant bit first.
+pub(crate) fn le_bits_to_usize(bits: &[bool]) -> usize {
+    bits.iter()
+        .enumerate()
+        .map(|(pos, bit)| (*bit as usize) << pos)
+        .sum()
+}
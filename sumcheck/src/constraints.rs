@@ -232,7 +232,7 @@ mod tests {
             let sumcheck = UnivariateSumcheck {
                 summation_domain: verifier_parameter.summation_domain,
             };
-            let sumcheck_ns = transcript.new_namespace(namespace, iop_trace!("sumcheck"));
+            let sumcheck_ns = transcript.new_namespace(namespace, iop_trace!("sumcheck"))?;
             sumcheck.register_sumcheck_commit_phase_var(
                 transcript,
                 sumcheck_ns,